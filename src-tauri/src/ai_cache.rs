@@ -0,0 +1,194 @@
+use crate::db::Database;
+use crate::encryption;
+use rusqlite::{Connection, OptionalExtension};
+use std::future::Future;
+
+// AI 响应缓存：提示词的全部输入和模型参数都相同的请求直接返回上次的结果，避免重复调用付费 API。
+// 响应可能包含加密笔记和书籍内容的翻译、摘要，与笔记内容一样加密存储；缓存键是以加密密钥计算的
+// HMAC，不能通过猜测笔记内容来验证
+
+/// 决定 AI 响应的全部请求参数
+pub struct CacheKeyParts<'a> {
+    pub action: &'a str,
+    pub note_title: &'a str,
+    pub note_content: &'a str,
+    pub highlighted_text: Option<&'a str>,
+    pub target_language: Option<&'a str>,
+    pub platform: &'a str,
+    pub model: &'a str,
+    pub temperature: f64,
+}
+
+/// 计算缓存键
+///
+/// 对全部请求参数取 HMAC-SHA256，任一参数（如翻译的目标语言、笔记标题、平台）不同时互不影响
+pub fn cache_key(parts: &CacheKeyParts, secret: &[u8]) -> String {
+    let temperature = parts.temperature.to_string();
+    let fields = [
+        parts.action,
+        parts.note_title,
+        parts.note_content,
+        parts.highlighted_text.unwrap_or_default(),
+        parts.target_language.unwrap_or_default(),
+        parts.platform,
+        parts.model,
+        &temperature,
+    ];
+    // 分隔符避免字段拼接后产生歧义
+    encryption::keyed_hash(secret, fields.join("\0").as_bytes())
+}
+
+/// 读取缓存的响应
+///
+/// 无法解密的条目（更换了密钥）视为未命中
+pub fn get_cached_response(conn: &Connection, key: &str, secret: &[u8]) -> Result<Option<String>, String> {
+    let stored: Option<String> = conn
+        .query_row("SELECT response FROM ai_cache WHERE cache_key = ?1", [key], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("读取 AI 缓存失败: {}", e))?;
+    Ok(stored.and_then(|stored| encryption::decrypt_content(&stored, secret).ok()))
+}
+
+/// 加密保存响应（同一个键只保留最新结果）
+pub fn save_response(conn: &Connection, key: &str, response: &str, secret: &[u8]) -> Result<(), String> {
+    let encrypted = encryption::encrypt_content(response, secret).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO ai_cache (cache_key, response, created_at) VALUES (?1, ?2, CURRENT_TIMESTAMP)",
+        rusqlite::params![key, encrypted],
+    )
+    .map_err(|e| format!("保存 AI 缓存失败: {}", e))?;
+    Ok(())
+}
+
+/// 清空缓存
+///
+/// # 返回
+/// 删除的条目数
+pub fn clear_cache(conn: &Connection) -> Result<usize, String> {
+    conn.execute("DELETE FROM ai_cache", [])
+        .map_err(|e| format!("清空 AI 缓存失败: {}", e))
+}
+
+/// 优先返回缓存，未命中（或 `force_refresh`）时调用 `fetch` 并缓存成功的结果
+///
+/// 只在读写缓存时短暂持有数据库锁，`fetch` 执行期间不持有
+///
+/// # 参数
+/// - `secret`: 加密密钥，用于加密和解密缓存的响应
+pub async fn cached_or_fetch<F, Fut>(
+    db: &Database,
+    key: &str,
+    secret: &[u8],
+    force_refresh: bool,
+    fetch: F,
+) -> Result<String, String>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<String, String>>,
+{
+    if !force_refresh {
+        if let Some(cached) = db.with_conn(|conn| get_cached_response(conn, key, secret))? {
+            return Ok(cached);
+        }
+    }
+
+    let response = fetch().await?;
+    // 缓存写入失败不影响本次结果
+    if let Err(e) = db.with_conn(|conn| save_response(conn, key, &response, secret)) {
+        eprintln!("警告: {}", e);
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::generate_key;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::TempDir;
+
+    async fn fetch_counting(calls: &AtomicUsize) -> Result<String, String> {
+        let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok(format!("第 {} 次请求的结果", n))
+    }
+
+    fn parts<'a>(note_title: &'a str, platform: &'a str, temperature: f64) -> CacheKeyParts<'a> {
+        CacheKeyParts {
+            action: "summarize",
+            note_title,
+            note_content: "笔记内容",
+            highlighted_text: None,
+            target_language: None,
+            platform,
+            model: "gpt-4o",
+            temperature,
+        }
+    }
+
+    #[test]
+    fn test_second_identical_call_hits_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::open(temp_dir.path().join("test.db")).unwrap();
+        let calls = AtomicUsize::new(0);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let secret = generate_key();
+
+        let key = cache_key(&parts("标题", "openai", 0.7), &secret);
+        let first = rt.block_on(cached_or_fetch(&db, &key, &secret, false, || fetch_counting(&calls))).unwrap();
+        let second = rt.block_on(cached_or_fetch(&db, &key, &secret, false, || fetch_counting(&calls))).unwrap();
+        assert_eq!(first, "第 1 次请求的结果");
+        assert_eq!(second, first);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 参数不同时不命中
+        let other = cache_key(&parts("标题", "openai", 0.2), &secret);
+        assert_ne!(other, key);
+        assert_ne!(cache_key(&parts("另一个标题", "openai", 0.7), &secret), key);
+        assert_ne!(cache_key(&parts("标题", "claude", 0.7), &secret), key);
+        assert_ne!(cache_key(&parts("标题", "openai", 0.7), &generate_key()), key);
+        rt.block_on(cached_or_fetch(&db, &other, &secret, false, || fetch_counting(&calls))).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // 强制刷新时重新请求并更新缓存
+        let refreshed = rt.block_on(cached_or_fetch(&db, &key, &secret, true, || fetch_counting(&calls))).unwrap();
+        assert_eq!(refreshed, "第 3 次请求的结果");
+        assert_eq!(db.with_conn(|conn| get_cached_response(conn, &key, &secret)).unwrap(), Some(refreshed));
+    }
+
+    #[test]
+    fn test_failed_fetch_is_not_cached() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::open(temp_dir.path().join("test.db")).unwrap();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let secret = generate_key();
+
+        let key = cache_key(&parts("标题", "openai", 1.0), &secret);
+        let result = rt.block_on(cached_or_fetch(&db, &key, &secret, false, || async { Err("请求失败".to_string()) }));
+        assert!(result.is_err());
+        assert_eq!(db.with_conn(|conn| get_cached_response(conn, &key, &secret)).unwrap(), None);
+
+        db.with_conn(|conn| save_response(conn, &key, "结果", &secret)).unwrap();
+        assert_eq!(db.with_conn(clear_cache).unwrap(), 1);
+        assert_eq!(db.with_conn(|conn| get_cached_response(conn, &key, &secret)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_cached_response_is_encrypted() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::open(temp_dir.path().join("test.db")).unwrap();
+        let secret = generate_key();
+
+        db.with_conn(|conn| save_response(conn, "k", "译文：量子纠缠", &secret)).unwrap();
+        let stored: String = db
+            .with_conn(|conn| {
+                conn.query_row("SELECT response FROM ai_cache WHERE cache_key = 'k'", [], |row| row.get(0))
+                    .map_err(|e| e.to_string())
+            })
+            .unwrap();
+        assert!(!stored.contains("量子纠缠"));
+        assert_eq!(db.with_conn(|conn| get_cached_response(conn, "k", &secret)).unwrap().as_deref(), Some("译文：量子纠缠"));
+
+        // 更换密钥后无法解密的条目视为未命中
+        assert_eq!(db.with_conn(|conn| get_cached_response(conn, "k", &generate_key())).unwrap(), None);
+    }
+}
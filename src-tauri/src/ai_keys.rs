@@ -0,0 +1,357 @@
+use crate::db::Database;
+use crate::encryption;
+use crate::error::AppError;
+use crate::AIRequestError;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::future::Future;
+
+// AI API Key 轮换：同一平台可以配置多个 Key，按最久未使用的顺序轮流使用。
+// 连续认证失败的 Key 自动停用，触发速率限制（429）的 Key 暂时冷却。
+// Key 使用与书籍内容相同的密钥加密存储
+
+/// 连续认证失败（401/403）达到该次数后停用 Key
+pub const MAX_AUTH_FAILURES: i32 = 3;
+
+/// 触发速率限制后 Key 的冷却时间（秒）
+pub const RATE_LIMIT_COOLDOWN_SECS: i64 = 60;
+
+/// API Key 信息（不包含 Key 本身）
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ApiKeyInfo {
+    pub id: i32,
+    pub config_id: i32,
+    pub key_suffix: String, // Key 的最后 4 个字符，用于区分
+    pub last_used: Option<String>,
+    pub disabled: bool,
+    pub cooldown_until: Option<String>, // 冷却结束时间，未冷却时为 None
+}
+
+/// 一次请求对 Key 状态的影响
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KeyOutcome {
+    Success,
+    Unauthorized,
+    RateLimited,
+    /// 与 Key 无关的错误（网络、超时等）
+    Other,
+}
+
+impl KeyOutcome {
+    fn of(result: &Result<String, AIRequestError>) -> Self {
+        match result {
+            Ok(_) => KeyOutcome::Success,
+            Err(AIRequestError::Unauthorized) => KeyOutcome::Unauthorized,
+            Err(AIRequestError::RateLimited) => KeyOutcome::RateLimited,
+            Err(_) => KeyOutcome::Other,
+        }
+    }
+}
+
+/// 添加 API Key
+///
+/// # 参数
+/// - `conn`: 数据库连接
+/// - `config_id`: 所属的 AI 配置 ID
+/// - `api_key`: API Key 明文
+/// - `key`: 加密密钥
+///
+/// # 返回
+/// 新 Key 的 ID
+pub fn add_api_key(conn: &Connection, config_id: i32, api_key: &str, key: &[u8]) -> Result<i64, AppError> {
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        return Err(AppError::Validation("API key 不能为空".to_string()));
+    }
+
+    let encrypted = encryption::encrypt_content(api_key, key).map_err(|e| AppError::Db(e.to_string()))?;
+    conn.execute(
+        "INSERT INTO ai_api_keys (config_id, api_key, key_suffix) VALUES (?1, ?2, ?3)",
+        rusqlite::params![config_id, encrypted, key_suffix(api_key)],
+    )
+    .map_err(|e| AppError::Db(format!("添加 API Key 失败: {}", e)))?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Key 的最后 4 个字符
+fn key_suffix(api_key: &str) -> String {
+    let chars: Vec<char> = api_key.chars().collect();
+    chars[chars.len().saturating_sub(4)..].iter().collect()
+}
+
+/// 获取 AI 配置的所有 API Key（按添加顺序）
+pub fn list_api_keys(conn: &Connection, config_id: i32) -> Result<Vec<ApiKeyInfo>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, config_id, key_suffix, last_used, disabled,
+                    CASE WHEN cooldown_until > strftime('%Y-%m-%d %H:%M:%f', 'now') THEN cooldown_until END
+             FROM ai_api_keys WHERE config_id = ?1 ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let keys = stmt
+        .query_map([config_id], |row| {
+            Ok(ApiKeyInfo {
+                id: row.get(0)?,
+                config_id: row.get(1)?,
+                key_suffix: row.get(2)?,
+                last_used: row.get(3)?,
+                disabled: row.get(4)?,
+                cooldown_until: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(keys)
+}
+
+/// 删除 API Key
+///
+/// # 返回
+/// 是否删除了 Key
+pub fn delete_api_key(conn: &Connection, id: i32) -> Result<bool, String> {
+    let affected = conn
+        .execute("DELETE FROM ai_api_keys WHERE id = ?1", [id])
+        .map_err(|e| format!("删除 API Key 失败: {}", e))?;
+    Ok(affected > 0)
+}
+
+/// 手动停用或启用 API Key（重新启用时清空失败计数和冷却）
+///
+/// # 返回
+/// Key 是否存在
+pub fn set_api_key_disabled(conn: &Connection, id: i32, disabled: bool) -> Result<bool, String> {
+    let affected = conn
+        .execute(
+            "UPDATE ai_api_keys SET disabled = ?1, auth_failures = 0, cooldown_until = NULL WHERE id = ?2",
+            rusqlite::params![disabled, id],
+        )
+        .map_err(|e| format!("更新 API Key 失败: {}", e))?;
+    Ok(affected > 0)
+}
+
+/// 判断 AI 配置是否配置了轮换 Key（不论是否可用）
+pub fn has_api_keys(conn: &Connection, config_id: i32) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM ai_api_keys WHERE config_id = ?1)",
+        [config_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// 取出最久未使用的可用 Key 并记录使用时间
+///
+/// 跳过已停用、冷却中以及 `exclude` 中（本次请求已经试过）的 Key
+fn acquire_api_key(
+    conn: &Connection,
+    config_id: i32,
+    key: &[u8],
+    exclude: &[i32],
+) -> Result<Option<(i32, String)>, String> {
+    let candidate: Option<(i32, String)> = conn
+        .prepare(
+            "SELECT id, api_key FROM ai_api_keys
+             WHERE config_id = ?1 AND disabled = 0
+               AND (cooldown_until IS NULL OR cooldown_until <= strftime('%Y-%m-%d %H:%M:%f', 'now'))
+             ORDER BY last_used IS NOT NULL, last_used, id",
+        )
+        .map_err(|e| e.to_string())?
+        .query_map([config_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(Result::ok)
+        .find(|(id, _)| !exclude.contains(id));
+
+    let Some((id, encrypted)) = candidate else {
+        return Ok(None);
+    };
+
+    conn.execute(
+        "UPDATE ai_api_keys SET last_used = strftime('%Y-%m-%d %H:%M:%f', 'now') WHERE id = ?1",
+        [id],
+    )
+    .map_err(|e| e.to_string())?;
+    let api_key = encryption::decrypt_content(&encrypted, key).map_err(|e| format!("解密 API Key 失败: {}", e))?;
+    Ok(Some((id, api_key)))
+}
+
+/// 按请求结果更新 Key 状态
+fn record_outcome(conn: &Connection, id: i32, outcome: KeyOutcome) -> Result<(), String> {
+    let result = match outcome {
+        KeyOutcome::Success => conn.execute(
+            "UPDATE ai_api_keys SET auth_failures = 0 WHERE id = ?1",
+            [id],
+        ),
+        KeyOutcome::Unauthorized => conn.execute(
+            "UPDATE ai_api_keys SET auth_failures = auth_failures + 1,
+                    disabled = CASE WHEN auth_failures + 1 >= ?2 THEN 1 ELSE disabled END
+             WHERE id = ?1",
+            rusqlite::params![id, MAX_AUTH_FAILURES],
+        ),
+        KeyOutcome::RateLimited => conn.execute(
+            "UPDATE ai_api_keys
+             SET cooldown_until = strftime('%Y-%m-%d %H:%M:%f', 'now', ?2)
+             WHERE id = ?1",
+            rusqlite::params![id, format!("+{} seconds", RATE_LIMIT_COOLDOWN_SECS)],
+        ),
+        KeyOutcome::Other => return Ok(()),
+    };
+    result.map(|_| ()).map_err(|e| format!("更新 API Key 状态失败: {}", e))
+}
+
+/// 使用轮换 Key 发送请求
+///
+/// 依次尝试最久未使用的可用 Key，遇到认证失败或速率限制时换下一个 Key；
+/// 没有配置轮换 Key 时使用 `fallback_key`（AI 配置中的单个 Key）
+///
+/// 只在读写 Key 状态时短暂持有数据库锁，`send` 执行期间不持有
+///
+/// # 参数
+/// - `db`: 数据库
+/// - `config_id`: AI 配置 ID
+/// - `key`: 加密密钥
+/// - `fallback_key`: 没有轮换 Key 时使用的 Key
+/// - `send`: 用给定的 API Key 发送请求
+pub async fn with_key_rotation<F, Fut>(
+    db: &Database,
+    config_id: i32,
+    key: &[u8],
+    fallback_key: Option<&str>,
+    mut send: F,
+) -> Result<String, AIRequestError>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, AIRequestError>>,
+{
+    let mut tried = Vec::new();
+    let mut last_error = None;
+
+    loop {
+        let Some((id, api_key)) = db.with_conn(|conn| acquire_api_key(conn, config_id, key, &tried))? else {
+            if let Some(error) = last_error {
+                return Err(error);
+            }
+            if db.with_conn(|conn| has_api_keys(conn, config_id))? {
+                return Err("所有 API Key 均已停用或正在冷却，请稍后重试".into());
+            }
+            return match fallback_key.filter(|api_key| !api_key.is_empty()) {
+                Some(api_key) => send(api_key.to_string()).await,
+                None => Err("API key 未配置".into()),
+            };
+        };
+
+        let result = send(api_key).await;
+        let outcome = KeyOutcome::of(&result);
+        // 状态写入失败不影响本次结果
+        if let Err(e) = db.with_conn(|conn| record_outcome(conn, id, outcome)) {
+            eprintln!("警告: {}", e);
+        }
+
+        match (outcome, result) {
+            (KeyOutcome::Unauthorized | KeyOutcome::RateLimited, Err(error)) => {
+                tried.push(id);
+                last_error = Some(error);
+            }
+            (_, result) => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, Database, i32, Vec<u8>) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::open(temp_dir.path().join("test.db")).unwrap();
+        let config_id = db
+            .with_conn(|conn| {
+                conn.query_row("SELECT id FROM ai_config WHERE platform = 'openai'", [], |row| row.get(0))
+            })
+            .unwrap();
+        (temp_dir, db, config_id, encryption::generate_key())
+    }
+
+    #[test]
+    fn test_rate_limited_key_advances_to_next() {
+        let (_temp_dir, db, config_id, key) = setup();
+        let (first, second) = db
+            .with_conn(|conn| {
+                Ok::<_, String>((
+                    add_api_key(conn, config_id, "sk-first-aaaa", &key)? as i32,
+                    add_api_key(conn, config_id, "sk-second-bbbb", &key)? as i32,
+                ))
+            })
+            .unwrap();
+
+        // 第一个 Key 触发速率限制，第二个 Key 成功
+        let used = Mutex::new(Vec::new());
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt.block_on(with_key_rotation(&db, config_id, &key, None, |api_key| {
+            used.lock().unwrap().push(api_key.clone());
+            async move {
+                if api_key == "sk-first-aaaa" {
+                    Err(AIRequestError::RateLimited)
+                } else {
+                    Ok("ok".to_string())
+                }
+            }
+        }));
+        assert_eq!(result, Ok("ok".to_string()));
+        assert_eq!(*used.lock().unwrap(), vec!["sk-first-aaaa", "sk-second-bbbb"]);
+
+        let keys = db.with_conn(|conn| list_api_keys(conn, config_id)).unwrap();
+        assert_eq!(keys[0].id, first);
+        assert_eq!(keys[0].key_suffix, "aaaa");
+        assert!(keys[0].cooldown_until.is_some());
+        assert_eq!(keys[1].id, second);
+        assert!(keys[1].cooldown_until.is_none());
+
+        // 冷却中的 Key 不再被选中
+        used.lock().unwrap().clear();
+        rt.block_on(with_key_rotation(&db, config_id, &key, None, |api_key| {
+            used.lock().unwrap().push(api_key);
+            async { Ok("ok".to_string()) }
+        }))
+        .unwrap();
+        assert_eq!(*used.lock().unwrap(), vec!["sk-second-bbbb"]);
+
+        // Key 加密存储
+        let stored: String = db
+            .with_conn(|conn| conn.query_row("SELECT api_key FROM ai_api_keys WHERE id = ?1", [first], |row| row.get(0)))
+            .unwrap();
+        assert!(!stored.contains("sk-first"));
+    }
+
+    #[test]
+    fn test_repeated_auth_failures_disable_key() {
+        let (_temp_dir, db, config_id, key) = setup();
+        db.with_conn(|conn| add_api_key(conn, config_id, "sk-revoked", &key)).unwrap();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        for _ in 0..MAX_AUTH_FAILURES {
+            let result = rt.block_on(with_key_rotation(&db, config_id, &key, Some("sk-fallback"), |_| async {
+                Err(AIRequestError::Unauthorized)
+            }));
+            assert_eq!(result, Err(AIRequestError::Unauthorized));
+        }
+        assert!(db.with_conn(|conn| list_api_keys(conn, config_id)).unwrap()[0].disabled);
+
+        // 配置了轮换 Key 但全部不可用时不回退到单个 Key
+        let result = rt.block_on(with_key_rotation(&db, config_id, &key, Some("sk-fallback"), |_| async {
+            Ok("ok".to_string())
+        }));
+        assert!(result.is_err());
+
+        // 没有轮换 Key 时使用配置中的 Key
+        let id = db.with_conn(|conn| list_api_keys(conn, config_id)).unwrap()[0].id;
+        assert!(db.with_conn(|conn| delete_api_key(conn, id)).unwrap());
+        let result = rt.block_on(with_key_rotation(&db, config_id, &key, Some("sk-fallback"), |api_key| async move {
+            Ok(api_key)
+        }));
+        assert_eq!(result, Ok("sk-fallback".to_string()));
+    }
+}
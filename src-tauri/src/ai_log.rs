@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// AI 调试日志：开启 ai_debug_log 设置后，每次 AI 请求的地址、请求体、状态码和响应片段
+// 以 JSON Lines 追加到日志文件；写入前替换掉 API Key，超过大小上限时轮转到 .1 文件
+
+/// 日志文件名
+const LOG_FILE_NAME: &str = "ai_debug.log";
+
+/// 单个日志文件的大小上限（字节），超过后轮转
+pub const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// 请求体和响应保留的最大字符数
+const MAX_SNIPPET_CHARS: usize = 2000;
+
+/// 替换 API Key 的占位符
+const REDACTED: &str = "[REDACTED]";
+
+/// 一次 AI 请求的日志
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AiLogEntry {
+    pub timestamp: String,
+    pub platform: String,
+    pub model: String,
+    pub url: String,
+    pub status: Option<u16>, // 请求未收到响应时为 None
+    pub request_body: String,
+    pub response_snippet: Option<String>,
+    pub error: Option<String>,
+}
+
+impl AiLogEntry {
+    /// 构建日志条目，所有文本中出现的 `secrets` 都会被替换并截断到上限
+    ///
+    /// # 参数
+    /// - `outcome`: 收到响应时为 (状态码, 响应正文)，否则为错误信息
+    /// - `secrets`: 需要从日志中去除的 API Key
+    pub fn new(
+        platform: &str,
+        model: &str,
+        url: &str,
+        request_body: &str,
+        outcome: Result<(u16, &str), String>,
+        secrets: &[&str],
+    ) -> Self {
+        let (status, response_snippet, error) = match outcome {
+            Ok((status, body)) => (Some(status), Some(snippet(&redact(body, secrets))), None),
+            Err(error) => (None, None, Some(redact(&error, secrets))),
+        };
+        AiLogEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            platform: platform.to_string(),
+            model: model.to_string(),
+            url: redact(url, secrets),
+            status,
+            request_body: snippet(&redact(request_body, secrets)),
+            response_snippet,
+            error,
+        }
+    }
+}
+
+/// 把文本中出现的 `secrets` 替换为占位符（空字符串忽略）
+pub fn redact(text: &str, secrets: &[&str]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(text.to_string(), |text, secret| text.replace(secret, REDACTED))
+}
+
+/// 截断到 MAX_SNIPPET_CHARS 个字符
+fn snippet(text: &str) -> String {
+    match text.char_indices().nth(MAX_SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+fn log_path(dir: &Path) -> PathBuf {
+    dir.join(LOG_FILE_NAME)
+}
+
+fn rotated_path(dir: &Path) -> PathBuf {
+    dir.join(format!("{}.1", LOG_FILE_NAME))
+}
+
+/// 追加一条日志，当前文件超过 MAX_LOG_BYTES 时先轮转（覆盖旧的 .1 文件）
+pub fn append_entry(dir: &Path, entry: &AiLogEntry) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("创建日志目录失败: {}", e))?;
+
+    let path = log_path(dir);
+    if fs::metadata(&path).is_ok_and(|meta| meta.len() >= MAX_LOG_BYTES) {
+        fs::rename(&path, rotated_path(dir)).map_err(|e| format!("轮转 AI 日志失败: {}", e))?;
+    }
+
+    let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("打开 AI 日志失败: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("写入 AI 日志失败: {}", e))
+}
+
+/// 读取最近的日志（最新的在前）
+///
+/// # 参数
+/// - `dir`: 日志目录
+/// - `limit`: 最多返回的条数
+pub fn read_recent_entries(dir: &Path, limit: usize) -> Result<Vec<AiLogEntry>, String> {
+    let mut entries = Vec::new();
+    for path in [log_path(dir), rotated_path(dir)] {
+        let Ok(content) = fs::read_to_string(&path) else { continue };
+        // 无法解析的行（如写入中断）直接跳过
+        entries.extend(
+            content
+                .lines()
+                .rev()
+                .filter_map(|line| serde_json::from_str::<AiLogEntry>(line).ok()),
+        );
+        if entries.len() >= limit {
+            break;
+        }
+    }
+    entries.truncate(limit);
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_log_rotation_keeps_recent_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let big_body = "x".repeat(MAX_SNIPPET_CHARS + 1);
+        let entry = |n: u16| AiLogEntry::new("openai", "gpt-4o", "https://api", &big_body, Ok((n, "{}")), &[]);
+
+        // 每条约 2KB，写满一个文件后继续写入会触发轮转
+        let count = (MAX_LOG_BYTES / 2000 + 10) as u16;
+        for n in 0..count {
+            append_entry(temp_dir.path(), &entry(n)).unwrap();
+        }
+        assert!(rotated_path(temp_dir.path()).exists());
+        assert!(fs::metadata(log_path(temp_dir.path())).unwrap().len() < MAX_LOG_BYTES);
+
+        let recent = read_recent_entries(temp_dir.path(), 3).unwrap();
+        let statuses: Vec<Option<u16>> = recent.iter().map(|e| e.status).collect();
+        assert_eq!(statuses, vec![Some(count - 1), Some(count - 2), Some(count - 3)]);
+        assert!(recent[0].request_body.ends_with('…'));
+    }
+}
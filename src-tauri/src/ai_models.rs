@@ -0,0 +1,176 @@
+use crate::{api_status_error, handle_request_error};
+use serde::Deserialize;
+
+// AI 模型列表：调用各平台的模型列表接口，接口不可用时退回内置的常用模型
+
+/// 模型列表请求的超时时间（秒）
+const LIST_TIMEOUT_SECS: u32 = 15;
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+    #[serde(default)]
+    models: Vec<GoogleModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct GoogleModelEntry {
+    /// 形如 "models/gemini-1.5-pro"
+    name: String,
+    #[serde(default, rename = "supportedGenerationMethods")]
+    supported_generation_methods: Vec<String>,
+}
+
+/// 平台内置的常用模型（没有 API Key 或平台不提供列表接口时使用）
+pub fn curated_models(platform: &str) -> Vec<String> {
+    let models: &[&str] = match platform {
+        "openai" | "openai-cn" => &["gpt-4o", "gpt-4o-mini", "gpt-4-turbo", "gpt-3.5-turbo"],
+        "anthropic" => &[
+            "claude-3-5-sonnet-latest",
+            "claude-3-5-haiku-latest",
+            "claude-3-opus-latest",
+            "claude-3-sonnet-20240229",
+        ],
+        "google" => &["gemini-1.5-pro", "gemini-1.5-flash", "gemini-pro"],
+        _ => &[],
+    };
+    models.iter().map(|model| model.to_string()).collect()
+}
+
+/// 解析模型列表接口的响应
+///
+/// OpenAI 和 Anthropic 返回 `{"data": [{"id": ...}]}`，Google 返回 `{"models": [{"name": "models/..."}]}`
+///
+/// # 返回
+/// 排序去重后的模型 ID；Google 只保留支持 generateContent 的模型
+pub fn parse_model_ids(platform: &str, body: &str) -> Result<Vec<String>, String> {
+    let list: ModelList = serde_json::from_str(body).map_err(|e| format!("解析模型列表失败: {}", e))?;
+
+    let mut ids: Vec<String> = if platform == "google" {
+        list.models
+            .into_iter()
+            .filter(|model| {
+                model.supported_generation_methods.is_empty()
+                    || model.supported_generation_methods.iter().any(|method| method == "generateContent")
+            })
+            .map(|model| model.name.trim_start_matches("models/").to_string())
+            .collect()
+    } else {
+        list.data.into_iter().map(|model| model.id).collect()
+    };
+    ids.sort();
+    ids.dedup();
+    Ok(ids)
+}
+
+/// 获取平台可用的模型 ID
+///
+/// # 参数
+/// - `base_url`: 为 None 时使用平台的官方地址
+/// - `proxy_url`: 代理地址，为 None 时直连
+///
+/// # 返回
+/// 未配置 API Key、平台未知或接口不存在（404）时返回内置列表
+pub async fn list_models(
+    platform: &str,
+    api_key: Option<&str>,
+    base_url: Option<&str>,
+    proxy_url: Option<&str>,
+) -> Result<Vec<String>, String> {
+    let api_key = match api_key.map(str::trim) {
+        Some(key) if !key.is_empty() => key,
+        _ => return Ok(curated_models(platform)),
+    };
+
+    let mut builder = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(u64::from(LIST_TIMEOUT_SECS)));
+    if let Some(proxy_url) = proxy_url {
+        let proxy = reqwest::Proxy::all(proxy_url).map_err(|e| format!("无效的代理地址「{}」: {}", proxy_url, e))?;
+        builder = builder.proxy(proxy);
+    }
+    let client = builder.build().map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
+
+    let request = match platform {
+        "openai" | "openai-cn" => {
+            let base_url = base_url.unwrap_or("https://api.openai.com/v1");
+            client
+                .get(format!("{}/models", base_url.trim_end_matches('/')))
+                .header("Authorization", format!("Bearer {}", api_key))
+        }
+        "anthropic" => {
+            let base_url = base_url.unwrap_or("https://api.anthropic.com");
+            client
+                .get(format!("{}/v1/models", base_url.trim_end_matches('/')))
+                .header("x-api-key", api_key)
+                .header("anthropic-version", "2023-06-01")
+        }
+        "google" => {
+            let base_url = base_url.unwrap_or("https://generativelanguage.googleapis.com");
+            client.get(format!("{}/v1beta/models?key={}", base_url.trim_end_matches('/'), api_key))
+        }
+        _ => return Ok(curated_models(platform)),
+    };
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| handle_request_error(e, LIST_TIMEOUT_SECS))?;
+
+    let status = response.status();
+    // 部分兼容接口（代理、国内中转）没有模型列表
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(curated_models(platform));
+    }
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(api_status_error(status, error_text, "").into());
+    }
+
+    let body = response.text().await.map_err(|e| format!("读取模型列表失败: {}", e))?;
+    parse_model_ids(platform, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_openai_models_response() {
+        let body = r#"{
+            "object": "list",
+            "data": [
+                {"id": "gpt-4o-mini", "object": "model", "created": 1721172741, "owned_by": "system"},
+                {"id": "gpt-4o", "object": "model", "created": 1715367049, "owned_by": "system"},
+                {"id": "text-embedding-3-small", "object": "model", "created": 1705948997, "owned_by": "system"}
+            ]
+        }"#;
+        assert_eq!(
+            parse_model_ids("openai", body).unwrap(),
+            vec!["gpt-4o", "gpt-4o-mini", "text-embedding-3-small"]
+        );
+        assert!(parse_model_ids("openai", "not json").is_err());
+    }
+
+    #[test]
+    fn test_parse_google_models_response() {
+        let body = r#"{"models": [
+            {"name": "models/gemini-1.5-pro", "supportedGenerationMethods": ["generateContent", "countTokens"]},
+            {"name": "models/embedding-001", "supportedGenerationMethods": ["embedContent"]}
+        ]}"#;
+        assert_eq!(parse_model_ids("google", body).unwrap(), vec!["gemini-1.5-pro"]);
+    }
+
+    #[test]
+    fn test_without_api_key_returns_curated_list() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let models = rt.block_on(list_models("anthropic", Some("  "), None, None)).unwrap();
+        assert_eq!(models, curated_models("anthropic"));
+        assert!(rt.block_on(list_models("unknown", Some("key"), None, None)).unwrap().is_empty());
+    }
+}
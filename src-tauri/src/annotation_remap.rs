@@ -0,0 +1,574 @@
+use crate::irp::{self, BlockHash};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+// 批注位置迁移模块：重新解析后章节结构变化时，按标题相似度把笔记迁移到新的章节，
+// 按内容块指纹把高亮和书签迁移到新的内容块
+
+/// 标题相似度阈值（0-1），低于该值视为无法匹配
+pub const TITLE_MATCH_THRESHOLD: f64 = 0.6;
+
+/// 迁移结果统计
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemapSummary {
+    /// 章节位置发生变化的笔记数
+    pub moved: usize,
+    /// 章节位置未变的笔记数
+    pub unchanged: usize,
+    /// 无法匹配、需要用户确认的笔记数
+    pub needs_review: usize,
+}
+
+/// 计算两个字符串的编辑距离（按字符计算，支持中文）
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+/// 标题相似度（0-1），忽略首尾空白和大小写
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let a = a.trim().to_lowercase();
+    let b = b.trim().to_lowercase();
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(&a, &b) as f64 / max_len as f64
+}
+
+/// 将旧章节索引映射到新章节索引
+///
+/// 按相似度从高到低贪心匹配，每个新章节最多匹配一个旧章节；
+/// 相似度相同时优先选择位置更接近的章节
+///
+/// # 返回
+/// 长度与 `old_titles` 相同，`None` 表示没有足够相似的新章节
+pub fn map_chapter_indices(old_titles: &[String], new_titles: &[String]) -> Vec<Option<usize>> {
+    let mut candidates: Vec<(f64, usize, usize, usize)> = Vec::new();
+    for (old_index, old_title) in old_titles.iter().enumerate() {
+        for (new_index, new_title) in new_titles.iter().enumerate() {
+            let score = title_similarity(old_title, new_title);
+            if score >= TITLE_MATCH_THRESHOLD {
+                candidates.push((score, old_index.abs_diff(new_index), old_index, new_index));
+            }
+        }
+    }
+
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    let mut mapping = vec![None; old_titles.len()];
+    let mut used = vec![false; new_titles.len()];
+    for (_, _, old_index, new_index) in candidates {
+        if mapping[old_index].is_none() && !used[new_index] {
+            mapping[old_index] = Some(new_index);
+            used[new_index] = true;
+        }
+    }
+
+    mapping
+}
+
+/// 根据新旧章节标题迁移书籍笔记的 chapter_index
+///
+/// 匹配成功的笔记更新 chapter_index 并清除 needs_review；
+/// 无法匹配的笔记保留原 chapter_index 并标记 needs_review
+///
+/// # 参数
+/// - `conn`: 数据库连接
+/// - `book_id`: 书籍 ID
+/// - `old_titles`: 重新解析前的章节标题（按 chapter_index 排列）
+/// - `new_titles`: 重新解析后的章节标题
+pub fn remap_notes(
+    conn: &Connection,
+    book_id: i32,
+    old_titles: &[String],
+    new_titles: &[String],
+) -> Result<RemapSummary, String> {
+    let mapping = map_chapter_indices(old_titles, new_titles);
+
+    let mut stmt = conn
+        .prepare("SELECT id, chapter_index FROM notes WHERE book_id = ?1 AND chapter_index IS NOT NULL")
+        .map_err(|e| e.to_string())?;
+    let notes = stmt
+        .query_map([book_id], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut summary = RemapSummary::default();
+    for (note_id, chapter_index) in notes {
+        let target = usize::try_from(chapter_index)
+            .ok()
+            .and_then(|index| mapping.get(index).copied().flatten());
+
+        match target {
+            Some(new_index) => {
+                conn.execute(
+                    "UPDATE notes SET chapter_index = ?1, needs_review = 0 WHERE id = ?2",
+                    rusqlite::params![new_index as i32, note_id],
+                )
+                .map_err(|e| format!("迁移笔记位置失败: {}", e))?;
+                if new_index as i32 == chapter_index {
+                    summary.unchanged += 1;
+                } else {
+                    summary.moved += 1;
+                }
+            }
+            None => {
+                conn.execute("UPDATE notes SET needs_review = 1 WHERE id = ?1", [note_id])
+                    .map_err(|e| format!("标记笔记失败: {}", e))?;
+                summary.needs_review += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// 将旧内容块映射到新内容块
+///
+/// 1. 指纹相同的块直接对应（重复内容选位置最近的块）
+/// 2. 内容有改动的块：若夹在两个已对应的块之间，且新旧两侧未对应的块数量相同，按顺序对应
+///
+/// `old` 和 `new` 均按 block_index 排列
+///
+/// # 返回
+/// 长度与 `old` 相同的新块 ID，`None` 表示无法定位
+pub fn reanchor_blocks(old: &[BlockHash], new: &[BlockHash]) -> Vec<Option<i32>> {
+    let mut mapping: Vec<Option<usize>> = vec![None; old.len()];
+    let mut used = vec![false; new.len()];
+
+    for (old_pos, old_block) in old.iter().enumerate() {
+        let Some(hash) = &old_block.content_hash else {
+            continue;
+        };
+        let best = new
+            .iter()
+            .enumerate()
+            .filter(|(new_pos, block)| !used[*new_pos] && block.content_hash.as_ref() == Some(hash))
+            .min_by_key(|(_, block)| block.block_index.abs_diff(old_block.block_index));
+        if let Some((new_pos, _)) = best {
+            mapping[old_pos] = Some(new_pos);
+            used[new_pos] = true;
+        }
+    }
+
+    let mut old_pos = 0;
+    while old_pos < old.len() {
+        if mapping[old_pos].is_some() {
+            old_pos += 1;
+            continue;
+        }
+        let gap_start = old_pos;
+        while old_pos < old.len() && mapping[old_pos].is_none() {
+            old_pos += 1;
+        }
+        let gap_end = old_pos;
+
+        let new_start = match gap_start {
+            0 => 0,
+            _ => mapping[gap_start - 1].map_or(0, |pos| pos + 1),
+        };
+        let new_end = match mapping.get(gap_end) {
+            Some(Some(pos)) => *pos,
+            _ => new.len(),
+        };
+        if new_end >= new_start
+            && new_end - new_start == gap_end - gap_start
+            && (new_start..new_end).all(|pos| !used[pos])
+        {
+            for (offset, new_pos) in (new_start..new_end).enumerate() {
+                mapping[gap_start + offset] = Some(new_pos);
+                used[new_pos] = true;
+            }
+        }
+    }
+
+    mapping.into_iter().map(|pos| pos.map(|pos| new[pos].block_id)).collect()
+}
+
+/// 读取书籍各章节的内容块指纹（重新解析前调用）
+///
+/// # 返回
+/// chapter_index → 按 block_index 排列的指纹
+pub fn collect_block_hashes(conn: &Connection, book_id: i32) -> Result<HashMap<i32, Vec<BlockHash>>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT c.chapter_index, b.id, b.block_index, b.content_hash
+             FROM blocks b JOIN chapters c ON c.id = b.chapter_id
+             WHERE c.book_id = ?1
+             ORDER BY c.chapter_index, b.block_index",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([book_id], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                BlockHash {
+                    block_id: row.get(1)?,
+                    block_index: row.get(2)?,
+                    content_hash: row.get(3)?,
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut by_chapter: HashMap<i32, Vec<BlockHash>> = HashMap::new();
+    for (chapter_index, hash) in rows {
+        by_chapter.entry(chapter_index).or_default().push(hash);
+    }
+    Ok(by_chapter)
+}
+
+/// 重新解析时把旧内容块映射到新内容块，每个旧章节只计算一次块映射
+struct BlockRemapper<'a> {
+    conn: &'a Connection,
+    book_id: i32,
+    old_blocks: &'a HashMap<i32, Vec<BlockHash>>,
+    mappings: HashMap<i32, HashMap<i32, Option<i32>>>,
+}
+
+impl<'a> BlockRemapper<'a> {
+    fn new(conn: &'a Connection, book_id: i32, old_blocks: &'a HashMap<i32, Vec<BlockHash>>) -> Self {
+        Self {
+            conn,
+            book_id,
+            old_blocks,
+            mappings: HashMap::new(),
+        }
+    }
+
+    /// 旧章节 `chapter_index` 中的内容块在新章节 `new_index` 中对应的块 ID，找不到时为 None
+    fn remap(&mut self, chapter_index: i32, new_index: usize, block_id: i32) -> Result<Option<i32>, String> {
+        let mapping = match self.mappings.entry(chapter_index) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let old = self.old_blocks.get(&chapter_index).map(Vec::as_slice).unwrap_or_default();
+                let new = match irp::get_chapter_by_index(self.conn, self.book_id, new_index as i32, None) {
+                    Ok(chapter) => irp::get_block_hashes(self.conn, chapter.id).map_err(|e| e.to_string())?,
+                    Err(_) => Vec::new(),
+                };
+                entry.insert(old.iter().map(|b| b.block_id).zip(reanchor_blocks(old, &new)).collect())
+            }
+        };
+        Ok(mapping.get(&block_id).copied().flatten())
+    }
+}
+
+/// 重新解析后迁移书籍高亮的章节和内容块
+///
+/// 章节按标题匹配，内容块按指纹匹配；无法定位的高亮保持不变
+///
+/// # 参数
+/// - `conn`: 数据库连接
+/// - `book_id`: 书籍 ID
+/// - `old_titles`: 重新解析前的章节标题（按 chapter_index 排列）
+/// - `new_titles`: 重新解析后的章节标题
+/// - `old_blocks`: 重新解析前的内容块指纹（见 `collect_block_hashes`）
+///
+/// # 返回
+/// 成功迁移的高亮数
+pub fn remap_highlights(
+    conn: &Connection,
+    book_id: i32,
+    old_titles: &[String],
+    new_titles: &[String],
+    old_blocks: &HashMap<i32, Vec<BlockHash>>,
+) -> Result<usize, String> {
+    let chapter_mapping = map_chapter_indices(old_titles, new_titles);
+
+    let mut stmt = conn
+        .prepare("SELECT id, chapter_index, block_id FROM highlights WHERE book_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let highlights = stmt
+        .query_map([book_id], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, Option<i32>>(2)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut blocks = BlockRemapper::new(conn, book_id, old_blocks);
+    let mut remapped = 0;
+    for (highlight_id, chapter_index, block_id) in highlights {
+        let Some(new_index) = usize::try_from(chapter_index)
+            .ok()
+            .and_then(|index| chapter_mapping.get(index).copied().flatten())
+        else {
+            continue;
+        };
+
+        let new_block_id = match block_id {
+            None => None,
+            Some(block_id) => match blocks.remap(chapter_index, new_index, block_id)? {
+                Some(new_block_id) => Some(new_block_id),
+                None => continue,
+            },
+        };
+
+        conn.execute(
+            "UPDATE highlights SET chapter_index = ?1, block_id = ?2 WHERE id = ?3",
+            rusqlite::params![new_index as i32, new_block_id, highlight_id],
+        )
+        .map_err(|e| format!("迁移高亮位置失败: {}", e))?;
+        remapped += 1;
+    }
+
+    Ok(remapped)
+}
+
+/// 重新解析后迁移书籍书签的章节和内容块
+///
+/// 章节按标题匹配，内容块按指纹匹配；内容块无法定位时书签退回到章节开头（block_id 置空），
+/// 章节无法匹配时保留章节序号，同样清除已失效的 block_id
+///
+/// # 参数
+/// 同 `remap_highlights`
+///
+/// # 返回
+/// 匹配到新章节的书签数
+pub fn remap_bookmarks(
+    conn: &Connection,
+    book_id: i32,
+    old_titles: &[String],
+    new_titles: &[String],
+    old_blocks: &HashMap<i32, Vec<BlockHash>>,
+) -> Result<usize, String> {
+    let chapter_mapping = map_chapter_indices(old_titles, new_titles);
+
+    let mut stmt = conn
+        .prepare("SELECT id, chapter_index, block_id FROM bookmarks WHERE book_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let bookmarks = stmt
+        .query_map([book_id], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, Option<i32>>(2)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut blocks = BlockRemapper::new(conn, book_id, old_blocks);
+    let mut remapped = 0;
+    for (bookmark_id, chapter_index, block_id) in bookmarks {
+        let new_index = usize::try_from(chapter_index)
+            .ok()
+            .and_then(|index| chapter_mapping.get(index).copied().flatten());
+        let matched = new_index.is_some();
+        let (new_index, new_block_id) = match (new_index, block_id) {
+            (Some(new_index), Some(block_id)) => (new_index as i32, blocks.remap(chapter_index, new_index, block_id)?),
+            (Some(new_index), None) => (new_index as i32, None),
+            (None, _) => (chapter_index, None),
+        };
+
+        conn.execute(
+            "UPDATE bookmarks SET chapter_index = ?1, block_id = ?2 WHERE id = ?3",
+            rusqlite::params![new_index, new_block_id, bookmark_id],
+        )
+        .map_err(|e| format!("迁移书签位置失败: {}", e))?;
+        if matched {
+            remapped += 1;
+        }
+    }
+
+    Ok(remapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use tempfile::TempDir;
+
+    fn titles(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("第一章", "第一节"), 1);
+    }
+
+    #[test]
+    fn test_map_reordered_chapters() {
+        let old = titles(&["序言", "第一章 开端", "第二章 发展", "第三章 结局"]);
+        let new = titles(&["第二章 发展", "第一章 开端", "第三章  结局", "后记"]);
+
+        let mapping = map_chapter_indices(&old, &new);
+        assert_eq!(mapping, vec![None, Some(1), Some(0), Some(2)]);
+    }
+
+    fn hashes(items: &[(i32, &str)]) -> Vec<BlockHash> {
+        items
+            .iter()
+            .enumerate()
+            .map(|(index, (block_id, hash))| BlockHash {
+                block_id: *block_id,
+                block_index: index as i32,
+                content_hash: Some(hash.to_string()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reanchor_blocks() {
+        // 插入了新段落 x，段落 c 被改写为 c2，段落 e 被删除
+        let old = hashes(&[(1, "a"), (2, "b"), (3, "c"), (4, "d"), (5, "e")]);
+        let new = hashes(&[(11, "x"), (12, "a"), (13, "b"), (14, "c2"), (15, "d")]);
+        assert_eq!(
+            reanchor_blocks(&old, &new),
+            vec![Some(12), Some(13), Some(14), Some(15), None]
+        );
+
+        // 重复内容对应到位置最近的块
+        let old = hashes(&[(1, "sep"), (2, "a"), (3, "sep")]);
+        let new = hashes(&[(11, "sep"), (12, "a"), (13, "sep")]);
+        assert_eq!(reanchor_blocks(&old, &new), vec![Some(11), Some(12), Some(13)]);
+    }
+
+    #[test]
+    fn test_highlights_follow_unchanged_blocks() {
+        use crate::async_import::clear_book_content;
+        use crate::highlights::{self, NewHighlight};
+        use crate::irp::TextRun;
+
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('书', '/test/reanchor')", [])
+            .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+        let runs = |text: &str| vec![TextRun { text: text.to_string(), marks: vec![], attributes: None }];
+
+        let chapter_id = irp::create_chapter(&conn, book_id, "第一章", 0, "explicit").unwrap() as i32;
+        irp::create_block(&conn, chapter_id, 0, "paragraph", &runs("开头"), None).unwrap();
+        let block_id = irp::create_block(&conn, chapter_id, 1, "paragraph", &runs("被高亮的段落"), None)
+            .unwrap() as i32;
+        let highlight_id = highlights::create_highlight(&conn, &NewHighlight {
+            book_id,
+            chapter_index: 0,
+            block_id: Some(block_id),
+            start_offset: 0,
+            end_offset: 3,
+            color: "#ffeb3b".to_string(),
+            note_id: None,
+        })
+        .unwrap() as i32;
+
+        // 重新解析：前面多了一章，本章开头多了一段，被高亮的段落内容不变
+        let old_blocks = collect_block_hashes(&conn, book_id).unwrap();
+        let old_titles = clear_book_content(&conn, book_id).unwrap();
+        irp::create_chapter(&conn, book_id, "前言", 0, "explicit").unwrap();
+        let chapter_id = irp::create_chapter(&conn, book_id, "第一章", 1, "explicit").unwrap() as i32;
+        irp::create_block(&conn, chapter_id, 0, "paragraph", &runs("新增段落"), None).unwrap();
+        irp::create_block(&conn, chapter_id, 1, "paragraph", &runs("开头"), None).unwrap();
+        let new_block_id = irp::create_block(&conn, chapter_id, 2, "paragraph", &runs("被高亮的段落"), None)
+            .unwrap() as i32;
+
+        let new_hashes = irp::get_block_hashes(&conn, chapter_id).unwrap();
+        assert_eq!(old_blocks[&0][1].content_hash, new_hashes[2].content_hash);
+
+        let remapped = remap_highlights(&conn, book_id, &old_titles, &titles(&["前言", "第一章"]), &old_blocks)
+            .unwrap();
+        assert_eq!(remapped, 1);
+        let highlight = highlights::get_highlight_by_id(&conn, highlight_id).unwrap();
+        assert_eq!((highlight.chapter_index, highlight.block_id), (1, Some(new_block_id)));
+        assert_eq!((highlight.start_offset, highlight.end_offset), (0, 3));
+    }
+
+    #[test]
+    fn test_bookmarks_follow_blocks_or_fall_back_to_chapter() {
+        use crate::async_import::clear_book_content;
+        use crate::bookmarks;
+        use crate::irp::TextRun;
+
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('书', '/test/bookmarks')", [])
+            .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+        let runs = |text: &str| vec![TextRun { text: text.to_string(), marks: vec![], attributes: None }];
+
+        let chapter_id = irp::create_chapter(&conn, book_id, "第一章", 0, "explicit").unwrap() as i32;
+        let kept_block = irp::create_block(&conn, chapter_id, 0, "paragraph", &runs("保留的段落"), None)
+            .unwrap() as i32;
+        let removed_block = irp::create_block(&conn, chapter_id, 1, "paragraph", &runs("删除的段落"), None)
+            .unwrap() as i32;
+        let kept = bookmarks::add_bookmark(&conn, book_id, 0, Some(kept_block), None).unwrap() as i32;
+        let removed = bookmarks::add_bookmark(&conn, book_id, 0, Some(removed_block), None).unwrap() as i32;
+
+        // 重新解析：前面多了一章，第二段被删除
+        let old_blocks = collect_block_hashes(&conn, book_id).unwrap();
+        let old_titles = clear_book_content(&conn, book_id).unwrap();
+        irp::create_chapter(&conn, book_id, "前言", 0, "explicit").unwrap();
+        let chapter_id = irp::create_chapter(&conn, book_id, "第一章", 1, "explicit").unwrap() as i32;
+        let new_block = irp::create_block(&conn, chapter_id, 0, "paragraph", &runs("保留的段落"), None)
+            .unwrap() as i32;
+
+        let remapped = remap_bookmarks(&conn, book_id, &old_titles, &titles(&["前言", "第一章"]), &old_blocks)
+            .unwrap();
+        assert_eq!(remapped, 2);
+        let kept = bookmarks::get_bookmark_by_id(&conn, kept).unwrap();
+        assert_eq!((kept.chapter_index, kept.block_id), (1, Some(new_block)));
+        // 内容块找不到时退回到章节，不保留失效的 block_id
+        let removed = bookmarks::get_bookmark_by_id(&conn, removed).unwrap();
+        assert_eq!((removed.chapter_index, removed.block_id), (1, None));
+    }
+
+    #[test]
+    fn test_notes_follow_titles() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('书', '/test/remap')", [])
+            .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        // 旧结构：0 序言，1 Chapter One，2 Chapter Two
+        for (title, chapter_index) in [("序言笔记", 0), ("第一章笔记", 1), ("第二章笔记", 2)] {
+            conn.execute(
+                "INSERT INTO notes (title, book_id, chapter_index) VALUES (?1, ?2, ?3)",
+                rusqlite::params![title, book_id, chapter_index],
+            )
+            .unwrap();
+        }
+
+        let old = titles(&["序言", "Chapter One", "Chapter Two"]);
+        let new = titles(&["Chapter Two", "Interlude", "chapter one"]);
+        let summary = remap_notes(&conn, book_id, &old, &new).unwrap();
+        assert_eq!(
+            summary,
+            RemapSummary { moved: 2, unchanged: 0, needs_review: 1 }
+        );
+
+        let note = |title: &str| -> (i32, bool) {
+            conn.query_row(
+                "SELECT chapter_index, needs_review FROM notes WHERE title = ?1",
+                [title],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+        };
+        assert_eq!(note("第一章笔记"), (2, false));
+        assert_eq!(note("第二章笔记"), (0, false));
+        // 无法匹配的笔记保留原位置并标记待确认
+        assert_eq!(note("序言笔记"), (0, true));
+    }
+}
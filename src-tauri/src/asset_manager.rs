@@ -2,7 +2,18 @@ use rusqlite::{Connection, Result};
 use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::{Path, PathBuf};
-use tauri::{AppHandle, Manager};
+use tauri::AppHandle;
+
+/// 资产存储
+///
+/// 解析器通过它保存从书中提取的图片、字体，不直接依赖 AppHandle，便于无界面测试
+pub trait AssetSink: Send + Sync {
+    /// 保存资产
+    ///
+    /// # 返回
+    /// 相对路径（格式：assets/{book_id}/{hash}.{ext}）
+    fn store(&self, book_id: i32, data: &[u8], original_path: &str) -> Result<String, String>;
+}
 
 /// 资产管理器
 /// 负责提取、存储和管理书籍资产（主要是图片）
@@ -30,53 +41,20 @@ impl AssetManager {
         image_data: &[u8],
         original_path: &str,
     ) -> Result<String, String> {
-        // 1. 生成唯一文件名 (SHA256 hash + 扩展名)
-        let mut hasher = Sha256::new();
-        hasher.update(image_data);
-        let hash = format!("{:x}", hasher.finalize());
-
-        let ext = Path::new(original_path)
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("png");
-
-        let filename = format!("{}.{}", &hash[..16], ext);
-
-        // 2. 保存到 app_data_dir/assets/{book_id}/
-        let app_data_dir = self
-            .app_handle
-            .path()
-            .app_data_dir()
-            .map_err(|e| e.to_string())?;
-        let asset_dir = app_data_dir.join("assets").join(book_id.to_string());
-        fs::create_dir_all(&asset_dir).map_err(|e| e.to_string())?;
-
-        let file_path = asset_dir.join(&filename);
-        fs::write(&file_path, image_data).map_err(|e| e.to_string())?;
-
-        // 3. 返回相对路径
-        let relative_path = format!("assets/{}/{}", book_id, filename);
-        Ok(relative_path)
+        let root_dir = crate::get_library_root(&self.app_handle)?;
+        store_asset(&root_dir, book_id, image_data, original_path)
     }
 
     /// 获取资产的完整路径
     pub fn get_asset_full_path(&self, relative_path: &str) -> Result<PathBuf, String> {
-        let app_data_dir = self
-            .app_handle
-            .path()
-            .app_data_dir()
-            .map_err(|e| e.to_string())?;
-        Ok(app_data_dir.join(relative_path))
+        let root_dir = crate::get_library_root(&self.app_handle)?;
+        Ok(root_dir.join(relative_path))
     }
 
     /// 清理书籍的所有资产
     pub fn cleanup_book_assets(&self, book_id: i32) -> Result<(), String> {
-        let app_data_dir = self
-            .app_handle
-            .path()
-            .app_data_dir()
-            .map_err(|e| e.to_string())?;
-        let asset_dir = app_data_dir.join("assets").join(book_id.to_string());
+        let root_dir = crate::get_library_root(&self.app_handle)?;
+        let asset_dir = root_dir.join("assets").join(book_id.to_string());
 
         if asset_dir.exists() {
             fs::remove_dir_all(&asset_dir).map_err(|e| e.to_string())?;
@@ -87,39 +65,132 @@ impl AssetManager {
 
     /// 清理孤立的资产（没有对应书籍的资产）
     pub fn cleanup_orphaned_assets(&self, conn: &Connection) -> Result<u32, String> {
-        // 获取所有有效的 book_id
-        let mut stmt = conn
-            .prepare("SELECT id FROM books")
-            .map_err(|e| e.to_string())?;
-        let valid_book_ids: Vec<i32> = stmt
-            .query_map([], |row| row.get(0))
-            .map_err(|e| e.to_string())?
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| e.to_string())?;
-
-        // 扫描 assets 目录
-        let app_data_dir = self
-            .app_handle
-            .path()
-            .app_data_dir()
-            .map_err(|e| e.to_string())?;
-        let assets_dir = app_data_dir.join("assets");
+        let root_dir = crate::get_library_root(&self.app_handle)?;
 
         let mut cleaned_count = 0;
+        for dir in orphaned_asset_dirs(conn, &root_dir)? {
+            fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+            cleaned_count += 1;
+        }
+
+        Ok(cleaned_count)
+    }
+}
 
-        if assets_dir.exists() {
-            for entry in fs::read_dir(&assets_dir).map_err(|e| e.to_string())? {
-                let entry = entry.map_err(|e| e.to_string())?;
-                if let Ok(book_id) = entry.file_name().to_string_lossy().parse::<i32>() {
-                    if !valid_book_ids.contains(&book_id) {
-                        fs::remove_dir_all(entry.path()).map_err(|e| e.to_string())?;
-                        cleaned_count += 1;
-                    }
+/// 扫描 {root_dir}/assets，找出没有对应书籍的资产目录
+pub fn orphaned_asset_dirs(conn: &Connection, root_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    // 获取所有有效的 book_id
+    let mut stmt = conn
+        .prepare("SELECT id FROM books")
+        .map_err(|e| e.to_string())?;
+    let valid_book_ids: Vec<i32> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let assets_dir = root_dir.join("assets");
+    let mut orphaned = Vec::new();
+
+    if assets_dir.exists() {
+        for entry in fs::read_dir(&assets_dir).map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            if let Ok(book_id) = entry.file_name().to_string_lossy().parse::<i32>() {
+                if !valid_book_ids.contains(&book_id) {
+                    orphaned.push(entry.path());
                 }
             }
         }
+    }
 
-        Ok(cleaned_count)
+    orphaned.sort();
+    Ok(orphaned)
+}
+
+/// 通过 AppHandle 找到书库目录并保存资产
+impl AssetSink for AssetManager {
+    fn store(&self, book_id: i32, data: &[u8], original_path: &str) -> Result<String, String> {
+        self.extract_image(book_id, data, original_path)
+    }
+}
+
+/// 以该路径为书库目录保存资产
+impl AssetSink for PathBuf {
+    fn store(&self, book_id: i32, data: &[u8], original_path: &str) -> Result<String, String> {
+        store_asset(self, book_id, data, original_path)
+    }
+}
+
+/// 内存中的资产存储（测试用）
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryAssetSink {
+    /// 相对路径 -> 资产数据
+    pub assets: std::sync::Mutex<std::collections::HashMap<String, Vec<u8>>>,
+}
+
+#[cfg(test)]
+impl AssetSink for MemoryAssetSink {
+    fn store(&self, book_id: i32, data: &[u8], original_path: &str) -> Result<String, String> {
+        let local_path = format!("assets/{}/{}", book_id, asset_file_name(data, original_path));
+        self.assets.lock().unwrap().insert(local_path.clone(), data.to_vec());
+        Ok(local_path)
+    }
+}
+
+/// 资产文件名：内容 SHA256 的前 16 位 + 原扩展名（没有扩展名时为 png）
+fn asset_file_name(data: &[u8], original_path: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    let hash = format!("{:x}", hasher.finalize());
+
+    let ext = Path::new(original_path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("png");
+
+    format!("{}.{}", &hash[..16], ext)
+}
+
+/// 保存资产文件到 {root_dir}/assets/{book_id}/
+///
+/// 文件名取内容的 SHA256 哈希，相同内容只保存一份
+///
+/// # 参数
+/// - `root_dir`: 书库目录
+/// - `book_id`: 书籍 ID
+/// - `data`: 资产二进制数据
+/// - `original_path`: 原始路径（用于提取扩展名）
+///
+/// # 返回
+/// 相对路径（格式：assets/{book_id}/{hash}.{ext}）
+pub fn store_asset(root_dir: &Path, book_id: i32, data: &[u8], original_path: &str) -> Result<String, String> {
+    // 1. 生成唯一文件名 (SHA256 hash + 扩展名)
+    let filename = asset_file_name(data, original_path);
+
+    // 2. 保存到书库目录下（已存在相同内容时跳过）
+    let asset_dir = root_dir.join("assets").join(book_id.to_string());
+    fs::create_dir_all(&asset_dir).map_err(|e| e.to_string())?;
+
+    let file_path = asset_dir.join(&filename);
+    if !file_path.exists() {
+        fs::write(&file_path, data).map_err(|e| e.to_string())?;
+    }
+
+    // 3. 返回相对路径
+    Ok(format!("assets/{}/{}", book_id, filename))
+}
+
+/// 根据扩展名判断资产类型（"font" 或 "image"）
+pub fn asset_type_for_path(path: &str) -> &'static str {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "ttf" | "otf" | "woff" | "woff2" | "eot" => "font",
+        _ => "image",
     }
 }
 
@@ -174,6 +245,99 @@ pub fn get_book_assets(conn: &Connection, book_id: i32) -> Result<Vec<(String, S
     Ok(assets)
 }
 
+/// 将本地文件路径转换为 webview 可加载的 asset 协议 URL
+///
+/// 与前端 `convertFileSrc` 的格式一致：Windows 使用 `http://asset.localhost/`，
+/// 其他平台使用 `asset://localhost/`，路径按 encodeURIComponent 规则编码
+pub fn asset_protocol_url(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9'
+            | b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    if cfg!(windows) {
+        format!("http://asset.localhost/{}", encoded)
+    } else {
+        format!("asset://localhost/{}", encoded)
+    }
+}
+
+/// 查找 HTML 中引用的资源对应的本地相对路径
+///
+/// 引用路径通常是相对章节文件的（如 `../images/a.png`），按路径后缀匹配映射中的原始路径
+pub fn find_local_path<'a>(assets: &'a [(String, String)], reference: &str) -> Option<&'a str> {
+    let reference = reference
+        .trim_start_matches("./")
+        .trim_start_matches("../")
+        .trim_start_matches('/');
+    if reference.is_empty() {
+        return None;
+    }
+    assets
+        .iter()
+        .find(|(original, _)| original == reference || original.ends_with(&format!("/{}", reference)))
+        .map(|(_, local_path)| local_path.as_str())
+}
+
+/// 将 HTML 中引用 EPUB 内部资源的 src/href 替换为本地资产 URL
+///
+/// HTML 中的路径通常是相对章节文件的（如 `../images/a.png`），
+/// 而映射中保存的是 EPUB 内的完整路径（如 `OEBPS/images/a.png`），因此按路径后缀匹配
+///
+/// # 参数
+/// - `html`: 章节 HTML
+/// - `assets`: `get_book_assets` 返回的 (原始路径, 本地相对路径) 列表
+/// - `app_data_dir`: 应用数据目录（本地相对路径的根目录）
+pub fn rewrite_asset_urls(html: &str, assets: &[(String, String)], app_data_dir: &Path) -> String {
+    if assets.is_empty() {
+        return html.to_string();
+    }
+
+    let pattern = regex::Regex::new(r#"(?i)\b(src|href|xlink:href)\s*=\s*"([^"]*)""#).unwrap();
+    pattern
+        .replace_all(html, |caps: &regex::Captures| {
+            let attr = &caps[1];
+            match find_local_path(assets, &caps[2]) {
+                Some(local_path) => {
+                    format!("{}=\"{}\"", attr, asset_protocol_url(&app_data_dir.join(local_path)))
+                }
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// 将样式表中 `url("...")` 引用的 EPUB 内资源替换为本地资产 URL
+///
+/// 保存的样式表已把引用解析为 EPUB 内的完整路径，因此按完整路径精确匹配
+///
+/// # 参数
+/// - `css`: 书籍样式表
+/// - `assets`: `get_book_assets` 返回的 (原始路径, 本地相对路径) 列表
+/// - `root_dir`: 本地相对路径的根目录
+pub fn rewrite_css_urls(css: &str, assets: &[(String, String)], root_dir: &Path) -> String {
+    if assets.is_empty() {
+        return css.to_string();
+    }
+
+    let pattern = regex::Regex::new(r#"url\("([^"]*)"\)"#).unwrap();
+    pattern
+        .replace_all(css, |caps: &regex::Captures| {
+            match assets.iter().find(|(original, _)| original == &caps[1]) {
+                Some((_, local_path)) => format!("url(\"{}\")", asset_protocol_url(&root_dir.join(local_path))),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -281,4 +445,44 @@ mod tests {
 
         assert_ne!(hash1, hash3);
     }
+
+    #[test]
+    fn test_rewrite_asset_urls() {
+        let assets = vec![(
+            "OEBPS/images/插图 1.png".to_string(),
+            "assets/1/abcd.png".to_string(),
+        )];
+        let html = r#"<p><img src="../images/插图 1.png" alt="图"/><a href="ch2.xhtml">下一章</a></p>"#;
+
+        let rewritten = rewrite_asset_urls(html, &assets, Path::new("/data"));
+        let expected = asset_protocol_url(Path::new("/data/assets/1/abcd.png"));
+        assert!(rewritten.contains(&format!("src=\"{}\"", expected)));
+        // 未映射的链接保持不变
+        assert!(rewritten.contains(r#"href="ch2.xhtml""#));
+        assert!(!expected.contains(' '));
+    }
+
+    #[test]
+    fn test_asset_protocol_url_shape() {
+        let url = asset_protocol_url(Path::new("/data/assets/1/插图 1.png"));
+        let prefix = if cfg!(windows) { "http://asset.localhost/" } else { "asset://localhost/" };
+        assert_eq!(url.strip_prefix(prefix), Some("%2Fdata%2Fassets%2F1%2F%E6%8F%92%E5%9B%BE%201.png"));
+
+        // asset 协议已启用，且只放行资产目录
+        let config: serde_json::Value = serde_json::from_str(include_str!("../tauri.conf.json")).unwrap();
+        let asset_protocol = &config["app"]["security"]["assetProtocol"];
+        assert_eq!(asset_protocol["enable"], true);
+        assert_eq!(asset_protocol["scope"], serde_json::json!(["$APPDATA/assets/**"]));
+    }
+
+    #[test]
+    fn test_rewrite_css_urls() {
+        let assets = vec![("OEBPS/images/bg.png".to_string(), "assets/1/abcd.png".to_string())];
+        let css = r#".reader-content p { background: url("OEBPS/images/bg.png") } .a { background: url("OEBPS/x.png") }"#;
+
+        let rewritten = rewrite_css_urls(css, &assets, Path::new("/data"));
+        let expected = asset_protocol_url(Path::new("/data/assets/1/abcd.png"));
+        assert!(rewritten.contains(&format!("url(\"{}\")", expected)));
+        assert!(rewritten.contains(r#"url("OEBPS/x.png")"#));
+    }
 }
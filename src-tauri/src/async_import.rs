@@ -3,11 +3,23 @@
 /// 处理书籍的异步导入流程，包括解析、资产提取和索引构建
 
 use tauri::{AppHandle, Emitter, Manager};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use crate::import_queue::{ImportQueue, ImportTask, ImportStatus};
-use crate::parser::ParserRouter;
+use crate::parser::epub_cover;
+use crate::parser::txt_parser::TxtParser;
+use crate::parser::{ParseResult, ParserRouter};
 use crate::db;
 use crate::irp;
+use crate::encryption;
+use crate::error::AppError;
+use rusqlite::OptionalExtension;
+use crate::annotation_remap;
+use crate::book_metadata;
+use crate::book_stats;
+use crate::highlight;
+use crate::reading_unit;
+use crate::asset_manager::{AssetManager, AssetSink};
+use std::sync::Arc;
 use chrono::Utc;
 use epub::doc::EpubDoc;
 use base64::{Engine as _, engine::general_purpose};
@@ -19,10 +31,19 @@ use base64::{Engine as _, engine::general_purpose};
 /// # 参数
 /// - `app`: Tauri 应用句柄
 /// - `file_path`: 文件路径
+/// - `encrypted`: 是否加密存储章节内容
+/// - `priority`: 队列优先级，越大越先处理（默认 0）
+/// - `encoding`: 用户指定的 TXT 编码（如 "big5"），为 None 时自动检测
 ///
 /// # 返回
 /// 书籍 ID
-pub async fn import_book_async(app: AppHandle, file_path: String) -> Result<i32, String> {
+pub async fn import_book_async(
+    app: AppHandle,
+    file_path: String,
+    encrypted: bool,
+    priority: i32,
+    encoding: Option<String>,
+) -> Result<i32, String> {
     let path = PathBuf::from(&file_path);
 
     // 检查文件是否存在
@@ -39,32 +60,8 @@ pub async fn import_book_async(app: AppHandle, file_path: String) -> Result<i32,
     if !router.supports(ext) {
         return Err("不支持的文件格式".to_string());
     }
-
-    // 对于 PDF 文件，提前检查是否为扫描版
-    if ext == "pdf" {
-        use std::fs;
-        let bytes = fs::read(&path)
-            .map_err(|e| format!("读取文件失败: {}", e))?;
-
-        // 尝试提取文本
-        match pdf_extract::extract_text_from_mem(&bytes) {
-            Ok(text) => {
-                // 检查文本内容是否足够（至少100个字符）
-                let text_len = text.trim().chars().count();
-                if text_len < 100 {
-                    return Err(format!(
-                        "此 PDF 文件无法提取有效文本内容（仅提取到 {} 个字符）。\n\n可能原因：\n1. 这是扫描版 PDF（图片格式），需要 OCR 识别\n2. PDF 文件已加密或受保护\n3. PDF 格式不标准\n\n建议：\n- 使用文字版 PDF\n- 或使用 OCR 工具转换后再导入",
-                        text_len
-                    ));
-                }
-            }
-            Err(e) => {
-                return Err(format!(
-                    "PDF 解析失败: {}。\n\n可能原因：\n1. 这是扫描版 PDF（图片格式），需要 OCR 识别\n2. PDF 文件已加密或受保护\n3. PDF 格式损坏或不标准\n\n建议：\n- 使用文字版 PDF\n- 或使用 OCR 工具转换后再导入",
-                    e
-                ));
-            }
-        }
+    if let Some(label) = &encoding {
+        TxtParser::encoding_for_label(label)?;
     }
 
     // 提取文件名作为临时标题
@@ -74,55 +71,198 @@ pub async fn import_book_async(app: AppHandle, file_path: String) -> Result<i32,
         .unwrap_or("未知书籍");
 
     // 创建书籍记录（状态为 pending）
-    let db_path = crate::get_db_path(&app);
-    let conn = db::init_db(&db_path).map_err(|e| e.to_string())?;
+    let book_id = crate::with_conn(&app, |conn| {
+        create_pending_book(conn, filename, &file_path, encrypted, encoding.as_deref()).map_err(AppError::Db)
+    })?;
+
+    enqueue_import(&app, book_id, path, priority)?;
+
+    Ok(book_id)
+}
 
+/// 创建待解析的书籍记录
+///
+/// # 参数
+/// - `title`: 临时标题（解析完成后由元数据替换）
+/// - `file_path`: 源文件路径
+/// - `encrypted`: 是否加密存储章节内容
+/// - `encoding`: 用户指定的 TXT 编码，为 None 时自动检测
+pub fn create_pending_book(
+    conn: &rusqlite::Connection,
+    title: &str,
+    file_path: &str,
+    encrypted: bool,
+    encoding: Option<&str>,
+) -> Result<i32, String> {
     conn.execute(
-        "INSERT INTO books (title, author, file_path, parse_status) VALUES (?1, ?2, ?3, ?4)",
-        rusqlite::params![filename, "未知作者", &file_path, "pending"],
+        "INSERT INTO books (title, author, file_path, parse_status, is_encrypted, text_encoding)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![title, "未知作者", file_path, "pending", encrypted, encoding],
     ).map_err(|e| e.to_string())?;
 
-    let book_id = conn.last_insert_rowid() as i32;
+    Ok(conn.last_insert_rowid() as i32)
+}
+
+/// 批量导入中单个文件的错误
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct ImportFileError {
+    pub path: String,
+    pub error: String,
+}
+
+/// 批量导入结果
+#[derive(serde::Serialize, Debug, Default)]
+pub struct ImportFilesResult {
+    /// 成功加入导入队列的书籍 ID（与受支持文件的顺序一致）
+    pub book_ids: Vec<i32>,
+    /// 不支持、不存在或加入队列失败的文件
+    pub errors: Vec<ImportFileError>,
+}
+
+/// 校验待导入的文件
+///
+/// # 返回
+/// (受支持的文件路径, 不存在或格式不支持的文件错误)，均保持输入顺序
+pub fn validate_import_paths(paths: &[String]) -> (Vec<String>, Vec<ImportFileError>) {
+    let router = ParserRouter::new();
+    let mut supported = Vec::new();
+    let mut errors = Vec::new();
+
+    for path in paths {
+        let file_path = PathBuf::from(path);
+        let ext = file_path.extension().and_then(|s| s.to_str()).unwrap_or("");
+        let error = if !router.supports(ext) {
+            Some(format!("不支持的文件格式: {}", if ext.is_empty() { "无扩展名" } else { ext }))
+        } else if !file_path.is_file() {
+            Some("文件不存在".to_string())
+        } else {
+            None
+        };
+
+        match error {
+            Some(error) => errors.push(ImportFileError { path: path.clone(), error }),
+            None => supported.push(path.clone()),
+        }
+    }
+
+    (supported, errors)
+}
+
+/// 批量导入文件（拖放或多选）
+///
+/// 受支持的文件逐个加入导入队列，单个文件失败不影响其他文件；`encoding` 应用于其中的 TXT 文件
+pub async fn import_files_async(app: AppHandle, paths: Vec<String>, encoding: Option<String>) -> ImportFilesResult {
+    let (supported, errors) = validate_import_paths(&paths);
+    let mut result = ImportFilesResult { book_ids: Vec::new(), errors };
+
+    for path in supported {
+        match import_book_async(app.clone(), path.clone(), false, 0, encoding.clone()).await {
+            Ok(book_id) => result.book_ids.push(book_id),
+            Err(error) => result.errors.push(ImportFileError { path, error }),
+        }
+    }
+
+    result
+}
+
+/// 重新解析书籍
+///
+/// 将书籍重新加入导入队列，处理时替换原有章节，并按章节标题迁移已有笔记
+///
+/// # 参数
+/// - `app`: Tauri 应用句柄
+/// - `book_id`: 书籍 ID
+pub async fn reparse_book_async(app: AppHandle, book_id: i32) -> Result<(), AppError> {
+    let file_path: String = crate::with_conn(&app, |conn| {
+        conn.query_row("SELECT file_path FROM books WHERE id = ?1", [book_id], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| AppError::NotFound("找不到书籍".to_string()))
+    })?;
+
+    let path = PathBuf::from(&file_path);
+    if !path.exists() {
+        return Err(AppError::Io("文件不存在".to_string()));
+    }
+
+    crate::with_conn(&app, |conn| {
+        conn.execute(
+            "UPDATE books SET parse_status = ?1 WHERE id = ?2",
+            rusqlite::params!["pending", book_id],
+        )?;
+        Ok(())
+    })?;
 
-    // 加入导入队列
+    // 重新解析由用户针对单本书发起，插到同优先级任务之前
     let queue = app.state::<ImportQueue>();
-    queue.enqueue(ImportTask {
+    queue.enqueue_front(new_task(book_id, path, 0)).map_err(AppError::Parse)?;
+    emit_queue_positions(&app);
+    spawn_queue_processor(&app);
+
+    Ok(())
+}
+
+/// 加入导入队列并启动后台处理
+fn enqueue_import(app: &AppHandle, book_id: i32, path: PathBuf, priority: i32) -> Result<(), String> {
+    let queue = app.state::<ImportQueue>();
+    queue.enqueue(new_task(book_id, path, priority))?;
+    emit_queue_positions(app);
+    spawn_queue_processor(app);
+
+    Ok(())
+}
+
+fn new_task(book_id: i32, path: PathBuf, priority: i32) -> ImportTask {
+    ImportTask {
         book_id,
-        file_path: path.clone(),
+        file_path: path,
         status: ImportStatus::Pending,
         progress: 0.0,
         created_at: Utc::now(),
-    })?;
+        priority,
+    }
+}
+
+/// 向前端发送所有排队任务的当前位置
+///
+/// 事件 `import-queued`：`{book_id, position, queue_length}`，position 从 1 开始
+fn emit_queue_positions(app: &AppHandle) {
+    let queue = app.state::<ImportQueue>();
+    let positions = queue.positions();
+    let queue_length = positions.len();
+    for (book_id, position) in positions {
+        let _ = app.emit("import-queued", serde_json::json!({
+            "book_id": book_id,
+            "position": position,
+            "queue_length": queue_length
+        }));
+    }
+}
+
+/// 启动后台处理（如果还没有运行）
+fn spawn_queue_processor(app: &AppHandle) {
+    if !app.state::<ImportQueue>().try_start_processor() {
+        return;
+    }
 
-    // 启动后台处理（如果还没有运行）
     let app_clone = app.clone();
     tokio::spawn(async move {
-        process_import_queue(app_clone).await;
+        process_import_queue(app_clone.clone()).await;
+        app_clone.state::<ImportQueue>().processor_stopped();
     });
-
-    Ok(book_id)
 }
 
 /// 处理导入队列
 ///
-/// 从队列中取出任务并处理
+/// 常驻循环：从队列中取出任务并处理，空闲时挂起等待新任务唤醒，应用退出时结束。
+/// 由 `spawn_queue_processor` 保证同一时间只有一个循环在运行
 async fn process_import_queue(app: AppHandle) {
     let queue = app.state::<ImportQueue>();
 
     loop {
-        // 从队列中取出任务
-        let task = match queue.dequeue() {
+        // 等待并取出任务（空闲时挂起，入队或任务完成时被唤醒）
+        let task = match queue.next_task().await {
             Ok(Some(t)) => t,
-            Ok(None) => {
-                // 队列为空或已达并发上限，等待一段时间后重试
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-
-                // 如果队列为空且没有活动任务，退出循环
-                if queue.queue_size() == 0 && queue.active_count() == 0 {
-                    break;
-                }
-                continue;
-            }
+            Ok(None) => break,
             Err(e) => {
                 eprintln!("获取任务失败: {}", e);
                 break;
@@ -135,6 +275,9 @@ async fn process_import_queue(app: AppHandle) {
             continue;
         }
 
+        // 队列前进，更新其余任务的位置
+        emit_queue_positions(&app);
+
         // 处理任务
         let app_clone = app.clone();
         let task_clone = task.clone();
@@ -143,13 +286,9 @@ async fn process_import_queue(app: AppHandle) {
                 eprintln!("导入任务失败 (book_id: {}): {}", task_clone.book_id, e);
 
                 // 更新状态为失败
-                let db_path = crate::get_db_path(&app_clone);
-                if let Ok(conn) = db::init_db(&db_path) {
-                    let _ = conn.execute(
-                        "UPDATE books SET parse_status = ?1 WHERE id = ?2",
-                        rusqlite::params![format!("failed: {}", e), task_clone.book_id],
-                    );
-                }
+                let _ = crate::with_conn(&app_clone, |conn| {
+                    mark_import_failed(conn, task_clone.book_id, &e).map_err(AppError::Db)
+                });
 
                 // 发送错误事件
                 let _ = app_clone.emit("import-error", serde_json::json!({
@@ -163,12 +302,21 @@ async fn process_import_queue(app: AppHandle) {
             let _ = queue.mark_completed(task_clone.book_id);
         });
     }
+
+    if queue.queue_size() > 0 || queue.active_count() > 0 {
+        eprintln!(
+            "导入队列已停止：{} 个任务未开始，{} 个任务未完成",
+            queue.queue_size(),
+            queue.active_count()
+        );
+    }
 }
 
 /// 处理单个导入任务
 async fn process_single_import(app: AppHandle, task: ImportTask) -> Result<(), String> {
+    // 解析耗时较长，使用独立连接，避免长时间占用共享连接
     let db_path = crate::get_db_path(&app);
-    let conn = db::init_db(&db_path).map_err(|e| e.to_string())?;
+    let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
 
     // 更新状态为 Parsing
     conn.execute(
@@ -183,68 +331,114 @@ async fn process_single_import(app: AppHandle, task: ImportTask) -> Result<(), S
         "progress": 0.1
     })).map_err(|e| e.to_string())?;
 
-    // 路由到对应的 Parser
-    let router = ParserRouter::new();
-    let parser = router.route(&task.file_path)?;
+    // 加密书籍使用应用密钥加密章节内容
+    let encrypted: bool = conn.query_row(
+        "SELECT COALESCE(is_encrypted, 0) FROM books WHERE id = ?1",
+        [task.book_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    let key = if encrypted {
+        Some(crate::get_encryption_key(&app)?)
+    } else {
+        None
+    };
 
-    // 解析文件
-    let result = parser.parse(&task.file_path, task.book_id, &conn)?;
+    let assets: Arc<dyn AssetSink> = Arc::new(AssetManager::new(app.clone()));
+    let remap_summary = import_file_into_db(&conn, task.book_id, &task.file_path, key.as_deref(), Some(assets), || {
+        let _ = app.emit("import-progress", serde_json::json!({
+            "book_id": task.book_id,
+            "status": "saving",
+            "progress": 0.5
+        }));
+    })?;
 
-    // 更新进度
+    if let Some(summary) = remap_summary {
+        let _ = app.emit("notes-remapped", serde_json::json!({
+            "book_id": task.book_id,
+            "moved": summary.moved,
+            "unchanged": summary.unchanged,
+            "needs_review": summary.needs_review
+        }));
+    }
+
+    // 发送完成事件
     app.emit("import-progress", serde_json::json!({
         "book_id": task.book_id,
-        "status": "saving",
-        "progress": 0.5
+        "status": "completed",
+        "progress": 1.0
     })).map_err(|e| e.to_string())?;
 
-    // 保存章节和块到数据库
-    for (chapter_index, chapter) in result.chapters.iter().enumerate() {
-        // 调试日志
-        eprintln!("[DEBUG] Saving chapter {}: title='{}', render_mode='{}', has_raw_html={}, raw_html_len={}",
-            chapter_index,
-            chapter.title,
-            chapter.render_mode,
-            chapter.raw_html.is_some(),
-            chapter.raw_html.as_ref().map(|h| h.len()).unwrap_or(0)
-        );
+    Ok(())
+}
 
-        let chapter_id = irp::create_chapter_with_html_and_level(
-            &conn,
-            task.book_id,
-            &chapter.title,
-            chapter_index as i32,
-            &chapter.confidence,
-            chapter.raw_html.as_deref(),
-            &chapter.render_mode,
-            chapter.heading_level,
-        ).map_err(|e| e.to_string())?;
+/// 解析文件并写入数据库（导入流程中不依赖 AppHandle 的部分）
+///
+/// 依次解析、替换旧章节、迁移笔记、保存元数据并标记导入完成
+///
+/// # 参数
+/// - `book_id`: 已创建的书籍记录 ID
+/// - `file_path`: 源文件路径
+/// - `key`: 加密书籍的密钥，未加密时为 None
+/// - `assets`: 资产存储，为 None 时不提取 EPUB 中的图片和样式资源
+/// - `on_saving`: 解析完成、开始保存时调用（用于发送进度）
+///
+/// # 返回
+/// 重新解析时的笔记迁移结果，首次导入为 None
+pub fn import_file_into_db(
+    conn: &rusqlite::Connection,
+    book_id: i32,
+    file_path: &Path,
+    key: Option<&[u8]>,
+    assets: Option<Arc<dyn AssetSink>>,
+    on_saving: impl FnOnce(),
+) -> Result<Option<annotation_remap::RemapSummary>, String> {
+    // 路由到对应的 Parser，TXT 使用导入时指定的编码
+    let encoding: Option<String> = conn
+        .query_row("SELECT text_encoding FROM books WHERE id = ?1", [book_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    let encoding = encoding.as_deref().map(TxtParser::encoding_for_label).transpose()?;
+    let router = match assets {
+        Some(sink) => ParserRouter::with_asset_sink(sink),
+        None => ParserRouter::new(),
+    }
+    .with_encoding_hint(encoding);
+    let parser = router.route(file_path)?;
 
-        eprintln!("[DEBUG] Chapter saved with id: {}", chapter_id);
+    // 解析文件
+    let result = parser.parse(file_path, book_id, conn)?;
 
-        // 只有 IRP 模式才保存 blocks（TXT、PDF）
-        // EPUB 和 Markdown 不需要保存 blocks
-        if chapter.render_mode == "irp" {
-            for (block_index, block) in chapter.blocks.iter().enumerate() {
-                irp::create_block(
-                    &conn,
-                    chapter_id as i32,
-                    block_index as i32,
-                    &block.block_type,
-                    &block.runs,
-                ).map_err(|e| e.to_string())?;
-            }
-        }
-    }
+    // 更新进度
+    on_saving();
+
+    // 章节、内容块、笔记迁移和元数据在同一事务中写入，任何一步失败都整体回滚，
+    // 不会留下只写了一半的章节（重新解析时旧章节也保持不变）
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+
+    // 重新解析时先移除旧章节，保留旧标题和内容块指纹用于迁移笔记、高亮和书签
+    let old_blocks = annotation_remap::collect_block_hashes(&tx, book_id)?;
+    let old_titles = clear_book_content(&tx, book_id)?;
+
+    // 保存章节和块到数据库
+    save_parse_result(&tx, book_id, &result, key)?;
+
+    let remap_summary = if old_titles.is_empty() {
+        None
+    } else {
+        let new_titles: Vec<String> = result.chapters.iter().map(|c| c.title.clone()).collect();
+        annotation_remap::remap_highlights(&tx, book_id, &old_titles, &new_titles, &old_blocks)?;
+        annotation_remap::remap_bookmarks(&tx, book_id, &old_titles, &new_titles, &old_blocks)?;
+        Some(annotation_remap::remap_notes(&tx, book_id, &old_titles, &new_titles)?)
+    };
 
     // 提取元数据和封面（仅对 EPUB 格式）
-    let (title, author, cover_base64) = if task.file_path.extension().and_then(|s| s.to_str()) == Some("epub") {
-        match EpubDoc::new(&task.file_path) {
+    let (title, author, cover_base64, metadata) = if file_path.extension().and_then(|s| s.to_str()) == Some("epub") {
+        match EpubDoc::new(file_path) {
             Ok(mut doc) => {
                 // 提取标题
                 let title = doc.mdata("title")
                     .map(|item| item.value.clone())
                     .unwrap_or_else(|| {
-                        task.file_path
+                        file_path
                             .file_stem()
                             .and_then(|s| s.to_str())
                             .unwrap_or("未知书籍")
@@ -256,21 +450,202 @@ async fn process_single_import(app: AppHandle, task: ImportTask) -> Result<(), S
                     .map(|item| item.value.clone())
                     .unwrap_or_else(|| "未知作者".to_string());
 
-                // 提取封面
-                let cover = doc.get_cover()
-                    .map(|(cover_data, _)| {
+                // 提取封面（元数据未声明时从封面页或开头的图片中查找）
+                let cover = epub_cover::find_cover(&mut doc)
+                    .map(|cover_data| {
                         format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(&cover_data))
                     });
 
-                (Some(title), Some(author), cover)
+                // 提取出版社、语言等其他元数据
+                let metadata = book_metadata::extract_epub_metadata(&doc);
+
+                (Some(title), Some(author), cover, Some(metadata))
             }
-            Err(_) => (None, None, None)
+            Err(_) => (None, None, None, None)
         }
     } else {
-        (None, None, None)
+        (None, None, None, None)
     };
 
+    if let Some(metadata) = metadata {
+        book_metadata::save_book_metadata(&tx, book_id, &metadata)?;
+    }
+
+    // 按内容检测语言（元数据未提供语言时使用）
+    let language = crate::parser::language::detect_result_language(&result);
+    book_metadata::save_detected_language(&tx, book_id, language)?;
+
+    // 保存书籍样式表（重新解析时覆盖旧样式）
+    tx.execute(
+        "UPDATE books SET stylesheet = ?1 WHERE id = ?2",
+        rusqlite::params![result.stylesheet, book_id],
+    ).map_err(|e| format!("保存样式表失败: {}", e))?;
+
+    // 保存解析警告（重新解析时覆盖旧警告）
+    save_parse_warnings(&tx, book_id, &result.warnings)?;
+
     // 更新书籍信息（包括标题、作者和封面）
+    mark_import_completed(&tx, book_id, &result, title, author, cover_base64)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    // 缓存字数统计（失败不影响导入结果）
+    if let Err(e) = crate::book_stats::cache_book_counts(conn, book_id, key) {
+        eprintln!("缓存字数统计失败 (book_id: {}): {}", book_id, e);
+    }
+
+    // 生成封面缩略图（无封面时跳过）
+    if let Err(e) = crate::cover::cache_cover_thumbnail(conn, book_id) {
+        eprintln!("生成封面缩略图失败 (book_id: {}): {}", book_id, e);
+    }
+
+    // 缓存封面主色调（无封面时跳过）
+    if let Err(e) = crate::cover::cache_cover_palette(conn, book_id) {
+        eprintln!("缓存封面配色失败 (book_id: {}): {}", book_id, e);
+    }
+
+    Ok(remap_summary)
+}
+
+/// 保存解析警告（JSON 数组），没有警告时清空
+pub fn save_parse_warnings(conn: &rusqlite::Connection, book_id: i32, warnings: &[String]) -> Result<(), String> {
+    let json = if warnings.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(warnings).map_err(|e| e.to_string())?)
+    };
+    conn.execute(
+        "UPDATE books SET parse_warnings = ?1 WHERE id = ?2",
+        rusqlite::params![json, book_id],
+    ).map_err(|e| format!("保存解析警告失败: {}", e))?;
+    Ok(())
+}
+
+/// 删除书籍已有的章节、内容块和 Reading Unit（重新解析前调用）
+///
+/// # 返回
+/// 旧章节标题（按 chapter_index 排列），首次导入时为空
+pub fn clear_book_content(conn: &rusqlite::Connection, book_id: i32) -> Result<Vec<String>, String> {
+    // 章节标题不加密，直接查询即可
+    let mut stmt = conn
+        .prepare("SELECT title FROM chapters WHERE book_id = ?1 ORDER BY chapter_index")
+        .map_err(|e| e.to_string())?;
+    let old_titles = stmt
+        .query_map([book_id], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    if old_titles.is_empty() {
+        return Ok(old_titles);
+    }
+
+    conn.execute(
+        "DELETE FROM blocks WHERE chapter_id IN (SELECT id FROM chapters WHERE book_id = ?1)",
+        [book_id],
+    ).map_err(|e| format!("删除旧内容块失败: {}", e))?;
+    conn.execute("DELETE FROM chapters WHERE book_id = ?1", [book_id])
+        .map_err(|e| format!("删除旧章节失败: {}", e))?;
+    // Reading Unit 引用旧内容块的 ID，随章节一起删除（连同按旧内容生成、可能用旧密钥加密的摘要），
+    // 由 save_parse_result 按新内容块重新生成，摘要在下次 summarize_book 时重新生成
+    conn.execute("DELETE FROM reading_units WHERE book_id = ?1", [book_id])
+        .map_err(|e| format!("删除旧阅读单元失败: {}", e))?;
+
+    Ok(old_titles)
+}
+
+/// 保存解析结果（章节和内容块）到数据库，并按章节生成 Reading Unit
+///
+/// # 参数
+/// - `conn`: 数据库连接
+/// - `book_id`: 书籍 ID
+/// - `result`: 解析结果
+/// - `key`: 加密密钥，不为空时加密存储 raw_html 和 runs_json
+///
+/// 不自行开启事务，导入流程在 `import_file_into_db` 的事务中调用
+pub fn save_parse_result(
+    conn: &rusqlite::Connection,
+    book_id: i32,
+    result: &ParseResult,
+    key: Option<&[u8]>,
+) -> Result<(), String> {
+    for (chapter_index, chapter) in result.chapters.iter().enumerate() {
+        let raw_html = match (&chapter.raw_html, key) {
+            (Some(html), Some(key)) => Some(
+                encryption::encrypt_content(html, key).map_err(|e| e.to_string())?,
+            ),
+            (raw_html, _) => raw_html.clone(),
+        };
+
+        let chapter_id = irp::create_chapter_with_html_and_level(
+            conn,
+            book_id,
+            &chapter.title,
+            chapter_index as i32,
+            &chapter.confidence,
+            raw_html.as_deref(),
+            &chapter.render_mode,
+            chapter.heading_level,
+        ).map_err(|e| e.to_string())?;
+
+        if let Some(content_type) = &chapter.content_type {
+            irp::set_chapter_content_type(conn, chapter_id as i32, content_type)
+                .map_err(|e| e.to_string())?;
+        }
+        if let Some(source_anchor) = &chapter.source_anchor {
+            irp::set_chapter_source_anchor(conn, chapter_id as i32, source_anchor)
+                .map_err(|e| e.to_string())?;
+        }
+
+        let char_count = book_stats::count_text(&result.plain_text(chapter_index)).char_count;
+        irp::set_chapter_char_count(conn, chapter_id as i32, char_count).map_err(|e| e.to_string())?;
+
+        // 只有 IRP 模式才保存 blocks（TXT、PDF）
+        // EPUB 和 Markdown 不需要保存 blocks
+        if chapter.render_mode == "irp" {
+            let blocks: Vec<irp::NewBlock> = chapter
+                .blocks
+                .iter()
+                .map(|block| {
+                    let highlighted_html = block.lang.as_deref().and_then(|lang| {
+                        let code: String = block.runs.iter().map(|run| run.text.as_str()).collect();
+                        highlight::try_highlight(lang, &code)
+                    });
+                    irp::NewBlock {
+                        block_type: &block.block_type,
+                        runs: &block.runs,
+                        heading_level: block.heading_level,
+                        lang: block.lang.as_deref(),
+                        highlighted_html,
+                    }
+                })
+                .collect();
+            irp::create_blocks(conn, chapter_id as i32, &blocks, key).map_err(|e| e.to_string())?;
+        }
+    }
+
+    // 按保存的章节和内容块重新划分 Reading Unit（替换旧的 Reading Unit）
+    reading_unit::build_book_units(conn, book_id, &result.chapters)?;
+
+    Ok(())
+}
+
+/// 导入失败后记录失败原因（`failed: {原因}`）
+pub fn mark_import_failed(conn: &rusqlite::Connection, book_id: i32, error: &str) -> Result<(), String> {
+    conn.execute(
+        "UPDATE books SET parse_status = ?1 WHERE id = ?2",
+        rusqlite::params![format!("failed: {}", error), book_id],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 导入完成后更新书籍记录（解析状态、质量、块数，以及 EPUB 的标题、作者和封面）
+pub fn mark_import_completed(
+    conn: &rusqlite::Connection,
+    book_id: i32,
+    result: &ParseResult,
+    title: Option<String>,
+    author: Option<String>,
+    cover_base64: Option<String>,
+) -> Result<(), String> {
     match (title, author, cover_base64) {
         (Some(t), Some(a), Some(c)) => {
             conn.execute(
@@ -282,7 +657,7 @@ async fn process_single_import(app: AppHandle, task: ImportTask) -> Result<(), S
                     format!("{:?}", result.quality),
                     result.total_blocks,
                     c,
-                    task.book_id
+                    book_id
                 ],
             ).map_err(|e| e.to_string())?;
         }
@@ -295,7 +670,7 @@ async fn process_single_import(app: AppHandle, task: ImportTask) -> Result<(), S
                     "completed",
                     format!("{:?}", result.quality),
                     result.total_blocks,
-                    task.book_id
+                    book_id
                 ],
             ).map_err(|e| e.to_string())?;
         }
@@ -306,27 +681,430 @@ async fn process_single_import(app: AppHandle, task: ImportTask) -> Result<(), S
                     "completed",
                     format!("{:?}", result.quality),
                     result.total_blocks,
-                    task.book_id
+                    book_id
                 ],
             ).map_err(|e| e.to_string())?;
         }
     }
 
-    // 发送完成事件
-    app.emit("import-progress", serde_json::json!({
-        "book_id": task.book_id,
-        "status": "completed",
-        "progress": 1.0
-    })).map_err(|e| e.to_string())?;
-
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pending_book_is_imported_with_chapters() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        let file_path = temp_dir.path().join("对话框导入.txt");
+        std::fs::write(&file_path, "第一章 开始\n\n第一段。\n\n第二章 继续\n\n第二段。\n").unwrap();
+
+        // 与 upload_epub_file / import_book 相同的流程：先创建 pending 记录，再由队列处理
+        let book_id = create_pending_book(&conn, "对话框导入", &file_path.to_string_lossy(), false, None).unwrap();
+        let mut saving = false;
+        let remapped = import_file_into_db(&conn, book_id, &file_path, None, None, || saving = true).unwrap();
+        assert!(saving);
+        assert!(remapped.is_none());
+
+        let chapters = irp::get_chapters_by_book(&conn, book_id, None).unwrap();
+        let titles: Vec<&str> = chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["第一章 开始", "第二章 继续"]);
+        let status: String = conn
+            .query_row("SELECT parse_status FROM books WHERE id = ?1", [book_id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(status, "completed");
+    }
+
+    #[test]
+    fn test_large_book_blocks_keep_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        let file_path = temp_dir.path().join("长篇.txt");
+        let mut text = String::new();
+        for (c, chapter) in ["第一章 上", "第二章 中", "第三章 下"].iter().enumerate() {
+            text.push_str(&format!("{}\n\n", chapter));
+            for i in 0..4000 {
+                text.push_str(&format!("正文{}-{}。\n\n", c, i));
+            }
+        }
+        std::fs::write(&file_path, text).unwrap();
+
+        let book_id = create_pending_book(&conn, "长篇", &file_path.to_string_lossy(), false, None).unwrap();
+        import_file_into_db(&conn, book_id, &file_path, None, None, || {}).unwrap();
+
+        let chapters = irp::get_chapters_by_book(&conn, book_id, None).unwrap();
+        assert_eq!(chapters.len(), 3);
+        for (c, chapter) in chapters.iter().enumerate() {
+            let blocks = irp::get_blocks_by_chapter(&conn, chapter.id, None).unwrap();
+            let paragraphs: Vec<&irp::Block> = blocks.iter().filter(|b| b.runs[0].text.starts_with("正文")).collect();
+            assert_eq!(paragraphs.len(), 4000);
+            assert_eq!(blocks.iter().map(|b| b.block_index).collect::<Vec<_>>(), (0..blocks.len() as i32).collect::<Vec<_>>());
+            for (i, block) in paragraphs.iter().enumerate() {
+                assert_eq!(block.runs[0].text, format!("正文{}-{}。", c, i));
+            }
+        }
+    }
+
+    #[test]
+    fn test_failed_import_leaves_no_partial_rows() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        let file_path = temp_dir.path().join("中断.txt");
+        std::fs::write(&file_path, "第一章 开始\n\n第一段。\n\n第二章 继续\n\n第二段。\n").unwrap();
+        let book_id = create_pending_book(&conn, "中断", &file_path.to_string_lossy(), false, None).unwrap();
+
+        // 第一章写入后、第二章写入时失败
+        conn.execute_batch(
+            "CREATE TEMP TRIGGER fail_second_chapter BEFORE INSERT ON chapters WHEN NEW.chapter_index = 1
+             BEGIN SELECT RAISE(ABORT, '模拟写入失败'); END;",
+        )
+        .unwrap();
+        let error = import_file_into_db(&conn, book_id, &file_path, None, None, || {}).unwrap_err();
+        assert!(error.contains("模拟写入失败"));
+        mark_import_failed(&conn, book_id, &error).unwrap();
+
+        let (chapters, blocks): (i64, i64) = conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM chapters), (SELECT COUNT(*) FROM blocks)",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((chapters, blocks), (0, 0));
+        let status: String = conn
+            .query_row("SELECT parse_status FROM books WHERE id = ?1", [book_id], |row| row.get(0))
+            .unwrap();
+        assert!(status.starts_with("failed: "));
+
+        // 失败后可以正常重试
+        conn.execute_batch("DROP TRIGGER fail_second_chapter").unwrap();
+        import_file_into_db(&conn, book_id, &file_path, None, None, || {}).unwrap();
+        assert_eq!(irp::get_chapters_by_book(&conn, book_id, None).unwrap().len(), 2);
+    }
+
     #[test]
-    fn test_module_exists() {
-        // 简单的模块存在性测试
-        assert!(true);
+    fn test_malformed_pdf_import_fails_without_panicking() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        let file_path = temp_dir.path().join("损坏.pdf");
+        std::fs::write(
+            &file_path,
+            "%PDF-1.4\n1 0 obj << /Type /Catalog /Pages 9 0 R >> endobj\ntrailer << /Root 1 0 R >>\n%%EOF",
+        )
+        .unwrap();
+        let book_id = create_pending_book(&conn, "损坏", &file_path.to_string_lossy(), false, None).unwrap();
+
+        let error = import_file_into_db(&conn, book_id, &file_path, None, None, || {}).unwrap_err();
+        assert!(error.contains("PDF 解析失败"), "{}", error);
+        assert!(irp::get_chapters_by_book(&conn, book_id, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_encoding_hint_changes_decoded_text() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        let file_path = temp_dir.path().join("繁體.txt");
+        let (bytes, _, _) = encoding_rs::BIG5.encode("第一章 開始\n\n這是繁體中文的內容。\n");
+        std::fs::write(&file_path, &bytes).unwrap();
+
+        let book_id = create_pending_book(&conn, "繁體", &file_path.to_string_lossy(), false, Some("big5")).unwrap();
+        let import = || {
+            import_file_into_db(&conn, book_id, &file_path, None, None, || {}).unwrap();
+            let chapter = irp::get_chapter_by_index(&conn, book_id, 0, None).unwrap();
+            crate::export::chapter_plain_text(&conn, chapter.id, None).unwrap()
+        };
+
+        let big5 = import();
+        // 重新解析时使用书籍记录中保存的编码
+        conn.execute("UPDATE books SET text_encoding = 'gbk' WHERE id = ?1", [book_id]).unwrap();
+        let gbk = import();
+        assert!(big5.contains("這是繁體中文的內容。"));
+        assert_ne!(big5, gbk);
+    }
+
+    #[test]
+    fn test_epub_chapters_keep_spine_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        let file_path = temp_dir.path().join("书.epub");
+        crate::parser::test_fixtures::write_epub(
+            &file_path,
+            "<dc:title>书</dc:title>",
+            &[("第一章 开端", "<p>第一章正文。</p>"), ("第二章 继续", "<p>第二章正文。</p>")],
+        );
+
+        let book_id = create_pending_book(&conn, "书", &file_path.to_string_lossy(), false, None).unwrap();
+        import_file_into_db(&conn, book_id, &file_path, None, None, || {}).unwrap();
+        let anchors: Vec<Option<String>> = irp::get_chapters_by_book(&conn, book_id, None)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.source_anchor)
+            .collect();
+        assert_eq!(
+            anchors,
+            vec![Some("OEBPS/ch1.xhtml".to_string()), Some("OEBPS/ch2.xhtml".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_warnings_are_saved() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        let file_path = temp_dir.path().join("乱码.txt");
+        std::fs::write(&file_path, b"Chapter 1\n\nHello \xff\xff world.\n").unwrap();
+
+        let book_id = create_pending_book(&conn, "乱码", &file_path.to_string_lossy(), false, None).unwrap();
+        import_file_into_db(&conn, book_id, &file_path, None, None, || {}).unwrap();
+        let warnings = || -> Option<String> {
+            conn.query_row("SELECT parse_warnings FROM books WHERE id = ?1", [book_id], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(warnings().as_deref(), Some(r#"["文件解码时出现错误，可能存在乱码"]"#));
+
+        // 重新解析没有问题的文件时清空旧警告
+        std::fs::write(&file_path, "Chapter 1\n\nHello world.\n").unwrap();
+        import_file_into_db(&conn, book_id, &file_path, None, None, || {}).unwrap();
+        assert_eq!(warnings(), None);
+    }
+
+    #[test]
+    fn test_validate_mixed_import_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = |name: &str| {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, "内容").unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let paths = vec![
+            file("a.txt"),
+            file("b.docx"),
+            file("c.MD"),
+            temp_dir.path().join("missing.epub").to_string_lossy().to_string(),
+            file("README"),
+            file("d.pdf"),
+        ];
+
+        let (supported, errors) = validate_import_paths(&paths);
+        assert_eq!(supported, vec![paths[0].clone(), paths[2].clone(), paths[5].clone()]);
+        assert_eq!(
+            errors,
+            vec![
+                ImportFileError { path: paths[1].clone(), error: "不支持的文件格式: docx".to_string() },
+                ImportFileError { path: paths[3].clone(), error: "文件不存在".to_string() },
+                ImportFileError { path: paths[4].clone(), error: "不支持的文件格式: 无扩展名".to_string() },
+            ]
+        );
+    }
+
+    /// 在全新数据库中解析并保存一个 TXT 文件
+    fn import_sample_txt(temp_dir: &TempDir) -> (rusqlite::Connection, i32, ParseResult) {
+        let conn = db::init_db(temp_dir.path().join("fresh.db")).unwrap();
+
+        let file_path = temp_dir.path().join("样书.txt");
+        std::fs::write(
+            &file_path,
+            "第一章 开始\n\n这是第一章的第一段。\n\n这是第一章的第二段。\n\n第二章 继续\n\n这是第二章的内容。\n",
+        )
+        .unwrap();
+
+        conn.execute(
+            "INSERT INTO books (title, author, file_path, parse_status) VALUES ('样书', '未知作者', ?1, 'pending')",
+            [file_path.to_string_lossy()],
+        )
+        .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        let router = ParserRouter::new();
+        let result = router
+            .route(&file_path)
+            .unwrap()
+            .parse(&file_path, book_id, &conn)
+            .unwrap();
+        save_parse_result(&conn, book_id, &result, None).unwrap();
+
+        (conn, book_id, result)
+    }
+
+    #[test]
+    fn test_imported_txt_chapter_blocks() {
+        let temp_dir = TempDir::new().unwrap();
+        let (conn, book_id, result) = import_sample_txt(&temp_dir);
+
+        let chapter = irp::get_chapter_by_index(&conn, book_id, 1, None).unwrap();
+        assert_eq!(chapter.title, result.chapters[1].title);
+        assert_eq!(chapter.render_mode, "irp");
+
+        let blocks = irp::get_blocks_by_chapter(&conn, chapter.id, None).unwrap();
+        assert_eq!(blocks.len(), result.chapters[1].blocks.len());
+        let texts: Vec<String> = blocks
+            .iter()
+            .map(|b| b.runs.iter().map(|r| r.text.as_str()).collect())
+            .collect();
+        assert!(texts.iter().any(|t| t.contains("这是第二章的内容")));
+
+        assert!(irp::get_chapter_by_index(&conn, book_id, 99, None).is_err());
+    }
+
+    #[test]
+    fn test_import_builds_reading_units() {
+        let temp_dir = TempDir::new().unwrap();
+        let (conn, book_id, result) = import_sample_txt(&temp_dir);
+
+        // 每个章节成为一个片段，阅读单元按顺序覆盖全书的内容块
+        let units = reading_unit::unit_editor::load_units(&conn, book_id, None).unwrap();
+        assert!(!units.is_empty());
+        assert_eq!(units[0].title, "第一章 开始");
+        let segment_count: usize = units.iter().map(|unit| unit.segment_ids.len()).sum();
+        assert_eq!(segment_count, result.chapters.len());
+
+        let first = irp::get_chapter_by_index(&conn, book_id, 0, None).unwrap();
+        let last = irp::get_chapter_by_index(&conn, book_id, 1, None).unwrap();
+        let first_blocks = irp::get_blocks_by_chapter(&conn, first.id, None).unwrap();
+        let last_blocks = irp::get_blocks_by_chapter(&conn, last.id, None).unwrap();
+        assert_eq!(units[0].start_block_id, first_blocks.first().unwrap().id);
+        assert_eq!(units.last().unwrap().end_block_id, last_blocks.last().unwrap().id);
+    }
+
+    #[test]
+    fn test_reparse_drops_stale_summaries() {
+        let temp_dir = TempDir::new().unwrap();
+        let (conn, book_id, result) = import_sample_txt(&temp_dir);
+        let key = [3u8; 32];
+        conn.execute("UPDATE books SET is_encrypted = 1 WHERE id = ?1", [book_id]).unwrap();
+
+        let units = reading_unit::unit_editor::load_units(&conn, book_id, Some(&key)).unwrap();
+        reading_unit::summarizer::save_unit_summary(&conn, &units[0].id, "旧摘要", "model", Some(&key)).unwrap();
+
+        clear_book_content(&conn, book_id).unwrap();
+        save_parse_result(&conn, book_id, &result, Some(&key)).unwrap();
+
+        let summaries: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM reading_units WHERE book_id = ?1 AND summary_text IS NOT NULL",
+                [book_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(summaries, 0);
+    }
+
+    #[test]
+    fn test_imported_chapter_char_count() {
+        let temp_dir = TempDir::new().unwrap();
+        let (conn, book_id, _result) = import_sample_txt(&temp_dir);
+
+        // 第二章：标题「第二章 继续」5 个字符 + 正文「这是第二章的内容。」9 个字符
+        let chapter = irp::get_chapter_by_index(&conn, book_id, 1, None).unwrap();
+        assert_eq!(chapter.char_count, Some(14));
+
+        conn.execute("UPDATE chapters SET char_count = NULL", []).unwrap();
+        let counts = book_stats::cache_chapter_char_counts(&conn, book_id, None).unwrap();
+        assert_eq!(counts[1].char_count, 14);
+        let chapter = irp::get_chapter_by_index(&conn, book_id, 1, None).unwrap();
+        assert_eq!(chapter.char_count, Some(14));
+    }
+
+    #[test]
+    fn test_import_txt_into_fresh_db() {
+        let temp_dir = TempDir::new().unwrap();
+        let (conn, book_id, result) = import_sample_txt(&temp_dir);
+
+        let chapters = irp::get_chapters_by_book(&conn, book_id, None).unwrap();
+        assert_eq!(chapters.len(), result.chapters.len());
+        assert!(!chapters.is_empty());
+        assert!(chapters.iter().all(|c| c.render_mode == "irp"));
+
+        let block_count: usize = chapters
+            .iter()
+            .map(|c| irp::get_blocks_by_chapter(&conn, c.id, None).unwrap().len())
+            .sum();
+        assert_eq!(block_count, result.total_blocks);
+        assert!(block_count > 0);
+
+        // 内容块和资产映射表带有外键约束
+        let blocks_fk: String = conn
+            .query_row(
+                "SELECT \"table\" FROM pragma_foreign_key_list('blocks')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(blocks_fk, "chapters");
+        let asset_fk: String = conn
+            .query_row(
+                "SELECT \"table\" FROM pragma_foreign_key_list('asset_mappings')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(asset_fk, "books");
+    }
+
+    #[test]
+    fn test_imported_book_reports_completed() {
+        let temp_dir = TempDir::new().unwrap();
+        let (conn, book_id, result) = import_sample_txt(&temp_dir);
+
+        let page = crate::query_books(&conn, None, None, None).unwrap();
+        assert_eq!(page.items[0].parse_status.as_deref(), Some("pending"));
+
+        mark_import_completed(&conn, book_id, &result, None, None, None).unwrap();
+
+        let page = crate::query_books(&conn, None, None, None).unwrap();
+        let book = &page.items[0];
+        assert_eq!(book.id, book_id);
+        assert_eq!(book.parse_status.as_deref(), Some("completed"));
+        assert_eq!(book.parse_quality, Some(format!("{:?}", result.quality)));
+        assert_eq!(book.total_blocks, result.total_blocks as i64);
+    }
+
+    #[test]
+    fn test_reparse_replaces_chapters_and_remaps_notes() {
+        let temp_dir = TempDir::new().unwrap();
+        let (conn, book_id, result) = import_sample_txt(&temp_dir);
+        let old_chapter_count = result.chapters.len();
+
+        let last_index = (old_chapter_count - 1) as i32;
+        conn.execute(
+            "INSERT INTO notes (title, book_id, chapter_index) VALUES ('笔记', ?1, ?2)",
+            rusqlite::params![book_id, last_index],
+        )
+        .unwrap();
+
+        // 第二次解析：章节顺序反转
+        let mut reparsed = result.clone();
+        reparsed.chapters.reverse();
+        let old_titles = clear_book_content(&conn, book_id).unwrap();
+        assert_eq!(old_titles.len(), old_chapter_count);
+        assert!(reading_unit::unit_editor::load_units(&conn, book_id, None).unwrap().is_empty());
+        save_parse_result(&conn, book_id, &reparsed, None).unwrap();
+
+        // 重新生成的阅读单元只引用新的内容块
+        let units = reading_unit::unit_editor::load_units(&conn, book_id, None).unwrap();
+        assert!(!units.is_empty());
+        for unit in &units {
+            for block_id in [unit.start_block_id, unit.end_block_id] {
+                let exists: bool = conn
+                    .query_row("SELECT EXISTS(SELECT 1 FROM blocks WHERE id = ?1)", [block_id], |row| row.get(0))
+                    .unwrap();
+                assert!(exists, "阅读单元引用了不存在的内容块 {}", block_id);
+            }
+        }
+
+        let new_titles: Vec<String> = reparsed.chapters.iter().map(|c| c.title.clone()).collect();
+        annotation_remap::remap_notes(&conn, book_id, &old_titles, &new_titles).unwrap();
+
+        // 旧章节已被替换，没有残留
+        assert_eq!(
+            irp::get_chapters_by_book(&conn, book_id, None).unwrap().len(),
+            old_chapter_count
+        );
+        let chapter_index: i32 = conn
+            .query_row("SELECT chapter_index FROM notes WHERE title = '笔记'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(new_titles[chapter_index as usize], old_titles[last_index as usize]);
     }
 }
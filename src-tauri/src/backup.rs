@@ -0,0 +1,297 @@
+use crate::db;
+use crate::encryption;
+use crate::error::AppError;
+use rusqlite::{backup::Progress, Connection, DatabaseName};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use tempfile::TempDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+// 书库备份模块：将数据库快照、assets/ 目录和用口令加密的加密密钥打包为一个 zip 文件，并支持从中恢复。
+// 笔记和加密书籍的内容只能用加密密钥解密，备份不带密钥时在其他设备或重装后无法恢复
+
+/// 备份包中的数据库文件名
+const DB_ENTRY: &str = "library.db";
+/// 备份包中的资产目录前缀
+const ASSETS_DIR: &str = "assets";
+/// 备份包中用口令加密的加密密钥
+const KEY_ENTRY: &str = "encryption.key";
+
+/// 导出整个书库
+///
+/// 使用 `VACUUM INTO` 生成一致的数据库快照，再与资产目录、用口令加密的加密密钥一起写入 zip
+///
+/// # 参数
+/// - `conn`: 数据库连接
+/// - `app_data_dir`: 应用数据目录（资产位于其中的 `assets/`）
+/// - `dest_path`: 备份文件路径
+/// - `key`: 当前的加密密钥
+/// - `passphrase`: 备份口令，恢复时需要输入同一口令
+pub fn export_library(
+    conn: &Connection,
+    app_data_dir: &Path,
+    dest_path: &Path,
+    key: &[u8],
+    passphrase: &str,
+) -> Result<(), AppError> {
+    if passphrase.is_empty() {
+        return Err(AppError::Validation("请设置备份口令：备份包含加密密钥，恢复时需要输入该口令".to_string()));
+    }
+    let wrapped_key = encryption::wrap_key(key, passphrase).map_err(|e| AppError::Io(e.to_string()))?;
+
+    let temp_dir = TempDir::new().map_err(|e| AppError::Io(format!("创建临时目录失败: {}", e)))?;
+    let snapshot_path = temp_dir.path().join(DB_ENTRY);
+    conn.execute("VACUUM INTO ?1", [snapshot_path.to_string_lossy()])
+        .map_err(|e| AppError::Db(format!("生成数据库快照失败: {}", e)))?;
+
+    let file = File::create(dest_path).map_err(|e| AppError::Io(format!("创建备份文件失败: {}", e)))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    add_file(&mut zip, DB_ENTRY, &snapshot_path, options).map_err(AppError::Io)?;
+    zip.start_file(KEY_ENTRY, options)
+        .map_err(|e| AppError::Io(format!("写入 {} 失败: {}", KEY_ENTRY, e)))?;
+    zip.write_all(wrapped_key.as_bytes())
+        .map_err(|e| AppError::Io(format!("写入 {} 失败: {}", KEY_ENTRY, e)))?;
+
+    let assets_dir = app_data_dir.join(ASSETS_DIR);
+    if assets_dir.is_dir() {
+        add_dir(&mut zip, &assets_dir, ASSETS_DIR, options).map_err(AppError::Io)?;
+    }
+
+    zip.finish().map_err(|e| AppError::Io(format!("写入备份文件失败: {}", e)))?;
+    Ok(())
+}
+
+fn add_file(
+    zip: &mut ZipWriter<File>,
+    name: &str,
+    path: &Path,
+    options: SimpleFileOptions,
+) -> Result<(), String> {
+    zip.start_file(name, options)
+        .map_err(|e| format!("写入 {} 失败: {}", name, e))?;
+    let mut source = File::open(path).map_err(|e| format!("读取 {} 失败: {}", name, e))?;
+    io::copy(&mut source, zip).map_err(|e| format!("写入 {} 失败: {}", name, e))?;
+    Ok(())
+}
+
+fn add_dir(
+    zip: &mut ZipWriter<File>,
+    dir: &Path,
+    prefix: &str,
+    options: SimpleFileOptions,
+) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("读取目录失败: {}", e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("读取目录失败: {}", e))?;
+        let path = entry.path();
+        let name = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+        if path.is_dir() {
+            add_dir(zip, &path, &name, options)?;
+        } else {
+            add_file(zip, &name, &path, options)?;
+        }
+    }
+    Ok(())
+}
+
+/// 从备份恢复整个书库（覆盖当前数据库和资产）
+///
+/// 数据库通过 SQLite backup API 写入当前连接，恢复后执行迁移以兼容旧版本备份
+///
+/// # 参数
+/// - `conn`: 当前数据库连接
+/// - `app_data_dir`: 应用数据目录
+/// - `src_path`: 备份文件路径
+/// - `passphrase`: 备份口令，口令错误时不修改当前书库
+/// - `confirm`: 必须为 true，防止误操作覆盖书库
+///
+/// # 返回
+/// 备份中的加密密钥，调用方需要用它替换当前密钥；旧版本的备份不含密钥，返回 None
+pub fn import_library(
+    conn: &mut Connection,
+    app_data_dir: &Path,
+    src_path: &Path,
+    passphrase: &str,
+    confirm: bool,
+) -> Result<Option<Vec<u8>>, AppError> {
+    if !confirm {
+        return Err(AppError::Validation("恢复备份会覆盖当前书库，请确认后重试".to_string()));
+    }
+
+    let file = File::open(src_path).map_err(|e| AppError::Io(format!("打开备份文件失败: {}", e)))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| AppError::Validation(format!("备份文件无效: {}", e)))?;
+
+    let key = match archive.by_name(KEY_ENTRY) {
+        Ok(mut entry) => {
+            let mut wrapped = String::new();
+            entry.read_to_string(&mut wrapped).map_err(|e| AppError::Io(format!("读取加密密钥失败: {}", e)))?;
+            Some(encryption::unwrap_key(&wrapped, passphrase).map_err(|e| AppError::Validation(format!("无法解开备份中的加密密钥: {}", e)))?)
+        }
+        Err(_) => None,
+    };
+
+    // 先解压数据库并校验，确认可用后再覆盖当前书库
+    let temp_dir = TempDir::new().map_err(|e| AppError::Io(format!("创建临时目录失败: {}", e)))?;
+    let snapshot_path = temp_dir.path().join(DB_ENTRY);
+    {
+        let mut entry = archive
+            .by_name(DB_ENTRY)
+            .map_err(|_| AppError::Validation("备份文件无效: 缺少 library.db".to_string()))?;
+        let mut out = File::create(&snapshot_path).map_err(|e| AppError::Io(format!("解压数据库失败: {}", e)))?;
+        io::copy(&mut entry, &mut out).map_err(|e| AppError::Io(format!("解压数据库失败: {}", e)))?;
+        out.flush().map_err(|e| AppError::Io(format!("解压数据库失败: {}", e)))?;
+    }
+    {
+        let snapshot = Connection::open(&snapshot_path).map_err(|e| AppError::Validation(format!("备份数据库无效: {}", e)))?;
+        let check: String = snapshot
+            .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+            .map_err(|e| AppError::Validation(format!("备份数据库无效: {}", e)))?;
+        if check != "ok" {
+            return Err(AppError::Validation(format!("备份数据库已损坏: {}", check)));
+        }
+    }
+
+    conn.restore(DatabaseName::Main, &snapshot_path, None::<fn(Progress)>)
+        .map_err(|e| AppError::Db(format!("恢复数据库失败: {}", e)))?;
+    db::run_migrations(conn).map_err(|e| AppError::Db(format!("升级备份数据库失败: {}", e)))?;
+
+    // 替换资产目录
+    let assets_dir = app_data_dir.join(ASSETS_DIR);
+    if assets_dir.exists() {
+        fs::remove_dir_all(&assets_dir).map_err(|e| AppError::Io(format!("清理资产目录失败: {}", e)))?;
+    }
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| AppError::Io(format!("读取备份文件失败: {}", e)))?;
+        // enclosed_name 会拒绝 ../ 等越界路径
+        let Some(relative) = entry.enclosed_name() else {
+            continue;
+        };
+        if !relative.starts_with(ASSETS_DIR) || entry.is_dir() {
+            continue;
+        }
+
+        let target = app_data_dir.join(&relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| AppError::Io(format!("创建资产目录失败: {}", e)))?;
+        }
+        let mut out = File::create(&target).map_err(|e| AppError::Io(format!("恢复资产失败: {}", e)))?;
+        io::copy(&mut entry, &mut out).map_err(|e| AppError::Io(format!("恢复资产失败: {}", e)))?;
+    }
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [7; 32];
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn seed_library(conn: &Connection) {
+        conn.execute_batch(
+            "INSERT INTO books (title, author, file_path) VALUES ('书一', '作者', '/books/1.epub');
+             INSERT INTO books (title, author, file_path) VALUES ('书二', '作者', '/books/2.txt');
+             INSERT INTO notes (title, content, book_id) VALUES ('笔记一', '内容', 1);
+             INSERT INTO notes (title, content, book_id) VALUES ('笔记二', '内容', 1);
+             INSERT INTO notes (title, content, book_id) VALUES ('笔记三', '内容', 2);",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_export_then_import_preserves_library() {
+        let temp_dir = TempDir::new().unwrap();
+        let app_data_dir = temp_dir.path().join("data");
+        fs::create_dir_all(app_data_dir.join("assets/1")).unwrap();
+        fs::write(app_data_dir.join("assets/1/cover.png"), b"png-bytes").unwrap();
+
+        let mut conn = db::init_db(app_data_dir.join("library.db")).unwrap();
+        seed_library(&conn);
+
+        let backup_path = temp_dir.path().join("backup.zip");
+        export_library(&conn, &app_data_dir, &backup_path, &KEY, "口令").unwrap();
+
+        // 备份之后修改书库
+        conn.execute_batch(
+            "DELETE FROM notes WHERE book_id = 2;
+             DELETE FROM books WHERE id = 2;
+             INSERT INTO notes (title) VALUES ('备份后新增');",
+        )
+        .unwrap();
+        fs::remove_file(app_data_dir.join("assets/1/cover.png")).unwrap();
+        fs::write(app_data_dir.join("assets/stale.png"), b"stale").unwrap();
+
+        let key = import_library(&mut conn, &app_data_dir, &backup_path, "口令", true).unwrap();
+        assert_eq!(key, Some(KEY.to_vec()));
+
+        assert_eq!(count(&conn, "books"), 2);
+        assert_eq!(count(&conn, "notes"), 3);
+        assert_eq!(
+            fs::read(app_data_dir.join("assets/1/cover.png")).unwrap(),
+            b"png-bytes"
+        );
+        assert!(!app_data_dir.join("assets/stale.png").exists());
+    }
+
+    #[test]
+    fn test_restore_into_fresh_app_dir_recovers_encrypted_notes() {
+        let temp_dir = TempDir::new().unwrap();
+        let old_dir = temp_dir.path().join("old");
+        let key = encryption::generate_key();
+        fs::create_dir_all(&old_dir).unwrap();
+        let conn = db::init_db(old_dir.join("library.db")).unwrap();
+        let encrypted = encryption::encrypt_content("加密的笔记", &key).unwrap();
+        conn.execute("INSERT INTO notes (title, content) VALUES ('笔记', ?1)", [&encrypted]).unwrap();
+
+        let backup_path = temp_dir.path().join("backup.zip");
+        assert!(export_library(&conn, &old_dir, &backup_path, &key, "").is_err());
+        export_library(&conn, &old_dir, &backup_path, &key, "口令").unwrap();
+
+        // 重装后的新目录：空书库和新生成的密钥
+        let new_dir = temp_dir.path().join("new");
+        fs::create_dir_all(&new_dir).unwrap();
+        let mut conn = db::init_db(new_dir.join("library.db")).unwrap();
+        assert!(import_library(&mut conn, &new_dir, &backup_path, "错误的口令", true).is_err());
+        assert_eq!(count(&conn, "notes"), 0);
+
+        let restored_key = import_library(&mut conn, &new_dir, &backup_path, "口令", true).unwrap().unwrap();
+        let content: String = conn.query_row("SELECT content FROM notes", [], |row| row.get(0)).unwrap();
+        assert_eq!(encryption::decrypt_content(&content, &restored_key).unwrap(), "加密的笔记");
+    }
+
+    #[test]
+    fn test_import_requires_confirmation() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut conn = db::init_db(temp_dir.path().join("library.db")).unwrap();
+        seed_library(&conn);
+
+        let backup_path = temp_dir.path().join("backup.zip");
+        export_library(&conn, temp_dir.path(), &backup_path, &KEY, "口令").unwrap();
+        conn.execute("DELETE FROM notes", []).unwrap();
+
+        assert!(import_library(&mut conn, temp_dir.path(), &backup_path, "口令", false).is_err());
+        assert_eq!(count(&conn, "notes"), 0);
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut conn = db::init_db(temp_dir.path().join("library.db")).unwrap();
+        seed_library(&conn);
+
+        let bogus_path = temp_dir.path().join("bogus.zip");
+        fs::write(&bogus_path, b"not a zip").unwrap();
+        assert!(import_library(&mut conn, temp_dir.path(), &bogus_path, "口令", true).is_err());
+        assert_eq!(count(&conn, "books"), 2);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::error::AppError;
+use crate::parser::language::BookLanguage;
+use epub::doc::EpubDoc;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use std::io::{Read, Seek};
+
+// 书籍元数据模块：导入时从 EPUB 的 OPF 中提取标题、作者以外的元数据，并记录按内容检测的语言
+
+/// 书籍元数据（字段缺失时为 None）
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct BookMetadata {
+    pub language: Option<String>,
+    pub publisher: Option<String>,
+    pub published_date: Option<String>,
+    /// ISBN、UUID 等唯一标识符
+    pub identifier: Option<String>,
+    pub description: Option<String>,
+}
+
+/// 从 EPUB 文档中提取元数据
+pub fn extract_epub_metadata<R: Read + Seek>(doc: &EpubDoc<R>) -> BookMetadata {
+    let value = |property: &str| {
+        doc.mdata(property)
+            .map(|item| item.value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    BookMetadata {
+        language: value("language"),
+        publisher: value("publisher"),
+        published_date: value("date"),
+        // 优先使用 package 的 unique-identifier 指向的标识符
+        identifier: doc
+            .unique_identifier
+            .clone()
+            .filter(|id| !id.trim().is_empty())
+            .or_else(|| value("identifier")),
+        description: value("description"),
+    }
+}
+
+/// 保存书籍元数据
+///
+/// 只写入有值的字段，已有值不会被 None 覆盖
+pub fn save_book_metadata(conn: &Connection, book_id: i32, metadata: &BookMetadata) -> Result<(), String> {
+    conn.execute(
+        "UPDATE books SET
+            language = COALESCE(?1, language),
+            publisher = COALESCE(?2, publisher),
+            published_date = COALESCE(?3, published_date),
+            identifier = COALESCE(?4, identifier),
+            description = COALESCE(?5, description)
+         WHERE id = ?6",
+        rusqlite::params![
+            metadata.language,
+            metadata.publisher,
+            metadata.published_date,
+            metadata.identifier,
+            metadata.description,
+            book_id
+        ],
+    )
+    .map_err(|e| format!("保存书籍元数据失败: {}", e))?;
+    Ok(())
+}
+
+/// 读取书籍元数据
+pub fn get_book_metadata(conn: &Connection, book_id: i32) -> Result<BookMetadata, AppError> {
+    conn.query_row(
+        "SELECT language, publisher, published_date, identifier, description FROM books WHERE id = ?1",
+        [book_id],
+        |row| {
+            Ok(BookMetadata {
+                language: row.get(0)?,
+                publisher: row.get(1)?,
+                published_date: row.get(2)?,
+                identifier: row.get(3)?,
+                description: row.get(4)?,
+            })
+        },
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound("找不到书籍".to_string()))
+}
+
+/// 保存检测到的书籍语言（EPUB 元数据中已有语言时不覆盖）
+pub fn save_detected_language(conn: &Connection, book_id: i32, language: BookLanguage) -> Result<(), String> {
+    conn.execute(
+        "UPDATE books SET language = COALESCE(language, ?1) WHERE id = ?2",
+        rusqlite::params![language.as_str(), book_id],
+    )
+    .map_err(|e| format!("保存书籍语言失败: {}", e))?;
+    Ok(())
+}
+
+/// 读取书籍语言，未知时视为混合语言
+pub fn get_book_language(conn: &Connection, book_id: i32) -> BookLanguage {
+    conn.query_row("SELECT language FROM books WHERE id = ?1", [book_id], |row| {
+        row.get::<_, Option<String>>(0)
+    })
+    .ok()
+    .flatten()
+    .map(|code| BookLanguage::from_code(&code))
+    .unwrap_or(BookLanguage::Mixed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::parser::test_fixtures::write_epub;
+    use tempfile::TempDir;
+
+    const RICH_METADATA: &str = r#"<dc:title>三体</dc:title>
+    <dc:creator>刘慈欣</dc:creator>
+    <dc:identifier id="bookid">urn:isbn:9787536692930</dc:identifier>
+    <dc:language>zh-CN</dc:language>
+    <dc:publisher>重庆出版社</dc:publisher>
+    <dc:date>2008-01-01</dc:date>
+    <dc:description>地球文明与三体文明的故事。</dc:description>"#;
+
+    #[test]
+    fn test_extract_rich_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let epub_path = temp_dir.path().join("rich.epub");
+        write_epub(&epub_path, RICH_METADATA, &[("第一章", "<p>正文</p>")]);
+
+        let doc = EpubDoc::new(&epub_path).unwrap();
+        let metadata = extract_epub_metadata(&doc);
+        assert_eq!(
+            metadata,
+            BookMetadata {
+                language: Some("zh-CN".to_string()),
+                publisher: Some("重庆出版社".to_string()),
+                published_date: Some("2008-01-01".to_string()),
+                identifier: Some("urn:isbn:9787536692930".to_string()),
+                description: Some("地球文明与三体文明的故事。".to_string()),
+            }
+        );
+
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('三体', '/test/rich.epub')", [])
+            .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+        save_book_metadata(&conn, book_id, &metadata).unwrap();
+        assert_eq!(get_book_metadata(&conn, book_id).unwrap(), metadata);
+
+        // 空元数据不会覆盖已有值
+        save_book_metadata(&conn, book_id, &BookMetadata::default()).unwrap();
+        assert_eq!(get_book_metadata(&conn, book_id).unwrap(), metadata);
+    }
+
+    #[test]
+    fn test_detected_language_does_not_override_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO books (title, file_path) VALUES ('a', '/a');
+             INSERT INTO books (title, file_path, language) VALUES ('b', '/b', 'en-US');",
+        )
+        .unwrap();
+
+        save_detected_language(&conn, 1, BookLanguage::Zh).unwrap();
+        save_detected_language(&conn, 2, BookLanguage::Zh).unwrap();
+        assert_eq!(get_book_language(&conn, 1), BookLanguage::Zh);
+        assert_eq!(get_book_language(&conn, 2), BookLanguage::En);
+        assert_eq!(get_book_language(&conn, 3), BookLanguage::Mixed);
+    }
+
+    #[test]
+    fn test_get_metadata_missing_book() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        assert!(get_book_metadata(&conn, 42).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use crate::book_stats::is_cjk;
+use crate::export;
+use crate::irp;
+use rusqlite::Connection;
+use serde::Serialize;
+
+// 书内搜索模块：在单本书的内容块（html/markdown 章节为去除标签后的段落）中查找关键词
+
+/// 匹配位置前后保留的字符数
+const SNIPPET_CONTEXT_CHARS: usize = 30;
+
+/// 书内搜索的一条匹配
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BookSearchMatch {
+    pub chapter_index: i32,
+    pub block_index: i32, // IRP 章节为内容块序号，html/markdown 章节为段落序号
+    pub snippet: String,
+    pub match_offset: usize, // 匹配在块文本中的字符偏移
+}
+
+/// 搜索选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool, // 只匹配完整单词（CJK 字符没有词边界，不受影响）
+}
+
+/// 在整本书中搜索
+///
+/// # 参数
+/// - `conn`: 数据库连接
+/// - `book_id`: 书籍 ID
+/// - `query`: 搜索词
+/// - `options`: 搜索选项
+/// - `key`: 加密书籍的解密密钥
+///
+/// # 返回
+/// 按章节、块顺序排列的所有匹配
+pub fn search_in_book(
+    conn: &Connection,
+    book_id: i32,
+    query: &str,
+    options: SearchOptions,
+    key: Option<&[u8]>,
+) -> Result<Vec<BookSearchMatch>, String> {
+    let query: Vec<char> = query.trim().chars().collect();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let chapters = irp::get_chapters_by_book(conn, book_id, key)
+        .map_err(|e| format!("获取章节失败: {}", e))?;
+
+    let mut matches = Vec::new();
+    for chapter in &chapters {
+        let texts: Vec<String> = match export::raw_content_to_text(
+            &chapter.render_mode,
+            chapter.raw_html.as_deref(),
+            chapter.chapter_index.max(0) as usize,
+        ) {
+            Some(text) => text
+                .split("\n\n")
+                .map(str::trim)
+                .filter(|paragraph| !paragraph.is_empty())
+                .map(str::to_string)
+                .collect(),
+            None => irp::get_blocks_by_chapter(conn, chapter.id, key)
+                .map_err(|e| format!("获取内容块失败: {}", e))?
+                .iter()
+                .map(|block| irp::extract_plain_text_from_runs(&block.runs))
+                .collect(),
+        };
+
+        for (block_index, text) in texts.iter().enumerate() {
+            let chars: Vec<char> = text.chars().collect();
+            for offset in find_matches(&chars, &query, options) {
+                matches.push(BookSearchMatch {
+                    chapter_index: chapter.chapter_index,
+                    block_index: block_index as i32,
+                    snippet: snippet(&chars, offset, query.len()),
+                    match_offset: offset,
+                });
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+/// 查找所有不重叠的匹配，返回字符偏移
+fn find_matches(text: &[char], query: &[char], options: SearchOptions) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut start = 0;
+    while start + query.len() <= text.len() {
+        let candidate = &text[start..start + query.len()];
+        let equal = candidate.iter().zip(query).all(|(a, b)| {
+            a == b || (!options.case_sensitive && a.to_lowercase().eq(b.to_lowercase()))
+        });
+        if equal && (!options.whole_word || at_word_boundary(text, start, query)) {
+            offsets.push(start);
+            start += query.len();
+        } else {
+            start += 1;
+        }
+    }
+    offsets
+}
+
+/// 判断匹配两侧是否为词边界
+///
+/// 只有匹配边缘和相邻字符都是非 CJK 的字母数字时才视为处在单词内部
+fn at_word_boundary(text: &[char], start: usize, query: &[char]) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() && !is_cjk(c);
+    let end = start + query.len();
+
+    let left_ok = start == 0 || !(is_word_char(text[start - 1]) && is_word_char(query[0]));
+    let right_ok = end == text.len() || !(is_word_char(text[end]) && is_word_char(query[query.len() - 1]));
+    left_ok && right_ok
+}
+
+/// 截取匹配前后的上下文
+fn snippet(text: &[char], offset: usize, len: usize) -> String {
+    let start = offset.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let end = (offset + len + SNIPPET_CONTEXT_CHARS).min(text.len());
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    snippet.extend(&text[start..end]);
+    if end < text.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::irp::TextRun;
+    use tempfile::TempDir;
+
+    fn run(text: &str) -> Vec<TextRun> {
+        vec![TextRun {
+            text: text.to_string(),
+            marks: vec![],
+            attributes: None,
+        }]
+    }
+
+    #[test]
+    fn test_search_term_in_two_chapters() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('搜索', '/test/search')", []).unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        let chapter_id = irp::create_chapter(&conn, book_id, "第一章", 0, "explicit").unwrap() as i32;
+        irp::create_block(&conn, chapter_id, 0, "paragraph", &run("Nothing here."), None).unwrap();
+        irp::create_block(&conn, chapter_id, 1, "paragraph", &run("The Whale surfaced."), None).unwrap();
+        irp::create_chapter_with_html(
+            &conn,
+            book_id,
+            "第二章",
+            1,
+            "explicit",
+            Some("<html><body><p>开头</p><p>白鲸 whale 与 whales 出现了</p></body></html>"),
+            "html",
+        )
+        .unwrap();
+
+        let matches = search_in_book(&conn, book_id, "whale", SearchOptions::default(), None).unwrap();
+        let positions: Vec<(i32, i32, usize)> =
+            matches.iter().map(|m| (m.chapter_index, m.block_index, m.match_offset)).collect();
+        assert_eq!(positions, vec![(0, 1, 4), (1, 1, 3), (1, 1, 11)]);
+        assert_eq!(matches[0].snippet, "The Whale surfaced.");
+
+        // 全词匹配排除 whales，区分大小写排除 Whale
+        let whole_word = SearchOptions { whole_word: true, ..Default::default() };
+        assert_eq!(search_in_book(&conn, book_id, "whale", whole_word, None).unwrap().len(), 2);
+        let case_sensitive = SearchOptions { case_sensitive: true, ..Default::default() };
+        assert_eq!(search_in_book(&conn, book_id, "whale", case_sensitive, None).unwrap().len(), 2);
+
+        // CJK 没有词边界，全词模式下仍能匹配
+        let cjk = search_in_book(&conn, book_id, "白鲸", whole_word, None).unwrap();
+        assert_eq!(cjk.len(), 1);
+        assert_eq!((cjk[0].chapter_index, cjk[0].block_index, cjk[0].match_offset), (1, 1, 0));
+
+        assert!(search_in_book(&conn, book_id, "  ", SearchOptions::default(), None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_markdown_match_in_chapter_after_fenced_heading() {
+        use crate::async_import::save_parse_result;
+        use crate::parser::{md_parser::MarkdownParser, Parser};
+
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('笔记', '/test/notes.md')", []).unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+        let path = temp_dir.path().join("notes.md");
+        std::fs::write(&path, "# 安装\n```sh\n# 安装依赖\n```\n# 使用\n运行 deep-reader 即可\n").unwrap();
+        let result = MarkdownParser::new().parse(&path, book_id, &conn).unwrap();
+        save_parse_result(&conn, book_id, &result, None).unwrap();
+
+        // 代码块中的 # 不是标题，匹配归属于导入时划分的第二章
+        let matches = search_in_book(&conn, book_id, "deep-reader", SearchOptions::default(), None).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].chapter_index, 1);
+        assert_eq!(search_in_book(&conn, book_id, "安装依赖", SearchOptions::default(), None).unwrap()[0].chapter_index, 0);
+    }
+
+    #[test]
+    fn test_snippet_is_trimmed_around_match() {
+        let text: Vec<char> = format!("{}needle{}", "a".repeat(40), "b".repeat(40)).chars().collect();
+        let offsets = find_matches(&text, &"NEEDLE".chars().collect::<Vec<_>>(), SearchOptions::default());
+        assert_eq!(offsets, vec![40]);
+        let snippet = snippet(&text, 40, 6);
+        assert_eq!(snippet, format!("…{}needle{}…", "a".repeat(30), "b".repeat(30)));
+    }
+}
@@ -0,0 +1,274 @@
+use crate::error::AppError;
+use crate::export::{self, ExportFormat};
+use crate::irp;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+
+// 书籍统计模块：字数统计与预计阅读时间
+
+/// 默认阅读速度（字/分钟）
+pub const DEFAULT_WORDS_PER_MINUTE: u32 = 300;
+
+/// 文本计数结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextCounts {
+    pub char_count: i64,
+    pub word_count: i64,
+}
+
+/// 书籍统计信息
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BookStats {
+    pub book_id: i32,
+    pub char_count: i64,
+    pub word_count: i64,
+    pub chapter_count: i32,
+    pub words_per_minute: u32,
+    pub reading_minutes: i64,
+}
+
+/// 判断字符是否为 CJK 字符（汉字、假名、谚文）
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{4E00}'..='\u{9FFF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{3040}'..='\u{309F}'
+        | '\u{30A0}'..='\u{30FF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{20000}'..='\u{2A6DF}'
+    )
+}
+
+/// 统计文本的字符数和词数
+///
+/// - 字符数：所有非空白字符
+/// - 词数：CJK 字符逐字计数，其他文字按空白分词（只含标点的片段不计）
+pub fn count_text(text: &str) -> TextCounts {
+    let mut counts = TextCounts::default();
+    let mut in_word = false;
+    let mut word_has_alnum = false;
+
+    let finish_word = |in_word: &mut bool, has_alnum: &mut bool, counts: &mut TextCounts| {
+        if *in_word && *has_alnum {
+            counts.word_count += 1;
+        }
+        *in_word = false;
+        *has_alnum = false;
+    };
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            finish_word(&mut in_word, &mut word_has_alnum, &mut counts);
+            continue;
+        }
+
+        counts.char_count += 1;
+
+        if is_cjk(c) {
+            finish_word(&mut in_word, &mut word_has_alnum, &mut counts);
+            counts.word_count += 1;
+        } else {
+            in_word = true;
+            word_has_alnum |= c.is_alphanumeric();
+        }
+    }
+    finish_word(&mut in_word, &mut word_has_alnum, &mut counts);
+
+    counts
+}
+
+/// 根据词数和阅读速度估算阅读分钟数（向上取整）
+pub fn estimate_reading_minutes(word_count: i64, words_per_minute: u32) -> i64 {
+    let wpm = words_per_minute.max(1) as i64;
+    (word_count + wpm - 1) / wpm
+}
+
+/// 从章节内容（blocks 或去除标签后的 raw_html）计算整本书的计数
+pub fn compute_book_counts(conn: &Connection, book_id: i32, key: Option<&[u8]>) -> Result<TextCounts, String> {
+    let text = export::export_book(conn, book_id, ExportFormat::Text, key)?;
+    Ok(count_text(&text))
+}
+
+/// 计算并缓存书籍的字数统计到 books 表
+pub fn cache_book_counts(conn: &Connection, book_id: i32, key: Option<&[u8]>) -> Result<TextCounts, String> {
+    let counts = compute_book_counts(conn, book_id, key)?;
+    conn.execute(
+        "UPDATE books SET char_count = ?1, word_count = ?2 WHERE id = ?3",
+        rusqlite::params![counts.char_count, counts.word_count, book_id],
+    )
+    .map_err(|e| format!("缓存字数统计失败: {}", e))?;
+    Ok(counts)
+}
+
+/// 章节字符数
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ChapterCharCount {
+    pub chapter_id: i32,
+    pub chapter_index: i32,
+    pub char_count: i64,
+}
+
+/// 重新计算并缓存书籍每个章节的字符数到 chapters 表
+///
+/// 导入时已经计算过字符数，这里用于旧版本导入的书籍
+///
+/// # 返回
+/// 按章节顺序排列的字符数
+pub fn cache_chapter_char_counts(
+    conn: &Connection,
+    book_id: i32,
+    key: Option<&[u8]>,
+) -> Result<Vec<ChapterCharCount>, String> {
+    let chapters = irp::get_chapters_by_book(conn, book_id, key)
+        .map_err(|e| format!("获取章节失败: {}", e))?;
+
+    let mut counts = Vec::with_capacity(chapters.len());
+    for chapter in &chapters {
+        let char_count = count_text(&export::chapter_plain_text(conn, chapter.id, key)?).char_count;
+        irp::set_chapter_char_count(conn, chapter.id, char_count)
+            .map_err(|e| format!("缓存章节字符数失败: {}", e))?;
+        counts.push(ChapterCharCount {
+            chapter_id: chapter.id,
+            chapter_index: chapter.chapter_index,
+            char_count,
+        });
+    }
+    Ok(counts)
+}
+
+/// 获取书籍统计信息，优先使用缓存的计数
+///
+/// # 参数
+/// - `conn`: 数据库连接
+/// - `book_id`: 书籍 ID
+/// - `words_per_minute`: 阅读速度（字/分钟）
+/// - `key`: 加密书籍的解密密钥（仅在需要重新计算时使用）
+pub fn get_book_stats(
+    conn: &Connection,
+    book_id: i32,
+    words_per_minute: u32,
+    key: Option<&[u8]>,
+) -> Result<BookStats, AppError> {
+    let cached: (Option<i64>, Option<i64>) = conn
+        .query_row(
+            "SELECT char_count, word_count FROM books WHERE id = ?1",
+            [book_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("找不到书籍".to_string()))?;
+
+    let counts = match cached {
+        (Some(char_count), Some(word_count)) => TextCounts {
+            char_count,
+            word_count,
+        },
+        _ => cache_book_counts(conn, book_id, key).map_err(AppError::Db)?,
+    };
+
+    let chapter_count: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM chapters WHERE book_id = ?1",
+        [book_id],
+        |row| row.get(0),
+    )?;
+
+    Ok(BookStats {
+        book_id,
+        char_count: counts.char_count,
+        word_count: counts.word_count,
+        chapter_count,
+        words_per_minute,
+        reading_minutes: estimate_reading_minutes(counts.word_count, words_per_minute),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::irp::{self, TextRun};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_count_text_mixed() {
+        let counts = count_text("你好，世界。 Hello world, it's me!");
+        // 你好，世界。 = 6 个字符，Hello world, it's me! = 18 个非空白字符
+        assert_eq!(counts.char_count, 24);
+        // 4 个汉字 + 4 个英文单词（标点不计词）
+        assert_eq!(counts.word_count, 8);
+    }
+
+    #[test]
+    fn test_count_text_empty() {
+        assert_eq!(count_text("  \n\t "), TextCounts::default());
+    }
+
+    #[test]
+    fn test_estimate_reading_minutes() {
+        assert_eq!(estimate_reading_minutes(0, 300), 0);
+        assert_eq!(estimate_reading_minutes(1, 300), 1);
+        assert_eq!(estimate_reading_minutes(600, 300), 2);
+        assert_eq!(estimate_reading_minutes(601, 300), 3);
+    }
+
+    #[test]
+    fn test_get_book_stats_known_fixture() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute(
+            "INSERT INTO books (title, author, file_path) VALUES ('统计', NULL, '/test/stats')",
+            [],
+        )
+        .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        let chapter_id =
+            irp::create_chapter(&conn, book_id, "第一章", 0, "explicit").unwrap() as i32;
+        irp::create_block(
+            &conn,
+            chapter_id,
+            0,
+            "paragraph",
+            &[TextRun {
+                text: "一二三四五 six seven".to_string(),
+                marks: vec![],
+                attributes: None,
+            }],
+            None,
+        )
+        .unwrap();
+        irp::create_chapter_with_html(
+            &conn,
+            book_id,
+            "第二章",
+            1,
+            "explicit",
+            Some("<html><body><p>eight <b>nine</b> 十</p></body></html>"),
+            "html",
+        )
+        .unwrap();
+
+        let stats = get_book_stats(&conn, book_id, 5, None).unwrap();
+        assert_eq!(stats.char_count, 5 + 3 + 5 + 5 + 4 + 1);
+        assert_eq!(stats.word_count, 10);
+        assert_eq!(stats.chapter_count, 2);
+        assert_eq!(stats.reading_minutes, 2);
+
+        // 计数已缓存到 books 表
+        let cached: Option<i64> = conn
+            .query_row("SELECT word_count FROM books WHERE id = ?1", [book_id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(cached, Some(10));
+    }
+
+    #[test]
+    fn test_get_book_stats_missing_book() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        assert!(matches!(
+            get_book_stats(&conn, 42, DEFAULT_WORDS_PER_MINUTE, None),
+            Err(AppError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+use rusqlite::{Connection, Result};
+use serde::{Deserialize, Serialize};
+
+// 书签模块：独立于笔记的“回到这里”标记
+
+/// 书签
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bookmark {
+    pub id: i32,
+    pub book_id: i32,
+    pub chapter_index: i32,
+    pub block_id: Option<i32>,
+    pub label: Option<String>,
+    pub created_at: String,
+}
+
+/// 添加书签
+///
+/// # 返回
+/// 新书签的 ID
+pub fn add_bookmark(
+    conn: &Connection,
+    book_id: i32,
+    chapter_index: i32,
+    block_id: Option<i32>,
+    label: Option<&str>,
+) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO bookmarks (book_id, chapter_index, block_id, label) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![book_id, chapter_index, block_id, label],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// 获取单个书签
+pub fn get_bookmark_by_id(conn: &Connection, id: i32) -> Result<Bookmark> {
+    conn.query_row(
+        "SELECT id, book_id, chapter_index, block_id, label, created_at FROM bookmarks WHERE id = ?1",
+        [id],
+        |row| {
+            Ok(Bookmark {
+                id: row.get(0)?,
+                book_id: row.get(1)?,
+                chapter_index: row.get(2)?,
+                block_id: row.get(3)?,
+                label: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        },
+    )
+}
+
+/// 获取书籍的所有书签（按阅读位置排序）
+pub fn list_bookmarks(conn: &Connection, book_id: i32) -> Result<Vec<Bookmark>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, book_id, chapter_index, block_id, label, created_at
+         FROM bookmarks WHERE book_id = ?1
+         ORDER BY chapter_index, block_id, id",
+    )?;
+
+    let bookmarks = stmt
+        .query_map([book_id], |row| {
+            Ok(Bookmark {
+                id: row.get(0)?,
+                book_id: row.get(1)?,
+                chapter_index: row.get(2)?,
+                block_id: row.get(3)?,
+                label: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(bookmarks)
+}
+
+/// 删除书签
+///
+/// # 返回
+/// 是否删除了书签
+pub fn remove_bookmark(conn: &Connection, id: i32) -> Result<bool> {
+    let affected = conn.execute("DELETE FROM bookmarks WHERE id = ?1", [id])?;
+    Ok(affected > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use tempfile::TempDir;
+
+    fn create_test_book() -> (TempDir, Connection, i32) {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let conn = db::init_db(&db_path).unwrap();
+
+        conn.execute(
+            "INSERT INTO books (title, author, file_path) VALUES (?1, ?2, ?3)",
+            rusqlite::params!["测试书籍", "测试作者", "/test/path"],
+        )
+        .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        (temp_dir, conn, book_id)
+    }
+
+    #[test]
+    fn test_add_and_list_bookmarks() {
+        let (_temp_dir, conn, book_id) = create_test_book();
+
+        add_bookmark(&conn, book_id, 3, Some(12), Some("稍后再读")).unwrap();
+        add_bookmark(&conn, book_id, 1, None, None).unwrap();
+
+        let bookmarks = list_bookmarks(&conn, book_id).unwrap();
+        assert_eq!(bookmarks.len(), 2);
+        // 按章节顺序排列
+        assert_eq!(bookmarks[0].chapter_index, 1);
+        assert_eq!(bookmarks[0].label, None);
+        assert_eq!(bookmarks[1].chapter_index, 3);
+        assert_eq!(bookmarks[1].block_id, Some(12));
+        assert_eq!(bookmarks[1].label.as_deref(), Some("稍后再读"));
+
+        // 其他书籍没有书签
+        assert!(list_bookmarks(&conn, book_id + 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_removing_book_removes_bookmarks() {
+        let (_temp_dir, conn, book_id) = create_test_book();
+        add_bookmark(&conn, book_id, 0, None, None).unwrap();
+        add_bookmark(&conn, book_id, 2, Some(5), Some("标记")).unwrap();
+
+        conn.execute("DELETE FROM books WHERE id = ?1", [book_id]).unwrap();
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM bookmarks", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_remove_bookmark() {
+        let (_temp_dir, conn, book_id) = create_test_book();
+
+        let id = add_bookmark(&conn, book_id, 0, None, None).unwrap() as i32;
+        assert!(get_bookmark_by_id(&conn, id).is_ok());
+
+        assert!(remove_bookmark(&conn, id).unwrap());
+        assert!(list_bookmarks(&conn, book_id).unwrap().is_empty());
+
+        // 重复删除返回 false
+        assert!(!remove_bookmark(&conn, id).unwrap());
+    }
+
+    #[test]
+    fn test_bookmarks_separate_from_notes() {
+        let (_temp_dir, conn, book_id) = create_test_book();
+
+        add_bookmark(&conn, book_id, 0, None, Some("书签")).unwrap();
+
+        let note_count: i32 = conn
+            .query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(note_count, 0);
+    }
+}
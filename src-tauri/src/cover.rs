@@ -0,0 +1,468 @@
+use crate::error::AppError;
+use base64::{engine::general_purpose, Engine as _};
+use image::imageops::FilterType;
+use image::ImageFormat;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Cursor;
+
+// 封面处理模块：封面解码、缩略图生成、主色调提取
+
+/// 无封面或解码失败时使用的中性色
+pub const NEUTRAL_COVER_COLOR: &str = "#9ca3af";
+
+/// 缩略图最长边（像素）
+pub const THUMBNAIL_MAX_SIZE: u32 = 300;
+
+/// 提取主色调前的缩略尺寸
+const PALETTE_SAMPLE_SIZE: u32 = 32;
+
+/// 封面配色
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CoverPalette {
+    /// 主色调（#rrggbb）
+    pub dominant_color: String,
+    /// 主色调是否为深色（前端据此选择浅色文字）
+    pub is_dark: bool,
+}
+
+impl CoverPalette {
+    /// 从 #rrggbb 颜色值构建配色
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let (r, g, b) = parse_hex_color(hex)?;
+        Some(CoverPalette {
+            dominant_color: format_hex_color(r, g, b),
+            is_dark: is_dark_color(r, g, b),
+        })
+    }
+
+    /// 中性默认配色
+    pub fn neutral() -> Self {
+        // NEUTRAL_COVER_COLOR 是合法的颜色常量
+        Self::from_hex(NEUTRAL_COVER_COLOR).unwrap_or(CoverPalette {
+            dominant_color: NEUTRAL_COVER_COLOR.to_string(),
+            is_dark: false,
+        })
+    }
+}
+
+/// 解码封面数据（支持 data URL 和纯 base64）
+pub fn decode_cover_data(cover: &str) -> Result<Vec<u8>, String> {
+    let payload = match cover.split_once("base64,") {
+        Some((_, data)) => data,
+        None => cover,
+    };
+
+    general_purpose::STANDARD
+        .decode(payload.trim())
+        .map_err(|e| format!("封面 base64 解码失败: {}", e))
+}
+
+/// 生成封面缩略图
+///
+/// 将图片等比缩放到最长边不超过 300px 并编码为 PNG；
+/// 原图已经足够小时直接返回原始数据
+pub fn create_thumbnail(image_data: &[u8]) -> Result<Vec<u8>, String> {
+    let img = image::load_from_memory(image_data).map_err(|e| format!("封面解码失败: {}", e))?;
+
+    if img.width() <= THUMBNAIL_MAX_SIZE && img.height() <= THUMBNAIL_MAX_SIZE {
+        return Ok(image_data.to_vec());
+    }
+
+    let thumbnail = img.resize(THUMBNAIL_MAX_SIZE, THUMBNAIL_MAX_SIZE, FilterType::Triangle);
+    let mut bytes = Vec::new();
+    thumbnail
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .map_err(|e| format!("缩略图编码失败: {}", e))?;
+
+    // 缩放后的 PNG 反而更大时（例如高压缩率的 JPEG 原图）保留原图
+    if bytes.len() >= image_data.len() {
+        return Ok(image_data.to_vec());
+    }
+
+    Ok(bytes)
+}
+
+/// 将图片数据编码为 data URL
+pub fn encode_data_url(image_data: &[u8]) -> String {
+    let mime = match image::guess_format(image_data) {
+        Ok(ImageFormat::Jpeg) => "image/jpeg",
+        Ok(ImageFormat::Gif) => "image/gif",
+        Ok(ImageFormat::WebP) => "image/webp",
+        _ => "image/png",
+    };
+    format!("data:{};base64,{}", mime, general_purpose::STANDARD.encode(image_data))
+}
+
+/// 为书籍生成并保存封面缩略图（books.cover_thumbnail）
+///
+/// # 返回
+/// 缩略图 data URL，没有封面时返回 None
+pub fn cache_cover_thumbnail(conn: &Connection, book_id: i32) -> Result<Option<String>, AppError> {
+    let cover: Option<String> = conn
+        .query_row("SELECT cover_image FROM books WHERE id = ?1", [book_id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("找不到书籍".to_string()))?;
+
+    let cover = match cover.filter(|c| !c.is_empty()) {
+        Some(cover) => cover,
+        None => return Ok(None),
+    };
+
+    let image_data = decode_cover_data(&cover).map_err(AppError::Parse)?;
+    let thumbnail = encode_data_url(&create_thumbnail(&image_data).map_err(AppError::Parse)?);
+
+    conn.execute(
+        "UPDATE books SET cover_thumbnail = ?1 WHERE id = ?2",
+        rusqlite::params![thumbnail, book_id],
+    )
+    .map_err(|e| AppError::Db(format!("保存缩略图失败: {}", e)))?;
+
+    Ok(Some(thumbnail))
+}
+
+/// 校验用户提供的封面图片：按文件头识别格式，并确认能完整解码
+pub fn validate_cover_image(image_data: &[u8]) -> Result<(), String> {
+    match image::guess_format(image_data) {
+        Ok(ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP) => {}
+        _ => return Err("不支持的封面图片格式（可选: PNG、JPEG、GIF、WebP）".to_string()),
+    }
+    image::load_from_memory(image_data).map_err(|e| format!("封面解码失败: {}", e))?;
+    Ok(())
+}
+
+/// 手动设置书籍封面，同时重新生成缩略图并清除缓存的配色
+///
+/// # 参数
+/// - `image_data`: 已通过 `validate_cover_image` 校验的图片数据
+///
+/// # 返回
+/// 新封面的缩略图 data URL
+pub fn set_book_cover(conn: &Connection, book_id: i32, image_data: &[u8]) -> Result<Option<String>, AppError> {
+    let updated = conn
+        .execute(
+            "UPDATE books SET cover_image = ?1, cover_thumbnail = NULL, cover_color = NULL WHERE id = ?2",
+            rusqlite::params![encode_data_url(image_data), book_id],
+        )
+        .map_err(|e| AppError::Db(format!("保存封面失败: {}", e)))?;
+    if updated == 0 {
+        return Err(AppError::NotFound("找不到书籍".to_string()));
+    }
+
+    cache_cover_thumbnail(conn, book_id)
+}
+
+/// 移除书籍封面（包括缩略图和缓存的配色）
+pub fn clear_book_cover(conn: &Connection, book_id: i32) -> Result<(), AppError> {
+    let updated = conn
+        .execute(
+            "UPDATE books SET cover_image = NULL, cover_thumbnail = NULL, cover_color = NULL WHERE id = ?1",
+            [book_id],
+        )
+        .map_err(|e| AppError::Db(format!("移除封面失败: {}", e)))?;
+    if updated == 0 {
+        return Err(AppError::NotFound("找不到书籍".to_string()));
+    }
+    Ok(())
+}
+
+/// 获取书籍封面缩略图，旧数据没有缩略图时按需生成（惰性迁移）
+pub fn get_cover_thumbnail(conn: &Connection, book_id: i32) -> Result<Option<String>, AppError> {
+    let (thumbnail, cover): (Option<String>, Option<String>) = conn
+        .query_row(
+            "SELECT cover_thumbnail, cover_image FROM books WHERE id = ?1",
+            [book_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("找不到书籍".to_string()))?;
+
+    if thumbnail.is_some() {
+        return Ok(thumbnail);
+    }
+
+    match cache_cover_thumbnail(conn, book_id) {
+        Ok(thumbnail) => Ok(thumbnail),
+        Err(e) => {
+            // 缩略图生成失败时退回原图，保证书库仍能显示封面
+            eprintln!("生成缩略图失败 (book_id: {}): {}", book_id, e);
+            Ok(cover)
+        }
+    }
+}
+
+/// 获取书籍的原始尺寸封面
+pub fn get_full_cover(conn: &Connection, book_id: i32) -> Result<Option<String>, AppError> {
+    conn.query_row("SELECT cover_image FROM books WHERE id = ?1", [book_id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("找不到书籍".to_string()))
+}
+
+/// 量化后的颜色桶：像素数及各通道总和
+#[derive(Default)]
+struct ColorBucket {
+    count: u64,
+    r: u64,
+    g: u64,
+    b: u64,
+}
+
+impl ColorBucket {
+    fn average(&self) -> (u8, u8, u8) {
+        (
+            (self.r / self.count) as u8,
+            (self.g / self.count) as u8,
+            (self.b / self.count) as u8,
+        )
+    }
+}
+
+/// 从图片数据中提取主色调
+///
+/// 先缩小到 32x32，再按 4 位精度量化颜色并统计出现次数，
+/// 取出现最多的颜色桶内像素的平均值作为主色调
+pub fn extract_palette(image_data: &[u8]) -> Result<CoverPalette, String> {
+    let img = image::load_from_memory(image_data).map_err(|e| format!("封面解码失败: {}", e))?;
+    let small = img
+        .resize_exact(PALETTE_SAMPLE_SIZE, PALETTE_SAMPLE_SIZE, FilterType::Triangle)
+        .to_rgba8();
+
+    let mut buckets: HashMap<(u8, u8, u8), ColorBucket> = HashMap::new();
+    for pixel in small.pixels() {
+        let [r, g, b, a] = pixel.0;
+        // 忽略几乎透明的像素
+        if a < 128 {
+            continue;
+        }
+        let bucket = buckets.entry((r >> 4, g >> 4, b >> 4)).or_default();
+        bucket.count += 1;
+        bucket.r += r as u64;
+        bucket.g += g as u64;
+        bucket.b += b as u64;
+    }
+
+    let dominant = buckets
+        .into_iter()
+        .max_by(|a, b| a.1.count.cmp(&b.1.count).then_with(|| b.0.cmp(&a.0)))
+        .map(|(_, bucket)| bucket.average());
+
+    match dominant {
+        Some((r, g, b)) => Ok(CoverPalette {
+            dominant_color: format_hex_color(r, g, b),
+            is_dark: is_dark_color(r, g, b),
+        }),
+        None => Ok(CoverPalette::neutral()),
+    }
+}
+
+/// 计算书籍封面配色并缓存到 books.cover_color
+///
+/// 没有封面或解码失败时返回中性默认值（不写入缓存）
+pub fn cache_cover_palette(conn: &Connection, book_id: i32) -> Result<CoverPalette, AppError> {
+    let cover: Option<String> = conn
+        .query_row("SELECT cover_image FROM books WHERE id = ?1", [book_id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("找不到书籍".to_string()))?;
+
+    let palette = match cover.filter(|c| !c.is_empty()) {
+        Some(cover) => match decode_cover_data(&cover).and_then(|data| extract_palette(&data)) {
+            Ok(palette) => palette,
+            Err(e) => {
+                eprintln!("提取封面配色失败 (book_id: {}): {}", book_id, e);
+                return Ok(CoverPalette::neutral());
+            }
+        },
+        None => return Ok(CoverPalette::neutral()),
+    };
+
+    conn.execute(
+        "UPDATE books SET cover_color = ?1 WHERE id = ?2",
+        rusqlite::params![palette.dominant_color, book_id],
+    )
+    .map_err(|e| AppError::Db(format!("缓存封面配色失败: {}", e)))?;
+
+    Ok(palette)
+}
+
+/// 获取书籍封面配色，优先使用缓存
+pub fn get_cover_palette(conn: &Connection, book_id: i32) -> Result<CoverPalette, AppError> {
+    let cached: Option<String> = conn
+        .query_row("SELECT cover_color FROM books WHERE id = ?1", [book_id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("找不到书籍".to_string()))?;
+
+    if let Some(palette) = cached.as_deref().and_then(CoverPalette::from_hex) {
+        return Ok(palette);
+    }
+
+    cache_cover_palette(conn, book_id)
+}
+
+fn parse_hex_color(hex: &str) -> Option<(u8, u8, u8)> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let r = u8::from_str_radix(hex.get(0..2)?, 16).ok()?;
+    let g = u8::from_str_radix(hex.get(2..4)?, 16).ok()?;
+    let b = u8::from_str_radix(hex.get(4..6)?, 16).ok()?;
+    Some((r, g, b))
+}
+
+fn format_hex_color(r: u8, g: u8, b: u8) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// 按感知亮度判断是否为深色
+fn is_dark_color(r: u8, g: u8, b: u8) -> bool {
+    let luminance = 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64;
+    luminance < 128.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use image::{GenericImageView, ImageBuffer, Rgba};
+    use tempfile::TempDir;
+
+    fn solid_png(color: [u8; 4], width: u32, height: u32) -> Vec<u8> {
+        let img = ImageBuffer::from_pixel(width, height, Rgba(color));
+        let mut bytes = Vec::new();
+        img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_extract_palette_solid_color() {
+        let png = solid_png([200, 30, 40, 255], 64, 96);
+        let palette = extract_palette(&png).unwrap();
+
+        assert_eq!(palette.dominant_color, "#c81e28");
+        assert!(palette.is_dark);
+
+        let light = extract_palette(&solid_png([250, 240, 220, 255], 10, 10)).unwrap();
+        assert_eq!(light.dominant_color, "#faf0dc");
+        assert!(!light.is_dark);
+    }
+
+    /// 生成带渐变的图片，避免纯色 PNG 压缩得过小
+    fn gradient_png(width: u32, height: u32) -> Vec<u8> {
+        let img = ImageBuffer::from_fn(width, height, |x, y| {
+            Rgba([(x % 256) as u8, (y % 256) as u8, ((x * y) % 256) as u8, 255])
+        });
+        let mut bytes = Vec::new();
+        img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_create_thumbnail_is_smaller() {
+        let source = gradient_png(600, 900);
+        let thumbnail = create_thumbnail(&source).unwrap();
+
+        assert!(thumbnail.len() < source.len());
+
+        let img = image::load_from_memory(&thumbnail).unwrap();
+        assert_eq!(img.dimensions(), (200, 300));
+    }
+
+    #[test]
+    fn test_create_thumbnail_keeps_small_image() {
+        let source = solid_png([1, 2, 3, 255], 100, 150);
+        assert_eq!(create_thumbnail(&source).unwrap(), source);
+    }
+
+    #[test]
+    fn test_cover_thumbnail_lazy_migration() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+
+        let full = encode_data_url(&gradient_png(400, 600));
+        conn.execute(
+            "INSERT INTO books (title, author, file_path, cover_image) VALUES ('旧书', NULL, '/a', ?1)",
+            [&full],
+        )
+        .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        let thumbnail = get_cover_thumbnail(&conn, book_id).unwrap().unwrap();
+        assert!(thumbnail.starts_with("data:image/png;base64,"));
+        assert!(thumbnail.len() < full.len());
+
+        // 缩略图已持久化，原图保持不变
+        let stored: Option<String> = conn
+            .query_row("SELECT cover_thumbnail FROM books WHERE id = ?1", [book_id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stored.as_deref(), Some(thumbnail.as_str()));
+        assert_eq!(get_full_cover(&conn, book_id).unwrap(), Some(full));
+    }
+
+    #[test]
+    fn test_decode_cover_data_url() {
+        let png = solid_png([0, 0, 255, 255], 4, 4);
+        let data_url = format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(&png));
+
+        assert_eq!(decode_cover_data(&data_url).unwrap(), png);
+        assert!(decode_cover_data("data:image/png;base64,@@@").is_err());
+    }
+
+    #[test]
+    fn test_set_and_clear_book_cover() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path, cover_color) VALUES ('纯文本', '/a.txt', '#000000')", [])
+            .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        assert!(validate_cover_image(b"not an image").is_err());
+        assert!(validate_cover_image(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n', 0, 0]).is_err());
+
+        let source = gradient_png(600, 900);
+        validate_cover_image(&source).unwrap();
+        let thumbnail = set_book_cover(&conn, book_id, &source).unwrap().unwrap();
+        let img = image::load_from_memory(&decode_cover_data(&thumbnail).unwrap()).unwrap();
+        assert_eq!(img.dimensions(), (200, 300));
+        assert_eq!(get_full_cover(&conn, book_id).unwrap(), Some(encode_data_url(&source)));
+        assert_ne!(get_cover_palette(&conn, book_id).unwrap().dominant_color, "#000000");
+
+        clear_book_cover(&conn, book_id).unwrap();
+        assert_eq!(get_cover_thumbnail(&conn, book_id).unwrap(), None);
+        assert_eq!(get_cover_palette(&conn, book_id).unwrap(), CoverPalette::neutral());
+        assert!(set_book_cover(&conn, 999, &source).is_err());
+        assert!(clear_book_cover(&conn, 999).is_err());
+    }
+
+    #[test]
+    fn test_get_cover_palette_caches_and_defaults() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+
+        let png = solid_png([10, 120, 60, 255], 20, 30);
+        let data_url = format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(&png));
+        conn.execute(
+            "INSERT INTO books (title, author, file_path, cover_image) VALUES ('有封面', NULL, '/a', ?1)",
+            [&data_url],
+        )
+        .unwrap();
+        let with_cover = conn.last_insert_rowid() as i32;
+        conn.execute(
+            "INSERT INTO books (title, author, file_path) VALUES ('无封面', NULL, '/b')",
+            [],
+        )
+        .unwrap();
+        let without_cover = conn.last_insert_rowid() as i32;
+
+        let palette = get_cover_palette(&conn, with_cover).unwrap();
+        assert_eq!(palette.dominant_color, "#0a783c");
+
+        let cached: Option<String> = conn
+            .query_row("SELECT cover_color FROM books WHERE id = ?1", [with_cover], |row| row.get(0))
+            .unwrap();
+        assert_eq!(cached.as_deref(), Some("#0a783c"));
+
+        assert_eq!(get_cover_palette(&conn, without_cover).unwrap(), CoverPalette::neutral());
+        assert!(get_cover_palette(&conn, 999).is_err());
+    }
+}
@@ -1,32 +1,75 @@
 use rusqlite::{Connection, Result};
 use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
 
-pub fn init_db<P: AsRef<Path>>(path: P) -> Result<Connection> {
+/// 共享数据库连接
+///
+/// 应用启动时打开一次并执行建表，之后所有命令通过 `with_conn` 复用同一个连接
+pub struct Database {
+    conn: Mutex<Connection>,
+}
+
+impl Database {
+    /// 打开数据库并初始化表结构（只在启动时调用一次）
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            conn: Mutex::new(init_db(path)?),
+        })
+    }
+
+    /// 在共享连接上执行操作
+    ///
+    /// 持有锁期间不要再次调用 `with_conn`，也不要跨 `.await` 使用连接
+    pub fn with_conn<T, E>(&self, f: impl FnOnce(&Connection) -> Result<T, E>) -> Result<T, E> {
+        // 某个命令 panic 不会破坏连接本身，直接继续使用
+        let conn = self.conn.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&conn)
+    }
+
+    /// 在共享连接上执行需要可变引用的操作（如从备份恢复）
+    pub fn with_conn_mut<T, E>(&self, f: impl FnOnce(&mut Connection) -> Result<T, E>) -> Result<T, E> {
+        let mut conn = self.conn.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&mut conn)
+    }
+}
+
+/// 打开数据库连接（不执行建表）
+///
+/// 用于后台导入等需要独立连接的场景，表结构由启动时的 `init_db` 保证
+pub fn open_db<P: AsRef<Path>>(path: P) -> Result<Connection> {
     let conn = Connection::open(path)?;
 
     conn.execute("PRAGMA encoding = 'UTF-8'", [])?;
-    
-    // 书籍表（已存在）
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS books (
+    // 外键检查按连接生效，显式开启而不依赖编译选项（bundled SQLite 默认开启，系统 SQLite 默认关闭），
+    // 删除书籍时级联删除章节、书签等
+    conn.execute("PRAGMA foreign_keys = ON", [])?;
+    // 共享连接与导入连接可能同时写入，等待锁释放而不是立即报错
+    conn.busy_timeout(Duration::from_secs(5))?;
+
+    Ok(conn)
+}
+
+/// 数据库迁移步骤：(版本号, SQL)
+///
+/// 按版本号顺序执行，每执行一步就把 `PRAGMA user_version` 更新为该版本。
+/// 新增表结构变更时只能在末尾追加新步骤，不能修改已发布的步骤。
+/// 每个 `ADD COLUMN` 单独占一个版本：版本追踪之前的旧数据库可能已经通过
+/// 临时的 ALTER 语句加过这些列，迁移时会跳过"列已存在"的错误。
+const MIGRATIONS: &[(i32, &str)] = &[
+    // 1: 初始表结构与默认数据
+    (1, "
+        CREATE TABLE IF NOT EXISTS books (
             id INTEGER PRIMARY KEY,
             title TEXT NOT NULL,
             author TEXT,
             file_path TEXT NOT NULL UNIQUE,
             cover_image TEXT,
             added_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        )",
-        [],
-    )?;
-
-    // 添加新字段到 books 表（用于多格式导入）
-    let _ = conn.execute("ALTER TABLE books ADD COLUMN parse_status TEXT DEFAULT 'pending'", []);
-    let _ = conn.execute("ALTER TABLE books ADD COLUMN parse_quality TEXT DEFAULT 'native'", []);
-    let _ = conn.execute("ALTER TABLE books ADD COLUMN total_blocks INTEGER DEFAULT 0", []);
-
-    // 章节表（IRP 架构）
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS chapters (
+        );
+
+        -- 章节表（IRP 架构）
+        CREATE TABLE IF NOT EXISTS chapters (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             book_id INTEGER NOT NULL,
             title TEXT NOT NULL,
@@ -34,18 +77,10 @@ pub fn init_db<P: AsRef<Path>>(path: P) -> Result<Connection> {
             confidence_level TEXT DEFAULT 'explicit',
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
             FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE
-        )",
-        [],
-    )?;
-
-    // 添加新字段到 chapters 表（用于混合渲染模式）
-    let _ = conn.execute("ALTER TABLE chapters ADD COLUMN raw_html TEXT", []);
-    let _ = conn.execute("ALTER TABLE chapters ADD COLUMN render_mode TEXT DEFAULT 'irp'", []);
-    let _ = conn.execute("ALTER TABLE chapters ADD COLUMN heading_level INTEGER DEFAULT 1", []);
-
-    // 内容块表（IRP 架构）
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS blocks (
+        );
+
+        -- 内容块表（IRP 架构）
+        CREATE TABLE IF NOT EXISTS blocks (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             chapter_id INTEGER NOT NULL,
             block_index INTEGER NOT NULL,
@@ -53,13 +88,10 @@ pub fn init_db<P: AsRef<Path>>(path: P) -> Result<Connection> {
             runs_json TEXT NOT NULL,
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
             FOREIGN KEY (chapter_id) REFERENCES chapters(id) ON DELETE CASCADE
-        )",
-        [],
-    )?;
+        );
 
-    // 资产映射表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS asset_mappings (
+        -- 资产映射表
+        CREATE TABLE IF NOT EXISTS asset_mappings (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             book_id INTEGER NOT NULL,
             original_path TEXT NOT NULL,
@@ -67,13 +99,10 @@ pub fn init_db<P: AsRef<Path>>(path: P) -> Result<Connection> {
             asset_type TEXT DEFAULT 'image',
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
             FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE
-        )",
-        [],
-    )?;
+        );
 
-    // 阅读进度表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS reading_progress (
+        -- 阅读进度表
+        CREATE TABLE IF NOT EXISTS reading_progress (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             book_id INTEGER NOT NULL,
             chapter_index INTEGER NOT NULL,
@@ -81,61 +110,33 @@ pub fn init_db<P: AsRef<Path>>(path: P) -> Result<Connection> {
             updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
             FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE,
             UNIQUE(book_id)
-        )",
-        [],
-    )?;
-
-    // 创建 IRP 相关索引
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_chapters_book_id ON chapters(book_id)",
-        [],
-    )?;
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_chapters_index ON chapters(book_id, chapter_index)",
-        [],
-    )?;
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_blocks_chapter_id ON blocks(chapter_id)",
-        [],
-    )?;
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_blocks_index ON blocks(chapter_id, block_index)",
-        [],
-    )?;
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_asset_mappings_book_id ON asset_mappings(book_id)",
-        [],
-    )?;
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_reading_progress_book_id ON reading_progress(book_id)",
-        [],
-    )?;
-
-    // 分类表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS categories (
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_chapters_book_id ON chapters(book_id);
+        CREATE INDEX IF NOT EXISTS idx_chapters_index ON chapters(book_id, chapter_index);
+        CREATE INDEX IF NOT EXISTS idx_blocks_chapter_id ON blocks(chapter_id);
+        CREATE INDEX IF NOT EXISTS idx_blocks_index ON blocks(chapter_id, block_index);
+        CREATE INDEX IF NOT EXISTS idx_asset_mappings_book_id ON asset_mappings(book_id);
+        CREATE INDEX IF NOT EXISTS idx_reading_progress_book_id ON reading_progress(book_id);
+
+        -- 分类表
+        CREATE TABLE IF NOT EXISTS categories (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             name TEXT NOT NULL UNIQUE,
             color TEXT,
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        )",
-        [],
-    )?;
-    
-    // 标签表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS tags (
+        );
+
+        -- 标签表
+        CREATE TABLE IF NOT EXISTS tags (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             name TEXT NOT NULL UNIQUE,
             color TEXT,
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        )",
-        [],
-    )?;
-    
-    // 笔记表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS notes (
+        );
+
+        -- 笔记表
+        CREATE TABLE IF NOT EXISTS notes (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             title TEXT NOT NULL,
             content TEXT,
@@ -143,66 +144,36 @@ pub fn init_db<P: AsRef<Path>>(path: P) -> Result<Connection> {
             book_id INTEGER,
             chapter_index INTEGER,
             highlighted_text TEXT,
-            annotation_type TEXT DEFAULT 'highlight',
             position_start INTEGER,
             position_end INTEGER,
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
             updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
             FOREIGN KEY (category_id) REFERENCES categories(id),
             FOREIGN KEY (book_id) REFERENCES books(id)
-        )",
-        [],
-    )?;
-    
-    // 尝试添加 annotation_type 字段（如果表已存在但没有该字段）
-    let _ = conn.execute("ALTER TABLE notes ADD COLUMN annotation_type TEXT DEFAULT 'highlight'", []);
-    
-    // 尝试添加 deleted_at 字段（如果表已存在但没有该字段）
-    let _ = conn.execute("ALTER TABLE notes ADD COLUMN deleted_at DATETIME", []);
-    
-    // 笔记-标签关联表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS note_tags (
+        );
+
+        -- 笔记-标签关联表
+        CREATE TABLE IF NOT EXISTS note_tags (
             note_id INTEGER NOT NULL,
             tag_id INTEGER NOT NULL,
             PRIMARY KEY (note_id, tag_id),
             FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE,
             FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
-        )",
-        [],
-    )?;
-    
-    // 创建索引以提高查询性能
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_notes_book_id ON notes(book_id)",
-        [],
-    )?;
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_notes_category_id ON notes(category_id)",
-        [],
-    )?;
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_notes_created_at ON notes(created_at)",
-        [],
-    )?;
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_notes_deleted_at ON notes(deleted_at)",
-        [],
-    )?;
-
-    // 插入默认分类
-    conn.execute(
-        "INSERT OR IGNORE INTO categories (name, color) VALUES
-         ('概念', '#3B82F6'),
-         ('观点', '#10B981'),
-         ('疑问', '#F59E0B'),
-         ('行动', '#EF4444')",
-        [],
-    )?;
-
-    // AI 配置表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS ai_config (
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_notes_book_id ON notes(book_id);
+        CREATE INDEX IF NOT EXISTS idx_notes_category_id ON notes(category_id);
+        CREATE INDEX IF NOT EXISTS idx_notes_created_at ON notes(created_at);
+
+        -- 默认分类
+        INSERT OR IGNORE INTO categories (name, color) VALUES
+            ('概念', '#3B82F6'),
+            ('观点', '#10B981'),
+            ('疑问', '#F59E0B'),
+            ('行动', '#EF4444');
+
+        -- AI 配置表
+        CREATE TABLE IF NOT EXISTS ai_config (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             platform TEXT NOT NULL UNIQUE,
             api_key TEXT,
@@ -213,59 +184,53 @@ pub fn init_db<P: AsRef<Path>>(path: P) -> Result<Connection> {
             is_active INTEGER DEFAULT 0,
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
             updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        )",
-        [],
-    )?;
-    
-    // 插入默认平台配置（不包含 API key）
-    conn.execute(
-        "INSERT OR IGNORE INTO ai_config (platform, model, is_active) VALUES 
-         ('openai', 'gpt-3.5-turbo', 0),
-         ('anthropic', 'claude-3-sonnet-20240229', 0),
-         ('google', 'gemini-pro', 0),
-         ('openai-cn', 'gpt-3.5-turbo', 0)",
-        [],
-    )?;
-    
-    // 笔记统计表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS note_statistics (
+        );
+
+        -- 默认平台配置（不包含 API key）
+        INSERT OR IGNORE INTO ai_config (platform, model, is_active) VALUES
+            ('openai', 'gpt-3.5-turbo', 0),
+            ('anthropic', 'claude-3-sonnet-20240229', 0),
+            ('google', 'gemini-pro', 0),
+            ('openai-cn', 'gpt-3.5-turbo', 0);
+
+        -- 笔记统计表
+        CREATE TABLE IF NOT EXISTS note_statistics (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             note_id INTEGER NOT NULL,
             action_type TEXT NOT NULL,
             action_time DATETIME DEFAULT CURRENT_TIMESTAMP,
             duration_seconds INTEGER,
             FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
-        )",
-        [],
-    )?;
-    
-    // 创建统计表索引
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_statistics_note_id ON note_statistics(note_id)",
-        [],
-    )?;
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_statistics_action_time ON note_statistics(action_time)",
-        [],
-    )?;
-    
-    // 创建统计视图
-    conn.execute(
-        "CREATE VIEW IF NOT EXISTS note_analytics AS
-         SELECT
-             DATE(action_time) as date,
-             action_type,
-             COUNT(*) as action_count,
-             AVG(duration_seconds) as avg_duration
-         FROM note_statistics
-         GROUP BY DATE(action_time), action_type",
-        [],
-    )?;
-
-    // Reading Unit 表（章节合并评分系统）
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS reading_units (
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_statistics_note_id ON note_statistics(note_id);
+        CREATE INDEX IF NOT EXISTS idx_statistics_action_time ON note_statistics(action_time);
+
+        -- 统计视图
+        CREATE VIEW IF NOT EXISTS note_analytics AS
+            SELECT
+                DATE(action_time) as date,
+                action_type,
+                COUNT(*) as action_count,
+                AVG(duration_seconds) as avg_duration
+            FROM note_statistics
+            GROUP BY DATE(action_time), action_type;
+    "),
+    // 2-4: 多格式导入
+    (2, "ALTER TABLE books ADD COLUMN parse_status TEXT DEFAULT 'pending'"),
+    (3, "ALTER TABLE books ADD COLUMN parse_quality TEXT DEFAULT 'native'"),
+    (4, "ALTER TABLE books ADD COLUMN total_blocks INTEGER DEFAULT 0"),
+    // 5-7: 混合渲染模式
+    (5, "ALTER TABLE chapters ADD COLUMN raw_html TEXT"),
+    (6, "ALTER TABLE chapters ADD COLUMN render_mode TEXT DEFAULT 'irp'"),
+    (7, "ALTER TABLE chapters ADD COLUMN heading_level INTEGER DEFAULT 1"),
+    // 8-10: 笔记标注类型与回收站
+    (8, "ALTER TABLE notes ADD COLUMN annotation_type TEXT DEFAULT 'highlight'"),
+    (9, "ALTER TABLE notes ADD COLUMN deleted_at DATETIME"),
+    (10, "CREATE INDEX IF NOT EXISTS idx_notes_deleted_at ON notes(deleted_at)"),
+    // 11: Reading Unit（章节合并评分系统）
+    (11, "
+        CREATE TABLE IF NOT EXISTS reading_units (
             id TEXT PRIMARY KEY,
             book_id INTEGER NOT NULL,
             title TEXT NOT NULL,
@@ -282,13 +247,10 @@ pub fn init_db<P: AsRef<Path>>(path: P) -> Result<Connection> {
             created_at INTEGER NOT NULL,
             FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE,
             FOREIGN KEY (parent_id) REFERENCES reading_units(id) ON DELETE CASCADE
-        )",
-        [],
-    )?;
+        );
 
-    // Debug 评分数据表（开发环境）
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS debug_segment_scores (
+        -- Debug 评分数据表（开发环境）
+        CREATE TABLE IF NOT EXISTS debug_segment_scores (
             segment_id TEXT PRIMARY KEY,
             book_id INTEGER NOT NULL,
             scores TEXT NOT NULL,
@@ -302,31 +264,183 @@ pub fn init_db<P: AsRef<Path>>(path: P) -> Result<Connection> {
             level INTEGER CHECK(level IN (1, 2)),
             created_at INTEGER NOT NULL,
             FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE
-        )",
-        [],
-    )?;
-
-    // 添加 chapter_rule_version 字段到 books 表
-    let _ = conn.execute("ALTER TABLE books ADD COLUMN chapter_rule_version TEXT DEFAULT 'v1.0'", []);
-
-    // 创建 Reading Unit 相关索引
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_reading_units_book_id ON reading_units(book_id)",
-        [],
-    )?;
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_reading_units_level ON reading_units(book_id, level)",
-        [],
-    )?;
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_reading_units_parent_id ON reading_units(parent_id)",
-        [],
-    )?;
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_debug_scores_book_id ON debug_segment_scores(book_id)",
-        [],
-    )?;
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_reading_units_book_id ON reading_units(book_id);
+        CREATE INDEX IF NOT EXISTS idx_reading_units_level ON reading_units(book_id, level);
+        CREATE INDEX IF NOT EXISTS idx_reading_units_parent_id ON reading_units(parent_id);
+        CREATE INDEX IF NOT EXISTS idx_debug_scores_book_id ON debug_segment_scores(book_id);
+    "),
+    (12, "ALTER TABLE books ADD COLUMN chapter_rule_version TEXT DEFAULT 'v1.0'"),
+    // 13: 书签表（独立于笔记的阅读位置标记）
+    (13, "
+        CREATE TABLE IF NOT EXISTS bookmarks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            book_id INTEGER NOT NULL,
+            chapter_index INTEGER NOT NULL,
+            block_id INTEGER,
+            label TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_bookmarks_book_id ON bookmarks(book_id);
+    "),
+    // 14-15: 字数统计缓存（导入完成后写入，NULL 表示尚未计算）
+    (14, "ALTER TABLE books ADD COLUMN char_count INTEGER"),
+    (15, "ALTER TABLE books ADD COLUMN word_count INTEGER"),
+    // 16: 封面主色调缓存（#rrggbb）
+    (16, "ALTER TABLE books ADD COLUMN cover_color TEXT"),
+    // 17: 封面缩略图（最长边 300px，cover_image 保留原图）
+    (17, "ALTER TABLE books ADD COLUMN cover_thumbnail TEXT"),
+    // 18: 加密书籍（blocks.runs_json 和 chapters.raw_html 加密存储）
+    (18, "ALTER TABLE books ADD COLUMN is_encrypted INTEGER DEFAULT 0"),
+    // 19: 重新解析后无法迁移到新章节的笔记，需要用户确认
+    (19, "ALTER TABLE notes ADD COLUMN needs_review INTEGER DEFAULT 0"),
+    // 20-24: EPUB 元数据（语言、出版社、出版日期、标识符、简介）
+    (20, "ALTER TABLE books ADD COLUMN language TEXT"),
+    (21, "ALTER TABLE books ADD COLUMN publisher TEXT"),
+    (22, "ALTER TABLE books ADD COLUMN published_date TEXT"),
+    (23, "ALTER TABLE books ADD COLUMN identifier TEXT"),
+    (24, "ALTER TABLE books ADD COLUMN description TEXT"),
+    // 25: 章节划分阈值预设（default/novel/technical/textbook）
+    (25, "ALTER TABLE books ADD COLUMN threshold_preset TEXT"),
+    // 26: 章节内容类型（EPUB landmarks 标记的前言/正文/后记）
+    (26, "ALTER TABLE chapters ADD COLUMN content_type TEXT"),
+    // 27: AI 响应缓存
+    (27, "
+        CREATE TABLE IF NOT EXISTS ai_cache (
+            cache_key TEXT PRIMARY KEY,
+            response TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+    "),
+    // 28: AI 请求超时时间（秒）
+    (28, "ALTER TABLE ai_config ADD COLUMN timeout_secs INTEGER NOT NULL DEFAULT 60"),
+    // 29: 应用设置（键值对）
+    (29, "
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+    "),
+    // 30: 书籍最近打开时间（"继续阅读"书架）
+    (30, "ALTER TABLE books ADD COLUMN opened_at DATETIME"),
+    // 31: 书籍样式表（EPUB 中收集并限定作用域的 CSS）
+    (31, "ALTER TABLE books ADD COLUMN stylesheet TEXT"),
+    // 32: 标题块的级别（1-6）
+    (32, "ALTER TABLE blocks ADD COLUMN heading_level INTEGER"),
+    // 33: 代码块的语言
+    (33, "ALTER TABLE blocks ADD COLUMN lang TEXT"),
+    // 34: 代码块预先高亮的 HTML（启用 syntax-highlight 特性时导入写入）
+    (34, "ALTER TABLE blocks ADD COLUMN highlighted_html TEXT"),
+    // 35: 按章节统计笔记数
+    (35, "CREATE INDEX IF NOT EXISTS idx_notes_book_chapter ON notes(book_id, chapter_index)"),
+    // 36: 高亮（按内容块内的字符偏移定位，可关联笔记）
+    (36, "
+        CREATE TABLE IF NOT EXISTS highlights (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            book_id INTEGER NOT NULL,
+            chapter_index INTEGER NOT NULL,
+            block_id INTEGER,
+            start_offset INTEGER NOT NULL,
+            end_offset INTEGER NOT NULL,
+            color TEXT NOT NULL,
+            note_id INTEGER,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE,
+            FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE SET NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_highlights_book_chapter ON highlights(book_id, chapter_index);
+    "),
+    // 37: 内容块指纹（runs 文本的 SHA256），重新解析后用于重新定位高亮
+    (37, "ALTER TABLE blocks ADD COLUMN content_hash TEXT"),
+    // 38: 解析警告（JSON 字符串数组，没有警告时为 NULL）
+    (38, "ALTER TABLE books ADD COLUMN parse_warnings TEXT"),
+    // 39: 章节字符数（导入时计算，用于目录显示章节长度和估算章节阅读时间）
+    (39, "ALTER TABLE chapters ADD COLUMN char_count INTEGER"),
+    // 40: AI 请求代理（http/https 代理地址，为空时直连）
+    (40, "ALTER TABLE ai_config ADD COLUMN proxy_url TEXT"),
+    // 41: Azure OpenAI 部署名
+    (41, "ALTER TABLE ai_config ADD COLUMN deployment TEXT"),
+    // 42: Azure OpenAI 接口版本（api-version）
+    (42, "ALTER TABLE ai_config ADD COLUMN api_version TEXT"),
+    // 43: Azure OpenAI 平台配置
+    (43, "INSERT OR IGNORE INTO ai_config (platform, model, is_active) VALUES ('azure-openai', 'gpt-4o', 0)"),
+    // 44: 轮换使用的 API Key（加密存储，按最久未使用轮流，认证失败停用，429 冷却）
+    (44, "
+        CREATE TABLE IF NOT EXISTS ai_api_keys (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            config_id INTEGER NOT NULL REFERENCES ai_config(id) ON DELETE CASCADE,
+            api_key TEXT NOT NULL,
+            key_suffix TEXT NOT NULL,
+            last_used TEXT,
+            disabled INTEGER NOT NULL DEFAULT 0,
+            auth_failures INTEGER NOT NULL DEFAULT 0,
+            cooldown_until TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE INDEX IF NOT EXISTS idx_ai_api_keys_config ON ai_api_keys(config_id);
+    "),
+    // 45: 导入时指定的 TXT 编码（为空时自动检测，重新解析时沿用）
+    (45, "ALTER TABLE books ADD COLUMN text_encoding TEXT"),
+    // 46: 笔记检索索引（rowid 为笔记 ID，只保存以加密密钥计算的 HMAC 词元，删除笔记时由触发器移除）
+    (46, "
+        CREATE VIRTUAL TABLE IF NOT EXISTS notes_search USING fts5(tokens);
+        CREATE TRIGGER IF NOT EXISTS notes_search_delete AFTER DELETE ON notes BEGIN
+            DELETE FROM notes_search WHERE rowid = old.id;
+        END;
+    "),
+    // 47: 章节在源文件中的位置（EPUB spine 路径、Markdown 行范围、PDF 页码范围）
+    (47, "ALTER TABLE chapters ADD COLUMN source_anchor TEXT"),
+];
 
+/// 读取数据库的 `PRAGMA user_version`
+pub fn get_user_version(conn: &Connection) -> Result<i32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// 判断是否为"列已存在"错误（版本追踪之前的数据库可能已有该列）
+fn is_duplicate_column_error(error: &rusqlite::Error) -> bool {
+    match error {
+        rusqlite::Error::SqliteFailure(_, Some(message)) => {
+            message.starts_with("duplicate column name")
+        }
+        _ => false,
+    }
+}
+
+/// 执行所有尚未应用的迁移步骤
+///
+/// 每一步在独立事务中执行，并在同一事务中更新 `user_version`，
+/// 中途失败时已完成的步骤会保留，下次启动从失败的步骤继续。
+///
+/// # 返回
+/// 本次执行的迁移步骤数
+pub fn run_migrations(conn: &Connection) -> Result<usize> {
+    let current = get_user_version(conn)?;
+    let mut applied = 0;
+
+    for (version, sql) in MIGRATIONS.iter().filter(|(version, _)| *version > current) {
+        let tx = conn.unchecked_transaction()?;
+        match tx.execute_batch(sql) {
+            Ok(()) => {}
+            Err(e) if is_duplicate_column_error(&e) => {}
+            Err(e) => return Err(e),
+        }
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+        applied += 1;
+    }
+
+    Ok(applied)
+}
+
+/// 打开数据库并执行迁移，使表结构升级到最新版本
+pub fn init_db<P: AsRef<Path>>(path: P) -> Result<Connection> {
+    let conn = open_db(path)?;
+    run_migrations(&conn)?;
     Ok(conn)
 }
 
@@ -383,4 +497,132 @@ mod tests {
         
         assert!(table_exists);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_database_seeds_once() {
+        let (_temp_dir, db_path) = create_test_db();
+        let db = Database::open(&db_path).unwrap();
+
+        let count_categories = |conn: &Connection| {
+            conn.query_row("SELECT COUNT(*) FROM categories", [], |row| row.get::<_, i32>(0))
+                .map_err(|e| e.to_string())
+        };
+        assert_eq!(db.with_conn(count_categories).unwrap(), 4);
+
+        // 删除一个默认分类后，后续调用不应重新插入种子数据
+        db.with_conn(|conn| {
+            conn.execute("DELETE FROM categories WHERE name = '概念'", [])
+                .map_err(|e| e.to_string())
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(db.with_conn(count_categories).unwrap(), 3);
+        }
+    }
+
+    fn schema_version() -> i32 {
+        MIGRATIONS.last().map(|(version, _)| *version).unwrap()
+    }
+
+    fn column_names(conn: &Connection, table: &str) -> Vec<String> {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table)).unwrap();
+        stmt.query_map([], |row| row.get(1))
+            .unwrap()
+            .collect::<Result<Vec<String>>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_fresh_db_reaches_latest_version() {
+        let (_temp_dir, db_path) = create_test_db();
+        let conn = init_db(&db_path).unwrap();
+        assert_eq!(get_user_version(&conn).unwrap(), schema_version());
+
+        // 再次执行时没有待应用的迁移
+        assert_eq!(run_migrations(&conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_migrate_v0_database() {
+        let (_temp_dir, db_path) = create_test_db();
+
+        // 构造版本追踪之前的旧数据库：user_version 为 0，
+        // books 已通过临时 ALTER 加过 parse_status，notes 带有 annotation_type
+        {
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE books (
+                    id INTEGER PRIMARY KEY,
+                    title TEXT NOT NULL,
+                    author TEXT,
+                    file_path TEXT NOT NULL UNIQUE,
+                    cover_image TEXT,
+                    added_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                );
+                ALTER TABLE books ADD COLUMN parse_status TEXT DEFAULT 'pending';
+                CREATE TABLE notes (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    title TEXT NOT NULL,
+                    content TEXT,
+                    category_id INTEGER,
+                    book_id INTEGER,
+                    chapter_index INTEGER,
+                    highlighted_text TEXT,
+                    annotation_type TEXT DEFAULT 'highlight',
+                    position_start INTEGER,
+                    position_end INTEGER,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                );
+                INSERT INTO books (title, author, file_path, parse_status)
+                    VALUES ('旧书', '作者', '/old/book.epub', 'completed');
+                INSERT INTO notes (title, book_id, annotation_type) VALUES ('旧笔记', 1, 'note');",
+            )
+            .unwrap();
+            assert_eq!(get_user_version(&conn).unwrap(), 0);
+        }
+
+        let conn = init_db(&db_path).unwrap();
+        assert_eq!(get_user_version(&conn).unwrap(), schema_version());
+
+        // 旧数据保留
+        let (title, status): (String, String) = conn
+            .query_row("SELECT title, parse_status FROM books WHERE id = 1", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(title, "旧书");
+        assert_eq!(status, "completed");
+        let annotation_type: String = conn
+            .query_row("SELECT annotation_type FROM notes WHERE id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(annotation_type, "note");
+
+        // 新列和新表已创建
+        let book_columns = column_names(&conn, "books");
+        for column in ["parse_quality", "total_blocks", "chapter_rule_version", "cover_thumbnail"] {
+            assert!(book_columns.iter().any(|c| c == column), "books 缺少列 {}", column);
+        }
+        let chapter_columns = column_names(&conn, "chapters");
+        for column in ["raw_html", "render_mode", "heading_level"] {
+            assert!(chapter_columns.iter().any(|c| c == column), "chapters 缺少列 {}", column);
+        }
+        assert!(column_names(&conn, "notes").iter().any(|c| c == "deleted_at"));
+        assert!(!column_names(&conn, "bookmarks").is_empty());
+    }
+
+    #[test]
+    fn test_migrations_resume_from_version() {
+        let (_temp_dir, db_path) = create_test_db();
+        let conn = init_db(&db_path).unwrap();
+
+        // 回退版本号模拟中途退出的升级：已存在的列不会导致迁移失败
+        conn.pragma_update(None, "user_version", 3).unwrap();
+        assert_eq!(
+            run_migrations(&conn).unwrap(),
+            (schema_version() - 3) as usize
+        );
+        assert_eq!(get_user_version(&conn).unwrap(), schema_version());
+    }
+}
@@ -0,0 +1,214 @@
+use crate::db;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::path::Path;
+
+// 诊断模块：汇总数据库与资产目录状态，帮助排查导入失败等问题（只读）
+
+/// 单个表的行数
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TableCount {
+    pub table: String,
+    pub rows: i64,
+}
+
+/// 某个解析状态下的书籍数量
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StatusCount {
+    pub status: String,
+    pub count: i64,
+}
+
+/// 诊断信息
+#[derive(Serialize, Debug, Clone)]
+pub struct Diagnostics {
+    pub db_path: String,
+    pub schema_version: i32,
+    pub table_counts: Vec<TableCount>,
+    pub books_by_status: Vec<StatusCount>,
+    pub asset_files_on_disk: i64,
+    pub asset_mapping_rows: i64,
+    pub active_ai_platform: Option<String>,
+    pub ai_key_configured: bool,
+}
+
+/// 收集诊断信息
+///
+/// # 参数
+/// - `conn`: 数据库连接
+/// - `db_path`: 数据库文件路径（仅用于展示）
+/// - `app_data_dir`: 应用数据目录，资产文件位于其中的 `assets/` 下
+pub fn collect_diagnostics(
+    conn: &Connection,
+    db_path: &Path,
+    app_data_dir: &Path,
+) -> Result<Diagnostics, String> {
+    let schema_version = db::get_user_version(conn).map_err(|e| e.to_string())?;
+
+    let table_counts = count_table_rows(conn)?;
+    let asset_mapping_rows = table_counts
+        .iter()
+        .find(|t| t.table == "asset_mappings")
+        .map(|t| t.rows)
+        .unwrap_or(0);
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT COALESCE(parse_status, 'unknown'), COUNT(*) FROM books
+             GROUP BY COALESCE(parse_status, 'unknown') ORDER BY 1",
+        )
+        .map_err(|e| e.to_string())?;
+    let books_by_status = stmt
+        .query_map([], |row| {
+            Ok(StatusCount {
+                status: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let active_ai: Option<(String, Option<String>, bool)> = conn
+        .query_row(
+            "SELECT platform, api_key,
+                    EXISTS(SELECT 1 FROM ai_api_keys k WHERE k.config_id = ai_config.id AND k.disabled = 0)
+             FROM ai_config WHERE is_active = 1 LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .ok();
+    let ai_key_configured = active_ai
+        .as_ref()
+        .is_some_and(|(_, key, has_rotating_keys)| {
+            *has_rotating_keys || key.as_deref().is_some_and(|key| !key.is_empty())
+        });
+
+    Ok(Diagnostics {
+        db_path: db_path.to_string_lossy().to_string(),
+        schema_version,
+        table_counts,
+        books_by_status,
+        asset_files_on_disk: count_files(&app_data_dir.join("assets")),
+        asset_mapping_rows,
+        active_ai_platform: active_ai.map(|(platform, _, _)| platform),
+        ai_key_configured,
+    })
+}
+
+/// 统计所有用户表的行数
+fn count_table_rows(conn: &Connection) -> Result<Vec<TableCount>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )
+        .map_err(|e| e.to_string())?;
+    let tables = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    tables
+        .into_iter()
+        .map(|table| {
+            // 表名来自 sqlite_master，不是用户输入
+            let rows = conn
+                .query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| row.get(0))
+                .map_err(|e| format!("统计表 {} 失败: {}", table, e))?;
+            Ok(TableCount { table, rows })
+        })
+        .collect()
+}
+
+/// 递归统计目录下的文件数（目录不存在时为 0）
+fn count_files(dir: &Path) -> i64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                count_files(&path)
+            } else {
+                1
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_collect_diagnostics_seeded_db() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("library.db");
+        let conn = db::init_db(&db_path).unwrap();
+
+        conn.execute_batch(
+            "INSERT INTO books (title, file_path, parse_status) VALUES ('a', '/a', 'completed');
+             INSERT INTO books (title, file_path, parse_status) VALUES ('b', '/b', 'completed');
+             INSERT INTO books (title, file_path, parse_status) VALUES ('c', '/c', 'failed: 格式错误');
+             INSERT INTO asset_mappings (book_id, original_path, local_path) VALUES (1, 'x.png', 'assets/1/x.png');
+             UPDATE ai_config SET is_active = 1, api_key = 'sk-test' WHERE platform = 'openai';",
+        )
+        .unwrap();
+
+        let asset_dir = temp_dir.path().join("assets").join("1");
+        std::fs::create_dir_all(&asset_dir).unwrap();
+        std::fs::write(asset_dir.join("x.png"), b"png").unwrap();
+        std::fs::write(asset_dir.join("orphan.png"), b"png").unwrap();
+
+        let diagnostics = collect_diagnostics(&conn, &db_path, temp_dir.path()).unwrap();
+
+        assert_eq!(diagnostics.db_path, db_path.to_string_lossy());
+        assert_eq!(
+            diagnostics.schema_version,
+            db::get_user_version(&conn).unwrap()
+        );
+        assert!(diagnostics.schema_version > 0);
+
+        let rows_of = |table: &str| {
+            diagnostics
+                .table_counts
+                .iter()
+                .find(|t| t.table == table)
+                .map(|t| t.rows)
+        };
+        assert_eq!(rows_of("books"), Some(3));
+        assert_eq!(rows_of("notes"), Some(0));
+        assert_eq!(rows_of("categories"), Some(4));
+
+        assert_eq!(
+            diagnostics.books_by_status,
+            vec![
+                StatusCount { status: "completed".to_string(), count: 2 },
+                StatusCount { status: "failed: 格式错误".to_string(), count: 1 },
+            ]
+        );
+        assert_eq!(diagnostics.asset_files_on_disk, 2);
+        assert_eq!(diagnostics.asset_mapping_rows, 1);
+        assert_eq!(diagnostics.active_ai_platform.as_deref(), Some("openai"));
+        assert!(diagnostics.ai_key_configured);
+    }
+
+    #[test]
+    fn test_collect_diagnostics_empty_library() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("library.db");
+        let conn = db::init_db(&db_path).unwrap();
+
+        let diagnostics = collect_diagnostics(&conn, &db_path, temp_dir.path()).unwrap();
+        assert!(diagnostics.books_by_status.is_empty());
+        assert_eq!(diagnostics.asset_files_on_disk, 0);
+        assert_eq!(diagnostics.active_ai_platform, None);
+        assert!(!diagnostics.ai_key_configured);
+    }
+}
@@ -3,8 +3,10 @@ use aes_gcm::{
     Aes256Gcm, Nonce,
 };
 use base64::{Engine as _, engine::general_purpose};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -21,6 +23,11 @@ pub enum EncryptionError {
 
 const KEY_SIZE: usize = 32; // 256 bits
 const NONCE_SIZE: usize = 12; // 96 bits for GCM
+const SALT_SIZE: usize = 16;
+/// 由口令派生密钥时 PBKDF2 的迭代次数
+const PASSPHRASE_ITERATIONS: u32 = 100_000;
+/// 解密备份中的密钥时接受的最大迭代次数（迭代次数来自备份文件，过大时拒绝以免长时间阻塞）
+const MAX_PASSPHRASE_ITERATIONS: u32 = 10_000_000;
 
 /// 生成256位加密密钥
 pub fn generate_key() -> Vec<u8> {
@@ -116,10 +123,115 @@ pub fn decrypt_content(encrypted: &str, key: &[u8]) -> Result<String, Encryption
         .map_err(|e| EncryptionError::DecryptionFailed(format!("UTF-8解码失败: {}", e)))
 }
 
+/// 以密钥计算 HMAC-SHA256，返回十六进制字符串
+///
+/// 用于需要比较相等、但不能暴露明文的指纹（如搜索词元、加密书籍的内容块指纹），
+/// 没有密钥时无法通过猜测明文来验证
+pub fn keyed_hash(key: &[u8], data: &[u8]) -> String {
+    hmac_sha256(key, data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC 接受任意长度的密钥");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// PBKDF2-HMAC-SHA256 由口令派生 256 位密钥
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; KEY_SIZE] {
+    pbkdf2::pbkdf2_hmac_array::<Sha256, KEY_SIZE>(passphrase.as_bytes(), salt, iterations)
+}
+
+/// 用口令加密密钥（用于备份），格式为 `迭代次数$盐$密文`
+pub fn wrap_key(key: &[u8], passphrase: &str) -> Result<String, EncryptionError> {
+    let salt: [u8; SALT_SIZE] = rand::random();
+    let wrapping_key = derive_key(passphrase, &salt, PASSPHRASE_ITERATIONS);
+    let wrapped = encrypt_content(&general_purpose::STANDARD.encode(key), &wrapping_key)?;
+    Ok(format!("{}${}${}", PASSPHRASE_ITERATIONS, general_purpose::STANDARD.encode(salt), wrapped))
+}
+
+/// 用口令解密 `wrap_key` 加密的密钥，口令错误时返回错误
+pub fn unwrap_key(wrapped: &str, passphrase: &str) -> Result<Vec<u8>, EncryptionError> {
+    let invalid = || EncryptionError::KeyManagementError("加密密钥格式不正确".to_string());
+    let mut parts = wrapped.trim().splitn(3, '$');
+    let (Some(iterations), Some(salt), Some(ciphertext)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(invalid());
+    };
+    // 低于当前迭代次数的备份可能被篡改以降低破解口令的成本，同样拒绝
+    let iterations: u32 = iterations.parse().map_err(|_| invalid())?;
+    if !(PASSPHRASE_ITERATIONS..=MAX_PASSPHRASE_ITERATIONS).contains(&iterations) {
+        return Err(invalid());
+    }
+    let salt = general_purpose::STANDARD.decode(salt).map_err(|_| invalid())?;
+
+    let wrapping_key = derive_key(passphrase, &salt, iterations);
+    let encoded = decrypt_content(ciphertext, &wrapping_key)
+        .map_err(|_| EncryptionError::KeyManagementError("口令错误".to_string()))?;
+    let key = general_purpose::STANDARD.decode(encoded).map_err(|_| invalid())?;
+    if key.len() != KEY_SIZE {
+        return Err(invalid());
+    }
+    Ok(key)
+}
+
+/// 保存密钥到文件（覆盖已有密钥，用于从备份恢复）
+pub fn save_key(key_path: &Path, key: &[u8]) -> Result<(), EncryptionError> {
+    if key.len() != KEY_SIZE {
+        return Err(EncryptionError::KeyManagementError("密钥长度不正确".to_string()));
+    }
+    if let Some(parent) = key_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(key_path, key)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_keyed_hash_matches_hmac_sha256() {
+        // RFC 4231 测试用例 2
+        assert_eq!(
+            keyed_hash(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_ne!(keyed_hash(b"key1", b"data"), keyed_hash(b"key2", b"data"));
+    }
+
+    #[test]
+    fn test_derive_key_matches_pbkdf2_sha256() {
+        // 常用的 PBKDF2-HMAC-SHA256 测试向量（password / salt）
+        assert_eq!(
+            derive_key("password", b"salt", 2).to_vec(),
+            hex("ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43")
+        );
+        assert_eq!(
+            derive_key("password", b"salt", 4096).to_vec(),
+            hex("c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a")
+        );
+    }
+
+    fn hex(value: &str) -> Vec<u8> {
+        (0..value.len()).step_by(2).map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_wrap_and_unwrap_key() {
+        let key = generate_key();
+        let wrapped = wrap_key(&key, "口令").unwrap();
+        assert!(!wrapped.contains(&general_purpose::STANDARD.encode(&key)));
+        assert_eq!(unwrap_key(&wrapped, "口令").unwrap(), key);
+        assert!(unwrap_key(&wrapped, "错误的口令").is_err());
+        assert!(unwrap_key("不是密钥", "口令").is_err());
+
+        // 迭代次数超出允许范围的备份直接拒绝，不按文件中的次数派生密钥
+        let (_, rest) = wrapped.split_once('$').unwrap();
+        assert!(unwrap_key(&format!("1${}", rest), "口令").is_err());
+        assert!(unwrap_key(&format!("{}${}", u32::MAX, rest), "口令").is_err());
+    }
+
     #[test]
     fn test_encrypt_decrypt() {
         let key = generate_key();
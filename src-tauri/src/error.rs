@@ -0,0 +1,77 @@
+use serde::Serialize;
+use thiserror::Error;
+
+// 命令错误类型：序列化为 { kind, message }，前端可按 kind 区分找不到、数据库错误、校验失败等情况
+
+/// 命令返回的错误
+#[derive(Error, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum AppError {
+    /// 书籍、章节、笔记等记录不存在
+    #[error("{0}")]
+    NotFound(String),
+    /// 数据库读写失败
+    #[error("{0}")]
+    Db(String),
+    /// 文件或内容解析失败
+    #[error("{0}")]
+    Parse(String),
+    /// 参数校验失败
+    #[error("{0}")]
+    Validation(String),
+    /// 记录已被其他窗口修改（乐观并发检查失败）
+    #[error("{0}")]
+    Conflict(String),
+    /// AI 服务请求失败
+    #[error("{0}")]
+    Ai(String),
+    /// 文件系统或网络 IO 失败
+    #[error("{0}")]
+    Io(String),
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => AppError::NotFound("找不到记录".to_string()),
+            other => AppError::Db(other.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+/// 供仍返回 `String` 的内部函数使用 `?` 传播命令错误
+impl From<AppError> for String {
+    fn from(e: AppError) -> Self {
+        e.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_kind_and_message() {
+        let json = serde_json::to_value(AppError::NotFound("找不到书籍".to_string())).unwrap();
+        assert_eq!(json, serde_json::json!({ "kind": "notFound", "message": "找不到书籍" }));
+        assert_eq!(AppError::Validation("标题不能为空".to_string()).to_string(), "标题不能为空");
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(
+            AppError::from(rusqlite::Error::QueryReturnedNoRows),
+            AppError::NotFound("找不到记录".to_string())
+        );
+        assert!(matches!(
+            AppError::from(std::io::Error::new(std::io::ErrorKind::NotFound, "missing")),
+            AppError::Io(_)
+        ));
+    }
+}
@@ -0,0 +1,509 @@
+use crate::irp::{self, Block, Chapter};
+use crate::parser::md_parser::markdown_headings;
+use rusqlite::Connection;
+use scraper::{ElementRef, Html, Node};
+use std::collections::HashSet;
+
+// 书籍导出模块：将书籍的全部章节导出为纯文本或 Markdown
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Text,
+    Markdown,
+}
+
+impl ExportFormat {
+    /// 从字符串解析导出格式（"txt" 或 "md"）
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "txt" => Ok(ExportFormat::Text),
+            "md" => Ok(ExportFormat::Markdown),
+            _ => Err(format!("不支持的导出格式: {}（可选值: txt, md）", value)),
+        }
+    }
+}
+
+/// 导出整本书
+///
+/// # 参数
+/// - `conn`: 数据库连接
+/// - `book_id`: 书籍 ID
+/// - `format`: 导出格式
+/// - `key`: 加密书籍的解密密钥
+///
+/// # 返回
+/// 拼接后的全书文本
+pub fn export_book(
+    conn: &Connection,
+    book_id: i32,
+    format: ExportFormat,
+    key: Option<&[u8]>,
+) -> Result<String, String> {
+    let chapters = irp::get_chapters_by_book(conn, book_id, key)
+        .map_err(|e| format!("获取章节失败: {}", e))?;
+
+    let mut sections: Vec<String> = Vec::new();
+    // Markdown 格式的章节共享同一份完整内容，只输出一次
+    let mut last_markdown: Option<String> = None;
+
+    for chapter in &chapters {
+        let section = match chapter.render_mode.as_str() {
+            "html" => export_html_chapter(chapter, format),
+            "markdown" => {
+                let source = chapter.raw_html.clone().unwrap_or_default();
+                if last_markdown.as_deref() == Some(source.as_str()) {
+                    continue;
+                }
+                last_markdown = Some(source.clone());
+                export_markdown_source(&source, format)
+            }
+            _ => {
+                let blocks = irp::get_blocks_by_chapter(conn, chapter.id, key)
+                    .map_err(|e| format!("获取内容块失败: {}", e))?;
+                export_blocks(&blocks, format)
+            }
+        };
+
+        if !section.trim().is_empty() {
+            sections.push(section.trim().to_string());
+        }
+    }
+
+    let mut output = sections.join("\n\n");
+    output.push('\n');
+    Ok(output)
+}
+
+/// 提取单个章节的纯文本（用于 AI 输入和导出）
+///
+/// 统一处理三种 render_mode：
+/// - html: 去除标签，按块级元素分段
+/// - markdown: 只取本章对应的标题段落，并去除 Markdown 语法
+/// - irp: 拼接各内容块的文本（跳过图片）
+///
+/// # 参数
+/// - `conn`: 数据库连接
+/// - `chapter_id`: 章节 ID
+/// - `key`: 加密书籍的解密密钥
+pub fn chapter_plain_text(conn: &Connection, chapter_id: i32, key: Option<&[u8]>) -> Result<String, String> {
+    let chapter = irp::get_chapter_by_id(conn, chapter_id, key)
+        .map_err(|e| format!("获取章节失败: {}", e))?;
+
+    if let Some(text) = raw_content_to_text(
+        &chapter.render_mode,
+        chapter.raw_html.as_deref(),
+        chapter.chapter_index.max(0) as usize,
+    ) {
+        return Ok(text);
+    }
+
+    let blocks = irp::get_blocks_by_chapter(conn, chapter_id, key)
+        .map_err(|e| format!("获取内容块失败: {}", e))?;
+    Ok(export_blocks(&blocks, ExportFormat::Text))
+}
+
+/// 将 html/markdown 章节的原始内容转为纯文本
+///
+/// `chapter_index` 用于定位 Markdown 章节在共享源文件中对应的标题段落
+///
+/// # 返回
+/// IRP 章节（或没有原始内容）返回 None，应改为读取内容块
+pub fn raw_content_to_text(render_mode: &str, raw_html: Option<&str>, chapter_index: usize) -> Option<String> {
+    let raw = raw_html?;
+    match render_mode {
+        "html" => Some(html_to_text(raw, ExportFormat::Text)),
+        "markdown" => Some(export_markdown_source(
+            markdown_section(raw, chapter_index),
+            ExportFormat::Text,
+        )),
+        _ => None,
+    }
+}
+
+/// 取出 Markdown 源文件中第 `heading_index` 个标题开始、到下一个标题之前的内容
+///
+/// 与导入时划分章节使用同一个标题扫描（`md_parser::markdown_headings`）；
+/// 没有标题或序号越界时返回整个源文件
+fn markdown_section(source: &str, heading_index: usize) -> &str {
+    let headings = markdown_headings(source);
+    match headings.get(heading_index) {
+        Some(heading) => {
+            let end = headings.get(heading_index + 1).map_or(source.len(), |next| next.offset);
+            &source[heading.offset..end]
+        }
+        None => source,
+    }
+}
+
+/// 将 IRP blocks 重建为文本
+fn export_blocks(blocks: &[Block], format: ExportFormat) -> String {
+    let mut parts = Vec::new();
+
+    for block in blocks {
+        let text = irp::extract_plain_text_from_runs(&block.runs);
+
+        let part = match (block.block_type.as_str(), format) {
+            ("heading", ExportFormat::Markdown) => {
+                let level = block.heading_level.unwrap_or(1).clamp(1, 6) as usize;
+                format!("{} {}", "#".repeat(level), text.trim())
+            }
+            ("code", ExportFormat::Markdown) => {
+                format!("```\n{}\n```", text.trim_end_matches('\n'))
+            }
+            ("code", ExportFormat::Text) => text.trim_end_matches('\n').to_string(),
+            ("image", ExportFormat::Markdown) => {
+                let alt = block.runs.first().and_then(|run| run.attribute("alt")).unwrap_or_default();
+                format!("![{}]({})", alt, text)
+            }
+            // 纯文本模式下图片没有可导出的内容
+            ("image", ExportFormat::Text) => continue,
+            _ => text.trim().to_string(),
+        };
+
+        if !part.is_empty() {
+            parts.push(part);
+        }
+    }
+
+    parts.join("\n\n")
+}
+
+/// 导出原始 Markdown 章节
+///
+/// 纯文本模式下去掉代码围栏行，只去除标题行的 `#` 标记（围栏代码块中以 `#` 开头的行保持原样），
+/// 其他行保留缩进
+fn export_markdown_source(source: &str, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Markdown => source.to_string(),
+        ExportFormat::Text => {
+            let heading_lines: HashSet<usize> =
+                markdown_headings(source).into_iter().map(|heading| heading.line_index).collect();
+            source
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim_start().starts_with("```"))
+                .map(|(index, line)| match heading_lines.contains(&index) {
+                    true => line.trim_start_matches('#').trim(),
+                    false => line.trim_end(),
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    }
+}
+
+/// 导出原始 HTML 章节（去除标签）
+fn export_html_chapter(chapter: &Chapter, format: ExportFormat) -> String {
+    match &chapter.raw_html {
+        Some(html) => html_to_text(html, format),
+        None => String::new(),
+    }
+}
+
+/// 使用 scraper 去除 HTML 标签，按块级元素分段
+pub fn html_to_text(html: &str, format: ExportFormat) -> String {
+    let document = Html::parse_document(html);
+    let mut collector = HtmlTextCollector {
+        format,
+        paragraphs: Vec::new(),
+        current: String::new(),
+    };
+    collector.walk(document.root_element());
+    collector.flush();
+    collector.paragraphs.join("\n\n")
+}
+
+struct HtmlTextCollector {
+    format: ExportFormat,
+    paragraphs: Vec<String>,
+    current: String,
+}
+
+impl HtmlTextCollector {
+    fn walk(&mut self, element: ElementRef) {
+        let name = element.value().name();
+        match name {
+            "head" | "script" | "style" => return,
+            "br" => {
+                self.current.push('\n');
+                return;
+            }
+            "pre" => {
+                self.flush();
+                let code: String = element.text().collect();
+                let code = code.trim_end_matches('\n');
+                if !code.trim().is_empty() {
+                    self.paragraphs.push(match self.format {
+                        ExportFormat::Markdown => format!("```\n{}\n```", code),
+                        ExportFormat::Text => code.to_string(),
+                    });
+                }
+                return;
+            }
+            _ => {}
+        }
+
+        let is_block = is_block_element(name);
+        if is_block {
+            self.flush();
+            if self.format == ExportFormat::Markdown {
+                if let Some(level) = heading_level(name) {
+                    self.current.push_str(&"#".repeat(level));
+                    self.current.push(' ');
+                }
+            }
+        }
+
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.push_text(text),
+                Node::Element(_) => {
+                    if let Some(child_element) = ElementRef::wrap(child) {
+                        self.walk(child_element);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if is_block {
+            self.flush();
+        }
+    }
+
+    /// 追加文本，合并连续空白
+    fn push_text(&mut self, text: &str) {
+        for (i, word) in text.split_whitespace().enumerate() {
+            let needs_space = if i == 0 {
+                text.starts_with(char::is_whitespace)
+            } else {
+                true
+            };
+            if needs_space && !self.current.is_empty() && !self.current.ends_with([' ', '\n']) {
+                self.current.push(' ');
+            }
+            self.current.push_str(word);
+        }
+        if text.ends_with(char::is_whitespace)
+            && !text.trim().is_empty()
+            && !self.current.ends_with([' ', '\n'])
+        {
+            self.current.push(' ');
+        }
+    }
+
+    fn flush(&mut self) {
+        let paragraph = self
+            .current
+            .lines()
+            .map(|line| line.trim())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let paragraph = paragraph.trim();
+        // 只有标题标记、没有正文的段落直接丢弃
+        if !paragraph.is_empty() && !paragraph.chars().all(|c| c == '#') {
+            self.paragraphs.push(paragraph.to_string());
+        }
+        self.current.clear();
+    }
+}
+
+fn is_block_element(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div"
+            | "section"
+            | "article"
+            | "blockquote"
+            | "li"
+            | "ul"
+            | "ol"
+            | "table"
+            | "tr"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "body"
+            | "header"
+            | "footer"
+            | "figure"
+            | "figcaption"
+    )
+}
+
+fn heading_level(name: &str) -> Option<usize> {
+    match name {
+        "h1" => Some(1),
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::irp::TextRun;
+    use tempfile::TempDir;
+
+    fn run(text: &str) -> Vec<TextRun> {
+        vec![TextRun {
+            text: text.to_string(),
+            marks: vec![],
+            attributes: None,
+        }]
+    }
+
+    /// 构造一本混合格式的书：一个 IRP 章节 + 一个 HTML 章节
+    fn create_mixed_book() -> (TempDir, Connection, i32) {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let conn = db::init_db(&db_path).unwrap();
+
+        conn.execute(
+            "INSERT INTO books (title, author, file_path) VALUES (?1, ?2, ?3)",
+            rusqlite::params!["测试书籍", "测试作者", "/test/mixed"],
+        )
+        .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        let chapter_id =
+            irp::create_chapter(&conn, book_id, "第一章", 0, "explicit").unwrap() as i32;
+        irp::create_block(&conn, chapter_id, 0, "heading", &run("第一章 开始"), None).unwrap();
+        irp::create_block(&conn, chapter_id, 1, "paragraph", &run("这是第一段。"), None).unwrap();
+        irp::create_block(&conn, chapter_id, 2, "code", &run("fn main() {}\n"), None).unwrap();
+
+        irp::create_chapter_with_html(
+            &conn,
+            book_id,
+            "第二章",
+            1,
+            "explicit",
+            Some("<html><head><title>x</title></head><body><h2>第二章</h2><p>HTML <b>正文</b>。</p></body></html>"),
+            "html",
+        )
+        .unwrap();
+
+        (temp_dir, conn, book_id)
+    }
+
+    #[test]
+    fn test_chapter_plain_text_both_render_modes() {
+        let (_temp_dir, conn, book_id) = create_mixed_book();
+        let chapters = irp::get_chapters_by_book(&conn, book_id, None).unwrap();
+
+        // IRP 章节：拼接内容块
+        let irp_text = chapter_plain_text(&conn, chapters[0].id, None).unwrap();
+        assert_eq!(irp_text, "第一章 开始\n\n这是第一段。\n\nfn main() {}");
+
+        // HTML 章节：去除标签
+        let html_text = chapter_plain_text(&conn, chapters[1].id, None).unwrap();
+        assert_eq!(html_text, "第二章\n\nHTML 正文。");
+        assert!(!html_text.contains('<'));
+    }
+
+    #[test]
+    fn test_markdown_chapter_plain_text_is_section() {
+        let source = "# 第一章\n第一章内容\n```\n# 不是标题\n```\n## 1.1 小节\n小节内容\n";
+        assert_eq!(
+            raw_content_to_text("markdown", Some(source), 0).unwrap(),
+            "第一章\n第一章内容\n# 不是标题"
+        );
+        assert_eq!(
+            raw_content_to_text("markdown", Some(source), 1).unwrap(),
+            "1.1 小节\n小节内容"
+        );
+        assert_eq!(raw_content_to_text("irp", None, 0), None);
+    }
+
+    #[test]
+    fn test_markdown_source_text_keeps_body_lines() {
+        let source = "## 小节\n    缩进的代码\n- 列表项\n```\n# 注释\n```\n";
+        assert_eq!(
+            export_markdown_source(source, ExportFormat::Text),
+            "小节\n    缩进的代码\n- 列表项\n# 注释"
+        );
+    }
+
+    #[test]
+    fn test_export_markdown_keeps_heading_levels() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('书', '/test/levels')", [])
+            .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+        let chapter_id = irp::create_chapter(&conn, book_id, "第一章", 0, "explicit").unwrap() as i32;
+
+        let heading = |runs, heading_level| irp::NewBlock {
+            block_type: "heading",
+            runs,
+            heading_level,
+            lang: None,
+            highlighted_html: None,
+        };
+        let (h1, h3, h9) = (run("章"), run("小节"), run("越界"));
+        let blocks = [heading(&h1, Some(1)), heading(&h3, Some(3)), heading(&h9, Some(9))];
+        irp::create_blocks(&conn, chapter_id, &blocks, None).unwrap();
+
+        let output = export_book(&conn, book_id, ExportFormat::Markdown, None).unwrap();
+        assert_eq!(output, "# 章\n\n### 小节\n\n###### 越界\n");
+    }
+
+    #[test]
+    fn test_export_format_parse() {
+        assert_eq!(ExportFormat::parse("txt"), Ok(ExportFormat::Text));
+        assert_eq!(ExportFormat::parse("md"), Ok(ExportFormat::Markdown));
+        assert!(ExportFormat::parse("pdf").is_err());
+    }
+
+    #[test]
+    fn test_export_markdown() {
+        let (_temp_dir, conn, book_id) = create_mixed_book();
+        let output = export_book(&conn, book_id, ExportFormat::Markdown, None).unwrap();
+
+        assert_eq!(
+            output,
+            "# 第一章 开始\n\n这是第一段。\n\n```\nfn main() {}\n```\n\n## 第二章\n\nHTML 正文。\n"
+        );
+    }
+
+    #[test]
+    fn test_export_text() {
+        let (_temp_dir, conn, book_id) = create_mixed_book();
+        let output = export_book(&conn, book_id, ExportFormat::Text, None).unwrap();
+
+        assert_eq!(
+            output,
+            "第一章 开始\n\n这是第一段。\n\nfn main() {}\n\n第二章\n\nHTML 正文。\n"
+        );
+    }
+
+    #[test]
+    fn test_export_shared_markdown_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute(
+            "INSERT INTO books (title, author, file_path) VALUES ('md', NULL, '/test/md')",
+            [],
+        )
+        .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        let source = "# A\n\ntext a\n\n# B\n\ntext b";
+        for (i, title) in ["A", "B"].iter().enumerate() {
+            irp::create_chapter_with_html(&conn, book_id, title, i as i32, "explicit", Some(source), "markdown")
+                .unwrap();
+        }
+
+        let output = export_book(&conn, book_id, ExportFormat::Markdown, None).unwrap();
+        assert_eq!(output, format!("{}\n", source));
+    }
+}
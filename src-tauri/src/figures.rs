@@ -0,0 +1,193 @@
+use crate::asset_manager;
+use crate::irp::{self, Block, TextRun};
+use rusqlite::Connection;
+use scraper::{ElementRef, Html, Selector};
+use serde::Serialize;
+use std::path::Path;
+
+// 图表目录：列出书中所有图片及其所在位置、图注和本地文件路径。
+// 图片来自 image 内容块和章节原始 HTML；图注优先取所在 <figure> 的 <figcaption>，
+// 否则取紧随图片的简短段落
+
+/// 作为图注的段落最大字符数
+const MAX_CAPTION_CHARS: usize = 200;
+
+/// 图片条目
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Figure {
+    pub chapter_index: i32,
+    /// 对应的 image 内容块（只出现在原始 HTML 中的图片为 None）
+    pub block_index: Option<i32>,
+    pub src: String,
+    pub alt: Option<String>,
+    pub caption: Option<String>,
+    /// 本地资产文件路径（没有资产映射时为 None）
+    pub local_path: Option<String>,
+}
+
+/// 获取书籍的图表目录（按章节和出现顺序排列）
+///
+/// # 参数
+/// - `root_dir`: 书库根目录（资产本地相对路径的根目录）
+/// - `key`: 加密书籍的解密密钥
+pub fn get_figures(conn: &Connection, book_id: i32, root_dir: &Path, key: Option<&[u8]>) -> Result<Vec<Figure>, String> {
+    let chapters = irp::get_chapters_by_book(conn, book_id, key).map_err(|e| format!("获取章节失败: {}", e))?;
+    let assets = asset_manager::get_book_assets(conn, book_id).map_err(|e| e.to_string())?;
+    let local_path = |src: &str| {
+        asset_manager::find_local_path(&assets, src).map(|path| root_dir.join(path).to_string_lossy().into_owned())
+    };
+
+    let mut figures = Vec::new();
+    for chapter in chapters {
+        let blocks = irp::get_blocks_by_chapter(conn, chapter.id, key).map_err(|e| format!("获取内容块失败: {}", e))?;
+        let mut html_images = chapter.raw_html.as_deref().map(html_images).unwrap_or_default();
+
+        for (i, block) in blocks.iter().enumerate().filter(|(_, block)| block.block_type == "image") {
+            let Some(src) = block.runs.first().map(|run| run.text.clone()) else {
+                continue;
+            };
+            // 图注依次取：解析时保存的图注、HTML 中同一图片的图注、后一个内容块
+            let html_caption = html_images
+                .iter()
+                .position(|image| image.text == src)
+                .and_then(|pos| html_images.remove(pos).attribute("caption").map(str::to_string));
+            let caption = block.runs[0]
+                .attribute("caption")
+                .map(str::to_string)
+                .or(html_caption)
+                .or_else(|| blocks.get(i + 1).and_then(block_caption));
+            figures.push(Figure {
+                chapter_index: chapter.chapter_index,
+                block_index: Some(block.block_index),
+                local_path: local_path(&src),
+                alt: block.runs[0].attribute("alt").map(str::to_string),
+                src,
+                caption,
+            });
+        }
+
+        // 没有对应内容块的图片（如 html 渲染模式的章节）
+        for image in html_images {
+            figures.push(Figure {
+                chapter_index: chapter.chapter_index,
+                block_index: None,
+                local_path: local_path(&image.text),
+                alt: image.attribute("alt").map(str::to_string),
+                caption: image.attribute("caption").map(str::to_string),
+                src: image.text,
+            });
+        }
+    }
+
+    Ok(figures)
+}
+
+/// 扫描 HTML 中的图片，按图片块的形式返回（alt 和图注在属性中）
+fn html_images(html: &str) -> Vec<TextRun> {
+    let document = Html::parse_document(html);
+    let img_selector = Selector::parse("img[src]").unwrap();
+    let caption_selector = Selector::parse("figcaption").unwrap();
+
+    document
+        .select(&img_selector)
+        .map(|img| {
+            let figcaption = img
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .find(|element| element.value().name() == "figure")
+                .and_then(|figure| figure.select(&caption_selector).next())
+                .map(|caption| element_text(&caption))
+                .filter(|text| !text.is_empty());
+            let caption = figcaption.or_else(|| adjacent_paragraph(img));
+            TextRun::image(img.value().attr("src").unwrap_or_default(), img.value().attr("alt"), caption.as_deref())
+        })
+        .collect()
+}
+
+/// 紧随图片（或只包裹图片的父元素）之后的简短段落
+fn adjacent_paragraph(img: ElementRef) -> Option<String> {
+    let next = next_element(img).or_else(|| {
+        let parent = img.parent().and_then(ElementRef::wrap)?;
+        if parent.value().name() == "body" {
+            return None;
+        }
+        next_element(parent)
+    })?;
+    if next.value().name() != "p" {
+        return None;
+    }
+    short_caption(element_text(&next))
+}
+
+fn next_element(element: ElementRef) -> Option<ElementRef> {
+    element.next_siblings().find_map(ElementRef::wrap)
+}
+
+/// 作为图注的内容块：简短的段落
+fn block_caption(block: &Block) -> Option<String> {
+    if block.block_type != "paragraph" {
+        return None;
+    }
+    short_caption(irp::extract_plain_text_from_runs(&block.runs).trim().to_string())
+}
+
+fn short_caption(text: String) -> Option<String> {
+    (!text.is_empty() && text.chars().count() <= MAX_CAPTION_CHARS).then_some(text)
+}
+
+fn element_text(element: &ElementRef) -> String {
+    element.text().collect::<Vec<_>>().join("").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use tempfile::TempDir;
+
+    fn run(text: &str) -> Vec<TextRun> {
+        vec![TextRun { text: text.to_string(), marks: vec![], attributes: None }]
+    }
+
+    #[test]
+    fn test_captioned_images_in_chapter() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('书', '/a.epub')", []).unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        let html = r#"<html><body>
+            <p>正文开头。</p>
+            <figure><img src="../images/chart.png"/><figcaption>图 1：年度趋势</figcaption></figure>
+            <p>中间的一段较长的正文。</p>
+            <div><img src="../images/map.jpg"/></div>
+            <p>图 2：路线示意</p>
+        </body></html>"#;
+        let chapter_id =
+            irp::create_chapter_with_html(&conn, book_id, "第一章", 0, "explicit", Some(html), "html").unwrap() as i32;
+        irp::create_block(&conn, chapter_id, 0, "paragraph", &run("正文开头。"), None).unwrap();
+        irp::create_block(&conn, chapter_id, 1, "image", &run("../images/chart.png"), None).unwrap();
+        irp::create_block(&conn, chapter_id, 2, "paragraph", &run("图 1：年度趋势"), None).unwrap();
+        irp::create_block(&conn, chapter_id, 3, "paragraph", &run("中间的一段较长的正文。"), None).unwrap();
+        irp::create_block(&conn, chapter_id, 4, "image", &run("../images/map.jpg"), None).unwrap();
+        irp::create_block(&conn, chapter_id, 5, "paragraph", &run("图 2：路线示意"), None).unwrap();
+        asset_manager::save_asset_mapping(&conn, book_id, "OEBPS/images/chart.png", "assets/1/c1.png", "image")
+            .unwrap();
+
+        let figures = get_figures(&conn, book_id, temp_dir.path(), None).unwrap();
+        assert_eq!(figures.len(), 2);
+
+        assert_eq!(figures[0].chapter_index, 0);
+        assert_eq!(figures[0].block_index, Some(1));
+        assert_eq!(figures[0].caption.as_deref(), Some("图 1：年度趋势"));
+        assert_eq!(
+            figures[0].local_path,
+            Some(temp_dir.path().join("assets/1/c1.png").to_string_lossy().into_owned())
+        );
+
+        assert_eq!(figures[1].block_index, Some(4));
+        assert_eq!(figures[1].src, "../images/map.jpg");
+        assert_eq!(figures[1].caption.as_deref(), Some("图 2：路线示意"));
+        assert_eq!(figures[1].local_path, None);
+    }
+}
@@ -0,0 +1,77 @@
+// 代码块语法高亮：启用 syntax-highlight 特性时用 syntect 生成带 class 的 token span，
+// 未启用或语言未知时退回转义后的纯文本
+//
+// 输出使用 class（前缀 hl-）而不是内联颜色，由前端样式按亮色/暗色主题着色
+
+/// 高亮代码
+///
+/// # 参数
+/// - `lang`: 代码语言（如 "rust"、"py"），None 时按纯文本处理
+/// - `code`: 代码文本
+///
+/// # 返回
+/// 可直接放进 `<pre><code>` 的 HTML
+pub fn highlight_code(lang: Option<&str>, code: &str) -> String {
+    lang.and_then(|lang| try_highlight(lang, code))
+        .unwrap_or_else(|| html_escape::encode_text(code).to_string())
+}
+
+/// 高亮代码块，用于导入时预先渲染
+///
+/// # 返回
+/// 未启用高亮或语言未知时返回 None
+#[cfg(feature = "syntax-highlight")]
+pub fn try_highlight(lang: &str, code: &str) -> Option<String> {
+    use std::sync::OnceLock;
+    use syntect::html::{ClassStyle, ClassedHTMLGenerator};
+    use syntect::parsing::SyntaxSet;
+    use syntect::util::LinesWithEndings;
+
+    // 加载语法定义较慢，只加载一次
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    let syntax_set = SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines);
+
+    let syntax = syntax_set.find_syntax_by_token(lang.trim())?;
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(
+        syntax,
+        syntax_set,
+        ClassStyle::SpacedPrefixed { prefix: "hl-" },
+    );
+    for line in LinesWithEndings::from(code) {
+        generator.parse_html_for_line_which_includes_newline(line).ok()?;
+    }
+    Some(generator.finalize())
+}
+
+/// 高亮代码块，用于导入时预先渲染
+///
+/// # 返回
+/// 未启用高亮或语言未知时返回 None
+#[cfg(not(feature = "syntax-highlight"))]
+pub fn try_highlight(_lang: &str, _code: &str) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_language_falls_back_to_plain_text() {
+        let code = "if a < b { return; }";
+        assert_eq!(highlight_code(Some("no-such-language"), code), "if a &lt; b { return; }");
+        assert_eq!(highlight_code(None, code), "if a &lt; b { return; }");
+    }
+
+    #[cfg(feature = "syntax-highlight")]
+    #[test]
+    fn test_highlight_rust_produces_token_spans() {
+        let html = highlight_code(Some("rust"), "fn main() {\n    let x = 1;\n}\n");
+        assert!(html.contains("<span class=\"hl-source hl-rust\">"), "{}", html);
+        assert!(html.contains("hl-keyword"), "{}", html);
+        assert!(html.contains("hl-entity hl-name hl-function"), "{}", html);
+        // 文本内容不变
+        assert!(html.contains("main"));
+        assert!(try_highlight("rs", "let x = 1;").is_some());
+    }
+}
@@ -0,0 +1,230 @@
+use rusqlite::{Connection, Result};
+use serde::{Deserialize, Serialize};
+
+// 高亮模块：按内容块内的字符偏移记录高亮范围和颜色，重新打开书籍时可准确还原
+
+/// 高亮
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub id: i32,
+    pub book_id: i32,
+    pub chapter_index: i32,
+    pub block_id: Option<i32>, // 为空时偏移相对于整章纯文本
+    pub start_offset: i32,     // 起始字符偏移（含）
+    pub end_offset: i32,       // 结束字符偏移（不含）
+    pub color: String,         // 十六进制颜色，如 "#ffeb3b"
+    pub note_id: Option<i32>,
+    pub created_at: String,
+}
+
+/// 新建高亮的参数
+#[derive(Deserialize, Debug, Clone)]
+pub struct NewHighlight {
+    pub book_id: i32,
+    pub chapter_index: i32,
+    pub block_id: Option<i32>,
+    pub start_offset: i32,
+    pub end_offset: i32,
+    pub color: String,
+    pub note_id: Option<i32>,
+}
+
+impl NewHighlight {
+    /// 校验偏移范围和颜色格式
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.start_offset < 0 || self.end_offset <= self.start_offset {
+            return Err(format!(
+                "无效的高亮范围: {}..{}",
+                self.start_offset, self.end_offset
+            ));
+        }
+        if !is_hex_color(&self.color) {
+            return Err(format!("无效的颜色: {}（应为 #rgb 或 #rrggbb）", self.color));
+        }
+        Ok(())
+    }
+}
+
+/// 判断是否为 #rgb 或 #rrggbb 格式的颜色
+fn is_hex_color(value: &str) -> bool {
+    match value.strip_prefix('#') {
+        Some(hex) => matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+const HIGHLIGHT_COLUMNS: &str =
+    "id, book_id, chapter_index, block_id, start_offset, end_offset, color, note_id, created_at";
+
+fn highlight_from_row(row: &rusqlite::Row) -> Result<Highlight> {
+    Ok(Highlight {
+        id: row.get(0)?,
+        book_id: row.get(1)?,
+        chapter_index: row.get(2)?,
+        block_id: row.get(3)?,
+        start_offset: row.get(4)?,
+        end_offset: row.get(5)?,
+        color: row.get(6)?,
+        note_id: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+/// 创建高亮（颜色统一存为小写）
+///
+/// # 返回
+/// 新高亮的 ID
+pub fn create_highlight(conn: &Connection, highlight: &NewHighlight) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO highlights (book_id, chapter_index, block_id, start_offset, end_offset, color, note_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![
+            highlight.book_id,
+            highlight.chapter_index,
+            highlight.block_id,
+            highlight.start_offset,
+            highlight.end_offset,
+            highlight.color.to_lowercase(),
+            highlight.note_id,
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// 获取单个高亮
+pub fn get_highlight_by_id(conn: &Connection, id: i32) -> Result<Highlight> {
+    conn.query_row(
+        &format!("SELECT {} FROM highlights WHERE id = ?1", HIGHLIGHT_COLUMNS),
+        [id],
+        highlight_from_row,
+    )
+}
+
+/// 获取章节的所有高亮（按块和起始偏移排序）
+pub fn get_highlights(conn: &Connection, book_id: i32, chapter_index: i32) -> Result<Vec<Highlight>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM highlights WHERE book_id = ?1 AND chapter_index = ?2
+         ORDER BY block_id, start_offset, id",
+        HIGHLIGHT_COLUMNS
+    ))?;
+
+    let highlights = stmt
+        .query_map([book_id, chapter_index], highlight_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(highlights)
+}
+
+/// 删除高亮
+///
+/// # 返回
+/// 是否删除了高亮
+pub fn delete_highlight(conn: &Connection, id: i32) -> Result<bool> {
+    let affected = conn.execute("DELETE FROM highlights WHERE id = ?1", [id])?;
+    Ok(affected > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use tempfile::TempDir;
+
+    fn create_test_book() -> (TempDir, Connection, i32) {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute(
+            "INSERT INTO books (title, author, file_path) VALUES ('测试书籍', '测试作者', '/test/path')",
+            [],
+        )
+        .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+        (temp_dir, conn, book_id)
+    }
+
+    fn new_highlight(book_id: i32, chapter_index: i32, start: i32, end: i32) -> NewHighlight {
+        NewHighlight {
+            book_id,
+            chapter_index,
+            block_id: Some(7),
+            start_offset: start,
+            end_offset: end,
+            color: "#FFEB3B".to_string(),
+            note_id: None,
+        }
+    }
+
+    #[test]
+    fn test_highlight_round_trip() {
+        let (_temp_dir, conn, book_id) = create_test_book();
+        conn.execute("INSERT INTO notes (title) VALUES ('批注')", []).unwrap();
+        let note_id = conn.last_insert_rowid() as i32;
+
+        let id = create_highlight(&conn, &NewHighlight {
+            note_id: Some(note_id),
+            ..new_highlight(book_id, 2, 5, 12)
+        })
+        .unwrap() as i32;
+
+        let highlight = get_highlight_by_id(&conn, id).unwrap();
+        assert_eq!(highlight.book_id, book_id);
+        assert_eq!(highlight.chapter_index, 2);
+        assert_eq!(highlight.block_id, Some(7));
+        assert_eq!((highlight.start_offset, highlight.end_offset), (5, 12));
+        assert_eq!(highlight.color, "#ffeb3b");
+        assert_eq!(highlight.note_id, Some(note_id));
+
+        assert!(delete_highlight(&conn, id).unwrap());
+        assert!(!delete_highlight(&conn, id).unwrap());
+    }
+
+    #[test]
+    fn test_get_highlights_by_chapter() {
+        let (_temp_dir, conn, book_id) = create_test_book();
+        create_highlight(&conn, &new_highlight(book_id, 1, 20, 25)).unwrap();
+        create_highlight(&conn, &new_highlight(book_id, 0, 0, 3)).unwrap();
+        create_highlight(&conn, &new_highlight(book_id, 1, 2, 8)).unwrap();
+
+        let highlights = get_highlights(&conn, book_id, 1).unwrap();
+        let ranges: Vec<(i32, i32)> = highlights.iter().map(|h| (h.start_offset, h.end_offset)).collect();
+        assert_eq!(ranges, vec![(2, 8), (20, 25)]);
+        assert_eq!(get_highlights(&conn, book_id, 0).unwrap().len(), 1);
+        assert!(get_highlights(&conn, book_id, 5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate_new_highlight() {
+        assert!(new_highlight(1, 0, 0, 1).validate().is_ok());
+        assert!(NewHighlight { color: "#abc".to_string(), ..new_highlight(1, 0, 0, 1) }.validate().is_ok());
+        assert!(new_highlight(1, 0, 3, 3).validate().is_err());
+        assert!(new_highlight(1, 0, -1, 3).validate().is_err());
+        assert!(NewHighlight { color: "yellow".to_string(), ..new_highlight(1, 0, 0, 1) }.validate().is_err());
+        assert!(NewHighlight { color: "#12345g".to_string(), ..new_highlight(1, 0, 0, 1) }.validate().is_err());
+    }
+
+    #[test]
+    fn test_removing_book_removes_highlights() {
+        let (_temp_dir, conn, book_id) = create_test_book();
+        create_highlight(&conn, &new_highlight(book_id, 0, 0, 4)).unwrap();
+        create_highlight(&conn, &new_highlight(book_id, 1, 2, 6)).unwrap();
+
+        conn.execute("DELETE FROM books WHERE id = ?1", [book_id]).unwrap();
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM highlights", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_deleting_note_keeps_highlight() {
+        let (_temp_dir, conn, book_id) = create_test_book();
+        conn.execute("INSERT INTO notes (title) VALUES ('批注')", []).unwrap();
+        let note_id = conn.last_insert_rowid() as i32;
+        let id = create_highlight(&conn, &NewHighlight {
+            note_id: Some(note_id),
+            ..new_highlight(book_id, 0, 0, 4)
+        })
+        .unwrap() as i32;
+
+        conn.execute("DELETE FROM notes WHERE id = ?1", [note_id]).unwrap();
+        assert_eq!(get_highlight_by_id(&conn, id).unwrap().note_id, None);
+    }
+}
@@ -1,8 +1,10 @@
 use std::collections::{VecDeque, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use serde::{Serialize, Deserialize};
 use std::path::PathBuf;
 use chrono::{DateTime, Utc};
+use tokio::sync::Notify;
 
 /// 导入状态枚举
 ///
@@ -38,6 +40,8 @@ pub struct ImportTask {
     pub progress: f32,
     /// 创建时间
     pub created_at: DateTime<Utc>,
+    /// 优先级，越大越先处理；相同优先级按入队顺序处理（默认 0）
+    pub priority: i32,
 }
 
 /// 导入队列
@@ -50,6 +54,12 @@ pub struct ImportQueue {
     active_tasks: Arc<Mutex<HashMap<i32, ImportTask>>>,
     /// 最大并发任务数
     max_concurrent: usize,
+    /// 后台处理循环是否在运行（同一时间只允许一个循环取任务）
+    processor_running: AtomicBool,
+    /// 应用正在退出，处理循环不再取新任务
+    shutting_down: AtomicBool,
+    /// 有新任务、任务完成或退出时唤醒处理循环
+    wakeup: Notify,
 }
 
 impl ImportQueue {
@@ -62,9 +72,41 @@ impl ImportQueue {
             tasks: Arc::new(Mutex::new(VecDeque::new())),
             active_tasks: Arc::new(Mutex::new(HashMap::new())),
             max_concurrent,
+            processor_running: AtomicBool::new(false),
+            shutting_down: AtomicBool::new(false),
+            wakeup: Notify::new(),
         }
     }
 
+    /// 占用后台处理循环
+    ///
+    /// # 返回
+    /// 成功占用返回 true，调用方负责启动循环并在退出时调用 `processor_stopped`；
+    /// 已有循环在运行或应用正在退出时返回 false
+    pub fn try_start_processor(&self) -> bool {
+        !self.is_shutting_down()
+            && self
+                .processor_running
+                .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+    }
+
+    /// 处理循环退出后释放占用
+    pub fn processor_stopped(&self) {
+        self.processor_running.store(false, Ordering::SeqCst);
+    }
+
+    /// 通知处理循环退出（应用退出时调用），之后不再取新任务
+    pub fn shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        self.wakeup.notify_one();
+    }
+
+    /// 应用是否正在退出
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
     /// 将任务加入队列
     ///
     /// # 参数
@@ -76,11 +118,29 @@ impl ImportQueue {
         let mut tasks = self.tasks.lock()
             .map_err(|e| format!("锁定任务队列失败: {}", e))?;
         tasks.push_back(task);
+        drop(tasks);
+        self.wakeup.notify_one();
+        Ok(())
+    }
+
+    /// 将任务插入队首（用于需要立即处理的任务）
+    ///
+    /// 仍然遵循优先级：只会排在同优先级任务之前
+    ///
+    /// # 参数
+    /// - `task`: 要加入的任务
+    pub fn enqueue_front(&self, task: ImportTask) -> Result<(), String> {
+        let mut tasks = self.tasks.lock()
+            .map_err(|e| format!("锁定任务队列失败: {}", e))?;
+        tasks.push_front(task);
+        drop(tasks);
+        self.wakeup.notify_one();
         Ok(())
     }
 
     /// 从队列中取出任务
     ///
+    /// 取出优先级最高的任务，优先级相同时取最早入队的任务；
     /// 如果当前活动任务数已达上限，返回 None
     ///
     /// # 返回
@@ -98,7 +158,55 @@ impl ImportQueue {
             return Ok(None);
         }
 
-        Ok(tasks.pop_front())
+        let next = Self::processing_order(&tasks).first().copied();
+        Ok(next.and_then(|index| tasks.remove(index)))
+    }
+
+    /// 等待下一个可以处理的任务（处理循环使用）
+    ///
+    /// 队列为空或已达并发上限时挂起，直到有新任务入队或活动任务完成；
+    /// 应用退出时返回 None
+    pub async fn next_task(&self) -> Result<Option<ImportTask>, String> {
+        loop {
+            if self.is_shutting_down() {
+                return Ok(None);
+            }
+            if let Some(task) = self.dequeue()? {
+                return Ok(Some(task));
+            }
+            self.wakeup.notified().await;
+        }
+    }
+
+    /// 按处理顺序排列的任务下标：优先级从高到低，同优先级保持入队顺序
+    fn processing_order(tasks: &VecDeque<ImportTask>) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..tasks.len()).collect();
+        // 稳定排序，同优先级不改变相对顺序
+        order.sort_by_key(|&index| std::cmp::Reverse(tasks[index].priority));
+        order
+    }
+
+    /// 获取所有排队任务的位置
+    ///
+    /// # 返回
+    /// (book_id, 位置) 列表，位置从 1 开始，1 表示下一个被处理
+    pub fn positions(&self) -> Vec<(i32, usize)> {
+        let Ok(tasks) = self.tasks.lock() else {
+            return Vec::new();
+        };
+        Self::processing_order(&tasks)
+            .into_iter()
+            .enumerate()
+            .map(|(position, index)| (tasks[index].book_id, position + 1))
+            .collect()
+    }
+
+    /// 获取任务在队列中的位置（从 1 开始），不在队列中时返回 None
+    pub fn position(&self, book_id: i32) -> Option<usize> {
+        self.positions()
+            .into_iter()
+            .find(|(id, _)| *id == book_id)
+            .map(|(_, position)| position)
     }
 
     /// 标记任务为活动状态
@@ -120,6 +228,9 @@ impl ImportQueue {
         let mut active = self.active_tasks.lock()
             .map_err(|e| format!("锁定活动任务失败: {}", e))?;
         active.remove(&book_id);
+        drop(active);
+        // 空出并发槽位，唤醒处理循环取下一个任务
+        self.wakeup.notify_one();
         Ok(())
     }
 
@@ -186,7 +297,145 @@ mod tests {
             status: ImportStatus::Pending,
             progress: 0.0,
             created_at: Utc::now(),
+            priority: 0,
+        }
+    }
+
+    fn create_priority_task(book_id: i32, priority: i32) -> ImportTask {
+        ImportTask {
+            priority,
+            ..create_test_task(book_id)
+        }
+    }
+
+    fn drain(queue: &ImportQueue) -> Vec<i32> {
+        std::iter::from_fn(|| queue.dequeue().unwrap())
+            .map(|task| task.book_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_priority_ordering() {
+        let queue = ImportQueue::new(3);
+        queue.enqueue(create_priority_task(1, 0)).unwrap();
+        queue.enqueue(create_priority_task(2, 5)).unwrap();
+        queue.enqueue(create_priority_task(3, 1)).unwrap();
+        queue.enqueue(create_priority_task(4, 5)).unwrap();
+
+        // 高优先级先出，同优先级按入队顺序
+        assert_eq!(drain(&queue), vec![2, 4, 3, 1]);
+    }
+
+    #[test]
+    fn test_equal_priority_is_fifo() {
+        let queue = ImportQueue::new(3);
+        for i in 1..=4 {
+            queue.enqueue(create_priority_task(i, 2)).unwrap();
+        }
+        assert_eq!(drain(&queue), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_positions_shift_as_tasks_dequeue() {
+        let queue = ImportQueue::new(3);
+        for i in 1..=3 {
+            queue.enqueue(create_test_task(i)).unwrap();
         }
+        assert_eq!(queue.positions(), vec![(1, 1), (2, 2), (3, 3)]);
+        assert_eq!(queue.position(3), Some(3));
+
+        queue.dequeue().unwrap();
+        assert_eq!(queue.positions(), vec![(2, 1), (3, 2)]);
+        assert_eq!(queue.position(1), None);
+
+        // 高优先级任务插入后，后面的任务位置后移
+        queue.enqueue(create_priority_task(4, 1)).unwrap();
+        assert_eq!(queue.positions(), vec![(4, 1), (2, 2), (3, 3)]);
+
+        queue.dequeue().unwrap();
+        queue.dequeue().unwrap();
+        assert_eq!(queue.position(3), Some(1));
+    }
+
+    #[test]
+    fn test_enqueue_front() {
+        let queue = ImportQueue::new(3);
+        queue.enqueue(create_test_task(1)).unwrap();
+        queue.enqueue(create_test_task(2)).unwrap();
+        queue.enqueue(create_priority_task(3, 1)).unwrap();
+        queue.enqueue_front(create_test_task(4)).unwrap();
+
+        // 插队任务排在同优先级任务之前，但不超过更高优先级的任务
+        assert_eq!(drain(&queue), vec![3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn test_concurrent_starts_claim_single_processor() {
+        let queue = Arc::new(ImportQueue::new(3));
+        let handles: Vec<_> = (0..16)
+            .map(|i| {
+                let queue = Arc::clone(&queue);
+                std::thread::spawn(move || {
+                    queue.enqueue(create_test_task(i)).unwrap();
+                    queue.try_start_processor()
+                })
+            })
+            .collect();
+        let started = handles.into_iter().map(|h| h.join().unwrap()).filter(|started| *started).count();
+        assert_eq!(started, 1);
+        assert_eq!(queue.queue_size(), 16);
+
+        // 循环退出后可以重新启动，应用退出后不再启动
+        assert!(!queue.try_start_processor());
+        queue.processor_stopped();
+        assert!(queue.try_start_processor());
+        queue.processor_stopped();
+        queue.shutdown();
+        assert!(!queue.try_start_processor());
+    }
+
+    #[test]
+    fn test_rapid_enqueues_wake_single_processor() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let queue = Arc::new(ImportQueue::new(2));
+        let processed = Arc::new(Mutex::new(Vec::new()));
+
+        rt.block_on(async {
+            // 与 spawn_queue_processor 相同：只有占用成功的一方启动循环
+            let mut processors = Vec::new();
+            for i in 0..50 {
+                queue.enqueue(create_test_task(i)).unwrap();
+                if queue.try_start_processor() {
+                    let queue = Arc::clone(&queue);
+                    let processed = Arc::clone(&processed);
+                    processors.push(tokio::spawn(async move {
+                        while let Some(task) = queue.next_task().await.unwrap() {
+                            queue.mark_active(task.clone()).unwrap();
+                            processed.lock().unwrap().push(task.book_id);
+                            queue.mark_completed(task.book_id).unwrap();
+                        }
+                        queue.processor_stopped();
+                    }));
+                }
+                tokio::task::yield_now().await;
+            }
+            assert_eq!(processors.len(), 1);
+
+            // 空闲的循环在新任务入队时被唤醒
+            while processed.lock().unwrap().len() < 50 {
+                tokio::task::yield_now().await;
+            }
+            queue.enqueue(create_test_task(50)).unwrap();
+            while processed.lock().unwrap().len() < 51 {
+                tokio::task::yield_now().await;
+            }
+
+            queue.shutdown();
+            processors.pop().unwrap().await.unwrap();
+        });
+
+        assert_eq!(*processed.lock().unwrap(), (0..=50).collect::<Vec<_>>());
+        assert!(!queue.try_start_processor());
     }
 
     #[test]
@@ -0,0 +1,206 @@
+use crate::asset_manager;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+// 书库完整性检查：在外键约束未生效的连接上删除书籍不会级联，会留下孤立的章节、内容块、资产映射、
+// 书签、高亮和阅读单元，资产文件也可能被手动删除。检查时统计这些数据，修复时删除
+// （资产目录的清理与 cleanup_orphaned_assets 相同）
+
+/// 书籍已不存在的章节
+const ORPHANED_CHAPTERS: &str = "FROM chapters WHERE book_id NOT IN (SELECT id FROM books)";
+
+/// 不属于任何现存书籍章节的内容块
+const ORPHANED_BLOCKS: &str =
+    "FROM blocks WHERE chapter_id NOT IN (SELECT c.id FROM chapters c JOIN books b ON b.id = c.book_id)";
+
+/// 书籍已不存在的资产映射
+const ORPHANED_ASSET_MAPPINGS: &str = "FROM asset_mappings WHERE book_id NOT IN (SELECT id FROM books)";
+
+/// 书籍已不存在的书签
+const ORPHANED_BOOKMARKS: &str = "FROM bookmarks WHERE book_id NOT IN (SELECT id FROM books)";
+
+/// 书籍已不存在的高亮
+const ORPHANED_HIGHLIGHTS: &str = "FROM highlights WHERE book_id NOT IN (SELECT id FROM books)";
+
+/// 书籍已不存在的阅读单元
+const ORPHANED_READING_UNITS: &str = "FROM reading_units WHERE book_id NOT IN (SELECT id FROM books)";
+
+/// 完整性检查结果
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct IntegrityReport {
+    /// 书籍已删除的章节数
+    pub orphaned_chapters: i64,
+    /// 章节已删除的内容块数
+    pub orphaned_blocks: i64,
+    /// 书籍已删除的资产映射数
+    pub orphaned_asset_mappings: i64,
+    /// 书籍已删除的书签数
+    pub orphaned_bookmarks: i64,
+    /// 书籍已删除的高亮数
+    pub orphaned_highlights: i64,
+    /// 书籍已删除的阅读单元数
+    pub orphaned_reading_units: i64,
+    /// 指向不存在文件的资产映射数
+    pub missing_asset_files: i64,
+    /// 没有对应书籍的资产目录数
+    pub orphaned_asset_dirs: i64,
+    /// 是否已删除上述孤立数据
+    pub repaired: bool,
+}
+
+impl IntegrityReport {
+    /// 是否没有发现任何问题
+    pub fn is_clean(&self) -> bool {
+        self.orphaned_chapters == 0
+            && self.orphaned_blocks == 0
+            && self.orphaned_asset_mappings == 0
+            && self.orphaned_bookmarks == 0
+            && self.orphaned_highlights == 0
+            && self.orphaned_reading_units == 0
+            && self.missing_asset_files == 0
+            && self.orphaned_asset_dirs == 0
+    }
+}
+
+/// 检查书库完整性，`repair` 为 true 时删除孤立数据
+///
+/// # 参数
+/// - `root_dir`: 书库目录，资产文件位于其中的 `assets/` 下
+///
+/// # 返回
+/// 检查（修复前）发现的问题数量
+pub fn check_integrity(conn: &Connection, root_dir: &Path, repair: bool) -> Result<IntegrityReport, String> {
+    let count = |condition: &str| -> Result<i64, String> {
+        conn.query_row(&format!("SELECT COUNT(*) {}", condition), [], |row| row.get(0))
+            .map_err(|e| format!("检查书库完整性失败: {}", e))
+    };
+
+    let missing_mapping_ids = missing_asset_mappings(conn, root_dir)?;
+    let orphaned_dirs = asset_manager::orphaned_asset_dirs(conn, root_dir)?;
+    let mut report = IntegrityReport {
+        orphaned_chapters: count(ORPHANED_CHAPTERS)?,
+        orphaned_blocks: count(ORPHANED_BLOCKS)?,
+        orphaned_asset_mappings: count(ORPHANED_ASSET_MAPPINGS)?,
+        orphaned_bookmarks: count(ORPHANED_BOOKMARKS)?,
+        orphaned_highlights: count(ORPHANED_HIGHLIGHTS)?,
+        orphaned_reading_units: count(ORPHANED_READING_UNITS)?,
+        missing_asset_files: missing_mapping_ids.len() as i64,
+        orphaned_asset_dirs: orphaned_dirs.len() as i64,
+        repaired: false,
+    };
+    if !repair || report.is_clean() {
+        return Ok(report);
+    }
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    // 先删除内容块，孤立章节下的内容块也一并删除
+    for condition in [
+        ORPHANED_BLOCKS,
+        ORPHANED_CHAPTERS,
+        ORPHANED_ASSET_MAPPINGS,
+        ORPHANED_BOOKMARKS,
+        ORPHANED_HIGHLIGHTS,
+        ORPHANED_READING_UNITS,
+    ] {
+        tx.execute(&format!("DELETE {}", condition), [])
+            .map_err(|e| format!("修复书库失败: {}", e))?;
+    }
+    for id in &missing_mapping_ids {
+        tx.execute("DELETE FROM asset_mappings WHERE id = ?1", [id])
+            .map_err(|e| format!("修复书库失败: {}", e))?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    for dir in &orphaned_dirs {
+        fs::remove_dir_all(dir).map_err(|e| format!("删除资产目录失败: {}", e))?;
+    }
+
+    report.repaired = true;
+    Ok(report)
+}
+
+/// 现存书籍中本地文件已不存在的资产映射 ID
+fn missing_asset_mappings(conn: &Connection, root_dir: &Path) -> Result<Vec<i64>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, local_path FROM asset_mappings WHERE book_id IN (SELECT id FROM books) ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let mappings = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(mappings
+        .into_iter()
+        .filter(|(_, local_path)| !root_dir.join(local_path).exists())
+        .map(|(id, _)| id)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::irp::{self, TextRun};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_and_repair_orphans() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        let runs = vec![TextRun { text: "内容".to_string(), marks: vec![], attributes: None }];
+
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('保留', '/a')", []).unwrap();
+        let kept = conn.last_insert_rowid() as i32;
+        let kept_chapter = irp::create_chapter(&conn, kept, "第一章", 0, "explicit").unwrap() as i32;
+        irp::create_block(&conn, kept_chapter, 0, "paragraph", &runs, None).unwrap();
+        let asset_dir = temp_dir.path().join("assets").join(kept.to_string());
+        fs::create_dir_all(&asset_dir).unwrap();
+        fs::write(asset_dir.join("a.png"), b"png").unwrap();
+        asset_manager::save_asset_mapping(&conn, kept, "a.png", &format!("assets/{}/a.png", kept), "image").unwrap();
+        asset_manager::save_asset_mapping(&conn, kept, "b.png", &format!("assets/{}/b.png", kept), "image").unwrap();
+
+        // 模拟在外键约束未生效时删除的书籍：留下章节、内容块、资产映射、书签、高亮、阅读单元和资产目录；
+        // 另有一个章节已删除的内容块。`open_db` 开启了外键，这里临时关闭以写入这些数据
+        conn.execute_batch("PRAGMA foreign_keys = OFF").unwrap();
+        let gone_chapter = irp::create_chapter(&conn, 99, "已删除", 0, "explicit").unwrap() as i32;
+        irp::create_block(&conn, gone_chapter, 0, "paragraph", &runs, None).unwrap();
+        irp::create_block(&conn, 12345, 0, "paragraph", &runs, None).unwrap();
+        asset_manager::save_asset_mapping(&conn, 99, "c.png", "assets/99/c.png", "image").unwrap();
+        fs::create_dir_all(temp_dir.path().join("assets").join("99")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO bookmarks (book_id, chapter_index) VALUES (99, 0);
+             INSERT INTO highlights (book_id, chapter_index, start_offset, end_offset, color) VALUES (99, 0, 0, 2, '#ffeb3b');
+             INSERT INTO reading_units (id, book_id, title, level, segment_ids, start_block_id, end_block_id, source, created_at)
+                 VALUES ('u1', 99, '单元', 1, '[]', 1, 1, 'heuristic', 0);
+             PRAGMA foreign_keys = ON;",
+        )
+        .unwrap();
+
+        let expected = IntegrityReport {
+            orphaned_chapters: 1,
+            orphaned_blocks: 2,
+            orphaned_asset_mappings: 1,
+            orphaned_bookmarks: 1,
+            orphaned_highlights: 1,
+            orphaned_reading_units: 1,
+            missing_asset_files: 1,
+            orphaned_asset_dirs: 1,
+            repaired: false,
+        };
+        assert_eq!(check_integrity(&conn, temp_dir.path(), false).unwrap(), expected);
+        // 只检查时不修改数据
+        assert_eq!(check_integrity(&conn, temp_dir.path(), false).unwrap(), expected);
+
+        let repaired = check_integrity(&conn, temp_dir.path(), true).unwrap();
+        assert_eq!(repaired, IntegrityReport { repaired: true, ..expected });
+        assert!(check_integrity(&conn, temp_dir.path(), false).unwrap().is_clean());
+        assert!(!temp_dir.path().join("assets").join("99").exists());
+
+        // 现存书籍的数据保持不变
+        assert_eq!(irp::get_blocks_by_chapter(&conn, kept_chapter, None).unwrap().len(), 1);
+        assert_eq!(asset_manager::get_book_assets(&conn, kept).unwrap().len(), 1);
+    }
+}
@@ -1,12 +1,15 @@
+use crate::encryption::{self, EncryptionError};
 use rusqlite::{Connection, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
 
 /// IRP (Intermediate Reading Representation) 数据模型
 /// 用于统一存储不同格式的文档内容
 
 /// 文本样式标记类型
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum MarkType {
     Bold,           // 加粗
     Italic,         // 斜体
@@ -18,7 +21,7 @@ pub enum MarkType {
 
 /// 文本样式标记
 /// 表示文本的样式标记（加粗、斜体、链接等）
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct TextMark {
     pub mark_type: MarkType,
     pub start: usize,
@@ -26,12 +29,53 @@ pub struct TextMark {
     pub attributes: Option<HashMap<String, String>>, // 额外属性，如链接的 href
 }
 
+/// HashMap 没有实现 Hash，属性按键排序后参与哈希，保证与 Eq 一致
+impl Hash for TextMark {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.mark_type.hash(state);
+        self.start.hash(state);
+        self.end.hash(state);
+        let attributes = self.attributes.as_ref().map(|attributes| {
+            let mut pairs: Vec<(&String, &String)> = attributes.iter().collect();
+            pairs.sort();
+            pairs
+        });
+        attributes.hash(state);
+    }
+}
+
 /// 文本运行单元
 /// 表示一段连续的文本及其样式标记
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TextRun {
     pub text: String,
     pub marks: Vec<TextMark>,
+    /// 额外属性，如图片的 alt 和图注（BTreeMap 保证哈希和序列化顺序稳定）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<BTreeMap<String, String>>,
+}
+
+impl TextRun {
+    /// 图片块的文本运行：`text` 为图片路径，非空的 alt 和图注保存在属性中
+    pub fn image(src: &str, alt: Option<&str>, caption: Option<&str>) -> Self {
+        let attributes: BTreeMap<String, String> = [("alt", alt), ("caption", caption)]
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let value = value?.trim();
+                (!value.is_empty()).then(|| (name.to_string(), value.to_string()))
+            })
+            .collect();
+        TextRun {
+            text: src.to_string(),
+            marks: vec![],
+            attributes: (!attributes.is_empty()).then_some(attributes),
+        }
+    }
+
+    /// 读取属性
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.as_ref()?.get(name).map(String::as_str)
+    }
 }
 
 /// 章节信息
@@ -45,6 +89,8 @@ pub struct Chapter {
     pub raw_html: Option<String>, // 原始 HTML（用于 EPUB 等格式）
     pub render_mode: String,       // "html" 或 "irp"
     pub heading_level: Option<i32>, // 标题层级（1-6），用于 Markdown 等格式
+    pub char_count: Option<i64>,    // 章节字符数（不含空白），旧版本导入的章节为空
+    pub source_anchor: Option<String>, // 章节在源文件中的位置（EPUB spine 路径、行或页码范围）
 }
 
 /// 内容块
@@ -56,6 +102,17 @@ pub struct Block {
     pub block_index: i32,
     pub block_type: String, // "paragraph", "heading", "image", "code"
     pub runs: Vec<TextRun>,
+    pub heading_level: Option<u8>, // 标题块的级别（1-6）
+    pub lang: Option<String>, // 代码块的语言
+    pub highlighted_html: Option<String>, // 代码块预先高亮的 HTML
+}
+
+/// 内容块指纹
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockHash {
+    pub block_id: i32,
+    pub block_index: i32,
+    pub content_hash: Option<String>, // 旧数据没有指纹时为 None
 }
 
 // ==================== Chapter CRUD 操作 ====================
@@ -103,131 +160,286 @@ pub fn create_chapter_with_html_and_level(
     Ok(conn.last_insert_rowid())
 }
 
-/// 获取书籍的所有章节
-pub fn get_chapters_by_book(conn: &Connection, book_id: i32) -> Result<Vec<Chapter>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, book_id, title, chapter_index, confidence_level, raw_html, render_mode, heading_level
-         FROM chapters WHERE book_id = ?1 ORDER BY chapter_index",
+/// 设置章节内容类型（"frontmatter"、"body" 或 "backmatter"）
+pub fn set_chapter_content_type(conn: &Connection, chapter_id: i32, content_type: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE chapters SET content_type = ?1 WHERE id = ?2",
+        rusqlite::params![content_type, chapter_id],
+    )?;
+    Ok(())
+}
+
+/// 设置章节在源文件中的位置
+pub fn set_chapter_source_anchor(conn: &Connection, chapter_id: i32, source_anchor: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE chapters SET source_anchor = ?1 WHERE id = ?2",
+        rusqlite::params![source_anchor, chapter_id],
+    )?;
+    Ok(())
+}
+
+/// 缓存章节字符数
+pub fn set_chapter_char_count(conn: &Connection, chapter_id: i32, char_count: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE chapters SET char_count = ?1 WHERE id = ?2",
+        rusqlite::params![char_count, chapter_id],
     )?;
+    Ok(())
+}
+
+/// 更新章节标题
+///
+/// # 返回
+/// 受影响的行数（章节不存在时为 0）
+pub fn update_chapter_title(conn: &Connection, chapter_id: i32, title: &str) -> Result<usize> {
+    conn.execute(
+        "UPDATE chapters SET title = ?1 WHERE id = ?2",
+        rusqlite::params![title, chapter_id],
+    )
+}
+
+const CHAPTER_COLUMNS: &str =
+    "c.id, c.book_id, c.title, c.chapter_index, c.confidence_level, c.raw_html, c.render_mode, c.heading_level,
+     COALESCE(bk.is_encrypted, 0), c.char_count, c.source_anchor";
+
+fn chapter_from_row(row: &rusqlite::Row, key: Option<&[u8]>) -> Result<Chapter> {
+    let encrypted: bool = row.get(8)?;
+    let raw_html: Option<String> = row.get(5)?;
+    let raw_html = match raw_html {
+        Some(html) if encrypted => Some(decrypt_column(&html, 5, key)?),
+        other => other,
+    };
+
+    Ok(Chapter {
+        id: row.get(0)?,
+        book_id: row.get(1)?,
+        title: row.get(2)?,
+        chapter_index: row.get(3)?,
+        confidence_level: row.get(4)?,
+        raw_html,
+        render_mode: row.get(6).unwrap_or_else(|_| "irp".to_string()),
+        heading_level: row.get(7).ok(),
+        char_count: row.get(9)?,
+        source_anchor: row.get(10)?,
+    })
+}
+
+/// 获取书籍的所有章节
+///
+/// `key` 用于解密加密书籍的 raw_html，未加密的书籍忽略该参数
+pub fn get_chapters_by_book(conn: &Connection, book_id: i32, key: Option<&[u8]>) -> Result<Vec<Chapter>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM chapters c LEFT JOIN books bk ON bk.id = c.book_id
+         WHERE c.book_id = ?1 ORDER BY c.chapter_index",
+        CHAPTER_COLUMNS
+    ))?;
 
     let chapters = stmt
-        .query_map([book_id], |row| {
-            Ok(Chapter {
-                id: row.get(0)?,
-                book_id: row.get(1)?,
-                title: row.get(2)?,
-                chapter_index: row.get(3)?,
-                confidence_level: row.get(4)?,
-                raw_html: row.get(5)?,
-                render_mode: row.get(6).unwrap_or_else(|_| "irp".to_string()),
-                heading_level: row.get(7).ok(),
-            })
-        })?
+        .query_map([book_id], |row| chapter_from_row(row, key))?
         .collect::<Result<Vec<_>, _>>()?;
 
     Ok(chapters)
 }
 
 /// 获取单个章节
-pub fn get_chapter_by_id(conn: &Connection, chapter_id: i32) -> Result<Chapter> {
+///
+/// `key` 用于解密加密书籍的 raw_html，未加密的书籍忽略该参数
+pub fn get_chapter_by_id(conn: &Connection, chapter_id: i32, key: Option<&[u8]>) -> Result<Chapter> {
     conn.query_row(
-        "SELECT id, book_id, title, chapter_index, confidence_level, raw_html, render_mode, heading_level
-         FROM chapters WHERE id = ?1",
+        &format!(
+            "SELECT {} FROM chapters c LEFT JOIN books bk ON bk.id = c.book_id WHERE c.id = ?1",
+            CHAPTER_COLUMNS
+        ),
         [chapter_id],
-        |row| {
-            Ok(Chapter {
-                id: row.get(0)?,
-                book_id: row.get(1)?,
-                title: row.get(2)?,
-                chapter_index: row.get(3)?,
-                confidence_level: row.get(4)?,
-                raw_html: row.get(5)?,
-                render_mode: row.get(6).unwrap_or_else(|_| "irp".to_string()),
-                heading_level: row.get(7).ok(),
-            })
-        },
+        |row| chapter_from_row(row, key),
+    )
+}
+
+/// 按书籍和章节序号获取章节
+pub fn get_chapter_by_index(
+    conn: &Connection,
+    book_id: i32,
+    chapter_index: i32,
+    key: Option<&[u8]>,
+) -> Result<Chapter> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM chapters c LEFT JOIN books bk ON bk.id = c.book_id
+             WHERE c.book_id = ?1 AND c.chapter_index = ?2",
+            CHAPTER_COLUMNS
+        ),
+        [book_id, chapter_index],
+        |row| chapter_from_row(row, key),
     )
 }
 
 // ==================== Block CRUD 操作 ====================
 
-/// 创建内容块
+/// 创建内容块（`key` 不为空时加密存储 runs_json，指纹以密钥计算）
+///
+/// 仅供测试逐条构造内容块，导入流程使用 `create_blocks` 批量写入
+#[cfg(test)]
 pub fn create_block(
     conn: &Connection,
     chapter_id: i32,
     block_index: i32,
     block_type: &str,
     runs: &[TextRun],
+    key: Option<&[u8]>,
 ) -> Result<i64> {
-    let runs_json = serde_json::to_string(runs)
-        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
-
     conn.execute(
-        "INSERT INTO blocks (chapter_id, block_index, block_type, runs_json)
-         VALUES (?1, ?2, ?3, ?4)",
-        rusqlite::params![chapter_id, block_index, block_type, runs_json],
+        "INSERT INTO blocks (chapter_id, block_index, block_type, runs_json, content_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![chapter_id, block_index, block_type, encode_runs(runs, key)?, block_content_hash(runs, key)],
     )?;
     Ok(conn.last_insert_rowid())
 }
 
-/// 获取章节的所有内容块
-pub fn get_blocks_by_chapter(conn: &Connection, chapter_id: i32) -> Result<Vec<Block>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, chapter_id, block_index, block_type, runs_json
-         FROM blocks WHERE chapter_id = ?1 ORDER BY block_index",
+/// 批量写入的内容块
+pub struct NewBlock<'a> {
+    pub block_type: &'a str,
+    pub runs: &'a [TextRun],
+    pub heading_level: Option<u8>,
+    pub lang: Option<&'a str>,
+    pub highlighted_html: Option<String>,
+}
+
+/// 批量创建章节的内容块，block_index 按列表顺序从 0 开始
+///
+/// 复用同一条预编译语句一次写入全部字段，调用方应在事务中调用以避免逐条提交
+pub fn create_blocks(conn: &Connection, chapter_id: i32, blocks: &[NewBlock], key: Option<&[u8]>) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO blocks (chapter_id, block_index, block_type, runs_json, content_hash, heading_level, lang, highlighted_html)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
     )?;
+    for (block_index, block) in blocks.iter().enumerate() {
+        let highlighted_html = match (&block.highlighted_html, key) {
+            (Some(html), Some(key)) => Some(
+                encryption::encrypt_content(html, key)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+            ),
+            (html, _) => html.clone(),
+        };
+        stmt.execute(rusqlite::params![
+            chapter_id,
+            block_index as i32,
+            block.block_type,
+            encode_runs(block.runs, key)?,
+            block_content_hash(block.runs, key),
+            block.heading_level,
+            block.lang,
+            highlighted_html,
+        ])?;
+    }
+    Ok(())
+}
+
+/// 序列化 runs（`key` 不为空时加密）
+fn encode_runs(runs: &[TextRun], key: Option<&[u8]>) -> Result<String> {
+    let runs_json = serde_json::to_string(runs)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    match key {
+        Some(key) => encryption::encrypt_content(&runs_json, key)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e))),
+        None => Ok(runs_json),
+    }
+}
+
+const BLOCK_COLUMNS: &str =
+    "b.id, b.chapter_id, b.block_index, b.block_type, b.runs_json, COALESCE(bk.is_encrypted, 0), b.heading_level, b.lang,
+     b.highlighted_html";
+
+const BLOCK_JOINS: &str =
+    "blocks b LEFT JOIN chapters c ON c.id = b.chapter_id LEFT JOIN books bk ON bk.id = c.book_id";
+
+fn block_from_row(row: &rusqlite::Row, key: Option<&[u8]>) -> Result<Block> {
+    let encrypted: bool = row.get(5)?;
+    let mut runs_json: String = row.get(4)?;
+    if encrypted {
+        runs_json = decrypt_column(&runs_json, 4, key)?;
+    }
+    let runs: Vec<TextRun> = serde_json::from_str(&runs_json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(
+            4,
+            rusqlite::types::Type::Text,
+            Box::new(e),
+        )
+    })?;
+    let highlighted_html: Option<String> = row.get(8)?;
+    let highlighted_html = match highlighted_html {
+        Some(html) if encrypted => Some(decrypt_column(&html, 8, key)?),
+        other => other,
+    };
+
+    Ok(Block {
+        id: row.get(0)?,
+        chapter_id: row.get(1)?,
+        block_index: row.get(2)?,
+        block_type: row.get(3)?,
+        runs,
+        heading_level: row.get(6)?,
+        lang: row.get(7)?,
+        highlighted_html,
+    })
+}
+
+/// 获取章节的所有内容块
+///
+/// `key` 用于解密加密书籍的 runs_json，未加密的书籍忽略该参数
+pub fn get_blocks_by_chapter(conn: &Connection, chapter_id: i32, key: Option<&[u8]>) -> Result<Vec<Block>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM {} WHERE b.chapter_id = ?1 ORDER BY b.block_index",
+        BLOCK_COLUMNS, BLOCK_JOINS
+    ))?;
 
     let blocks = stmt
+        .query_map([chapter_id], |row| block_from_row(row, key))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(blocks)
+}
+
+/// 获取章节各内容块的指纹（按 block_index 排序）
+pub fn get_block_hashes(conn: &Connection, chapter_id: i32) -> Result<Vec<BlockHash>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, block_index, content_hash FROM blocks WHERE chapter_id = ?1 ORDER BY block_index",
+    )?;
+    let hashes = stmt
         .query_map([chapter_id], |row| {
-            let runs_json: String = row.get(4)?;
-            let runs: Vec<TextRun> = serde_json::from_str(&runs_json).map_err(|e| {
-                rusqlite::Error::FromSqlConversionFailure(
-                    4,
-                    rusqlite::types::Type::Text,
-                    Box::new(e),
-                )
-            })?;
-
-            Ok(Block {
-                id: row.get(0)?,
-                chapter_id: row.get(1)?,
-                block_index: row.get(2)?,
-                block_type: row.get(3)?,
-                runs,
+            Ok(BlockHash {
+                block_id: row.get(0)?,
+                block_index: row.get(1)?,
+                content_hash: row.get(2)?,
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
-
-    Ok(blocks)
+    Ok(hashes)
 }
 
 /// 获取单个内容块
-pub fn get_block_by_id(conn: &Connection, block_id: i32) -> Result<Block> {
+pub fn get_block_by_id(conn: &Connection, block_id: i32, key: Option<&[u8]>) -> Result<Block> {
     conn.query_row(
-        "SELECT id, chapter_id, block_index, block_type, runs_json
-         FROM blocks WHERE id = ?1",
+        &format!("SELECT {} FROM {} WHERE b.id = ?1", BLOCK_COLUMNS, BLOCK_JOINS),
         [block_id],
-        |row| {
-            let runs_json: String = row.get(4)?;
-            let runs: Vec<TextRun> = serde_json::from_str(&runs_json).map_err(|e| {
-                rusqlite::Error::FromSqlConversionFailure(
-                    4,
-                    rusqlite::types::Type::Text,
-                    Box::new(e),
-                )
-            })?;
-
-            Ok(Block {
-                id: row.get(0)?,
-                chapter_id: row.get(1)?,
-                block_index: row.get(2)?,
-                block_type: row.get(3)?,
-                runs,
-            })
-        },
+        |row| block_from_row(row, key),
     )
 }
 
+/// 解密加密书籍的列内容，缺少密钥或密文被篡改时返回错误
+fn decrypt_column(value: &str, column: usize, key: Option<&[u8]>) -> Result<String> {
+    let key = key.ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            column,
+            rusqlite::types::Type::Text,
+            Box::new(EncryptionError::KeyManagementError("书籍内容已加密，缺少密钥".to_string())),
+        )
+    })?;
+
+    encryption::decrypt_content(value, key).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
 // ==================== 辅助函数 ====================
 
 /// 从 TextRun 数组中提取纯文本
@@ -238,6 +450,22 @@ pub fn extract_plain_text_from_runs(runs: &[TextRun]) -> String {
         .join("")
 }
 
+/// 计算内容块指纹（十六进制），与样式标记无关
+///
+/// 加密书籍（`key` 不为空）使用以密钥计算的 HMAC，不能通过猜测明文验证内容；
+/// 其他书籍为拼接后纯文本的 SHA256
+pub fn block_content_hash(runs: &[TextRun], key: Option<&[u8]>) -> String {
+    let text = extract_plain_text_from_runs(runs);
+    match key {
+        Some(key) => encryption::keyed_hash(key, text.as_bytes()),
+        None => {
+            let mut hasher = Sha256::new();
+            hasher.update(text.as_bytes());
+            format!("{:x}", hasher.finalize())
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -252,6 +480,7 @@ mod tests {
                 end: 4,
                 attributes: None,
             }],
+            attributes: None,
         }];
 
         let json = serde_json::to_string(&runs).unwrap();
@@ -267,14 +496,203 @@ mod tests {
             TextRun {
                 text: "Hello ".to_string(),
                 marks: vec![],
+                attributes: None,
             },
             TextRun {
                 text: "World".to_string(),
                 marks: vec![],
+                attributes: None,
             },
         ];
 
         let text = extract_plain_text_from_runs(&runs);
         assert_eq!(text, "Hello World");
     }
+
+    use crate::db;
+    use tempfile::TempDir;
+
+    fn create_encrypted_book() -> (TempDir, Connection, i32, i32) {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute(
+            "INSERT INTO books (title, file_path, is_encrypted) VALUES ('加密书', '/test/encrypted', 1)",
+            [],
+        )
+        .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+        let chapter_id = create_chapter(&conn, book_id, "第一章", 0, "explicit").unwrap() as i32;
+        (temp_dir, conn, book_id, chapter_id)
+    }
+
+    fn sample_runs() -> Vec<TextRun> {
+        vec![TextRun {
+            text: "机密内容".to_string(),
+            marks: vec![],
+            attributes: None,
+        }]
+    }
+
+    #[test]
+    fn test_encrypted_blocks_round_trip() {
+        let (_temp_dir, conn, book_id, chapter_id) = create_encrypted_book();
+        let key = encryption::generate_key();
+
+        let block_id = create_block(&conn, chapter_id, 0, "paragraph", &sample_runs(), Some(&key)).unwrap() as i32;
+        let html_chapter_id = create_chapter_with_html(
+            &conn,
+            book_id,
+            "第二章",
+            1,
+            "explicit",
+            Some(&encryption::encrypt_content("<p>机密 HTML</p>", &key).unwrap()),
+            "html",
+        )
+        .unwrap() as i32;
+
+        // 数据库中不保存明文
+        let stored: String = conn
+            .query_row("SELECT runs_json FROM blocks WHERE id = ?1", [block_id], |row| row.get(0))
+            .unwrap();
+        assert!(!stored.contains("机密内容"));
+
+        let blocks = get_blocks_by_chapter(&conn, chapter_id, Some(&key)).unwrap();
+        assert_eq!(extract_plain_text_from_runs(&blocks[0].runs), "机密内容");
+        let block = get_block_by_id(&conn, block_id, Some(&key)).unwrap();
+        assert_eq!(block.runs[0].text, "机密内容");
+
+        let chapter = get_chapter_by_id(&conn, html_chapter_id, Some(&key)).unwrap();
+        assert_eq!(chapter.raw_html.as_deref(), Some("<p>机密 HTML</p>"));
+        let chapters = get_chapters_by_book(&conn, book_id, Some(&key)).unwrap();
+        assert_eq!(chapters[1].raw_html.as_deref(), Some("<p>机密 HTML</p>"));
+
+        // 缺少密钥时返回错误而不是密文
+        assert!(get_blocks_by_chapter(&conn, chapter_id, None).is_err());
+        assert!(get_chapter_by_id(&conn, html_chapter_id, None).is_err());
+    }
+
+    #[test]
+    fn test_tampered_ciphertext_fails_cleanly() {
+        let (_temp_dir, conn, _book_id, chapter_id) = create_encrypted_book();
+        let key = encryption::generate_key();
+
+        let block_id = create_block(&conn, chapter_id, 0, "paragraph", &sample_runs(), Some(&key)).unwrap() as i32;
+
+        // 翻转密文中的一个字符
+        let stored: String = conn
+            .query_row("SELECT runs_json FROM blocks WHERE id = ?1", [block_id], |row| row.get(0))
+            .unwrap();
+        let mut tampered: Vec<char> = stored.chars().collect();
+        let i = tampered.len() / 2;
+        tampered[i] = if tampered[i] == 'A' { 'B' } else { 'A' };
+        let tampered: String = tampered.into_iter().collect();
+        conn.execute("UPDATE blocks SET runs_json = ?1 WHERE id = ?2", rusqlite::params![tampered, block_id])
+            .unwrap();
+
+        let err = get_blocks_by_chapter(&conn, chapter_id, Some(&key)).unwrap_err();
+        assert!(err.to_string().contains("解密失败"), "unexpected error: {}", err);
+
+        // 错误的密钥同样失败
+        create_block(&conn, chapter_id, 1, "paragraph", &sample_runs(), Some(&key)).unwrap();
+        let wrong_key = encryption::generate_key();
+        assert!(get_block_by_id(&conn, block_id + 1, Some(&wrong_key)).is_err());
+    }
+
+    #[test]
+    fn test_unencrypted_book_ignores_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('明文书', '/test/plain')", [])
+            .unwrap();
+        let chapter_id = create_chapter(&conn, conn.last_insert_rowid() as i32, "第一章", 0, "explicit")
+            .unwrap() as i32;
+        create_block(&conn, chapter_id, 0, "paragraph", &sample_runs(), None).unwrap();
+
+        let key = encryption::generate_key();
+        let blocks = get_blocks_by_chapter(&conn, chapter_id, Some(&key)).unwrap();
+        assert_eq!(blocks[0].runs[0].text, "机密内容");
+    }
+
+    #[test]
+    fn test_block_heading_level_and_lang_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('书', '/test/levels')", []).unwrap();
+        let chapter_id = create_chapter(&conn, conn.last_insert_rowid() as i32, "第一章", 0, "explicit")
+            .unwrap() as i32;
+        let runs = sample_runs();
+        let block = |block_type, heading_level, lang| NewBlock {
+            block_type,
+            runs: &runs,
+            heading_level,
+            lang,
+            highlighted_html: None,
+        };
+        create_blocks(&conn, chapter_id, &[block("heading", Some(4), None), block("code", None, Some("python"))], None)
+            .unwrap();
+
+        let blocks = get_blocks_by_chapter(&conn, chapter_id, None).unwrap();
+        assert_eq!(blocks[0].heading_level, Some(4));
+        assert_eq!(blocks[1].heading_level, None);
+        assert_eq!(blocks[1].lang.as_deref(), Some("python"));
+    }
+
+    #[test]
+    fn test_block_content_hash_tracks_text() {
+        let (_temp_dir, conn, _book_id, chapter_id) = create_encrypted_book();
+        let key = encryption::generate_key();
+        create_block(&conn, chapter_id, 0, "paragraph", &sample_runs(), Some(&key)).unwrap();
+        create_block(&conn, chapter_id, 1, "paragraph", &sample_runs(), None).unwrap();
+        let edited = vec![TextRun { text: "机密内容。".to_string(), marks: vec![], attributes: None }];
+        create_block(&conn, chapter_id, 2, "paragraph", &edited, None).unwrap();
+
+        // 指纹与样式标记无关
+        let bold = vec![TextRun {
+            text: "机密内容".to_string(),
+            marks: vec![TextMark { mark_type: MarkType::Bold, start: 0, end: 4, attributes: None }],
+            attributes: None,
+        }];
+        assert_eq!(block_content_hash(&bold, None), block_content_hash(&sample_runs(), None));
+        assert_eq!(block_content_hash(&bold, Some(&key)), block_content_hash(&sample_runs(), Some(&key)));
+
+        let hashes = get_block_hashes(&conn, chapter_id).unwrap();
+        assert_eq!(hashes.iter().map(|h| h.block_index).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(hashes[1].content_hash.as_deref(), Some(block_content_hash(&sample_runs(), None).as_str()));
+        assert_ne!(hashes[1].content_hash, hashes[2].content_hash);
+
+        // 加密存储的内容块不保存明文的 SHA256，指纹以密钥计算
+        assert_eq!(hashes[0].content_hash.as_deref(), Some(block_content_hash(&sample_runs(), Some(&key)).as_str()));
+        assert_ne!(hashes[0].content_hash, hashes[1].content_hash);
+    }
+
+    #[test]
+    fn test_block_highlighted_html_is_encrypted_at_rest() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute(
+            "INSERT INTO books (title, file_path, is_encrypted) VALUES ('书', '/test/highlight', 1)",
+            [],
+        )
+        .unwrap();
+        let chapter_id = create_chapter(&conn, conn.last_insert_rowid() as i32, "第一章", 0, "explicit")
+            .unwrap() as i32;
+        let key = encryption::generate_key();
+        let runs = sample_runs();
+        let code = NewBlock {
+            block_type: "code",
+            runs: &runs,
+            heading_level: None,
+            lang: Some("rust"),
+            highlighted_html: Some("<span class=\"hl-keyword\">fn</span>".to_string()),
+        };
+        create_blocks(&conn, chapter_id, &[code], Some(&key)).unwrap();
+
+        let stored: String = conn
+            .query_row("SELECT highlighted_html FROM blocks WHERE chapter_id = ?1", [chapter_id], |row| row.get(0))
+            .unwrap();
+        assert!(!stored.contains("hl-keyword"));
+
+        let blocks = get_blocks_by_chapter(&conn, chapter_id, Some(&key)).unwrap();
+        assert_eq!(blocks[0].highlighted_html.as_deref(), Some("<span class=\"hl-keyword\">fn</span>"));
+    }
 }
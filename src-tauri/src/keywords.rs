@@ -0,0 +1,188 @@
+use crate::book_stats::is_cjk;
+use crate::export::{self, ExportFormat};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashMap;
+
+// 关键词提取模块：统计书籍文本的词频，用于学习辅助、标签建议和词云
+//
+// 分词规则：
+// - 拉丁文字：按非字母数字字符切分，转小写，去掉单字母和纯数字
+// - CJK 文字：连续 CJK 字符按二元组（相邻两字）切分
+// 两者都会去掉停用词
+
+/// 默认返回的关键词数量
+pub const DEFAULT_TOP_N: usize = 20;
+
+/// 英文停用词
+const LATIN_STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "been",
+    "before", "but", "by", "can", "could", "did", "do", "does", "for", "from", "had", "has",
+    "have", "he", "her", "him", "his", "how", "if", "in", "into", "is", "it", "its", "just",
+    "me", "more", "my", "no", "not", "now", "of", "on", "one", "only", "or", "other", "our",
+    "out", "she", "so", "some", "than", "that", "the", "their", "them", "then", "there",
+    "these", "they", "this", "those", "to", "up", "us", "was", "we", "were", "what", "when",
+    "which", "who", "will", "with", "would", "you", "your",
+];
+
+/// 中文停用词（二元组）
+const CJK_STOPWORDS: &[&str] = &[
+    "一个", "一些", "不是", "之后", "也是", "于是", "从而", "他们", "以及", "但是", "你们",
+    "其中", "只是", "可以", "可能", "因为", "她们", "如果", "就是", "已经", "我们", "所以",
+    "没有", "然后", "这个", "这些", "这样", "那个", "那些", "自己", "还是", "什么", "怎么",
+];
+
+/// 出现在二元组中即视为无意义的虚字
+const CJK_STOP_CHARS: &[char] = &[
+    '的', '了', '是', '在', '和', '与', '及', '也', '就', '都', '而', '着', '之', '把', '被', '吗',
+    '呢', '吧', '啊',
+];
+
+/// 关键词及其出现次数
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Keyword {
+    pub term: String,
+    pub count: i64,
+}
+
+/// 将文本切分为关键词候选（已去除停用词）
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut latin = String::new();
+    let mut cjk: Vec<char> = Vec::new();
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            push_latin_token(&mut latin, &mut tokens);
+            cjk.push(c);
+        } else if c.is_alphanumeric() {
+            push_cjk_tokens(&mut cjk, &mut tokens);
+            latin.extend(c.to_lowercase());
+        } else {
+            push_latin_token(&mut latin, &mut tokens);
+            push_cjk_tokens(&mut cjk, &mut tokens);
+        }
+    }
+    push_latin_token(&mut latin, &mut tokens);
+    push_cjk_tokens(&mut cjk, &mut tokens);
+
+    tokens
+}
+
+fn push_latin_token(word: &mut String, tokens: &mut Vec<String>) {
+    let is_stopword = word.chars().count() < 2
+        || word.chars().all(|c| c.is_numeric())
+        || LATIN_STOPWORDS.contains(&word.as_str());
+    if !is_stopword {
+        tokens.push(word.clone());
+    }
+    word.clear();
+}
+
+fn push_cjk_tokens(run: &mut Vec<char>, tokens: &mut Vec<String>) {
+    for pair in run.windows(2) {
+        if pair.iter().any(|c| CJK_STOP_CHARS.contains(c)) {
+            continue;
+        }
+        let term: String = pair.iter().collect();
+        if !CJK_STOPWORDS.contains(&term.as_str()) {
+            tokens.push(term);
+        }
+    }
+    run.clear();
+}
+
+/// 统计文本中出现次数最多的关键词
+///
+/// # 参数
+/// - `text`: 文本
+/// - `top_n`: 返回的关键词数量
+///
+/// # 返回
+/// 按出现次数降序排列的关键词（次数相同时按字典序）
+pub fn extract_keywords_from_text(text: &str, top_n: usize) -> Vec<Keyword> {
+    let mut counts: HashMap<String, i64> = HashMap::new();
+    for token in tokenize(text) {
+        *counts.entry(token).or_insert(0) += 1;
+    }
+
+    let mut keywords: Vec<Keyword> = counts
+        .into_iter()
+        .map(|(term, count)| Keyword { term, count })
+        .collect();
+    keywords.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.term.cmp(&b.term)));
+    keywords.truncate(top_n);
+    keywords
+}
+
+/// 提取整本书的关键词（文本来自内容块或去除标签后的 raw_html）
+///
+/// # 参数
+/// - `conn`: 数据库连接
+/// - `book_id`: 书籍 ID
+/// - `top_n`: 返回的关键词数量
+/// - `key`: 加密书籍的解密密钥
+pub fn extract_keywords(
+    conn: &Connection,
+    book_id: i32,
+    top_n: usize,
+    key: Option<&[u8]>,
+) -> Result<Vec<Keyword>, String> {
+    let text = export::export_book(conn, book_id, ExportFormat::Text, key)?;
+    Ok(extract_keywords_from_text(&text, top_n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::irp::{self, TextRun};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tokenize_mixed_text() {
+        let tokens = tokenize("The Reader's guide, 2024: 深度阅读的方法");
+        assert_eq!(tokens, vec!["reader", "guide", "深度", "度阅", "阅读", "方法"]);
+    }
+
+    #[test]
+    fn test_repeated_term_ranks_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('关键词', '/test/keywords')", [])
+            .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        let chapter_id = irp::create_chapter(&conn, book_id, "第一章", 0, "explicit").unwrap() as i32;
+        irp::create_block(
+            &conn,
+            chapter_id,
+            0,
+            "paragraph",
+            &[TextRun {
+                text: "Entropy is the key idea. The entropy of a system grows, and entropy explains time."
+                    .to_string(),
+                marks: vec![],
+                attributes: None,
+            }],
+            None,
+        )
+        .unwrap();
+        irp::create_chapter_with_html(
+            &conn,
+            book_id,
+            "第二章",
+            1,
+            "explicit",
+            Some("<html><body><p>Entropy again, and the system again.</p></body></html>"),
+            "html",
+        )
+        .unwrap();
+
+        let keywords = extract_keywords(&conn, book_id, 3, None).unwrap();
+        assert_eq!(keywords.len(), 3);
+        assert_eq!(keywords[0], Keyword { term: "entropy".to_string(), count: 4 });
+        assert_eq!(keywords[1], Keyword { term: "again".to_string(), count: 2 });
+        assert_eq!(keywords[2], Keyword { term: "system".to_string(), count: 2 });
+    }
+}
@@ -0,0 +1,186 @@
+use crate::error::AppError;
+use rusqlite::Connection;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 书库目录：数据库和资产文件默认放在应用数据目录，可改到外接硬盘或同步文件夹
+//
+// 覆盖路径保存在应用数据目录下的独立文件中（数据库本身的位置不能记录在数据库里）。
+// 启动时解析一次并作为 `LibraryRoot` 托管，运行期间数据库、资产、备份等都使用同一个目录，
+// 修改设置后重启才切换
+
+/// 记录书库目录覆盖路径的文件名
+const OVERRIDE_FILE: &str = "library_root";
+/// 数据库文件名
+pub const DB_FILE: &str = "library.db";
+
+/// 读取书库目录覆盖设置
+///
+/// # 返回
+/// 未设置或设置为空时返回 None
+pub fn get_override(app_data_dir: &Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(app_data_dir.join(OVERRIDE_FILE)).ok()?;
+    let root = content.trim();
+    (!root.is_empty()).then(|| PathBuf::from(root))
+}
+
+/// 当前生效的书库目录
+pub fn resolve(app_data_dir: &Path) -> PathBuf {
+    get_override(app_data_dir).unwrap_or_else(|| app_data_dir.to_path_buf())
+}
+
+/// 本次运行使用的书库目录（启动时解析，运行期间不随设置变化）
+pub struct LibraryRoot(PathBuf);
+
+impl LibraryRoot {
+    /// 按当前设置解析书库目录
+    pub fn new(app_data_dir: &Path) -> Self {
+        Self(resolve(app_data_dir))
+    }
+
+    /// 书库目录
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// 数据库路径
+    pub fn db_path(&self) -> PathBuf {
+        self.0.join(DB_FILE)
+    }
+
+    /// 资产目录（webview 通过 asset 协议只能访问该目录）
+    pub fn assets_dir(&self) -> PathBuf {
+        self.0.join("assets")
+    }
+}
+
+/// 检查目录可写（不存在时创建）
+fn ensure_writable(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("无法创建书库目录: {}", e))?;
+    let probe = dir.join(".deep-reader-write-test");
+    fs::write(&probe, b"ok").map_err(|e| format!("书库目录不可写: {}", e))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+/// 递归复制目录
+fn copy_dir(src: &Path, dest: &Path) -> Result<(), String> {
+    fs::create_dir_all(dest).map_err(|e| format!("创建目录失败: {}", e))?;
+    for entry in fs::read_dir(src).map_err(|e| format!("读取目录失败: {}", e))? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let target = dest.join(entry.file_name());
+        if entry.path().is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target).map_err(|e| format!("复制文件失败: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+/// 设置书库目录
+///
+/// 新位置在下次启动时生效，本次运行的 `LibraryRoot` 不变
+///
+/// # 参数
+/// - `conn`: 当前数据库连接（迁移时用于生成一致的数据库快照）
+/// - `app_data_dir`: 应用数据目录
+/// - `new_root`: 新的书库目录，为 None 时恢复默认位置
+/// - `migrate`: 是否把当前数据库和资产文件复制到新位置
+///
+/// # 返回
+/// 新的书库目录
+pub fn set_library_root(
+    conn: &Connection,
+    app_data_dir: &Path,
+    new_root: Option<&Path>,
+    migrate: bool,
+) -> Result<PathBuf, AppError> {
+    let current_root = resolve(app_data_dir);
+    let target_root = new_root.map_or_else(|| app_data_dir.to_path_buf(), Path::to_path_buf);
+    if new_root.is_some_and(|root| !root.is_absolute()) {
+        return Err(AppError::Validation("书库目录必须是绝对路径".to_string()));
+    }
+    ensure_writable(&target_root).map_err(AppError::Io)?;
+
+    if migrate && target_root != current_root {
+        let target_db = target_root.join(DB_FILE);
+        if target_db.exists() {
+            return Err(AppError::Validation("目标目录已存在书库，请选择空目录或不迁移数据".to_string()));
+        }
+        conn.execute("VACUUM INTO ?1", [target_db.to_string_lossy()])
+            .map_err(|e| AppError::Db(format!("复制数据库失败: {}", e)))?;
+
+        let assets_dir = current_root.join("assets");
+        if assets_dir.is_dir() {
+            copy_dir(&assets_dir, &target_root.join("assets")).map_err(AppError::Io)?;
+        }
+    }
+
+    fs::create_dir_all(app_data_dir)?;
+    let override_path = app_data_dir.join(OVERRIDE_FILE);
+    match new_root {
+        Some(root) => fs::write(&override_path, root.to_string_lossy().as_bytes())
+            .map_err(|e| AppError::Io(format!("保存书库目录设置失败: {}", e)))?,
+        None => {
+            if override_path.exists() {
+                fs::remove_file(&override_path).map_err(|e| AppError::Io(format!("清除书库目录设置失败: {}", e)))?;
+            }
+        }
+    }
+
+    Ok(target_root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn db_path(app_data_dir: &Path) -> PathBuf {
+        LibraryRoot::new(app_data_dir).db_path()
+    }
+
+    #[test]
+    fn test_db_path_honors_override() {
+        let app_data = TempDir::new().unwrap();
+        let external = TempDir::new().unwrap();
+        assert_eq!(db_path(app_data.path()), app_data.path().join(DB_FILE));
+
+        let running = LibraryRoot::new(app_data.path());
+        let conn = crate::db::init_db(running.db_path()).unwrap();
+        set_library_root(&conn, app_data.path(), Some(external.path()), false).unwrap();
+        assert_eq!(db_path(app_data.path()), external.path().join(DB_FILE));
+        assert_eq!(resolve(app_data.path()), external.path());
+        // 本次运行的目录不变，数据库和资产路径保持一致
+        assert_eq!(running.path(), app_data.path());
+        assert_eq!(running.db_path(), app_data.path().join(DB_FILE));
+
+        // 恢复默认位置
+        set_library_root(&conn, app_data.path(), None, false).unwrap();
+        assert_eq!(db_path(app_data.path()), app_data.path().join(DB_FILE));
+    }
+
+    #[test]
+    fn test_migrate_copies_database_and_assets() {
+        let app_data = TempDir::new().unwrap();
+        let external = TempDir::new().unwrap();
+        let target = external.path().join("书库");
+        let conn = crate::db::init_db(db_path(app_data.path())).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('迁移的书', '/a')", []).unwrap();
+        fs::create_dir_all(app_data.path().join("assets/1")).unwrap();
+        fs::write(app_data.path().join("assets/1/a.png"), b"png").unwrap();
+
+        set_library_root(&conn, app_data.path(), Some(&target), true).unwrap();
+
+        let migrated = Connection::open(db_path(app_data.path())).unwrap();
+        let title: String = migrated.query_row("SELECT title FROM books", [], |row| row.get(0)).unwrap();
+        assert_eq!(title, "迁移的书");
+        assert_eq!(fs::read(target.join("assets/1/a.png")).unwrap(), b"png");
+
+        // 目标已有书库时拒绝覆盖
+        set_library_root(&conn, app_data.path(), None, false).unwrap();
+        assert!(set_library_root(&conn, app_data.path(), Some(&target), true).is_err());
+        assert!(set_library_root(&conn, app_data.path(), Some(Path::new("relative/dir")), false).is_err());
+    }
+}
@@ -0,0 +1,429 @@
+use crate::annotation_remap;
+use crate::error::AppError;
+use crate::irp::{self, BlockHash};
+use rusqlite::{Connection, OptionalExtension};
+use scraper::{Html, Selector};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+// 稳定位置定位符：由章节标题指纹、内容块指纹和块内字符偏移组成，
+// 不依赖数据库 ID，重新解析或在其他设备上导入同一本书后仍能定位到相同内容。
+// 格式：`dr1:{章节指纹}/{章节序号}!{内容块指纹}/{块序号}:{偏移}`，序号仅作为就近匹配的提示。
+// HTML 章节（EPUB）没有内容块，改用段落：`dr1:{章节指纹}/{章节序号}!p{段落文本指纹}/{段落序号}:{偏移}`
+
+/// 定位符版本前缀
+const LOCATOR_PREFIX: &str = "dr1:";
+
+/// 指纹保留的十六进制字符数
+const FINGERPRINT_LEN: usize = 16;
+
+/// HTML 章节段落指纹的前缀
+const PARAGRAPH_PREFIX: &str = "p";
+
+/// HTML 章节中作为段落定位的元素（按文档顺序，忽略没有文字的元素）
+const PARAGRAPH_SELECTOR: &str = "p, h1, h2, h3, h4, h5, h6, li, pre, dt, dd, figcaption";
+
+/// 定位符指向的内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocatorKind {
+    /// IRP 章节的内容块
+    Block,
+    /// HTML 章节的段落
+    Paragraph,
+}
+
+/// 位置定位符
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locator {
+    pub chapter_key: String,
+    pub chapter_index: i32,
+    pub kind: LocatorKind,
+    /// 内容块或段落文本的指纹
+    pub block_hash: String,
+    /// 内容块或段落的序号
+    pub block_index: i32,
+    pub offset: usize,
+}
+
+impl fmt::Display for Locator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = match self.kind {
+            LocatorKind::Block => "",
+            LocatorKind::Paragraph => PARAGRAPH_PREFIX,
+        };
+        write!(
+            f,
+            "{}{}/{}!{}{}/{}:{}",
+            LOCATOR_PREFIX, self.chapter_key, self.chapter_index, prefix, self.block_hash, self.block_index, self.offset
+        )
+    }
+}
+
+impl Locator {
+    /// 解析定位符字符串
+    pub fn parse(value: &str) -> Result<Self, AppError> {
+        let invalid = || AppError::Validation(format!("无效的位置定位符: {}", value));
+        let body = value.strip_prefix(LOCATOR_PREFIX).ok_or_else(invalid)?;
+        let (chapter, rest) = body.split_once('!').ok_or_else(invalid)?;
+        let (block, offset) = rest.rsplit_once(':').ok_or_else(invalid)?;
+        let (chapter_key, chapter_index) = chapter.split_once('/').ok_or_else(invalid)?;
+        let (block_hash, block_index) = block.split_once('/').ok_or_else(invalid)?;
+        let (kind, block_hash) = match block_hash.strip_prefix(PARAGRAPH_PREFIX) {
+            Some(hash) => (LocatorKind::Paragraph, hash),
+            None => (LocatorKind::Block, block_hash),
+        };
+        let is_fingerprint = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit());
+        if !is_fingerprint(chapter_key) || !is_fingerprint(block_hash) {
+            return Err(invalid());
+        }
+
+        Ok(Locator {
+            chapter_key: chapter_key.to_string(),
+            chapter_index: chapter_index.parse().map_err(|_| invalid())?,
+            kind,
+            block_hash: block_hash.to_string(),
+            block_index: block_index.parse().map_err(|_| invalid())?,
+            offset: offset.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// 定位符解析得到的当前位置
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResolvedLocator {
+    pub chapter_id: i32,
+    pub chapter_index: i32,
+    /// 内容块 ID，HTML 章节为 None
+    pub block_id: Option<i32>,
+    /// 内容块序号，HTML 章节为段落序号
+    pub block_index: i32,
+    pub offset: usize,
+}
+
+/// 章节指纹：去除首尾空白并转小写后的标题 SHA256 前缀
+pub fn chapter_key(title: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(title.trim().to_lowercase().as_bytes());
+    fingerprint(&format!("{:x}", hasher.finalize()))
+}
+
+fn fingerprint(hash: &str) -> String {
+    hash.chars().take(FINGERPRINT_LEN).collect()
+}
+
+/// HTML 章节各段落的文本指纹（按 `PARAGRAPH_SELECTOR` 的文档顺序）
+///
+/// 文本合并连续空白后计算，重新解析时空白或标签属性的变化不影响指纹
+pub fn html_paragraph_hashes(html: &str) -> Vec<String> {
+    let selector = Selector::parse(PARAGRAPH_SELECTOR).unwrap();
+    Html::parse_fragment(html)
+        .select(&selector)
+        .filter_map(|element| {
+            let text = element.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ");
+            (!text.is_empty()).then(|| {
+                let mut hasher = Sha256::new();
+                hasher.update(text.as_bytes());
+                fingerprint(&format!("{:x}", hasher.finalize()))
+            })
+        })
+        .collect()
+}
+
+/// 为章节中的内容块位置生成定位符
+///
+/// # 参数
+/// - `chapter_index`: 章节序号
+/// - `block_id`: 内容块 ID（必须属于该章节）
+/// - `offset`: 块内字符偏移
+/// - `key`: 加密书籍的解密密钥（旧数据没有保存指纹时用于重新计算）
+pub fn make_locator(
+    conn: &Connection,
+    book_id: i32,
+    chapter_index: i32,
+    block_id: i32,
+    offset: usize,
+    key: Option<&[u8]>,
+) -> Result<Locator, AppError> {
+    let (chapter_id, title): (i32, String) = conn
+        .query_row(
+            "SELECT id, title FROM chapters WHERE book_id = ?1 AND chapter_index = ?2",
+            rusqlite::params![book_id, chapter_index],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("找不到章节".to_string()))?;
+
+    let block = irp::get_block_hashes(conn, chapter_id)?
+        .into_iter()
+        .find(|block| block.block_id == block_id)
+        .ok_or_else(|| AppError::NotFound("找不到内容块".to_string()))?;
+    let block_hash = match block.content_hash {
+        Some(hash) => hash,
+        None => {
+            let block = irp::get_block_by_id(conn, block_id, key)
+                .map_err(|e| AppError::Db(format!("获取内容块失败: {}", e)))?;
+            // 与导入时一致：只有加密书籍的指纹以密钥计算
+            let encrypted: bool = conn.query_row(
+                "SELECT COALESCE(is_encrypted, 0) FROM books WHERE id = ?1",
+                [book_id],
+                |row| row.get(0),
+            )?;
+            irp::block_content_hash(&block.runs, key.filter(|_| encrypted))
+        }
+    };
+
+    Ok(Locator {
+        chapter_key: chapter_key(&title),
+        chapter_index,
+        kind: LocatorKind::Block,
+        block_hash: fingerprint(&block_hash),
+        block_index: block.block_index,
+        offset,
+    })
+}
+
+/// 为 HTML 章节中的段落位置生成定位符
+///
+/// # 参数
+/// - `chapter_index`: 章节序号
+/// - `paragraph_index`: 段落序号（与 `html_paragraph_hashes` 的顺序一致）
+/// - `offset`: 段落内字符偏移
+/// - `key`: 加密书籍的解密密钥
+pub fn make_html_locator(
+    conn: &Connection,
+    book_id: i32,
+    chapter_index: i32,
+    paragraph_index: i32,
+    offset: usize,
+    key: Option<&[u8]>,
+) -> Result<Locator, AppError> {
+    let chapter = irp::get_chapter_by_index(conn, book_id, chapter_index, key)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("找不到章节".to_string()))?;
+    if chapter.render_mode != "html" {
+        return Err(AppError::Validation("章节不是 HTML 章节，请使用内容块定位".to_string()));
+    }
+
+    let paragraphs = html_paragraph_hashes(chapter.raw_html.as_deref().unwrap_or_default());
+    let block_hash = usize::try_from(paragraph_index)
+        .ok()
+        .and_then(|index| paragraphs.get(index))
+        .ok_or_else(|| AppError::NotFound("找不到段落".to_string()))?;
+
+    Ok(Locator {
+        chapter_key: chapter_key(&chapter.title),
+        chapter_index,
+        kind: LocatorKind::Paragraph,
+        block_hash: block_hash.clone(),
+        block_index: paragraph_index,
+        offset,
+    })
+}
+
+/// 将定位符解析为书籍中的当前位置
+///
+/// 优先在标题指纹相同（多个时取序号最接近）的章节中查找内容块；
+/// 章节改名或拆分导致找不到时，在全书中按内容块指纹查找。
+/// HTML 章节的定位符按同样的顺序查找段落，`key` 用于解密加密书籍的 raw_html
+pub fn resolve_locator(
+    conn: &Connection,
+    book_id: i32,
+    locator: &Locator,
+    key: Option<&[u8]>,
+) -> Result<ResolvedLocator, AppError> {
+    if locator.kind == LocatorKind::Paragraph {
+        return resolve_paragraph(conn, book_id, locator, key);
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT id, title, chapter_index FROM chapters WHERE book_id = ?1 ORDER BY chapter_index")?;
+    let chapters = stmt
+        .query_map([book_id], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, i32>(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut same_title: Vec<&(i32, String, i32)> =
+        chapters.iter().filter(|(_, title, _)| chapter_key(title) == locator.chapter_key).collect();
+    same_title.sort_by_key(|(_, _, index)| index.abs_diff(locator.chapter_index));
+    for (chapter_id, _, chapter_index) in same_title {
+        let blocks = irp::get_block_hashes(conn, *chapter_id)?;
+        if let Some(block) = closest_block(&blocks, locator) {
+            return Ok(resolved(*chapter_id, *chapter_index, block, locator));
+        }
+    }
+
+    let by_chapter = annotation_remap::collect_block_hashes(conn, book_id).map_err(AppError::Db)?;
+    let mut candidates: Vec<(i32, &BlockHash)> = by_chapter
+        .iter()
+        .filter_map(|(chapter_index, blocks)| closest_block(blocks, locator).map(|block| (*chapter_index, block)))
+        .collect();
+    candidates.sort_by_key(|(chapter_index, _)| chapter_index.abs_diff(locator.chapter_index));
+    let (chapter_index, block) = candidates
+        .first()
+        .ok_or_else(|| AppError::NotFound("找不到定位符对应的位置".to_string()))?;
+    let chapter_id = chapters
+        .iter()
+        .find(|(_, _, index)| index == chapter_index)
+        .map(|(id, _, _)| *id)
+        .ok_or_else(|| AppError::NotFound("找不到章节".to_string()))?;
+    Ok(resolved(chapter_id, *chapter_index, block, locator))
+}
+
+/// 在 HTML 章节中解析段落定位符
+fn resolve_paragraph(
+    conn: &Connection,
+    book_id: i32,
+    locator: &Locator,
+    key: Option<&[u8]>,
+) -> Result<ResolvedLocator, AppError> {
+    let mut chapters: Vec<_> = irp::get_chapters_by_book(conn, book_id, key)?
+        .into_iter()
+        .filter(|chapter| chapter.render_mode == "html")
+        .collect();
+    // 标题指纹相同的章节优先，其次按序号就近
+    chapters.sort_by_key(|chapter| {
+        (
+            chapter_key(&chapter.title) != locator.chapter_key,
+            chapter.chapter_index.abs_diff(locator.chapter_index),
+        )
+    });
+
+    for chapter in chapters {
+        let paragraphs = html_paragraph_hashes(chapter.raw_html.as_deref().unwrap_or_default());
+        let closest = paragraphs
+            .iter()
+            .enumerate()
+            .filter(|(_, hash)| hash.starts_with(&locator.block_hash))
+            .map(|(index, _)| index as i32)
+            .min_by_key(|index| index.abs_diff(locator.block_index));
+        if let Some(paragraph_index) = closest {
+            return Ok(ResolvedLocator {
+                chapter_id: chapter.id,
+                chapter_index: chapter.chapter_index,
+                block_id: None,
+                block_index: paragraph_index,
+                offset: locator.offset,
+            });
+        }
+    }
+    Err(AppError::NotFound("找不到定位符对应的位置".to_string()))
+}
+
+/// 指纹匹配的内容块中序号最接近提示的一个
+fn closest_block<'a>(blocks: &'a [BlockHash], locator: &Locator) -> Option<&'a BlockHash> {
+    blocks
+        .iter()
+        .filter(|block| block.content_hash.as_deref().is_some_and(|hash| hash.starts_with(&locator.block_hash)))
+        .min_by_key(|block| block.block_index.abs_diff(locator.block_index))
+}
+
+fn resolved(chapter_id: i32, chapter_index: i32, block: &BlockHash, locator: &Locator) -> ResolvedLocator {
+    ResolvedLocator {
+        chapter_id,
+        chapter_index,
+        block_id: Some(block.block_id),
+        block_index: block.block_index,
+        offset: locator.offset,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::async_import::save_parse_result;
+    use crate::irp::TextRun;
+    use crate::parser::test_fixtures::write_epub;
+    use crate::parser::{epub_parser::EpubParser, Parser};
+    use tempfile::TempDir;
+
+    fn runs(text: &str) -> Vec<TextRun> {
+        vec![TextRun { text: text.to_string(), marks: vec![], attributes: None }]
+    }
+
+    // 按给定章节内容重建书籍的章节和内容块，返回各块 ID
+    fn rebuild(conn: &Connection, book_id: i32, chapters: &[(&str, &[&str])]) -> Vec<Vec<i32>> {
+        conn.execute("DELETE FROM chapters WHERE book_id = ?1", [book_id]).unwrap();
+        chapters
+            .iter()
+            .enumerate()
+            .map(|(index, (title, texts))| {
+                let chapter_id = irp::create_chapter(conn, book_id, title, index as i32, "explicit").unwrap() as i32;
+                texts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, text)| {
+                        irp::create_block(conn, chapter_id, i as i32, "paragraph", &runs(text), None).unwrap() as i32
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_locator_survives_rebuilt_blocks() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('书', '/a')", []).unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        let first: &[&str] = &["开头", "重复段落", "目标段落", "重复段落"];
+        let block_ids = rebuild(&conn, book_id, &[("第一章", first), ("第二章", &["结尾"])]);
+        let locator = make_locator(&conn, book_id, 0, block_ids[0][3], 2, None).unwrap();
+        let text = locator.to_string();
+        assert_eq!(Locator::parse(&text).unwrap(), locator);
+
+        // 重新解析后多出一个前言章节，内容块 ID 全部变化
+        let chapters: &[(&str, &[&str])] = &[("前言", &["序"]), ("第一章", first), ("第二章", &["结尾"])];
+        let block_ids = rebuild(&conn, book_id, chapters);
+        let resolved = resolve_locator(&conn, book_id, &Locator::parse(&text).unwrap(), None).unwrap();
+        assert_eq!(resolved.chapter_index, 1);
+        assert_eq!(resolved.block_id, Some(block_ids[1][3]));
+        assert_eq!(resolved.block_index, 3);
+        assert_eq!(resolved.offset, 2);
+
+        // 章节改名后按内容块指纹在全书查找
+        let block_ids = rebuild(&conn, book_id, &[("Chapter One", first)]);
+        let resolved = resolve_locator(&conn, book_id, &locator, None).unwrap();
+        assert_eq!(resolved.block_id, Some(block_ids[0][3]));
+
+        assert!(Locator::parse("dr1:zz/0!ab/1:2").is_err());
+    }
+
+    #[test]
+    fn test_locator_survives_reimported_epub() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path, is_encrypted) VALUES ('书', '/a', 1)", []).unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+        let key = crate::encryption::generate_key();
+
+        let import = |chapters: &[(&str, &str)]| {
+            let path = temp_dir.path().join("book.epub");
+            write_epub(&path, "<dc:title>书</dc:title>", chapters);
+            let result = EpubParser::new().parse(&path, book_id, &conn).unwrap();
+            conn.execute("DELETE FROM chapters WHERE book_id = ?1", [book_id]).unwrap();
+            save_parse_result(&conn, book_id, &result, Some(&key)).unwrap();
+        };
+        let first = "<p>开头</p><p>重复段落</p><p>目标  段落</p><p>重复段落</p>";
+        import(&[("第一章", first), ("第二章", "<p>结尾</p>")]);
+
+        let locator = make_html_locator(&conn, book_id, 0, 2, 3, Some(&key)).unwrap();
+        let text = locator.to_string();
+        assert!(text.contains("!p"));
+        assert_eq!(Locator::parse(&text).unwrap(), locator);
+        assert!(make_html_locator(&conn, book_id, 0, 9, 0, Some(&key)).is_err());
+
+        // 重新导入后多出一个前言章节，段落的空白和标签属性也有变化
+        let changed = r#"<p>开头</p><p>重复段落</p><p class="x">目标 段落</p><p>重复段落</p>"#;
+        import(&[("前言", "<p>序</p>"), ("第一章", changed), ("第二章", "<p>结尾</p>")]);
+        let resolved = resolve_locator(&conn, book_id, &Locator::parse(&text).unwrap(), Some(&key)).unwrap();
+        assert_eq!((resolved.chapter_index, resolved.block_id, resolved.block_index, resolved.offset), (1, None, 2, 3));
+
+        // 重复段落按序号就近匹配，章节改名后在全书中查找
+        let repeated = make_html_locator(&conn, book_id, 1, 3, 0, Some(&key)).unwrap();
+        import(&[("Chapter One", changed)]);
+        let resolved = resolve_locator(&conn, book_id, &repeated, Some(&key)).unwrap();
+        assert_eq!((resolved.chapter_index, resolved.block_index), (0, 3));
+    }
+}
@@ -0,0 +1,176 @@
+use crate::encryption;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::BTreeSet;
+
+// 笔记搜索索引：笔记内容加密存储，索引中不能出现明文。创建和编辑笔记时把解密后的标题、内容和
+// 高亮文本切成 1～3 字的片段，以加密密钥计算 HMAC 后作为词元写入 notes_search；检索时对查询词
+// 同样计算词元并用 MATCH 找出候选笔记，再解密确认。删除笔记由触发器同步移除索引
+
+/// 参与索引的最长片段字数，查询词不短于该长度时按该长度切分
+const MAX_GRAM_CHARS: usize = 3;
+
+/// 词元保留的十六进制位数
+const TOKEN_HEX_LEN: usize = 16;
+
+/// 按 `notes_search` 检索笔记 ID 的子查询条件，`?1` 为 `match_query` 生成的 MATCH 表达式
+pub const SEARCH_CONDITION: &str = "n.id IN (SELECT rowid FROM notes_search WHERE notes_search MATCH ?1)";
+
+/// 解密笔记字段，解密失败时视为未加密的旧数据保留原值
+fn decrypt_field(value: Option<String>, key: &[u8]) -> Option<String> {
+    value
+        .filter(|value| !value.is_empty())
+        .map(|value| encryption::decrypt_content(&value, key).unwrap_or(value))
+}
+
+/// 片段的词元：带长度前缀的 HMAC，不同长度的片段互不冲突
+fn token(gram: &[char], key: &[u8]) -> String {
+    let text: String = gram.iter().collect();
+    let mut hash = encryption::keyed_hash(key, format!("{}:{}", gram.len(), text).as_bytes());
+    hash.truncate(TOKEN_HEX_LEN);
+    hash
+}
+
+/// 文本中所有 1～3 字片段的词元
+fn text_tokens(text: &str, key: &[u8], tokens: &mut BTreeSet<String>) {
+    let chars: Vec<char> = text.to_lowercase().chars().collect();
+    for len in 1..=MAX_GRAM_CHARS {
+        for gram in chars.windows(len) {
+            tokens.insert(token(gram, key));
+        }
+    }
+}
+
+/// 生成查询词的 MATCH 表达式，查询词为空时返回 None
+///
+/// 不短于 3 字的查询词要求包含其中所有 3 字片段；更短的查询词直接匹配整词的词元。
+/// 片段不要求相邻，结果需要用 `note_matches` 在解密后确认
+pub fn match_query(query: &str, key: &[u8]) -> Option<String> {
+    let chars: Vec<char> = query.to_lowercase().chars().collect();
+    if chars.is_empty() {
+        return None;
+    }
+    let len = chars.len().min(MAX_GRAM_CHARS);
+    let tokens: BTreeSet<String> = chars.windows(len).map(|gram| token(gram, key)).collect();
+    Some(tokens.iter().map(|token| format!("\"{}\"", token)).collect::<Vec<_>>().join(" "))
+}
+
+/// 解密后的笔记字段中是否包含查询词（不区分大小写）
+pub fn note_matches(query: &str, fields: &[Option<&str>]) -> bool {
+    let query = query.to_lowercase();
+    fields.iter().flatten().any(|field| field.to_lowercase().contains(&query))
+}
+
+/// 重新索引单个笔记（创建或编辑后调用），笔记不存在时移除索引
+pub fn reindex_note(conn: &Connection, note_id: i32, key: &[u8]) -> Result<(), String> {
+    let note: Option<(String, Option<String>, Option<String>)> = conn
+        .query_row(
+            "SELECT title, content, highlighted_text FROM notes WHERE id = ?1",
+            [note_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    conn.execute("DELETE FROM notes_search WHERE rowid = ?1", [note_id])
+        .map_err(|e| format!("更新笔记索引失败: {}", e))?;
+    if let Some((title, content, highlighted_text)) = note {
+        let mut tokens = BTreeSet::new();
+        text_tokens(&title, key, &mut tokens);
+        for field in [decrypt_field(content, key), decrypt_field(highlighted_text, key)].iter().flatten() {
+            text_tokens(field, key, &mut tokens);
+        }
+        conn.execute(
+            "INSERT INTO notes_search (rowid, tokens) VALUES (?1, ?2)",
+            rusqlite::params![note_id, tokens.into_iter().collect::<Vec<_>>().join(" ")],
+        )
+        .map_err(|e| format!("更新笔记索引失败: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 清空并重建全部笔记的索引（更换密钥或索引损坏时使用）
+///
+/// # 返回
+/// 索引的笔记数
+pub fn rebuild_index(conn: &Connection, key: &[u8]) -> Result<usize, String> {
+    conn.execute("DELETE FROM notes_search", []).map_err(|e| format!("重建笔记索引失败: {}", e))?;
+    index_missing(conn, key)
+}
+
+/// 为尚未索引的笔记（升级前的笔记或绕过命令写入的笔记）补建索引，已有索引的笔记不受影响
+pub fn ensure_index(conn: &Connection, key: &[u8]) -> Result<(), String> {
+    index_missing(conn, key).map(|_| ())
+}
+
+/// 索引所有不在 `notes_search` 中的笔记，返回索引的笔记数
+fn index_missing(conn: &Connection, key: &[u8]) -> Result<usize, String> {
+    let mut stmt = conn
+        .prepare("SELECT id FROM notes WHERE id NOT IN (SELECT rowid FROM notes_search)")
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, i32>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for id in &ids {
+        reindex_note(conn, *id, key)?;
+    }
+    Ok(ids.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use tempfile::TempDir;
+
+    fn indexed_ids(conn: &Connection, query: &str, key: &[u8]) -> Vec<i32> {
+        let sql = format!("SELECT n.id FROM notes n WHERE {} ORDER BY n.id", SEARCH_CONDITION);
+        let mut stmt = conn.prepare(&sql).unwrap();
+        stmt.query_map([match_query(query, key).unwrap()], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rebuild_and_delete_keep_index_in_sync() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        let key = encryption::generate_key();
+        let encrypted = encryption::encrypt_content("关于量子纠缠的笔记", &key).unwrap();
+        conn.execute("INSERT INTO notes (title, content) VALUES ('物理', ?1)", [&encrypted]).unwrap();
+        conn.execute("INSERT INTO notes (title, content) VALUES ('旧笔记', '未加密的内容')", []).unwrap();
+
+        ensure_index(&conn, &key).unwrap();
+        assert_eq!(indexed_ids(&conn, "量子纠缠", &key), vec![1]);
+        assert_eq!(indexed_ids(&conn, "量子", &key), vec![1]);
+        assert_eq!(indexed_ids(&conn, "未加密", &key), vec![2]);
+
+        // 索引中只有词元，没有明文
+        let stored: String = conn.query_row("SELECT tokens FROM notes_search WHERE rowid = 1", [], |row| row.get(0)).unwrap();
+        assert!(!stored.contains("量子") && !stored.contains("物理"));
+
+        // 已有索引的笔记不重复索引，损坏的索引由重建恢复
+        conn.execute("UPDATE notes_search SET tokens = '' WHERE rowid = 1", []).unwrap();
+        ensure_index(&conn, &key).unwrap();
+        assert!(indexed_ids(&conn, "量子纠缠", &key).is_empty());
+        assert_eq!(rebuild_index(&conn, &key).unwrap(), 2);
+        assert_eq!(indexed_ids(&conn, "量子纠缠", &key), vec![1]);
+
+        // 其他密钥生成的查询词元匹配不到
+        assert!(indexed_ids(&conn, "量子纠缠", &encryption::generate_key()).is_empty());
+
+        conn.execute("DELETE FROM notes WHERE id = 1", []).unwrap();
+        let indexed: i64 = conn.query_row("SELECT COUNT(*) FROM notes_search", [], |row| row.get(0)).unwrap();
+        assert_eq!(indexed, 1);
+    }
+
+    #[test]
+    fn test_note_matches_confirms_candidates() {
+        assert!(note_matches("abc", &[Some("xABCy"), None]));
+        // 片段都出现但不相邻
+        assert!(!note_matches("abcd", &[Some("abc bcd")]));
+        assert!(match_query("", b"key").is_none());
+    }
+}
@@ -1,5 +1,6 @@
 use regex::Regex;
 use super::*;
+use super::language::BookLanguage;
 
 /// 章节信息
 ///
@@ -28,36 +29,78 @@ pub struct ChapterDetector {
 impl ChapterDetector {
     /// 创建新的章节检测器实例
     ///
-    /// 初始化所有章节标题匹配模式
+    /// 初始化所有章节标题匹配模式（中英文均启用）
     pub fn new() -> Self {
-        let patterns = vec![
+        Self::for_language(BookLanguage::Mixed)
+    }
+
+    /// 按书籍语言创建章节检测器
+    ///
+    /// 中文书籍不启用英文章节模式，英文书籍不启用中文章节模式，
+    /// 以减少正文被误判为章节标题；混合语言启用全部模式
+    pub fn for_language(language: BookLanguage) -> Self {
+        // 内置模式都是静态正则，编译不会失败
+        Self::with_custom_patterns(language, &[]).unwrap()
+    }
+
+    /// 按书籍语言创建章节检测器，并在内置模式之前加入用户自定义的章节标题模式
+    ///
+    /// # 参数
+    /// - `language`: 书籍语言
+    /// - `custom_patterns`: 用户提供的正则表达式
+    ///
+    /// # 返回
+    /// 任一自定义模式不是合法正则时返回错误
+    pub fn with_custom_patterns(language: BookLanguage, custom_patterns: &[String]) -> Result<Self, regex::Error> {
+        let mut sources: Vec<&str> = custom_patterns.iter().map(String::as_str).collect();
+
+        if language != BookLanguage::En {
             // 中文章节标题
-            Regex::new(r"^第[零一二三四五六七八九十百千万\d]+章").unwrap(),
-            Regex::new(r"^第\d+章").unwrap(),
-            Regex::new(r"^第[零一二三四五六七八九十百千万\d]+节").unwrap(),
-            Regex::new(r"^第\d+节").unwrap(),
+            sources.extend([
+                r"^第[零一二三四五六七八九十百千万\d]+章",
+                r"^第\d+章",
+                r"^第[零一二三四五六七八九十百千万\d]+节",
+                r"^第\d+节",
+            ]);
+        }
 
+        if language != BookLanguage::Zh {
             // 英文章节标题
-            Regex::new(r"^Chapter\s+\d+").unwrap(),
-            Regex::new(r"^CHAPTER\s+\d+").unwrap(),
-            Regex::new(r"^Section\s+\d+").unwrap(),
-            Regex::new(r"^SECTION\s+\d+").unwrap(),
+            sources.extend([
+                r"^Chapter\s+\d+",
+                r"^CHAPTER\s+\d+",
+                r"^Section\s+\d+",
+                r"^SECTION\s+\d+",
+            ]);
+        }
 
+        sources.extend([
             // Markdown 标题
-            Regex::new(r"^#\s+").unwrap(),
-            Regex::new(r"^##\s+").unwrap(),
+            r"^#\s+",
+            r"^##\s+",
 
             // 数字章节
-            Regex::new(r"^\d+\.\s+").unwrap(),
-            Regex::new(r"^\d+、").unwrap(),
+            r"^\d+\.\s+",
+            r"^\d+、",
+        ]);
 
-            // 其他常见格式
-            Regex::new(r"^卷\s*[零一二三四五六七八九十百千万\d]+").unwrap(),
-            Regex::new(r"^Part\s+\d+").unwrap(),
-            Regex::new(r"^PART\s+\d+").unwrap(),
-        ];
+        // 其他常见格式
+        if language != BookLanguage::En {
+            sources.push(r"^卷\s*[零一二三四五六七八九十百千万\d]+");
+        }
+        if language != BookLanguage::Zh {
+            sources.extend([
+                r"^Part\s+\d+",
+                r"^PART\s+\d+",
+            ]);
+        }
+
+        let patterns = sources
+            .into_iter()
+            .map(Regex::new)
+            .collect::<Result<Vec<_>, _>>()?;
 
-        Self { patterns }
+        Ok(Self { patterns })
     }
 
     /// 第一层：显式章节识别
@@ -237,6 +280,8 @@ impl ChapterDetector {
             render_mode: "irp".to_string(),
             heading_level: None,
             anchor_id: None,
+            content_type: None,
+            source_anchor: None,
         }]
     }
 
@@ -271,6 +316,8 @@ impl ChapterDetector {
                     render_mode: "irp".to_string(),
                     heading_level: None,
                     anchor_id: None,
+                    content_type: None,
+                    source_anchor: None,
                 });
             }
         }
@@ -285,6 +332,8 @@ impl ChapterDetector {
                 render_mode: "irp".to_string(),
                 heading_level: None,
                 anchor_id: None,
+                content_type: None,
+                source_anchor: None,
             });
         }
 
@@ -309,10 +358,28 @@ mod tests {
             runs: vec![TextRun {
                 text: text.to_string(),
                 marks: vec![],
+                attributes: None,
             }],
+            heading_level: None,
+            lang: None,
         }
     }
 
+    #[test]
+    fn test_language_specific_patterns() {
+        let zh = ChapterDetector::for_language(BookLanguage::Zh);
+        assert!(zh.detect_explicit("第一章 开端").is_some());
+        assert!(zh.detect_explicit("Chapter 1").is_none());
+
+        let en = ChapterDetector::for_language(BookLanguage::En);
+        assert!(en.detect_explicit("Chapter 1").is_some());
+        assert!(en.detect_explicit("第一章 开端").is_none());
+
+        let mixed = ChapterDetector::new();
+        assert!(mixed.detect_explicit("Chapter 1").is_some());
+        assert!(mixed.detect_explicit("第一章 开端").is_some());
+    }
+
     #[test]
     fn test_explicit_detection_chinese() {
         let detector = ChapterDetector::new();
@@ -475,4 +542,14 @@ mod tests {
         assert_eq!(info.confidence, "explicit");
         assert_eq!(info.start_index, 0);
     }
+
+    #[test]
+    fn test_custom_patterns() {
+        let detector = ChapterDetector::with_custom_patterns(BookLanguage::Zh, &[r"^卷首".to_string()]).unwrap();
+        assert!(detector.detect_explicit("卷首语").is_some());
+        assert!(detector.detect_explicit("第一章 开始").is_some());
+
+        // 非法的用户模式返回错误而不是 panic
+        assert!(ChapterDetector::with_custom_patterns(BookLanguage::Zh, &["第(".to_string()]).is_err());
+    }
 }
@@ -0,0 +1,132 @@
+use super::epub_landmarks::resolve_href;
+use epub::doc::EpubDoc;
+use regex::Regex;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+// EPUB 封面查找：元数据未声明封面时，依次尝试封面页中的图片、名为 cover 的图片资源，
+// 以及书籍开头几个文档中的第一张图片
+
+/// 未声明封面时，查找图片的开头 spine 文档数（避免把正文中的插图当作封面）
+const MAX_COVER_SCAN_ITEMS: usize = 3;
+
+/// 查找 EPUB 封面图片数据
+///
+/// # 返回
+/// 图片原始字节，找不到可识别的图片时返回 None
+pub fn find_cover<R: Read + Seek>(doc: &mut EpubDoc<R>) -> Option<Vec<u8>> {
+    if let Some((data, _)) = doc.get_cover() {
+        if is_image(&data) {
+            return Some(data);
+        }
+    }
+
+    let spine_paths: Vec<(String, PathBuf)> = doc
+        .spine
+        .iter()
+        .filter_map(|item| Some((item.idref.clone(), doc.resources.get(&item.idref)?.path.clone())))
+        .collect();
+
+    // 封面页（spine 中 id 或文件名含 cover 的文档）引用的图片
+    let cover_page = spine_paths.iter().find(|(id, path)| is_cover_name(id, path));
+    if let Some(data) = cover_page.and_then(|(_, path)| first_image_in_page(doc, path)) {
+        return Some(data);
+    }
+
+    // id 或文件名含 cover 的图片资源
+    let mut named: Vec<PathBuf> = doc
+        .resources
+        .iter()
+        .filter(|(id, resource)| resource.mime.starts_with("image/") && is_cover_name(id, &resource.path))
+        .map(|(_, resource)| resource.path.clone())
+        .collect();
+    named.sort();
+    if let Some(data) = named.iter().find_map(|path| image_at(doc, path)) {
+        return Some(data);
+    }
+
+    spine_paths
+        .iter()
+        .take(MAX_COVER_SCAN_ITEMS)
+        .find_map(|(_, path)| first_image_in_page(doc, path))
+}
+
+fn is_cover_name(id: &str, path: &Path) -> bool {
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+    id.to_lowercase().contains("cover") || file_name.contains("cover")
+}
+
+fn is_image(data: &[u8]) -> bool {
+    image::guess_format(data).is_ok()
+}
+
+fn image_at<R: Read + Seek>(doc: &mut EpubDoc<R>, path: &Path) -> Option<Vec<u8>> {
+    doc.get_resource_by_path(path).filter(|data| is_image(data))
+}
+
+/// 文档中第一个 `<img src>` 或 SVG `<image href>` 指向的图片
+fn first_image_in_page<R: Read + Seek>(doc: &mut EpubDoc<R>, page_path: &Path) -> Option<Vec<u8>> {
+    let html = doc.get_resource_str_by_path(page_path)?;
+    let image_regex =
+        Regex::new(r#"(?is)<(?:img\b[^>]*?\ssrc|(?:svg:)?image\b[^>]*?\s(?:xlink:)?href)\s*=\s*(?:"([^"]*)"|'([^']*)')"#)
+            .unwrap();
+    let page_dir = page_path.parent().unwrap_or(Path::new(""));
+
+    let references: Vec<String> = image_regex
+        .captures_iter(&html)
+        .filter_map(|captures| captures.get(1).or_else(|| captures.get(2)).map(|m| m.as_str().to_string()))
+        .collect();
+    references
+        .iter()
+        .find_map(|reference| image_at(doc, Path::new(&resolve_href(page_dir, reference))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_fixtures::{write_epub, write_epub_with_resources};
+    use image::{ImageFormat, Rgb, RgbImage};
+    use std::io::Cursor;
+    use tempfile::TempDir;
+
+    fn png(color: [u8; 3]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        RgbImage::from_pixel(4, 6, Rgb(color))
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_find_undeclared_cover() {
+        let temp_dir = TempDir::new().unwrap();
+        let front = png([200, 10, 10]);
+        let figure = png([10, 10, 200]);
+
+        // 元数据未声明封面，取开头文档中的第一张图片
+        let path = temp_dir.path().join("book.epub");
+        let resources: &[(&str, &str, &[u8])] =
+            &[("images/figure.png", "image/png", &figure), ("images/front.png", "image/png", &front)];
+        let chapters = [
+            ("扉页", r#"<div><img src="images/front.png" alt=""/></div>"#),
+            ("第一章", r#"<p>正文</p><img src="images/figure.png"/>"#),
+        ];
+        write_epub_with_resources(&path, "<dc:title>书</dc:title>", &chapters, resources);
+        let mut doc = EpubDoc::new(&path).unwrap();
+        assert!(doc.get_cover().is_none());
+        assert_eq!(find_cover(&mut doc), Some(front.clone()));
+
+        // 名为 cover 的图片资源优先于正文开头的图片
+        let resources: &[(&str, &str, &[u8])] =
+            &[("images/figure.png", "image/png", &figure), ("images/Cover.png", "image/png", &front)];
+        let chapters = [("第一章", r#"<img src="images/figure.png"/>"#)];
+        write_epub_with_resources(&path, "<dc:title>书</dc:title>", &chapters, resources);
+        let mut doc = EpubDoc::new(&path).unwrap();
+        assert_eq!(find_cover(&mut doc), Some(front));
+
+        // 没有任何图片
+        write_epub(&path, "<dc:title>书</dc:title>", &[("第一章", "<p>正文</p>")]);
+        let mut doc = EpubDoc::new(&path).unwrap();
+        assert_eq!(find_cover(&mut doc), None);
+    }
+}
@@ -0,0 +1,190 @@
+// EPUB 语义标记：从 OPF guide、EPUB3 导航文档的 landmarks 和章节的 epub:type 中识别前言、正文和后记
+
+use epub::doc::EpubDoc;
+use regex::Regex;
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+use std::path::{Component, Path};
+
+/// 将 EPUB 语义类型映射为内容类型（"frontmatter"、"body"、"backmatter"）
+///
+/// 同时兼容 EPUB2 guide 的写法（`title-page`、`text`）和 EPUB3 结构语义词表（`titlepage`、`bodymatter`）；
+/// `value` 可以是空格分隔的多个类型，按顺序取第一个能识别的
+pub fn semantic_content_type(value: &str) -> Option<&'static str> {
+    value.split_whitespace().find_map(|token| {
+        // EPUB3 允许带前缀的写法，如 "z3998:preface"
+        let token = token.rsplit(':').next().unwrap_or(token).to_ascii_lowercase();
+        match token.as_str() {
+            "cover" | "frontmatter" | "titlepage" | "title-page" | "halftitlepage" | "copyright-page"
+            | "toc" | "loi" | "lot" | "foreword" | "preface" | "dedication" | "epigraph"
+            | "acknowledgments" | "acknowledgements" | "imprint" | "seriespage" | "contributors"
+            | "other-credits" | "abstract" => Some("frontmatter"),
+            "bodymatter" | "text" | "chapter" | "part" | "division" | "volume" => Some("body"),
+            "backmatter" | "afterword" | "appendix" | "glossary" | "bibliography" | "index"
+            | "colophon" | "endnotes" | "rearnotes" | "notes" | "conclusion" => Some("backmatter"),
+            _ => None,
+        }
+    })
+}
+
+/// 收集书中标记的 landmarks
+///
+/// # 返回
+/// 规范化后的资源完整路径 -> 内容类型；EPUB3 导航文档中的 landmarks 优先于 EPUB2 guide
+pub fn collect_landmarks<R: Read + Seek>(doc: &mut EpubDoc<R>) -> HashMap<String, &'static str> {
+    let mut landmarks = HashMap::new();
+
+    let opf_path = doc.root_file.clone();
+    if let Some(opf) = doc.get_resource_str_by_path(&opf_path) {
+        for (semantic, href) in guide_references(&opf) {
+            if let Some(content_type) = semantic_content_type(&semantic) {
+                landmarks.insert(resolve_href(&doc.root_base, &href), content_type);
+            }
+        }
+    }
+
+    if let Some(nav_id) = doc.get_nav_id() {
+        let nav_path = doc.resources.get(&nav_id).map(|resource| resource.path.clone());
+        if let Some(nav_path) = nav_path {
+            if let Some(nav) = doc.get_resource_str_by_path(&nav_path) {
+                let nav_dir = nav_path.parent().unwrap_or(Path::new(""));
+                for (semantic, href) in nav_landmarks(&nav) {
+                    if let Some(content_type) = semantic_content_type(&semantic) {
+                        landmarks.insert(resolve_href(nav_dir, &href), content_type);
+                    }
+                }
+            }
+        }
+    }
+
+    landmarks
+}
+
+/// 从章节 HTML 的 `<body>` 或 `<section>` 的 epub:type 属性识别内容类型
+pub fn html_content_type(html: &str) -> Option<&'static str> {
+    let tag_regex = Regex::new(r"(?is)<(?:body|section)\b[^>]*>").unwrap();
+    let content_type = tag_regex
+        .find_iter(html)
+        .filter_map(|tag| attribute(tag.as_str(), "epub:type"))
+        .find_map(|semantic| semantic_content_type(&semantic));
+    content_type
+}
+
+/// 规范化资源路径：统一使用正斜杠并消去 "." 和 ".."
+pub fn normalize_path(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().replace('\\', "/")),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+/// 解析相对于 `base` 的 href（去掉锚点）
+pub fn resolve_href(base: &Path, href: &str) -> String {
+    let path = href.split('#').next().unwrap_or(href);
+    normalize_path(&base.join(path))
+}
+
+/// OPF `<guide>` 中的 (type, href) 列表
+fn guide_references(opf: &str) -> Vec<(String, String)> {
+    let reference_regex = Regex::new(r"(?is)<(?:\w+:)?reference\b[^>]*>").unwrap();
+    reference_regex
+        .find_iter(opf)
+        .filter_map(|tag| Some((attribute(tag.as_str(), "type")?, attribute(tag.as_str(), "href")?)))
+        .collect()
+}
+
+/// 导航文档 `<nav epub:type="landmarks">` 中的 (epub:type, href) 列表
+fn nav_landmarks(nav: &str) -> Vec<(String, String)> {
+    let nav_regex = Regex::new(r"(?is)<nav\b([^>]*)>(.*?)</nav>").unwrap();
+    let link_regex = Regex::new(r"(?is)<a\b[^>]*>").unwrap();
+
+    nav_regex
+        .captures_iter(nav)
+        .filter(|nav| {
+            attribute(&nav[1], "epub:type")
+                .is_some_and(|types| types.split_whitespace().any(|t| t == "landmarks"))
+        })
+        .flat_map(|nav| {
+            link_regex
+                .find_iter(nav.get(2).map_or("", |m| m.as_str()))
+                .filter_map(|tag| Some((attribute(tag.as_str(), "epub:type")?, attribute(tag.as_str(), "href")?)))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// 读取标签中的属性值
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!(r#"(?i)(?:^|[\s<]){}\s*=\s*(?:"([^"]*)"|'([^']*)')"#, regex::escape(name));
+    let captures = Regex::new(&pattern).ok()?.captures(tag)?;
+    captures
+        .get(1)
+        .or_else(|| captures.get(2))
+        .map(|value| value.as_str().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_fixtures::write_epub_with_guide;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_semantic_content_type() {
+        assert_eq!(semantic_content_type("cover"), Some("frontmatter"));
+        assert_eq!(semantic_content_type("frontmatter copyright-page"), Some("frontmatter"));
+        assert_eq!(semantic_content_type("bodymatter chapter"), Some("body"));
+        assert_eq!(semantic_content_type("text"), Some("body"));
+        assert_eq!(semantic_content_type("z3998:afterword"), Some("backmatter"));
+        assert_eq!(semantic_content_type("pagebreak"), None);
+    }
+
+    #[test]
+    fn test_html_content_type() {
+        let html = r#"<html><body><section epub:type="copyright-page"><p>© 2024</p></section></body></html>"#;
+        assert_eq!(html_content_type(html), Some("frontmatter"));
+        assert_eq!(html_content_type(r#"<body epub:type='appendix'><p>附录</p></body>"#), Some("backmatter"));
+        assert_eq!(html_content_type("<body><p>正文</p></body>"), None);
+    }
+
+    #[test]
+    fn test_nav_landmarks() {
+        let nav = r##"<nav epub:type="toc"><ol><li><a href="ch1.xhtml">第一章</a></li></ol></nav>
+            <nav epub:type="landmarks"><ol>
+              <li><a epub:type="cover" href="cover.xhtml">封面</a></li>
+              <li><a epub:type="bodymatter" href="text/ch1.xhtml#start">正文</a></li>
+            </ol></nav>"##;
+        assert_eq!(
+            nav_landmarks(nav),
+            vec![
+                ("cover".to_string(), "cover.xhtml".to_string()),
+                ("bodymatter".to_string(), "text/ch1.xhtml#start".to_string()),
+            ]
+        );
+        assert_eq!(resolve_href(Path::new("OEBPS/nav"), "../text/ch1.xhtml#start"), "OEBPS/text/ch1.xhtml");
+    }
+
+    #[test]
+    fn test_collect_guide_landmarks() {
+        let temp_dir = TempDir::new().unwrap();
+        let epub_path = temp_dir.path().join("guide.epub");
+        write_epub_with_guide(
+            &epub_path,
+            r#"<dc:title>测试</dc:title><dc:identifier id="bookid">test</dc:identifier>"#,
+            &[("封面", "<p>封面</p>"), ("第一章", "<p>正文</p>")],
+            r#"<reference type="cover" title="封面" href="ch1.xhtml"/><reference type="text" title="正文" href="ch2.xhtml#top"/>"#,
+        );
+
+        let mut doc = EpubDoc::new(&epub_path).unwrap();
+        let landmarks = collect_landmarks(&mut doc);
+        assert_eq!(landmarks.get("OEBPS/ch1.xhtml"), Some(&"frontmatter"));
+        assert_eq!(landmarks.get("OEBPS/ch2.xhtml"), Some(&"body"));
+    }
+}
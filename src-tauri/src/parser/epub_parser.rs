@@ -1,34 +1,52 @@
 use super::*;
 use epub::doc::EpubDoc;
+use regex::{Captures, Regex};
 use scraper::{Html, Selector, ElementRef};
 use crate::irp::{TextRun, TextMark, MarkType};
-use crate::asset_manager::{AssetManager, save_asset_mapping};
-use tauri::AppHandle;
+use crate::asset_manager::{get_local_path, save_asset_mapping, AssetSink};
 use std::collections::HashMap;
+use std::sync::Arc;
+use super::html_sanitizer::sanitize_html;
+use super::epub_landmarks::{collect_landmarks, html_content_type, normalize_path};
+use super::epub_styles::{collect_book_styles, extract_style_assets};
+use crate::reading_unit::FeatureExtractor;
+
+/// 默认的短章节合并阈值（正文字符数），与阅读单元“很短”的长度档（< 300 字）一致
+pub const DEFAULT_MIN_CHAPTER_CHARS: usize = 300;
 
 /// EPUB 解析器
 ///
 /// 支持标准 EPUB 格式的电子书解析
 #[derive(Clone)]
 pub struct EpubParser {
-    app_handle: Option<AppHandle>,
+    /// 资产存储，为空时不提取图片和样式资源（如预览解析）
+    asset_sink: Option<Arc<dyn AssetSink>>,
+    /// 正文少于该字符数、且没有强标题的章节并入上一章（0 表示不合并）
+    pub min_chapter_chars: usize,
 }
 
 impl EpubParser {
     /// 创建新的 EPUB 解析器实例
     pub fn new() -> Self {
-        Self { app_handle: None }
+        Self {
+            asset_sink: None,
+            min_chapter_chars: DEFAULT_MIN_CHAPTER_CHARS,
+        }
     }
 
-    /// 创建带有 AppHandle 的 EPUB 解析器实例（用于图片提取）
-    pub fn with_app_handle(app_handle: AppHandle) -> Self {
+    /// 创建带有资产存储的 EPUB 解析器实例（用于提取图片和样式资源）
+    pub fn with_asset_sink(asset_sink: Arc<dyn AssetSink>) -> Self {
         Self {
-            app_handle: Some(app_handle),
+            asset_sink: Some(asset_sink),
+            ..Self::new()
         }
     }
 
     /// 解析 HTML 内容为 Blocks
     ///
+    /// 导入时 EPUB 只保存净化后的原始 HTML（见 `parse`），不经过这里；
+    /// 行内样式和链接在原始 HTML 中原样保留，这里的 run 合并与标记处理只作用于 IRP 块路径
+    ///
     /// # 参数
     /// - `html`: HTML 字符串
     ///
@@ -52,30 +70,40 @@ impl EpubParser {
                         blocks.push(BlockData {
                             block_type: "paragraph".to_string(),
                             runs,
+                            heading_level: None,
+                            lang: None,
                         });
                     }
                 }
                 // 标题
                 "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
-                    let runs = self.extract_runs_from_element(&element)?;
+                    let mut runs = self.extract_runs_from_element(&element)?;
+                    runs.iter_mut().for_each(Self::decode_run_entities);
                     if !runs.is_empty() && !runs.iter().all(|r| r.text.trim().is_empty()) {
                         blocks.push(BlockData {
                             block_type: "heading".to_string(),
                             runs,
+                            heading_level: tag_name[1..].parse().ok(),
+                            lang: None,
                         });
                     }
                 }
                 // 图片
-                "img" => {
-                    if let Some(src) = element.value().attr("src") {
-                        blocks.push(BlockData {
-                            block_type: "image".to_string(),
-                            runs: vec![TextRun {
-                                text: src.to_string(),
-                                marks: vec![],
-                            }],
-                        });
-                    }
+                "img" => blocks.extend(Self::image_block(&element, None)),
+                // 插图：每张图片一个图片块，图注保存在图片属性中
+                "figure" => {
+                    let img_selector = Selector::parse("img[src]").unwrap();
+                    let caption_selector = Selector::parse("figcaption").unwrap();
+                    let caption = element
+                        .select(&caption_selector)
+                        .next()
+                        .map(|caption| caption.text().collect::<Vec<_>>().join(" "))
+                        .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "));
+                    blocks.extend(
+                        element
+                            .select(&img_selector)
+                            .filter_map(|img| Self::image_block(&img, caption.as_deref())),
+                    );
                 }
                 // 代码块
                 "pre" => {
@@ -84,6 +112,8 @@ impl EpubParser {
                         blocks.push(BlockData {
                             block_type: "code".to_string(),
                             runs,
+                            heading_level: None,
+                            lang: Self::code_language(&element),
                         });
                     }
                 }
@@ -94,6 +124,8 @@ impl EpubParser {
                         blocks.push(BlockData {
                             block_type: "paragraph".to_string(),
                             runs,
+                            heading_level: None,
+                            lang: None,
                         });
                     }
                 }
@@ -104,6 +136,172 @@ impl EpubParser {
         Ok(blocks)
     }
 
+    /// 由 `<img>` 构建图片块，保留 alt 文本和图注
+    fn image_block(img: &ElementRef, caption: Option<&str>) -> Option<BlockData> {
+        let src = img.value().attr("src")?;
+        Some(BlockData {
+            block_type: "image".to_string(),
+            runs: vec![TextRun::image(src, img.value().attr("alt"), caption)],
+            heading_level: None,
+            lang: None,
+        })
+    }
+
+    /// 识别 `<pre>` 代码块的语言
+    ///
+    /// 支持 `<pre>` 或其中 `<code>` 上的 `class="language-xxx"`/`class="lang-xxx"` 和 `data-lang`/`data-language`
+    fn code_language(pre: &ElementRef) -> Option<String> {
+        let code_selector = Selector::parse("code").unwrap();
+        std::iter::once(*pre)
+            .chain(pre.select(&code_selector).take(1))
+            .find_map(|element| {
+                let attrs = element.value();
+                attrs
+                    .attr("data-lang")
+                    .or_else(|| attrs.attr("data-language"))
+                    .map(str::to_string)
+                    .or_else(|| {
+                        attrs.classes().find_map(|class| {
+                            class
+                                .strip_prefix("language-")
+                                .or_else(|| class.strip_prefix("lang-"))
+                                .map(str::to_string)
+                        })
+                    })
+            })
+            .filter(|lang| !lang.is_empty())
+    }
+
+    /// 收集章节中的脚注/尾注内容
+    ///
+    /// 识别带 id 的 `<aside>` 以及 epub:type 为 footnote、endnote、rearnote、note 的元素
+    ///
+    /// # 参数
+    /// - `html`: 章节 HTML
+    /// - `file_path`: 章节文件路径（只使用文件名部分）
+    ///
+    /// # 返回
+    /// "文件名#id" -> 脚注文本
+    fn collect_note_texts(html: &str, file_path: &str) -> HashMap<String, String> {
+        let document = Html::parse_document(html);
+        let selector = Selector::parse("[id]").unwrap();
+        document
+            .select(&selector)
+            .filter(|element| {
+                element.value().name() == "aside"
+                    || element.value().attr("epub:type").is_some_and(|types| {
+                        types
+                            .split_whitespace()
+                            .any(|t| matches!(t, "footnote" | "endnote" | "rearnote" | "note"))
+                    })
+            })
+            .filter_map(|element| {
+                let id = element.value().attr("id")?;
+                Some((Self::note_key(file_path, id), Self::note_text(&element)))
+            })
+            .collect()
+    }
+
+    /// 解析文档中的脚注引用（`<a epub:type="noteref">`）
+    ///
+    /// 同一文档内的锚点直接在文档中查找，指向其他文件的锚点在 `external_notes` 中查找
+    ///
+    /// # 返回
+    /// 引用的 href -> 脚注文本
+    fn resolve_noterefs(document: &Html, external_notes: &HashMap<String, String>) -> HashMap<String, String> {
+        let link_selector = Selector::parse("a[href]").unwrap();
+        let id_selector = Selector::parse("[id]").unwrap();
+        let mut notes = HashMap::new();
+
+        for link in document.select(&link_selector) {
+            let is_noteref = link
+                .value()
+                .attr("epub:type")
+                .is_some_and(|types| types.split_whitespace().any(|t| t == "noteref"));
+            let href = link.value().attr("href").unwrap_or_default();
+            let Some((file, id)) = href.split_once('#') else {
+                continue;
+            };
+            if !is_noteref || notes.contains_key(href) {
+                continue;
+            }
+
+            let text = if file.is_empty() {
+                document
+                    .select(&id_selector)
+                    .find(|element| element.value().attr("id") == Some(id))
+                    .map(|element| Self::note_text(&element))
+            } else {
+                external_notes.get(&Self::note_key(file, id)).cloned()
+            };
+            if let Some(text) = text.filter(|text| !text.is_empty()) {
+                notes.insert(href.to_string(), text);
+            }
+        }
+
+        notes
+    }
+
+    /// 收集全书所有 spine 文档（包括非线性的注释页）中的脚注，用于解析跨文件的脚注引用
+    fn collect_book_notes(doc: &mut EpubDoc<std::io::BufReader<std::fs::File>>) -> HashMap<String, String> {
+        let mut notes = HashMap::new();
+        for spine_index in 0..doc.get_num_chapters() {
+            let Some(path) = spine_path(doc, spine_index) else {
+                continue;
+            };
+            if !doc.set_current_chapter(spine_index) {
+                continue;
+            }
+            if let Some((html, _mime)) = doc.get_current_str() {
+                notes.extend(Self::collect_note_texts(&html, &path));
+            }
+        }
+        notes
+    }
+
+    /// 为章节 HTML 中的脚注引用添加 `data-footnote` 属性（脚注文本），阅读时直接显示脚注内容
+    ///
+    /// `html` 为 `sanitize_html` 的输出（属性值都用双引号）；同一文档内的脚注直接在章节中查找，
+    /// 其他文件的脚注在 `external_notes` 中查找
+    fn annotate_noterefs(html: &str, external_notes: &HashMap<String, String>) -> String {
+        let notes = Self::resolve_noterefs(&Html::parse_document(html), external_notes);
+        if notes.is_empty() {
+            return html.to_string();
+        }
+
+        let tag_regex = Regex::new(r#"(?is)<a\b[^>]*>"#).unwrap();
+        let href_regex = Regex::new(r#"(?is)\shref="([^"]*)""#).unwrap();
+        let noteref_regex = Regex::new(r#"(?is)\sepub:type="[^"]*\bnoteref\b[^"]*""#).unwrap();
+        tag_regex
+            .replace_all(html, |caps: &Captures| {
+                let tag = &caps[0];
+                let note = href_regex
+                    .captures(tag)
+                    .filter(|_| noteref_regex.is_match(tag))
+                    .and_then(|href| notes.get(html_escape::decode_html_entities(&href[1]).as_ref()));
+                match note {
+                    Some(note) => format!(
+                        r#"{} data-footnote="{}">"#,
+                        &tag[..tag.len() - 1],
+                        html_escape::encode_double_quoted_attribute(note)
+                    ),
+                    None => tag.to_string(),
+                }
+            })
+            .into_owned()
+    }
+
+    /// 脚注索引键：文件名#id
+    fn note_key(file_path: &str, id: &str) -> String {
+        let file_name = file_path.rsplit(['/', '\\']).next().unwrap_or(file_path);
+        format!("{}#{}", file_name, id)
+    }
+
+    /// 脚注文本（空白折叠为单个空格）
+    fn note_text(element: &ElementRef) -> String {
+        element.text().flat_map(str::split_whitespace).collect::<Vec<_>>().join(" ")
+    }
+
     /// 从 HTML 内容中提取章节标题
     ///
     /// 优先从 h1-h6 标题标签提取，如果没有则尝试从第一个段落提取
@@ -114,7 +312,7 @@ impl EpubParser {
         for tag in &["h1", "h2", "h3", "h4", "h5", "h6"] {
             if let Ok(selector) = Selector::parse(tag) {
                 if let Some(element) = document.select(&selector).next() {
-                    let text = element.text().collect::<String>().trim().to_string();
+                    let text = decode_title(&element.text().collect::<String>());
                     if !text.is_empty() {
                         return Some(text);
                     }
@@ -126,7 +324,7 @@ impl EpubParser {
         // 很多 EPUB 书籍的章节标题是普通段落文本
         if let Ok(selector) = Selector::parse("p") {
             if let Some(element) = document.select(&selector).next() {
-                let text = element.text().collect::<String>().trim().to_string();
+                let text = decode_title(&element.text().collect::<String>());
                 // 检查是否像章节标题（包含"章"、"节"、"序"等关键字，且长度合理）
                 if !text.is_empty() && text.len() < 100 && self.looks_like_chapter_title(&text) {
                     return Some(text);
@@ -137,6 +335,23 @@ impl EpubParser {
         None
     }
 
+    /// 解码 run 中残留的 HTML 实体
+    ///
+    /// 解码后文本变短，标记范围截断到新的长度
+    fn decode_run_entities(run: &mut TextRun) {
+        let decoded = html_escape::decode_html_entities(&run.text);
+        if decoded == run.text {
+            return;
+        }
+
+        run.text = decoded.into_owned();
+        let len = run.text.len();
+        for mark in &mut run.marks {
+            mark.start = mark.start.min(len);
+            mark.end = mark.end.min(len);
+        }
+    }
+
     /// 判断文本是否看起来像章节标题
     fn looks_like_chapter_title(&self, text: &str) -> bool {
         // 检查是否包含章节相关的关键字
@@ -162,7 +377,7 @@ impl EpubParser {
     /// 递归处理元素及其子元素，提取文本和样式标记
     fn extract_runs_from_element(&self, element: &ElementRef) -> Result<Vec<TextRun>, String> {
         let mut runs = Vec::new();
-        self.extract_runs_recursive(element, &mut runs, &Vec::new())?;
+        self.extract_runs_recursive(element, &mut runs, &[])?;
 
         // 合并相邻的相同样式的 runs
         let merged_runs = self.merge_runs(runs);
@@ -172,75 +387,71 @@ impl EpubParser {
 
     /// 递归提取文本运行
     ///
+    /// 祖先元素的标记（包括链接及其属性）会累积传递给所有后代文本节点，
+    /// 因此嵌套或交叠的样式在每个 run 上都能得到完整的标记组合
+    ///
     /// # 参数
     /// - `element`: 当前元素
     /// - `runs`: 累积的 runs 列表
-    /// - `current_marks`: 当前活动的样式标记类型
+    /// - `active_marks`: 祖先元素上生效的标记（范围在生成 run 时确定）
     fn extract_runs_recursive(
         &self,
         element: &ElementRef,
         runs: &mut Vec<TextRun>,
-        current_marks: &Vec<MarkType>,
+        active_marks: &[TextMark],
     ) -> Result<(), String> {
         let tag_name = element.value().name();
 
         // 确定当前元素添加的新标记
-        let mut new_marks = current_marks.clone();
-        match tag_name {
-            "strong" | "b" => new_marks.push(MarkType::Bold),
-            "em" | "i" => new_marks.push(MarkType::Italic),
-            "u" => new_marks.push(MarkType::Underline),
-            "s" | "strike" | "del" => new_marks.push(MarkType::Strikethrough),
-            "code" => new_marks.push(MarkType::Code),
-            _ => {}
-        }
-
-        // 处理链接
-        let link_href = if tag_name == "a" {
-            element.value().attr("href").map(|s| s.to_string())
-        } else {
-            None
+        let mark_type = match tag_name {
+            "strong" | "b" => Some(MarkType::Bold),
+            "em" | "i" => Some(MarkType::Italic),
+            "u" => Some(MarkType::Underline),
+            "s" | "strike" | "del" => Some(MarkType::Strikethrough),
+            "code" => Some(MarkType::Code),
+            // 处理链接
+            "a" if element.value().attr("href").is_some() => Some(MarkType::Link),
+            _ => None,
         };
 
+        let mut marks = active_marks.to_vec();
+        if let Some(mark_type) = mark_type {
+            let attributes = if mark_type == MarkType::Link {
+                let mut attrs = HashMap::new();
+                attrs.insert(
+                    "href".to_string(),
+                    element.value().attr("href").unwrap_or_default().to_string(),
+                );
+                Some(attrs)
+            } else {
+                None
+            };
+            let mark = TextMark { mark_type, start: 0, end: 0, attributes };
+            // 重复嵌套的相同标记（如 <b><b>）只保留一个
+            if !marks.iter().any(|m| Self::same_mark(m, &mark)) {
+                marks.push(mark);
+            }
+        }
+
         // 遍历子节点
         for child in element.children() {
             if let Some(text) = child.value().as_text() {
-                // 文本节点
+                // 文本节点：每个生效标记覆盖整个文本
                 let text_content = text.to_string();
                 if !text_content.is_empty() {
-                    let mut marks = Vec::new();
                     let text_len = text_content.len();
-
-                    // 添加样式标记
-                    for mark_type in &new_marks {
-                        marks.push(TextMark {
-                            mark_type: mark_type.clone(),
-                            start: 0,
-                            end: text_len,
-                            attributes: None,
-                        });
-                    }
-
-                    // 添加链接标记
-                    if let Some(ref href) = link_href {
-                        let mut attrs = HashMap::new();
-                        attrs.insert("href".to_string(), href.clone());
-                        marks.push(TextMark {
-                            mark_type: MarkType::Link,
-                            start: 0,
-                            end: text_len,
-                            attributes: Some(attrs),
-                        });
-                    }
-
                     runs.push(TextRun {
                         text: text_content,
-                        marks,
+                        marks: marks
+                            .iter()
+                            .map(|m| TextMark { start: 0, end: text_len, ..m.clone() })
+                            .collect(),
+                        attributes: None,
                     });
                 }
             } else if let Some(child_element) = ElementRef::wrap(child) {
                 // 元素节点，递归处理
-                self.extract_runs_recursive(&child_element, runs, &new_marks)?;
+                self.extract_runs_recursive(&child_element, runs, &marks)?;
             }
         }
 
@@ -249,26 +460,40 @@ impl EpubParser {
 
     /// 合并相邻的相同样式的 runs
     fn merge_runs(&self, runs: Vec<TextRun>) -> Vec<TextRun> {
-        if runs.is_empty() {
+        // 快速路径：0 或 1 个 run 无需合并
+        if runs.len() < 2 {
             return runs;
         }
 
-        let mut merged = Vec::new();
-        let mut current = runs[0].clone();
-
-        for run in runs.into_iter().skip(1) {
-            // 检查样式是否相同
-            if self.marks_equal(&current.marks, &run.marks) {
-                // 合并文本
-                current.text.push_str(&run.text);
-                // 更新标记的结束位置
-                for mark in &mut current.marks {
-                    mark.end = current.text.len();
-                }
-            } else {
+        let mut merged = Vec::with_capacity(runs.len());
+        let mut iter = runs.into_iter();
+        let mut current = iter.next().unwrap();
+
+        for run in iter {
+            // 检查样式（类型和属性）是否相同
+            if !self.marks_equal(&current.marks, &run.marks) {
                 // 样式不同，保存当前 run 并开始新的
-                merged.push(current);
-                current = run;
+                merged.push(std::mem::replace(&mut current, run));
+                continue;
+            }
+
+            // 合并文本，后一个 run 的标记按偏移量平移，而不是覆盖为整段
+            let offset = current.text.len();
+            current.text.push_str(&run.text);
+
+            for mark in run.marks {
+                let start = mark.start + offset;
+                let end = mark.end + offset;
+                // 与前一段首尾相接的相同标记直接延长
+                if let Some(existing) = current
+                    .marks
+                    .iter_mut()
+                    .find(|m| m.end == start && Self::same_mark(m, &mark))
+                {
+                    existing.end = end;
+                } else {
+                    current.marks.push(TextMark { start, end, ..mark });
+                }
             }
         }
 
@@ -288,14 +513,27 @@ impl EpubParser {
     }
 
     /// 回退逻辑：当没有 TOC 时，解析所有章节
-    fn parse_all_chapters(&self, doc: &mut EpubDoc<std::io::BufReader<std::fs::File>>) -> Result<ParseResult, String> {
+    fn parse_all_chapters(
+        &self,
+        doc: &mut EpubDoc<std::io::BufReader<std::fs::File>>,
+        book_id: i32,
+        conn: &Connection,
+    ) -> Result<ParseResult, String> {
         let mut chapters = Vec::new();
+        let mut warnings = Vec::new();
         let total_blocks = 0;
 
         // 获取章节数量
         let num_chapters = doc.get_num_chapters();
+        let landmarks = collect_landmarks(doc);
+        let notes = Self::collect_book_notes(doc);
 
         for i in 0..num_chapters {
+            // 非线性条目（注释、答案等）不属于正文阅读顺序
+            if !is_linear(doc, i) {
+                continue;
+            }
+
             // 设置当前章节
             if !doc.set_current_chapter(i) {
                 continue;
@@ -312,103 +550,269 @@ impl EpubParser {
             // 尝试从 HTML 内容中提取标题
             let title = self.extract_title_from_html(&html_content)
                 .unwrap_or_else(|| format!("第 {} 章", chapters.len() + 1));
+            let content_type = self.chapter_content_type(doc, i, &landmarks, &html_content);
+            let source_anchor = spine_path(doc, i);
+            self.extract_images(doc, i, &html_content, book_id, conn, &mut warnings);
 
             // EPUB 只保存原始 HTML，不生成 IRP blocks
             chapters.push(ChapterData {
                 title,
                 blocks: Vec::new(), // 空的 blocks，不需要生成
                 confidence: "explicit".to_string(),
-                raw_html: Some(html_content.clone()),
+                raw_html: Some(Self::annotate_noterefs(&sanitize_html(&html_content), &notes)),
                 render_mode: "html".to_string(),
                 heading_level: None, // EPUB 不使用 heading_level
                 anchor_id: None, // EPUB 不使用 anchor_id
+                content_type,
+                source_anchor,
             });
         }
 
+        let mut chapters = self.coalesce_short_chapters(chapters);
+        link_resolver::resolve_links(&mut chapters);
+
         Ok(ParseResult {
             chapters,
             total_blocks,
             quality: ParseQuality::Native,
+            stylesheet: None,
+            warnings,
         })
     }
 
+    /// 合并被拆得过碎的章节
+    ///
+    /// 部分 EPUB 把一个逻辑章节拆成许多很小的 HTML 文件；正文少于 `min_chapter_chars`、
+    /// 没有 h1 和强章标题的正文章节，把内容追加到上一个正文章节。
+    /// 封面、版权页等前后附文本身就很短，不参与合并
+    fn coalesce_short_chapters(&self, chapters: Vec<ChapterData>) -> Vec<ChapterData> {
+        if self.min_chapter_chars == 0 {
+            return chapters;
+        }
+
+        let extractor = FeatureExtractor::new();
+        let mut merged: Vec<ChapterData> = Vec::with_capacity(chapters.len());
+        for chapter in chapters {
+            let html = chapter.raw_html.as_deref().unwrap_or("");
+            let should_merge = match merged.last() {
+                Some(previous) => {
+                    is_body(&previous.content_type)
+                        && is_body(&chapter.content_type)
+                        && !extractor.is_strong_heading(chapter.title.trim())
+                        && !self.is_h1_title(html)
+                        && text_length(html) < self.min_chapter_chars
+                }
+                None => false,
+            };
+
+            match merged.last_mut() {
+                Some(previous) if should_merge => {
+                    let target = previous.raw_html.get_or_insert_with(String::new);
+                    append_body(target, html);
+                }
+                _ => merged.push(chapter),
+            }
+        }
+        merged
+    }
+
+    /// 识别章节的内容类型
+    ///
+    /// 优先使用 guide/landmarks 中对该文件的标记，其次使用章节 HTML 的 epub:type
+    fn chapter_content_type<R: std::io::Read + std::io::Seek>(
+        &self,
+        doc: &EpubDoc<R>,
+        spine_index: usize,
+        landmarks: &HashMap<String, &'static str>,
+        html: &str,
+    ) -> Option<String> {
+        spine_path(doc, spine_index)
+            .and_then(|path| landmarks.get(&path).copied())
+            .or_else(|| html_content_type(html))
+            .map(str::to_string)
+    }
+
     /// 检查两个标记列表是否相等
     fn marks_equal(&self, marks1: &[TextMark], marks2: &[TextMark]) -> bool {
         if marks1.len() != marks2.len() {
             return false;
         }
 
-        // 简化比较：只比较标记类型
-        let types1: Vec<_> = marks1.iter().map(|m| &m.mark_type).collect();
-        let types2: Vec<_> = marks2.iter().map(|m| &m.mark_type).collect();
+        // 比较标记类型和属性（如链接的 href），与嵌套顺序无关
+        marks1.iter().all(|a| marks2.iter().any(|b| Self::same_mark(a, b)))
+    }
 
-        types1 == types2
+    /// 两个标记的类型和属性是否相同（不比较范围）
+    fn same_mark(a: &TextMark, b: &TextMark) -> bool {
+        a.mark_type == b.mark_type && a.attributes == b.attributes
     }
 
-    /// 提取并保存图片资产
+    /// 检查章节 `<img>` 引用的图片并提取到资产存储
+    ///
+    /// 缺失的图片记为警告；设置了资产存储时保存图片和资产映射（已有映射的跳过），
+    /// 读取章节时 `rewrite_asset_urls` 按映射把 src 替换为本地资产 URL
     ///
     /// # 参数
-    /// - `blocks`: 内容块列表
     /// - `doc`: EPUB 文档
+    /// - `spine_index`: 章节在 spine 中的序号
+    /// - `html`: 章节 HTML
     /// - `book_id`: 书籍 ID
     /// - `conn`: 数据库连接
-    fn extract_images(
+    /// - `warnings`: 解析警告
+    fn extract_images<R: std::io::Read + std::io::Seek>(
         &self,
-        mut blocks: Vec<BlockData>,
-        doc: &mut EpubDoc<std::io::BufReader<std::fs::File>>,
+        doc: &mut EpubDoc<R>,
+        spine_index: usize,
+        html: &str,
         book_id: i32,
         conn: &Connection,
-    ) -> Result<Vec<BlockData>, String> {
-        // 如果没有 AppHandle，无法提取图片
-        let app_handle = match &self.app_handle {
-            Some(handle) => handle,
-            None => return Ok(blocks),
+        warnings: &mut Vec<String>,
+    ) {
+        let Some(chapter_path) = doc
+            .spine
+            .get(spine_index)
+            .and_then(|item| doc.resources.get(&item.idref))
+            .map(|resource| resource.path.clone())
+        else {
+            return;
         };
+        let chapter_dir = chapter_path.parent().unwrap_or(Path::new(""));
 
-        let asset_manager = AssetManager::new(app_handle.clone());
-
-        for block in &mut blocks {
-            if block.block_type == "image" {
-                if let Some(run) = block.runs.first_mut() {
-                    let original_path = &run.text.clone();
-
-                    // 从 EPUB 中提取图片数据
-                    if let Some(image_data) = doc.get_resource_by_path(original_path) {
-                        // 保存图片并获取本地路径
-                        match asset_manager.extract_image(book_id, &image_data, original_path) {
-                            Ok(local_path) => {
-                                // 保存资产映射到数据库
-                                let _ = save_asset_mapping(
-                                    conn,
-                                    book_id,
-                                    original_path,
-                                    &local_path,
-                                    "image",
-                                );
-
-                                // 更新路径为本地路径
-                                run.text = local_path;
-                            }
-                            Err(e) => {
-                                eprintln!("提取图片失败 {}: {}", original_path, e);
-                            }
-                        }
+        for image_path in image_sources(html, chapter_dir) {
+            let exists = doc.resources.values().any(|resource| normalize_path(&resource.path) == image_path);
+            if !exists {
+                push_warning(warnings, format!("找不到图片资源: {}", image_path));
+                continue;
+            }
+
+            // 没有资产存储时（如预览解析）只检查不提取
+            let Some(sink) = &self.asset_sink else {
+                continue;
+            };
+            if matches!(get_local_path(conn, book_id, &image_path), Ok(Some(_))) {
+                continue;
+            }
+            let Some(data) = doc.get_resource_by_path(&image_path) else {
+                continue;
+            };
+            match sink.store(book_id, &data, &image_path) {
+                Ok(local_path) => {
+                    if let Err(e) = save_asset_mapping(conn, book_id, &image_path, &local_path, "image") {
+                        eprintln!("保存资产映射失败 {}: {}", image_path, e);
                     }
                 }
+                Err(e) => eprintln!("提取图片失败 {}: {}", image_path, e),
             }
         }
+    }
 
-        Ok(blocks)
+    /// 提取样式表引用的资源（字体、背景图片）到书库目录并保存资产映射
+    ///
+    /// # 参数
+    /// - `resources`: 资源在 EPUB 内的完整路径
+    fn extract_style_assets<R: std::io::Read + std::io::Seek>(
+        &self,
+        doc: &mut EpubDoc<R>,
+        resources: &[String],
+        book_id: i32,
+        conn: &Connection,
+    ) {
+        // 没有资产存储时无法保存资源，读取样式时保留 EPUB 内的路径
+        if let Some(sink) = &self.asset_sink {
+            extract_style_assets(doc, resources, book_id, conn, sink.as_ref());
+        }
+    }
+}
+
+/// spine 条目是否属于线性阅读顺序（`linear="no"` 的条目只能通过链接访问）
+fn is_linear<R: std::io::Read + std::io::Seek>(doc: &EpubDoc<R>, spine_index: usize) -> bool {
+    doc.spine.get(spine_index).is_none_or(|item| item.linear)
+}
+
+/// spine 条目对应文档在 EPUB 内的规范化路径
+fn spine_path<R: std::io::Read + std::io::Seek>(doc: &EpubDoc<R>, spine_index: usize) -> Option<String> {
+    doc.spine
+        .get(spine_index)
+        .and_then(|item| doc.resources.get(&item.idref))
+        .map(|resource| normalize_path(&resource.path))
+}
+
+/// 章节中 `<img>` 引用的 EPUB 内图片（解析为 EPUB 内的完整路径，外部和内嵌图片除外）
+fn image_sources(html: &str, chapter_dir: &Path) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("img[src]").unwrap();
+    document
+        .select(&selector)
+        .filter_map(|img| img.value().attr("src"))
+        .filter(|src| {
+            let lower = src.to_ascii_lowercase();
+            !(lower.starts_with("data:") || lower.starts_with("http:") || lower.starts_with("https:"))
+        })
+        .map(|src| {
+            let path = src.split(['#', '?']).next().unwrap_or(src);
+            normalize_path(&chapter_dir.join(path))
+        })
+        .collect()
+}
+
+/// 是否为正文章节（未标记内容类型的章节视为正文）
+fn is_body(content_type: &Option<String>) -> bool {
+    matches!(content_type.as_deref(), None | Some("body"))
+}
+
+/// 章节 HTML 的正文字符数（不含空白）
+fn text_length(html: &str) -> usize {
+    crate::export::raw_content_to_text("html", Some(html), 0)
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .count()
+}
+
+/// 把 `html` 的 `<body>` 内容追加到 `target` 的 `</body>` 之前（没有 body 时追加到末尾）
+fn append_body(target: &mut String, html: &str) {
+    let document = Html::parse_document(html);
+    let body = Selector::parse("body")
+        .ok()
+        .and_then(|selector| document.select(&selector).next().map(|body| body.inner_html()))
+        .unwrap_or_else(|| html.to_string());
+
+    match target.to_ascii_lowercase().rfind("</body>") {
+        Some(end) => target.insert_str(end, &body),
+        None => target.push_str(&body),
+    }
+}
+
+/// 记录警告（相同的警告只记录一次）
+fn push_warning(warnings: &mut Vec<String>, warning: String) {
+    eprintln!("警告: {}", warning);
+    if !warnings.contains(&warning) {
+        warnings.push(warning);
     }
 }
 
+/// 解码标题中残留的 HTML 实体并去除首尾空白
+///
+/// 部分 EPUB 的目录和标题经过了二次转义（如 `&amp;amp;`、`&amp;#20013;`），
+/// 解析一次后仍会留下 `&amp;`、`&#20013;` 这样的实体
+fn decode_title(text: &str) -> String {
+    html_escape::decode_html_entities(text).trim().to_string()
+}
+
+
 impl Parser for EpubParser {
-    fn parse(&self, file_path: &Path, _book_id: i32, _conn: &Connection) -> Result<ParseResult, String> {
+    fn parse(&self, file_path: &Path, book_id: i32, conn: &Connection) -> Result<ParseResult, String> {
         // 打开 EPUB 文件
         let mut doc = EpubDoc::new(file_path)
             .map_err(|e| format!("EPUB 解析错误: {}", e))?;
 
+        // 收集书中的样式表（限定作用域），并提取样式引用的资源
+        let styles = collect_book_styles(&mut doc);
+        self.extract_style_assets(&mut doc, &styles.resources, book_id, conn);
+        let stylesheet = (!styles.css.is_empty()).then_some(styles.css);
+
         let mut chapters = Vec::new();
+        let mut warnings = Vec::new();
         let total_blocks = 0;
 
         // 获取 TOC（目录）
@@ -417,7 +821,9 @@ impl Parser for EpubParser {
         // 如果没有 TOC，回退到遍历所有章节的旧逻辑
         if toc.is_empty() {
             eprintln!("警告: EPUB 文件没有 TOC，使用所有章节");
-            return self.parse_all_chapters(&mut doc);
+            let mut result = self.parse_all_chapters(&mut doc, book_id, conn)?;
+            result.stylesheet = stylesheet;
+            return Ok(result);
         }
 
         // 建立 path -> resource_id 的映射
@@ -439,6 +845,10 @@ impl Parser for EpubParser {
             }
         }
 
+        // 书中标记的 landmarks（封面、版权页、正文起点等）
+        let landmarks = collect_landmarks(&mut doc);
+        let notes = Self::collect_book_notes(&mut doc);
+
         // 建立 resource_id -> spine_index 的映射
         let mut id_to_spine_index = std::collections::HashMap::new();
         for (spine_idx, spine_item) in doc.spine.iter().enumerate() {
@@ -479,7 +889,7 @@ impl Parser for EpubParser {
             let resource_id = match resource_id {
                 Some(id) => id,
                 None => {
-                    eprintln!("警告: 找不到 TOC 条目的资源: {}", content_path);
+                    push_warning(&mut warnings, format!("找不到目录条目「{}」对应的文件: {}", decode_title(&nav_point.label), content_path));
                     continue;
                 }
             };
@@ -493,6 +903,12 @@ impl Parser for EpubParser {
                 }
             };
 
+            // 非线性条目（注释、答案等）即使出现在目录中也不作为正文章节
+            if !is_linear(&doc, spine_index) {
+                eprintln!("警告: 跳过非线性 Spine 条目: {} (id: {})", content_path, resource_id);
+                continue;
+            }
+
             // 设置当前章节
             if !doc.set_current_chapter(spine_index) {
                 eprintln!("警告: 无法设置章节: {}", spine_index);
@@ -509,24 +925,34 @@ impl Parser for EpubParser {
             let (html_content, _mime) = content.unwrap();
 
             // 使用 TOC 中的标题
-            let title = nav_point.label.clone();
+            let title = decode_title(&nav_point.label);
+            let content_type = self.chapter_content_type(&doc, spine_index, &landmarks, &html_content);
+            let source_anchor = spine_path(&doc, spine_index);
+            self.extract_images(&mut doc, spine_index, &html_content, book_id, conn, &mut warnings);
 
             // EPUB 只保存原始 HTML，不生成 IRP blocks
             chapters.push(ChapterData {
                 title,
                 blocks: Vec::new(), // 空的 blocks，不需要生成
                 confidence: "explicit".to_string(),
-                raw_html: Some(html_content.clone()),
+                raw_html: Some(Self::annotate_noterefs(&sanitize_html(&html_content), &notes)),
                 render_mode: "html".to_string(),
                 heading_level: None, // EPUB 不使用 heading_level
                 anchor_id: None, // EPUB 不使用 anchor_id
+                content_type,
+                source_anchor,
             });
         }
 
+        let mut chapters = self.coalesce_short_chapters(chapters);
+        link_resolver::resolve_links(&mut chapters);
+
         Ok(ParseResult {
             chapters,
             total_blocks,
             quality: ParseQuality::Native,
+            stylesheet,
+            warnings,
         })
     }
 
@@ -574,6 +1000,26 @@ mod tests {
         assert_eq!(blocks[2].block_type, "paragraph");
     }
 
+    #[test]
+    fn test_heading_blocks_keep_level() {
+        let parser = EpubParser::new();
+        let html = "<body><h1>一</h1><h2>二</h2><h3>三</h3><h4>四</h4><h5>五</h5><h6>六</h6><p>正文</p></body>";
+
+        let blocks = parser.parse_html_to_blocks(html).unwrap();
+        let levels: Vec<Option<u8>> = blocks.iter().map(|block| block.heading_level).collect();
+        assert_eq!(levels, vec![Some(1), Some(2), Some(3), Some(4), Some(5), Some(6), None]);
+    }
+
+    #[test]
+    fn test_pre_block_language() {
+        let parser = EpubParser::new();
+        let html = r#"<body><pre><code class="hljs language-python">print(1)</code></pre><pre data-lang="sql">SELECT 1</pre><pre>plain</pre></body>"#;
+
+        let blocks = parser.parse_html_to_blocks(html).unwrap();
+        let langs: Vec<Option<&str>> = blocks.iter().map(|block| block.lang.as_deref()).collect();
+        assert_eq!(langs, vec![Some("python"), Some("sql"), None]);
+    }
+
     #[test]
     fn test_extract_bold_text() {
         let parser = EpubParser::new();
@@ -621,6 +1067,27 @@ mod tests {
         assert_eq!(blocks[0].runs[0].text, "images/cover.jpg");
     }
 
+    #[test]
+    fn test_image_alt_and_figcaption_preserved() {
+        let parser = EpubParser::new();
+        let html = r#"<body>
+            <img src="images/a.png" alt="diagram" />
+            <figure><img src="images/b.png" alt="" /><figcaption>图 2 <em>流程</em></figcaption></figure>
+        </body>"#;
+
+        let blocks = parser.parse_html_to_blocks(html).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].runs[0].attribute("alt"), Some("diagram"));
+        assert_eq!(blocks[1].runs[0].text, "images/b.png");
+        assert_eq!(blocks[1].runs[0].attribute("alt"), None);
+        assert_eq!(blocks[1].runs[0].attribute("caption"), Some("图 2 流程"));
+
+        // 属性随 runs_json 持久化
+        let json = serde_json::to_string(&blocks[0].runs).unwrap();
+        let runs: Vec<TextRun> = serde_json::from_str(&json).unwrap();
+        assert_eq!(runs[0].attribute("alt"), Some("diagram"));
+    }
+
     #[test]
     fn test_merge_runs() {
         let parser = EpubParser::new();
@@ -629,10 +1096,12 @@ mod tests {
             TextRun {
                 text: "Hello ".to_string(),
                 marks: vec![],
+                attributes: None,
             },
             TextRun {
                 text: "World".to_string(),
                 marks: vec![],
+                attributes: None,
             },
         ];
 
@@ -641,6 +1110,108 @@ mod tests {
         assert_eq!(merged[0].text, "Hello World");
     }
 
+    fn link_run(text: &str, href: &str) -> TextRun {
+        let mut attrs = HashMap::new();
+        attrs.insert("href".to_string(), href.to_string());
+        TextRun {
+            text: text.to_string(),
+            marks: vec![TextMark {
+                mark_type: MarkType::Link,
+                start: 0,
+                end: text.len(),
+                attributes: Some(attrs),
+            }],
+            attributes: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_runs_keeps_different_links() {
+        let parser = EpubParser::new();
+
+        let runs = vec![link_run("第一", "a.html"), link_run("第二", "b.html")];
+        let merged = parser.merge_runs(runs);
+
+        assert_eq!(merged.len(), 2);
+        let href = |run: &TextRun| run.marks[0].attributes.as_ref().unwrap()["href"].clone();
+        assert_eq!(href(&merged[0]), "a.html");
+        assert_eq!(href(&merged[1]), "b.html");
+    }
+
+    #[test]
+    fn test_merge_runs_same_link() {
+        let parser = EpubParser::new();
+
+        let merged = parser.merge_runs(vec![link_run("ab", "a.html"), link_run("cd", "a.html")]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].text, "abcd");
+        assert_eq!(merged[0].marks.len(), 1);
+        assert_eq!((merged[0].marks[0].start, merged[0].marks[0].end), (0, 4));
+    }
+
+    #[test]
+    fn test_merge_runs_offsets_partial_marks() {
+        let parser = EpubParser::new();
+
+        // 标记只覆盖 run 的一部分时，合并后按偏移量保留原范围
+        let partial = |text: &str, start: usize, end: usize| TextRun {
+            text: text.to_string(),
+            marks: vec![TextMark {
+                mark_type: MarkType::Bold,
+                start,
+                end,
+                attributes: None,
+            }],
+            attributes: None,
+        };
+        let merged = parser.merge_runs(vec![partial("abcd", 0, 2), partial("efgh", 1, 3)]);
+
+        assert_eq!(merged.len(), 1);
+        let ranges: Vec<_> = merged[0].marks.iter().map(|m| (m.start, m.end)).collect();
+        assert_eq!(ranges, vec![(0, 2), (5, 7)]);
+    }
+
+    fn runs_from_html(html: &str) -> Vec<TextRun> {
+        let parser = EpubParser::new();
+        let document = Html::parse_fragment(html);
+        let selector = Selector::parse("p").unwrap();
+        let element = document.select(&selector).next().unwrap();
+        parser.extract_runs_from_element(&element).unwrap()
+    }
+
+    fn mark_types(run: &TextRun) -> Vec<MarkType> {
+        run.marks.iter().map(|m| m.mark_type.clone()).collect()
+    }
+
+    #[test]
+    fn test_nested_inline_styles() {
+        let runs = runs_from_html("<p>plain <b>bold <i>both</i></b></p>");
+
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs[0].text, "plain ");
+        assert!(runs[0].marks.is_empty());
+        assert_eq!(runs[1].text, "bold ");
+        assert_eq!(mark_types(&runs[1]), vec![MarkType::Bold]);
+        assert_eq!(runs[2].text, "both");
+        assert_eq!(mark_types(&runs[2]), vec![MarkType::Bold, MarkType::Italic]);
+        for run in &runs {
+            for mark in &run.marks {
+                assert_eq!((mark.start, mark.end), (0, run.text.len()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_link_marks_reach_nested_text() {
+        let runs = runs_from_html("<p><a href=\"n.html#1\">see <em>note</em></a></p>");
+
+        assert_eq!(runs.len(), 2);
+        assert_eq!(mark_types(&runs[0]), vec![MarkType::Link]);
+        assert_eq!(mark_types(&runs[1]), vec![MarkType::Link, MarkType::Italic]);
+        let href = runs[1].marks[0].attributes.as_ref().unwrap()["href"].clone();
+        assert_eq!(href, "n.html#1");
+    }
+
     #[test]
     fn test_extract_title_from_html() {
         let parser = EpubParser::new();
@@ -666,6 +1237,207 @@ mod tests {
         assert_eq!(parser.extract_title_from_html(html_empty), None);
     }
 
+    #[test]
+    fn test_decode_title_entities() {
+        assert_eq!(decode_title("Tom &amp; Jerry"), "Tom & Jerry");
+        assert_eq!(decode_title("&#20013;&#x6587;目录"), "中文目录");
+        assert_eq!(decode_title(" a &lt; b &gt; c "), "a < b > c");
+
+        // 二次转义的标题解析一次后仍残留实体，需要再解码
+        let parser = EpubParser::new();
+        let html = "<html><body><h1>Tom &amp;amp; Jerry &amp;#20013;</h1></body></html>";
+        assert_eq!(parser.extract_title_from_html(html), Some("Tom & Jerry 中".to_string()));
+    }
+
+    #[test]
+    fn test_imported_toc_titles_decode_entities() {
+        use crate::db;
+        use crate::parser::test_fixtures::write_epub;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        write_epub(
+            &path,
+            "<dc:title>实体</dc:title>",
+            &[("Tom &amp;amp; Jerry", "<h1>一</h1><p>正文一</p>"), ("&amp;#20013; &amp;lt;二&amp;gt;", "<h1>二</h1><p>正文二</p>")],
+        );
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+
+        let result = EpubParser::new().parse(&path, 1, &conn).unwrap();
+        let titles: Vec<&str> = result.chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["Tom & Jerry", "中 <二>"]);
+    }
+
+    #[test]
+    fn test_heading_runs_decode_entities() {
+        let parser = EpubParser::new();
+        let blocks = parser
+            .parse_html_to_blocks("<html><body><h2>A &amp;lt; <b>B</b></h2><p>x &amp;lt; y</p></body></html>")
+            .unwrap();
+
+        let heading: String = blocks[0].runs.iter().map(|r| r.text.as_str()).collect();
+        assert_eq!(heading, "A < B");
+        assert!(blocks[0].runs.iter().all(|r| r.marks.iter().all(|m| m.end <= r.text.len())));
+        // 正文只解析一次，保留字面文本
+        assert_eq!(blocks[1].runs[0].text, "x &lt; y");
+    }
+
+    #[test]
+    fn test_noteref_gets_footnote_content() {
+        let html = sanitize_html(
+            r##"<html><body>
+            <p>正文<a epub:type="noteref" href="#fn1">1</a>继续</p>
+            <p>普通<a href="#fn1">链接</a></p>
+            <aside epub:type="footnote" id="fn1"><p>这是   "脚注"内容。</p></aside>
+        </body></html>"##,
+        );
+        let html = EpubParser::annotate_noterefs(&html, &HashMap::new());
+
+        assert!(html.contains(r#" data-footnote="这是 &quot;脚注&quot;内容。">1</a>"#), "{}", html);
+        // 普通链接不附加脚注
+        assert!(html.contains(r##"<a href="#fn1">链接</a>"##));
+    }
+
+    #[test]
+    fn test_noteref_resolves_other_chapter() {
+        use crate::parser::test_fixtures::write_epub_with_non_linear;
+        use tempfile::TempDir;
+
+        let notes_html = r#"<html><body><section epub:type="endnotes">
+            <p epub:type="endnote" id="n2">尾注二</p></section></body></html>"#;
+        let notes = EpubParser::collect_note_texts(notes_html, "OEBPS/Text/notes.xhtml");
+        assert_eq!(notes.get("notes.xhtml#n2"), Some(&"尾注二".to_string()));
+
+        // 尾注在非线性的注释页中，导入后正文章节的 raw_html 中带有尾注内容
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        write_epub_with_non_linear(
+            &path,
+            "<dc:title>书</dc:title>",
+            &[
+                ("第一章", r#"<p>见<a epub:type="noteref" href="ch2.xhtml#n2">2</a></p>"#),
+                ("注释", r#"<p epub:type="endnote" id="n2">尾注二</p>"#),
+            ],
+            &[1],
+        );
+        let conn = crate::db::init_db(temp_dir.path().join("test.db")).unwrap();
+        let mut parser = EpubParser::new();
+        parser.min_chapter_chars = 0;
+        let result = parser.parse(&path, 1, &conn).unwrap();
+        assert_eq!(result.chapters.len(), 1);
+        let html = result.chapters[0].raw_html.as_deref().unwrap();
+        assert!(html.contains(r#" data-footnote="尾注二">2</a>"#), "{}", html);
+    }
+
+    #[test]
+    fn test_images_are_extracted_to_asset_sink() {
+        use crate::asset_manager::{asset_protocol_url, get_book_assets, rewrite_asset_urls, MemoryAssetSink};
+        use crate::db;
+        use crate::parser::test_fixtures::write_epub_with_resources;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("images.epub");
+        let image_data: &[u8] = b"\x89PNG fake image";
+        write_epub_with_resources(
+            &path,
+            "<dc:title>插图</dc:title>",
+            &[(
+                "第一章",
+                "<p><img src=\"images/a.png\" alt=\"插图\"/></p><p><img src=\"images/missing.png\"/></p>",
+            )],
+            &[("images/a.png", "image/png", image_data)],
+        );
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('插图', '/a')", []).unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        let sink = Arc::new(MemoryAssetSink::default());
+        let result = EpubParser::with_asset_sink(sink.clone()).parse(&path, book_id, &conn).unwrap();
+        assert_eq!(result.warnings, vec!["找不到图片资源: OEBPS/images/missing.png".to_string()]);
+
+        // 图片保存到资产存储，并记录 EPUB 内路径到本地路径的映射
+        let assets = get_book_assets(&conn, book_id).unwrap();
+        assert_eq!(assets.len(), 1);
+        let (original_path, local_path) = &assets[0];
+        assert_eq!(original_path, "OEBPS/images/a.png");
+        assert_eq!(sink.assets.lock().unwrap().get(local_path).map(Vec::as_slice), Some(image_data));
+
+        // 读取章节时 src 替换为本地资产 URL
+        let root_dir = temp_dir.path().join("library");
+        let html = rewrite_asset_urls(result.chapters[0].raw_html.as_deref().unwrap(), &assets, &root_dir);
+        assert!(html.contains(&format!("src=\"{}\"", asset_protocol_url(&root_dir.join(local_path)))), "{}", html);
+        assert!(html.contains("src=\"images/missing.png\""));
+
+        // 没有资产存储时只检查不提取
+        conn.execute("DELETE FROM asset_mappings", []).unwrap();
+        let result = EpubParser::new().parse(&path, book_id, &conn).unwrap();
+        assert_eq!(result.warnings.len(), 1);
+        assert!(get_book_assets(&conn, book_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_short_chapters_merge_into_previous() {
+        use crate::db;
+        use crate::parser::test_fixtures::write_epub;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        let long = format!("<p>{}</p>", "长篇正文。".repeat(80));
+        let fragments: Vec<String> = (1..=3).map(|i| format!("<p>片段{}：{}</p>", i, "短".repeat(46))).collect();
+        write_epub(
+            &path,
+            "<dc:title>碎片</dc:title>",
+            &[
+                ("第一章 开端", &long),
+                ("片段一", &fragments[0]),
+                ("片段二", &fragments[1]),
+                ("片段三", &fragments[2]),
+                ("第二章 继续", "<p>第二章很短，但有强标题。</p>"),
+            ],
+        );
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+
+        let result = EpubParser::new().parse(&path, 1, &conn).unwrap();
+        let titles: Vec<&str> = result.chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["第一章 开端", "第二章 继续"]);
+        let html = result.chapters[0].raw_html.as_deref().unwrap();
+        let positions: Vec<usize> = ["长篇正文", "片段1", "片段2", "片段3"]
+            .iter()
+            .map(|text| html.find(text).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(html.find("片段3").unwrap() < html.find("</body>").unwrap());
+
+        // 阈值为 0 时不合并
+        let parser = EpubParser { min_chapter_chars: 0, ..EpubParser::new() };
+        assert_eq!(parser.parse(&path, 1, &conn).unwrap().chapters.len(), 5);
+    }
+
+    #[test]
+    fn test_non_linear_spine_items_are_excluded() {
+        use crate::db;
+        use crate::parser::test_fixtures::write_epub_with_non_linear;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        write_epub_with_non_linear(
+            &path,
+            "<dc:title>线性</dc:title>",
+            &[("第一章", "<p>正文一</p>"), ("注释", "<p>注释内容</p>"), ("第二章", "<p>正文二</p>")],
+            &[1],
+        );
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+
+        let result = EpubParser::new().parse(&path, 1, &conn).unwrap();
+        let titles: Vec<&str> = result.chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["第一章", "第二章"]);
+        assert!(result.chapters[1].raw_html.as_deref().unwrap().contains("正文二"));
+    }
+
     #[test]
     fn test_is_h1_title() {
         let parser = EpubParser::new();
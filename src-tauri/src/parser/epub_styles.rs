@@ -0,0 +1,478 @@
+// EPUB 样式：收集章节引用的 CSS，限定作用域后合并为整本书的样式表
+//
+// 选择器统一加上阅读区容器的前缀，避免书中的样式影响应用界面；
+// `url(...)` 引用解析为 EPUB 内的完整路径，由资产映射在读取时替换为本地资产 URL
+
+use crate::asset_manager::{asset_type_for_path, get_local_path, save_asset_mapping, AssetSink};
+use epub::doc::EpubDoc;
+use regex::Regex;
+use rusqlite::Connection;
+use std::collections::HashSet;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+use super::epub_landmarks::normalize_path;
+
+/// 书籍样式的作用域（前端阅读区容器的类名）
+pub const STYLE_SCOPE: &str = ".reader-content";
+
+/// 收集到的书籍样式
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookStyles {
+    /// 合并并限定作用域后的 CSS
+    pub css: String,
+    /// CSS 中 `url(...)` 引用的 EPUB 内资源完整路径（字体、图片），按首次出现排序
+    pub resources: Vec<String>,
+}
+
+/// 收集书籍样式
+///
+/// 按 spine 顺序读取章节中 `<link rel="stylesheet">` 引用的外部样式表和内联 `<style>`，
+/// 外部样式表按路径去重，`@import` 引用的样式表排在引用者之前
+pub fn collect_book_styles<R: Read + Seek>(doc: &mut EpubDoc<R>) -> BookStyles {
+    let chapter_paths: Vec<PathBuf> = doc
+        .spine
+        .iter()
+        .filter_map(|item| doc.resources.get(&item.idref).map(|resource| resource.path.clone()))
+        .collect();
+
+    let mut collector = StyleCollector::default();
+    for chapter_path in chapter_paths {
+        let Some(html) = doc.get_resource_str_by_path(&chapter_path) else {
+            continue;
+        };
+        let chapter_dir = chapter_path.parent().unwrap_or(Path::new("")).to_path_buf();
+
+        for href in stylesheet_links(&html) {
+            let css_path = resolve_href(&chapter_dir, &href);
+            collector.add_stylesheet(doc, &css_path);
+        }
+        for inline in inline_styles(&html) {
+            collector.add_css(&inline, &chapter_dir);
+        }
+    }
+
+    BookStyles {
+        css: collector.blocks.join("\n"),
+        resources: collector.resources,
+    }
+}
+
+/// 提取样式表引用的资源（字体、背景图片）并保存资产映射
+///
+/// 字体的资产类型为 "font"，其余为 "image"；文件按内容哈希去重，已有映射的资源跳过
+///
+/// # 参数
+/// - `resources`: `BookStyles::resources` 中的 EPUB 内完整路径
+/// - `sink`: 资产存储
+///
+/// # 返回
+/// 新保存的资产数量
+pub fn extract_style_assets<R: Read + Seek>(
+    doc: &mut EpubDoc<R>,
+    resources: &[String],
+    book_id: i32,
+    conn: &Connection,
+    sink: &dyn AssetSink,
+) -> usize {
+    let mut saved = 0;
+    for original_path in resources {
+        if matches!(get_local_path(conn, book_id, original_path), Ok(Some(_))) {
+            continue;
+        }
+        let Some(data) = doc.get_resource_by_path(original_path) else {
+            eprintln!("警告: 找不到样式引用的资源: {}", original_path);
+            continue;
+        };
+
+        let asset_type = asset_type_for_path(original_path);
+        match sink.store(book_id, &data, original_path) {
+            Ok(local_path) => match save_asset_mapping(conn, book_id, original_path, &local_path, asset_type) {
+                Ok(_) => saved += 1,
+                Err(e) => eprintln!("保存资产映射失败 {}: {}", original_path, e),
+            },
+            Err(e) => eprintln!("提取样式资源失败 {}: {}", original_path, e),
+        }
+    }
+    saved
+}
+
+#[derive(Default)]
+struct StyleCollector {
+    blocks: Vec<String>,
+    resources: Vec<String>,
+    seen_sheets: HashSet<String>,
+    seen_inline: HashSet<String>,
+}
+
+impl StyleCollector {
+    /// 加入外部样式表（已加入过的跳过）
+    fn add_stylesheet<R: Read + Seek>(&mut self, doc: &mut EpubDoc<R>, css_path: &str) {
+        if !self.seen_sheets.insert(css_path.to_string()) {
+            return;
+        }
+        let Some(css) = doc.get_resource_str_by_path(css_path) else {
+            eprintln!("警告: 找不到样式表: {}", css_path);
+            return;
+        };
+        let css_dir = Path::new(css_path).parent().unwrap_or(Path::new("")).to_path_buf();
+
+        let css = strip_comments(&css);
+        for import in imports(&css) {
+            let import_path = resolve_href(&css_dir, &import);
+            self.add_stylesheet(doc, &import_path);
+        }
+        self.push(&css, &css_dir);
+    }
+
+    /// 加入章节内联样式（内容相同的只加入一次）
+    fn add_css(&mut self, css: &str, base: &Path) {
+        let css = strip_comments(css);
+        if self.seen_inline.insert(css.clone()) {
+            self.push(&css, base);
+        }
+    }
+
+    fn push(&mut self, css: &str, base: &Path) {
+        let css = resolve_urls(css, base, &mut self.resources);
+        let scoped = scope_css(&css, STYLE_SCOPE);
+        if !scoped.is_empty() {
+            self.blocks.push(scoped);
+        }
+    }
+}
+
+/// 解析相对于 `base` 的 href（去掉锚点和查询参数）
+fn resolve_href(base: &Path, href: &str) -> String {
+    let path = href.split(['#', '?']).next().unwrap_or(href);
+    normalize_path(&base.join(path))
+}
+
+/// 章节 HTML 中 `<link rel="stylesheet">` 的 href 列表
+fn stylesheet_links(html: &str) -> Vec<String> {
+    let link_regex = Regex::new(r"(?is)<link\b[^>]*>").unwrap();
+    link_regex
+        .find_iter(html)
+        .filter(|tag| {
+            attribute(tag.as_str(), "rel").is_some_and(|rel| {
+                rel.split_whitespace().any(|value| value.eq_ignore_ascii_case("stylesheet"))
+            })
+        })
+        .filter_map(|tag| attribute(tag.as_str(), "href"))
+        .collect()
+}
+
+/// 章节 HTML 中 `<style>` 的内容
+fn inline_styles(html: &str) -> Vec<String> {
+    let style_regex = Regex::new(r"(?is)<style\b[^>]*>(.*?)</style>").unwrap();
+    style_regex
+        .captures_iter(html)
+        .map(|caps| caps[1].to_string())
+        .filter(|css| !css.trim().is_empty())
+        .collect()
+}
+
+/// 读取标签中的属性值
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!(r#"(?i)(?:^|[\s<]){}\s*=\s*(?:"([^"]*)"|'([^']*)')"#, regex::escape(name));
+    let captures = Regex::new(&pattern).ok()?.captures(tag)?;
+    captures
+        .get(1)
+        .or_else(|| captures.get(2))
+        .map(|value| value.as_str().to_string())
+}
+
+/// 去掉 CSS 注释
+fn strip_comments(css: &str) -> String {
+    Regex::new(r"(?s)/\*.*?\*/").unwrap().replace_all(css, "").into_owned()
+}
+
+/// `@import` 引用的样式表地址
+fn imports(css: &str) -> Vec<String> {
+    let import_regex = Regex::new(r#"(?i)@import\s+(?:url\(\s*)?["']?([^"')\s;]+)"#).unwrap();
+    import_regex
+        .captures_iter(css)
+        .map(|caps| caps[1].to_string())
+        .filter(|href| !is_external(href))
+        .collect()
+}
+
+/// 是否为外部或内嵌地址（不对应 EPUB 内的资源）
+fn is_external(reference: &str) -> bool {
+    let lower = reference.to_ascii_lowercase();
+    lower.is_empty()
+        || lower.starts_with('#')
+        || lower.starts_with("data:")
+        || lower.starts_with("http:")
+        || lower.starts_with("https:")
+        || lower.starts_with("//")
+}
+
+/// 把 `url(...)` 中的相对路径解析为 EPUB 内的完整路径，并记录引用的资源
+fn resolve_urls(css: &str, base: &Path, resources: &mut Vec<String>) -> String {
+    let url_regex = Regex::new(r#"(?i)url\(\s*(?:"([^"]*)"|'([^']*)'|([^)"'\s]*))\s*\)"#).unwrap();
+    url_regex
+        .replace_all(css, |caps: &regex::Captures| {
+            let reference = caps
+                .get(1)
+                .or_else(|| caps.get(2))
+                .or_else(|| caps.get(3))
+                .map_or("", |m| m.as_str());
+            if is_external(reference) {
+                return caps[0].to_string();
+            }
+
+            let path = resolve_href(base, reference);
+            if !resources.contains(&path) {
+                resources.push(path.clone());
+            }
+            format!("url(\"{}\")", path)
+        })
+        .into_owned()
+}
+
+/// 为 CSS 中的选择器加上作用域前缀
+///
+/// `html`、`body` 和 `:root` 替换为作用域本身；`@media`/`@supports` 内的规则同样处理，
+/// `@font-face`、`@keyframes` 等原样保留，`@import`、`@charset`、`@page` 等丢弃
+pub fn scope_css(css: &str, scope: &str) -> String {
+    let mut output = Vec::new();
+    let mut rest = css;
+
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+
+        let brace = rest.find('{');
+        let semicolon = rest.find(';');
+        // 没有块的语句（@import、@charset 或残缺的规则）
+        if let Some(semicolon) = semicolon.filter(|&s| brace.is_none_or(|b| s < b)) {
+            rest = &rest[semicolon + 1..];
+            continue;
+        }
+        let Some(brace) = brace else { break };
+
+        let prelude = rest[..brace].trim();
+        let Some(body_len) = matching_brace(&rest[brace..]) else { break };
+        let body = &rest[brace + 1..brace + body_len];
+        rest = &rest[brace + body_len + 1..];
+
+        if let Some(at_rule) = prelude.strip_prefix('@') {
+            let name = at_rule
+                .split(|c: char| c.is_whitespace() || c == '(')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            match name.as_str() {
+                "media" | "supports" => {
+                    let inner = scope_css(body, scope);
+                    if !inner.is_empty() {
+                        output.push(format!("{} {{\n{}\n}}", prelude, inner));
+                    }
+                }
+                "font-face" | "keyframes" | "-webkit-keyframes" | "counter-style" | "font-feature-values" => {
+                    output.push(format!("{} {{{}}}", prelude, body));
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        if prelude.is_empty() {
+            continue;
+        }
+        let selectors: Vec<String> = split_selectors(prelude)
+            .into_iter()
+            .map(|selector| scope_selector(selector, scope))
+            .collect();
+        output.push(format!("{} {{{}}}", selectors.join(", "), body));
+    }
+
+    output.join("\n")
+}
+
+/// 以 `{` 开头的文本中与之匹配的 `}` 的位置
+fn matching_brace(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 按顶层逗号拆分选择器列表（忽略括号内的逗号，如 `:is(a, b)`）
+fn split_selectors(prelude: &str) -> Vec<&str> {
+    let mut selectors = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in prelude.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                selectors.push(prelude[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    selectors.push(prelude[start..].trim());
+    selectors.into_iter().filter(|s| !s.is_empty()).collect()
+}
+
+/// 为单个选择器加上作用域前缀
+fn scope_selector(selector: &str, scope: &str) -> String {
+    let mut rest = selector;
+    let mut replaced = false;
+    // 去掉开头的 html、body、:root（书中的根元素对应阅读区容器）
+    loop {
+        let trimmed = rest.trim_start();
+        let root = [":root", "html", "body"].into_iter().find(|root| {
+            trimmed.len() >= root.len()
+                && trimmed[..root.len()].eq_ignore_ascii_case(root)
+                && !trimmed[root.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '-' || c == '_')
+        });
+        match root {
+            Some(root) => {
+                rest = &trimmed[root.len()..];
+                replaced = true;
+            }
+            None => break,
+        }
+    }
+
+    if replaced {
+        let rest = rest.trim_start_matches([' ', '>']).trim_start();
+        if rest.is_empty() {
+            scope.to_string()
+        } else {
+            format!("{} {}", scope, rest)
+        }
+    } else {
+        format!("{} {}", scope, selector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_fixtures::write_epub_with_resources;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scope_css_prefixes_selectors() {
+        let css = "@charset \"utf-8\";\n\
+                   body { margin: 0 }\n\
+                   html body > p.first, h1 { color: red }\n\
+                   @media screen and (min-width: 600px) { p { font-size: 1.2em } }\n\
+                   @font-face { font-family: Book; src: url(\"OEBPS/fonts/a.ttf\") }\n\
+                   @page { margin: 5pt }";
+        let scoped = scope_css(css, ".reader-content");
+
+        assert!(scoped.contains(".reader-content { margin: 0 }"), "{}", scoped);
+        assert!(scoped.contains(".reader-content p.first, .reader-content h1 { color: red }"), "{}", scoped);
+        assert!(scoped.contains("@media screen and (min-width: 600px) {\n.reader-content p { font-size: 1.2em }\n}"), "{}", scoped);
+        assert!(scoped.contains("@font-face { font-family: Book;"), "{}", scoped);
+        assert!(!scoped.contains("@charset"));
+        assert!(!scoped.contains("@page"));
+        // 以 body 开头的其他标签名不是根元素
+        assert_eq!(scope_selector("bodytext", ".r"), ".r bodytext");
+    }
+
+    #[test]
+    fn test_linked_stylesheet_is_captured_and_scoped() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("styled.epub");
+        write_epub_with_resources(
+            &path,
+            r#"<dc:title>有样式的书</dc:title><dc:identifier id="bookid">styled</dc:identifier>"#,
+            &[("第一章", "<p class=\"dropcap\">正文</p>"), ("第二章", "<p>正文</p>")],
+            &[
+                (
+                    "styles/book.css",
+                    "text/css",
+                    "/* 首字下沉 */\n@import \"base.css\";\n.dropcap::first-letter { font-size: 3em; background: url(../images/bg.png) }"
+                        .as_bytes(),
+                ),
+                ("styles/base.css", "text/css", "body { font-family: serif }".as_bytes()),
+                ("images/bg.png", "image/png", "png".as_bytes()),
+            ],
+        );
+
+        let mut doc = EpubDoc::new(&path).unwrap();
+        let styles = collect_book_styles(&mut doc);
+
+        // 两个章节引用同一样式表，只收集一次；@import 的样式表排在前面
+        assert_eq!(styles.css.matches("font-size: 3em").count(), 1);
+        let base = styles.css.find(".reader-content { font-family: serif }").unwrap();
+        let dropcap = styles
+            .css
+            .find(".reader-content .dropcap::first-letter { font-size: 3em; background: url(\"OEBPS/images/bg.png\") }")
+            .unwrap();
+        assert!(base < dropcap, "{}", styles.css);
+        assert!(!styles.css.contains("@import"));
+        assert_eq!(styles.resources, vec!["OEBPS/images/bg.png"]);
+    }
+
+    #[test]
+    fn test_embedded_font_is_extracted_with_mapping() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("fonts.epub");
+        let font_data: &[u8] = b"\x00\x01\x00\x00fake-ttf";
+        write_epub_with_resources(
+            &path,
+            r#"<dc:title>内嵌字体</dc:title><dc:identifier id="bookid">fonts</dc:identifier>"#,
+            &[("第一章", "<p>正文</p>")],
+            &[
+                (
+                    "css/fonts.css",
+                    "text/css",
+                    "@font-face { font-family: 'Book Serif'; src: url('../fonts/BookSerif.ttf') format('truetype') }\n\
+                     p { font-family: 'Book Serif' }"
+                        .as_bytes(),
+                ),
+                ("fonts/BookSerif.ttf", "font/ttf", font_data),
+            ],
+        );
+
+        let conn = crate::db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('内嵌字体', '/a')", []).unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+        let root_dir = temp_dir.path().join("library");
+
+        let mut doc = EpubDoc::new(&path).unwrap();
+        let styles = collect_book_styles(&mut doc);
+        assert_eq!(styles.resources, vec!["OEBPS/fonts/BookSerif.ttf"]);
+        assert_eq!(extract_style_assets(&mut doc, &styles.resources, book_id, &conn, &root_dir), 1);
+        // 已有映射时不重复保存
+        assert_eq!(extract_style_assets(&mut doc, &styles.resources, book_id, &conn, &root_dir), 0);
+
+        let (local_path, asset_type): (String, String) = conn
+            .query_row(
+                "SELECT local_path, asset_type FROM asset_mappings WHERE book_id = ?1 AND original_path = ?2",
+                rusqlite::params![book_id, "OEBPS/fonts/BookSerif.ttf"],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(asset_type, "font");
+        assert!(local_path.ends_with(".ttf"));
+        assert_eq!(std::fs::read(root_dir.join(&local_path)).unwrap(), font_data);
+
+        // 读取样式时 @font-face 的 src 指向本地资产
+        let assets = crate::asset_manager::get_book_assets(&conn, book_id).unwrap();
+        let css = crate::asset_manager::rewrite_css_urls(&styles.css, &assets, &root_dir);
+        let font_url = crate::asset_manager::asset_protocol_url(&root_dir.join(&local_path));
+        assert!(css.contains(&format!("@font-face {{ font-family: 'Book Serif'; src: url(\"{}\") format('truetype') }}", font_url)), "{}", css);
+    }
+}
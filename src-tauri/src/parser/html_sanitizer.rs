@@ -0,0 +1,288 @@
+use scraper::node::Element;
+use scraper::{ElementRef, Html, Node};
+
+/// 整体移除（包括子节点）的危险元素
+const DROPPED_ELEMENTS: &[&str] = &[
+    "script", "noscript", "iframe", "frame", "frameset", "object", "embed", "applet",
+    "form", "input", "button", "textarea", "select", "base", "meta",
+];
+
+/// 无闭合标签的空元素
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "br", "col", "hr", "img", "link", "source", "track", "wbr",
+];
+
+/// 内容不转义的原始文本元素（仅限 HTML 命名空间）
+const RAW_TEXT_ELEMENTS: &[&str] = &["style"];
+
+/// 保留空白原样输出的元素（其后代同样保留）
+const PRESERVE_WHITESPACE_ELEMENTS: &[&str] = &["pre", "code", "textarea", "listing", "xmp"];
+
+/// HTML 命名空间；SVG、MathML 中的 `<style>` 是普通元素，浏览器会把其中的文本重新解析为标记
+const HTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
+
+/// 值为 URL、需要检查协议的属性
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "poster", "action", "formaction", "background", "cite"];
+
+/// 清理 EPUB 章节 HTML，移除脚本等可执行内容
+///
+/// 在保存 raw_html 前调用，前端会把该 HTML 注入 webview：
+/// - 移除 `<script>`、`<iframe>`、表单等元素及其内容
+/// - 移除 `on*` 事件属性和 `srcdoc`
+/// - URL 属性只保留相对路径和 http(s)/mailto 链接，图片额外允许 `data:image/`
+/// - `<link>` 只保留指向书内资源（相对路径）的引用，避免加载远程资源
+///
+/// 排版相关的元素（段落、标题、强调、图片、样式表等）原样保留，注释会被丢弃。
+/// 文本中的换行缩进等连续空白折叠为一个空格，`<pre>`、`<code>` 内的文本保持原样
+pub fn sanitize_html(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+
+    if html.to_ascii_lowercase().contains("<html") {
+        let document = Html::parse_document(html);
+        for child in document.tree.root().children() {
+            write_node(child.value(), ElementRef::wrap(child), &mut output, TextMode::Collapse);
+        }
+    } else {
+        // 片段解析时会包一层 <html>，只输出其内容
+        let fragment = Html::parse_fragment(html);
+        for child in fragment.root_element().children() {
+            write_node(child.value(), ElementRef::wrap(child), &mut output, TextMode::Collapse);
+        }
+    }
+
+    output
+}
+
+/// 文本节点的输出方式
+#[derive(Clone, Copy, PartialEq)]
+enum TextMode {
+    /// 原始文本元素的内容，不转义
+    Raw,
+    /// 转义，空白原样保留
+    Preserve,
+    /// 转义，连续空白折叠为一个空格
+    Collapse,
+}
+
+/// 输出单个节点；`element_ref` 为元素节点对应的 ElementRef（用于遍历子节点）
+fn write_node(node: &Node, element_ref: Option<ElementRef>, output: &mut String, mode: TextMode) {
+    match node {
+        Node::Doctype(doctype) => {
+            output.push_str(&format!("<!DOCTYPE {}>", doctype.name()));
+        }
+        Node::Text(text) => match mode {
+            TextMode::Raw => output.push_str(text),
+            TextMode::Preserve => output.push_str(&escape_text(text)),
+            TextMode::Collapse => output.push_str(&escape_text(&collapse_whitespace(text))),
+        },
+        Node::Element(element) => {
+            let name = element.name();
+            if DROPPED_ELEMENTS.contains(&name) {
+                return;
+            }
+            if name == "link" && !element.attr("href").is_some_and(is_local_url) {
+                return;
+            }
+
+            output.push('<');
+            output.push_str(name);
+            write_attributes(element, output);
+            output.push('>');
+
+            if VOID_ELEMENTS.contains(&name) {
+                return;
+            }
+
+            let child_mode = if RAW_TEXT_ELEMENTS.contains(&name) && &*element.name.ns == HTML_NAMESPACE {
+                TextMode::Raw
+            } else if mode == TextMode::Preserve || PRESERVE_WHITESPACE_ELEMENTS.contains(&name) {
+                TextMode::Preserve
+            } else {
+                TextMode::Collapse
+            };
+            if let Some(element_ref) = element_ref {
+                for child in element_ref.children() {
+                    write_node(child.value(), ElementRef::wrap(child), output, child_mode);
+                }
+            }
+
+            output.push_str("</");
+            output.push_str(name);
+            output.push('>');
+        }
+        // 注释和处理指令直接丢弃
+        _ => {}
+    }
+}
+
+fn write_attributes(element: &Element, output: &mut String) {
+    let tag = element.name();
+    for (name, value) in element.attrs.iter() {
+        let local = (*name.local).to_ascii_lowercase();
+        if local.starts_with("on") || local == "srcdoc" {
+            continue;
+        }
+        if URL_ATTRIBUTES.contains(&local.as_str()) && !is_safe_url(value, tag == "img") {
+            continue;
+        }
+
+        output.push(' ');
+        if let Some(prefix) = &name.prefix {
+            output.push_str(prefix);
+            output.push(':');
+        }
+        output.push_str(&name.local);
+        output.push_str("=\"");
+        output.push_str(&escape_attribute(value));
+        output.push('"');
+    }
+}
+
+/// 判断 URL 是否安全：相对路径、锚点或允许的协议
+fn is_safe_url(url: &str, allow_data_image: bool) -> bool {
+    let normalized = normalize_url(url);
+    match url_scheme(&normalized) {
+        None => true,
+        Some(scheme) => {
+            matches!(scheme, "http" | "https" | "mailto")
+                || (allow_data_image && normalized.starts_with("data:image/"))
+        }
+    }
+}
+
+/// 判断 URL 是否指向书内资源：没有协议，也不是 `//host` 形式的协议相对地址
+fn is_local_url(url: &str) -> bool {
+    // 浏览器把反斜杠当作斜杠处理
+    let normalized = normalize_url(url).replace('\\', "/");
+    url_scheme(&normalized).is_none() && !normalized.starts_with("//")
+}
+
+/// 浏览器解析协议时会忽略空白和控制字符，先去掉再判断
+fn normalize_url(url: &str) -> String {
+    url.chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// URL 的协议部分；冒号出现在路径、查询或锚点之后时不是协议
+fn url_scheme(normalized: &str) -> Option<&str> {
+    let colon = normalized.find(':')?;
+    let scheme = &normalized[..colon];
+    (!scheme.contains(['/', '?', '#'])).then_some(scheme)
+}
+
+/// 把连续的 HTML 空白字符（空格、制表、换行）折叠为一个空格，不间断空格保留
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut prev_space = false;
+    for c in text.chars() {
+        if matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0c') {
+            if !prev_space {
+                collapsed.push(' ');
+            }
+            prev_space = true;
+        } else {
+            collapsed.push(c);
+            prev_space = false;
+        }
+    }
+    collapsed
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn escape_attribute(value: &str) -> String {
+    escape_text(value).replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_removes_scripts_and_handlers() {
+        let html = r#"<p>正文<script>alert(1)</script><b>加粗</b></p><img src="images/a.png" onerror="alert(2)" alt="图"/>"#;
+        let clean = sanitize_html(html);
+
+        assert!(!clean.contains("<script"));
+        assert!(!clean.contains("alert"));
+        assert!(!clean.contains("onerror"));
+        assert!(clean.contains("<b>加粗</b>"));
+        assert!(clean.contains(r#"src="images/a.png""#));
+        assert!(clean.contains(r#"alt="图""#));
+    }
+
+    #[test]
+    fn test_filters_unsafe_urls() {
+        let html = r##"<a href="javascript:alert(1)">坏</a><a href=" JaVa&#x09;script:x">坏</a><a href="https://example.com/a">好</a><a href="ch2.xhtml#n1">内</a><img src="data:image/png;base64,AAAA"/><a href="data:text/html,x">坏</a>"##;
+        let clean = sanitize_html(html);
+
+        assert!(!clean.to_lowercase().contains("javascript"));
+        assert!(!clean.contains("data:text"));
+        assert!(clean.contains(r#"href="https://example.com/a""#));
+        assert!(clean.contains(r#"href="ch2.xhtml#n1""#));
+        assert!(clean.contains(r#"src="data:image/png;base64,AAAA""#));
+    }
+
+    #[test]
+    fn test_adjacent_links_keep_their_targets() {
+        let html = r#"<p><a href="ch1.xhtml">上一章</a><a href="ch3.xhtml">下一章</a></p>"#;
+        let clean = sanitize_html(html);
+
+        assert!(clean.contains(r#"<a href="ch1.xhtml">上一章</a><a href="ch3.xhtml">下一章</a>"#));
+    }
+
+    #[test]
+    fn test_nested_inline_styles_are_kept() {
+        let html = "<p><b>bold <i>both</i></b> plain</p>";
+        let clean = sanitize_html(html);
+
+        assert!(clean.contains("<p><b>bold <i>both</i></b> plain</p>"));
+    }
+
+    #[test]
+    fn test_keeps_document_structure() {
+        let html = r#"<?xml version="1.0"?><!DOCTYPE html><html><head><title>章</title><style>p { color: red; }</style></head><body><h1>标题</h1><!-- 注释 --><p>a &lt; b</p></body></html>"#;
+        let clean = sanitize_html(html);
+
+        assert!(clean.starts_with("<!DOCTYPE html>"));
+        assert!(clean.contains("<style>p { color: red; }</style>"));
+        assert!(clean.contains("<h1>标题</h1>"));
+        assert!(clean.contains("<p>a &lt; b</p>"));
+        assert!(!clean.contains("注释"));
+    }
+
+    #[test]
+    fn test_foreign_style_text_is_escaped() {
+        let html = "<svg><style>&lt;img src=x onerror=alert(1)&gt;</style></svg><style>p > b { color: red; }</style>";
+        let clean = sanitize_html(html);
+
+        assert!(!clean.contains("<img"));
+        assert!(clean.contains("<style>&lt;img src=x onerror=alert(1)&gt;</style></svg>"));
+        // HTML 的 <style> 内容仍原样输出
+        assert!(clean.contains("<style>p > b { color: red; }</style>"));
+    }
+
+    #[test]
+    fn test_only_local_links_are_kept() {
+        let html = r#"<link rel="stylesheet" href="../styles/book.css"/><link rel="stylesheet" href="https://example.com/a.css"/><link rel="stylesheet" href="//example.com/b.css"/><link rel="stylesheet"/><p>正文</p>"#;
+        let clean = sanitize_html(html);
+
+        assert!(clean.contains(r#"href="../styles/book.css""#));
+        assert!(!clean.contains("example.com"));
+        assert_eq!(clean.matches("<link").count(), 1);
+    }
+
+    #[test]
+    fn test_whitespace_collapsed_except_in_pre() {
+        let html = "<p>\n    这是\n    <b>加粗</b>   文字\u{a0}\u{a0}</p>\n<pre>  fn main() {\n      <span>println!();</span>\n  }</pre><p><code>a  b</code></p>";
+        let clean = sanitize_html(html);
+
+        assert!(clean.contains("<p> 这是 <b>加粗</b> 文字\u{a0}\u{a0}</p> <pre>"));
+        assert!(clean.contains("<pre>  fn main() {\n      <span>println!();</span>\n  }</pre>"));
+        assert!(clean.contains("<code>a  b</code>"));
+    }
+}
@@ -0,0 +1,169 @@
+use super::*;
+
+/// 语言检测时采样的最大块数
+const SAMPLE_BLOCKS: usize = 50;
+/// 按章节采样纯文本时的最大字符数
+const SAMPLE_CHARS: usize = 5000;
+/// 中文或拉丁字母占比超过该值时判定为单一语言，否则为混合
+const DOMINANT_RATIO: f64 = 0.8;
+
+/// 书籍语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookLanguage {
+    /// 中文
+    Zh,
+    /// 英文
+    En,
+    /// 中英混合或无法判断
+    Mixed,
+}
+
+impl BookLanguage {
+    /// 存储到 `books.language` 的语言代码
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookLanguage::Zh => "zh",
+            BookLanguage::En => "en",
+            BookLanguage::Mixed => "mixed",
+        }
+    }
+
+    /// 从语言代码解析（兼容 EPUB 元数据中的 "zh-CN"、"en-US" 等写法）
+    pub fn from_code(code: &str) -> Self {
+        let code = code.trim().to_lowercase();
+        if code.starts_with("zh") {
+            BookLanguage::Zh
+        } else if code.starts_with("en") {
+            BookLanguage::En
+        } else {
+            BookLanguage::Mixed
+        }
+    }
+
+    /// AI 系统提示词中的回答语言要求
+    pub fn reply_instruction(&self) -> &'static str {
+        match self {
+            BookLanguage::Zh => "请用中文回答。",
+            BookLanguage::En => "Please answer in English.",
+            BookLanguage::Mixed => "请使用与用户提问相同的语言回答。",
+        }
+    }
+}
+
+/// 判断字符是否为中日韩统一表意文字
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{4E00}'..='\u{9FFF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2A6DF}')
+}
+
+/// 根据文本中中文字符与拉丁字母的比例判断语言
+pub fn detect_text_language(text: &str) -> BookLanguage {
+    let mut cjk = 0usize;
+    let mut latin = 0usize;
+    for c in text.chars() {
+        if is_cjk(c) {
+            cjk += 1;
+        } else if c.is_ascii_alphabetic() {
+            latin += 1;
+        }
+    }
+
+    // 一个汉字约等于一个英文单词，按约 5 个字母折算，避免英文字母数天然偏多
+    let cjk_weight = cjk as f64;
+    let latin_weight = latin as f64 / 5.0;
+    let total = cjk_weight + latin_weight;
+    if total == 0.0 {
+        return BookLanguage::Mixed;
+    }
+
+    if cjk_weight / total >= DOMINANT_RATIO {
+        BookLanguage::Zh
+    } else if latin_weight / total >= DOMINANT_RATIO {
+        BookLanguage::En
+    } else {
+        BookLanguage::Mixed
+    }
+}
+
+/// 采样前若干内容块检测语言
+///
+/// # 参数
+/// - `blocks`: 内容块列表（只使用前 50 个）
+pub fn detect_language<'a>(blocks: impl IntoIterator<Item = &'a BlockData>) -> BookLanguage {
+    let sample: String = blocks
+        .into_iter()
+        .take(SAMPLE_BLOCKS)
+        .flat_map(|block| block.runs.iter().map(|run| run.text.as_str()))
+        .collect();
+    detect_text_language(&sample)
+}
+
+/// 检测解析结果的语言
+///
+/// 按章节顺序采样纯文本，EPUB 等只保存原始 HTML 的章节同样适用
+pub fn detect_result_language(result: &ParseResult) -> BookLanguage {
+    let mut sample = String::new();
+    for chapter_index in 0..result.chapters.len() {
+        if sample.chars().count() >= SAMPLE_CHARS {
+            break;
+        }
+        sample.push_str(&result.plain_text(chapter_index));
+        sample.push('\n');
+    }
+    detect_text_language(&sample)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::irp::TextRun;
+
+    fn paragraphs(texts: &[&str]) -> Vec<BlockData> {
+        texts
+            .iter()
+            .map(|text| BlockData {
+                block_type: "paragraph".to_string(),
+                runs: vec![TextRun { text: text.to_string(), marks: vec![], attributes: None }],
+                heading_level: None,
+                lang: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detect_chinese() {
+        let blocks = paragraphs(&[
+            "第一章 开端",
+            "那是一个晴朗的早晨，他推开窗户，看见远处的山峦笼罩在薄雾之中。",
+            "他想起了十年前离开家乡时 Mom 说过的话。",
+        ]);
+        assert_eq!(detect_language(&blocks), BookLanguage::Zh);
+    }
+
+    #[test]
+    fn test_detect_english() {
+        let blocks = paragraphs(&[
+            "Chapter 1",
+            "It was a bright cold day in April, and the clocks were striking thirteen.",
+            "Winston Smith slipped quickly through the glass doors of Victory Mansions.",
+        ]);
+        assert_eq!(detect_language(&blocks), BookLanguage::En);
+    }
+
+    #[test]
+    fn test_detect_mixed_and_empty() {
+        let blocks = paragraphs(&["机器学习 machine learning 是人工智能 artificial intelligence 的分支"]);
+        assert_eq!(detect_language(&blocks), BookLanguage::Mixed);
+        assert_eq!(detect_language(&paragraphs(&["12345 ..."])), BookLanguage::Mixed);
+    }
+
+    #[test]
+    fn test_from_code() {
+        assert_eq!(BookLanguage::from_code("zh-CN"), BookLanguage::Zh);
+        assert_eq!(BookLanguage::from_code("en-US"), BookLanguage::En);
+        assert_eq!(BookLanguage::from_code("mixed"), BookLanguage::Mixed);
+    }
+}
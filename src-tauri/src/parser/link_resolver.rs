@@ -0,0 +1,253 @@
+use super::epub_landmarks::resolve_href;
+use super::ChapterData;
+use crate::irp::MarkType;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::path::Path;
+
+// 书内链接解析：解析完成后把指向书内位置的链接（EPUB 跨文件链接、Markdown 页内锚点）映射为
+// 章节序号和锚点 ID。链接标记写入 chapter_index、anchor_id 属性，HTML 章节的 <a> 写入
+// data-chapter-index、data-anchor-id 属性；http(s) 等外部链接和找不到目标的链接保持不变
+
+/// 书内链接的目标
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkTarget {
+    /// 目标章节在解析结果中的序号
+    pub chapter_index: usize,
+    /// 章节内的锚点 ID，链接到章节开头时为 None
+    pub anchor_id: Option<String>,
+}
+
+/// 解析所有章节中的书内链接
+///
+/// EPUB 章节按 `source_anchor` 中的文档路径查找目标，Markdown 的页内锚点按标题查找
+pub fn resolve_links(chapters: &mut [ChapterData]) {
+    let resolver = LinkResolver::new(chapters);
+
+    for (index, chapter) in chapters.iter_mut().enumerate() {
+        let source_anchor = chapter.source_anchor.clone();
+        let is_html = chapter.render_mode == "html";
+        let resolve = |href: &str| resolver.resolve(href, index, source_anchor.as_deref(), is_html);
+
+        let link_marks = chapter
+            .blocks
+            .iter_mut()
+            .flat_map(|block| block.runs.iter_mut())
+            .flat_map(|run| run.marks.iter_mut())
+            .filter(|mark| mark.mark_type == MarkType::Link);
+        for mark in link_marks {
+            let Some(attributes) = mark.attributes.as_mut() else {
+                continue;
+            };
+            let Some(target) = attributes.get("href").and_then(|href| resolve(href)) else {
+                continue;
+            };
+            attributes.insert("chapter_index".to_string(), target.chapter_index.to_string());
+            if let Some(anchor_id) = target.anchor_id {
+                attributes.insert("anchor_id".to_string(), anchor_id);
+            }
+        }
+
+        if is_html {
+            if let Some(html) = chapter.raw_html.as_mut() {
+                *html = annotate_html_links(html, resolve);
+            }
+        }
+    }
+}
+
+/// 书内链接目标的索引
+struct LinkResolver {
+    /// 规范化的文档路径 -> 章节序号（同一文档拆成多个章节时取第一个）
+    files: HashMap<String, usize>,
+    /// 标题的锚点形式 -> 章节序号
+    headings: HashMap<String, usize>,
+}
+
+impl LinkResolver {
+    fn new(chapters: &[ChapterData]) -> Self {
+        let mut files = HashMap::new();
+        let mut headings = HashMap::new();
+
+        for (index, chapter) in chapters.iter().enumerate() {
+            if chapter.render_mode == "html" {
+                if let Some(path) = &chapter.source_anchor {
+                    files.entry(path.clone()).or_insert(index);
+                }
+            }
+            headings.entry(heading_slug(&chapter.title)).or_insert(index);
+            for block in chapter.blocks.iter().filter(|block| block.block_type == "heading") {
+                let text: String = block.runs.iter().map(|run| run.text.as_str()).collect();
+                headings.entry(heading_slug(&text)).or_insert(index);
+            }
+        }
+
+        Self { files, headings }
+    }
+
+    /// 解析单个 href
+    ///
+    /// # 参数
+    /// - `chapter_index`: 链接所在章节的序号
+    /// - `source_anchor`: 链接所在章节的源位置（EPUB 为文档路径，相对链接以其所在目录为基准）
+    /// - `is_html`: 链接所在章节是否为 EPUB 的 HTML 章节（页内锚点指向本章节）
+    ///
+    /// # 返回
+    /// 外部链接或找不到目标时返回 None
+    fn resolve(
+        &self,
+        href: &str,
+        chapter_index: usize,
+        source_anchor: Option<&str>,
+        is_html: bool,
+    ) -> Option<LinkTarget> {
+        if is_external(href) {
+            return None;
+        }
+
+        let (path, fragment) = href.split_once('#').unwrap_or((href, ""));
+        let path = path.split('?').next().unwrap_or(path);
+        let anchor_id = Some(fragment.to_string()).filter(|fragment| !fragment.is_empty());
+
+        if path.is_empty() {
+            let fragment = anchor_id.as_deref()?;
+            let chapter_index = if is_html {
+                chapter_index
+            } else {
+                *self.headings.get(&heading_slug(fragment))?
+            };
+            return Some(LinkTarget { chapter_index, anchor_id });
+        }
+
+        let base = source_anchor.and_then(|anchor| Path::new(anchor).parent()).unwrap_or(Path::new(""));
+        let chapter_index = *self.files.get(&resolve_href(base, path))?;
+        Some(LinkTarget { chapter_index, anchor_id })
+    }
+}
+
+/// 是否为外部链接（带协议，如 http:、mailto:，或以 // 开头）
+fn is_external(href: &str) -> bool {
+    href.starts_with("//") || href.split(['/', '#', '?']).next().is_some_and(|head| head.contains(':'))
+}
+
+/// 标题的锚点形式（与常见 Markdown 渲染器一致：小写，空白换成连字符，去掉标点）
+fn heading_slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_whitespace() => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 为 HTML 中指向书内位置的 `<a href>` 添加目标属性
+fn annotate_html_links(html: &str, resolve: impl Fn(&str) -> Option<LinkTarget>) -> String {
+    let tag_regex = Regex::new(r#"(?is)<a\b[^>]*?\shref\s*=\s*"([^"]*)"[^>]*?(/?)>"#).unwrap();
+    tag_regex
+        .replace_all(html, |caps: &Captures| {
+            let tag = &caps[0];
+            let href = html_escape::decode_html_entities(&caps[1]);
+            let Some(target) = resolve(&href) else {
+                return tag.to_string();
+            };
+
+            let mut attributes = format!(r#" data-chapter-index="{}""#, target.chapter_index);
+            if let Some(anchor_id) = &target.anchor_id {
+                attributes.push_str(&format!(
+                    r#" data-anchor-id="{}""#,
+                    html_escape::encode_double_quoted_attribute(anchor_id)
+                ));
+            }
+            let end = tag.len() - 1 - caps[2].len();
+            format!("{}{}{}", &tag[..end], attributes, &tag[end..])
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::irp::{TextMark, TextRun};
+    use crate::parser::test_fixtures::write_epub;
+    use crate::parser::{epub_parser::EpubParser, BlockData, Parser};
+    use tempfile::TempDir;
+
+    fn link_block(href: &str) -> BlockData {
+        BlockData {
+            block_type: "paragraph".to_string(),
+            runs: vec![TextRun {
+                text: "链接".to_string(),
+                marks: vec![TextMark {
+                    mark_type: MarkType::Link,
+                    start: 0,
+                    end: 6,
+                    attributes: Some(HashMap::from([("href".to_string(), href.to_string())])),
+                }],
+                attributes: None,
+            }],
+            heading_level: None,
+            lang: None,
+        }
+    }
+
+    fn link_attributes(chapter: &ChapterData, block: usize) -> HashMap<String, String> {
+        chapter.blocks[block].runs[0].marks[0].attributes.clone().unwrap()
+    }
+
+    #[test]
+    fn test_epub_cross_file_link() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        write_epub(
+            &path,
+            "<dc:title>书</dc:title>",
+            &[
+                ("第一章 开端", r#"<h2 id="sec2">第二节</h2><p>第一章正文。</p>"#),
+                (
+                    "第二章 继续",
+                    r##"<p>见<a href="ch1.xhtml#sec2">第一章第二节</a>、<a href="#top">本章</a>和<a href="https://example.com/ch1.xhtml">网站</a>。</p>"##,
+                ),
+            ],
+        );
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+
+        // 解析 EPUB 时 HTML 章节中的书内链接写入目标属性
+        let result = EpubParser::new().parse(&path, 1, &conn).unwrap();
+        let html = result.chapters[1].raw_html.as_deref().unwrap();
+        assert!(html.contains(r##"href="ch1.xhtml#sec2" data-chapter-index="0" data-anchor-id="sec2""##));
+        assert!(html.contains(r##"href="#top" data-chapter-index="1" data-anchor-id="top""##));
+        assert!(html.contains(r#"<a href="https://example.com/ch1.xhtml">"#));
+
+        // 链接标记写入 chapter_index、anchor_id 属性
+        let mut chapters = result.chapters.clone();
+        chapters[1].blocks = vec![
+            link_block("ch1.xhtml#sec2"),
+            link_block("../OEBPS/ch1.xhtml"),
+            link_block("https://example.com/ch1.xhtml"),
+            link_block("missing.xhtml#a"),
+        ];
+        resolve_links(&mut chapters);
+        let attributes = link_attributes(&chapters[1], 0);
+        assert_eq!(attributes["chapter_index"], "0");
+        assert_eq!(attributes["anchor_id"], "sec2");
+        let attributes = link_attributes(&chapters[1], 1);
+        assert_eq!(attributes["chapter_index"], "0");
+        assert!(!attributes.contains_key("anchor_id"));
+        assert_eq!(link_attributes(&chapters[1], 2).len(), 1);
+        assert_eq!(link_attributes(&chapters[1], 3).len(), 1);
+    }
+
+    #[test]
+    fn test_heading_slug() {
+        assert_eq!(heading_slug("Getting Started!"), "getting-started");
+        assert_eq!(heading_slug(" 第二章 深入 "), "第二章-深入");
+        assert!(is_external("https://example.com"));
+        assert!(is_external("mailto:a@b.c"));
+        assert!(!is_external("chapter3.xhtml#sec2"));
+        assert!(!is_external("#heading"));
+    }
+}
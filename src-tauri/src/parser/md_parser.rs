@@ -1,5 +1,5 @@
 use super::*;
-use pulldown_cmark::{Parser as MdParser, Event, Tag, HeadingLevel};
+use pulldown_cmark::{Parser as MdParser, Event, Tag, HeadingLevel, CodeBlockKind};
 use std::fs;
 use crate::irp::{TextRun, TextMark, MarkType};
 use std::collections::HashMap;
@@ -31,6 +31,11 @@ impl MarkdownParser {
         let mut current_text = String::new();
         let mut current_marks: Vec<MarkType> = Vec::new();
         let mut heading_level = 0;
+        // 正在读取的图片 alt 文本（图片标签内的文本不属于段落）
+        let mut image_alt: Option<String> = None;
+        // 正在读取的链接（起始偏移、href）和当前段落中已结束的链接标记
+        let mut link_start: Option<(usize, String)> = None;
+        let mut link_marks: Vec<TextMark> = Vec::new();
 
         for event in parser {
             match event {
@@ -65,6 +70,8 @@ impl MarkdownParser {
                             render_mode: "irp".to_string(),
                             heading_level: Some(heading_level as u32),
                             anchor_id: None,
+                            content_type: None,
+                            source_anchor: None,
                         });
                     } else {
                         // H3-H6 作为标题块
@@ -74,7 +81,10 @@ impl MarkdownParser {
                                 runs: vec![TextRun {
                                     text: current_text.clone(),
                                     marks: vec![],
+                                    attributes: None,
                                 }],
+                                heading_level: Some(heading_level as u8),
+                                lang: None,
                             });
                         }
                     }
@@ -85,6 +95,7 @@ impl MarkdownParser {
                 Event::Start(Tag::Paragraph) => {
                     current_text.clear();
                     current_marks.clear();
+                    link_marks.clear();
                 }
                 // 段落结束
                 Event::End(Tag::Paragraph) => {
@@ -94,8 +105,15 @@ impl MarkdownParser {
                                 block_type: "paragraph".to_string(),
                                 runs: vec![TextRun {
                                     text: current_text.clone(),
-                                    marks: self.create_marks(&current_text, &current_marks),
+                                    marks: self
+                                        .create_marks(&current_text, &current_marks)
+                                        .into_iter()
+                                        .chain(link_marks.drain(..))
+                                        .collect(),
+                                    attributes: None,
                                 }],
+                                heading_level: None,
+                                lang: None,
                             });
                         }
                     }
@@ -107,14 +125,17 @@ impl MarkdownParser {
                     current_text.clear();
                 }
                 // 代码块结束
-                Event::End(Tag::CodeBlock(_)) => {
+                Event::End(Tag::CodeBlock(kind)) => {
                     if let Some(ref mut chapter) = current_chapter {
                         chapter.blocks.push(BlockData {
                             block_type: "code".to_string(),
                             runs: vec![TextRun {
                                 text: current_text.clone(),
                                 marks: vec![],
+                                attributes: None,
                             }],
+                            heading_level: None,
+                            lang: Self::code_block_language(&kind),
                         });
                     }
                     current_text.clear();
@@ -136,7 +157,10 @@ impl MarkdownParser {
                                 runs: vec![TextRun {
                                     text: current_text.clone(),
                                     marks: vec![],
+                                    attributes: None,
                                 }],
+                                heading_level: None,
+                                lang: None,
                             });
                         }
                     }
@@ -165,29 +189,39 @@ impl MarkdownParser {
                 }
                 // 链接
                 Event::Start(Tag::Link(_, dest_url, _)) => {
-                    // 记录链接，但在文本中处理
-                    let mut attrs = HashMap::new();
-                    attrs.insert("href".to_string(), dest_url.to_string());
-                    // 暂时存储链接信息
+                    link_start = Some((current_text.len(), dest_url.to_string()));
+                }
+                Event::End(Tag::Link(_, _, _)) => {
+                    if let Some((start, href)) = link_start.take() {
+                        link_marks.push(TextMark {
+                            mark_type: MarkType::Link,
+                            start,
+                            end: current_text.len(),
+                            attributes: Some(HashMap::from([("href".to_string(), href)])),
+                        });
+                    }
                 }
-                Event::End(Tag::Link(_, _, _)) => {}
                 // 图片
-                Event::Start(Tag::Image(_, dest_url, _)) => {
+                Event::Start(Tag::Image(_, _, _)) => {
+                    image_alt = Some(String::new());
+                }
+                // 图片的 title 作为图注
+                Event::End(Tag::Image(_, dest_url, title)) => {
+                    let alt = image_alt.take();
                     if let Some(ref mut chapter) = current_chapter {
                         chapter.blocks.push(BlockData {
                             block_type: "image".to_string(),
-                            runs: vec![TextRun {
-                                text: dest_url.to_string(),
-                                marks: vec![],
-                            }],
+                            runs: vec![TextRun::image(&dest_url, alt.as_deref(), Some(&title))],
+                            heading_level: None,
+                            lang: None,
                         });
                     }
                 }
-                Event::End(Tag::Image(_, _, _)) => {}
                 // 文本
-                Event::Text(text) => {
-                    current_text.push_str(&text);
-                }
+                Event::Text(text) => match image_alt.as_mut() {
+                    Some(alt) => alt.push_str(&text),
+                    None => current_text.push_str(&text),
+                },
                 // 行内代码
                 Event::Code(code) => {
                     current_text.push_str(&code);
@@ -221,6 +255,8 @@ impl MarkdownParser {
                 render_mode: "irp".to_string(),
                 heading_level: Some(1),
                 anchor_id: None,
+                content_type: None,
+                source_anchor: None,
             });
         }
 
@@ -242,6 +278,60 @@ impl MarkdownParser {
             })
             .collect()
     }
+
+    /// 代码块的语言
+    ///
+    /// 围栏代码块取信息字符串的第一个词（如 ```rust,ignore 中的 rust），缩进代码块没有语言
+    fn code_block_language(kind: &CodeBlockKind) -> Option<String> {
+        match kind {
+            CodeBlockKind::Fenced(info) => info
+                .split(|c: char| c.is_whitespace() || c == ',' || c == '{')
+                .next()
+                .filter(|lang| !lang.is_empty())
+                .map(str::to_string),
+            CodeBlockKind::Indented => None,
+        }
+    }
+}
+
+/// Markdown 源文件中的标题行
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkdownHeading {
+    /// 行号（从 0 开始）
+    pub line_index: usize,
+    /// 标题级别（1-6）
+    pub level: u32,
+    /// 标题行在源文件中的字节偏移
+    pub offset: usize,
+}
+
+/// 扫描 Markdown 源文件中的 ATX 标题（行首 1～6 个 `#` 后跟空格），围栏代码块中的行除外
+///
+/// 导入时按这些标题划分章节，导出和搜索按同样的标题截取章节内容
+pub fn markdown_headings(content: &str) -> Vec<MarkdownHeading> {
+    let mut headings = Vec::new();
+    // 当前围栏代码块的开始标记（``` 或 ~~~）
+    let mut fence: Option<&str> = None;
+    let mut offset = 0;
+
+    for (line_index, line) in content.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"].into_iter().find(|marker| trimmed.starts_with(*marker));
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some(open), Some(marker)) if open == marker => fence = None,
+            (None, None) => {
+                let level = line.chars().take_while(|c| *c == '#').count();
+                if (1..=6).contains(&level) && line[level..].starts_with(' ') {
+                    headings.push(MarkdownHeading { line_index, level: level as u32, offset });
+                }
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+
+    headings
 }
 
 impl Parser for MarkdownParser {
@@ -251,13 +341,16 @@ impl Parser for MarkdownParser {
             .map_err(|e| format!("读取文件失败: {}", e))?;
 
         // 按 H1/H2 标题分割 Markdown 内容
-        let chapters = self.split_markdown_by_headings(&content)?;
+        let mut chapters = self.split_markdown_by_headings(&content)?;
+        link_resolver::resolve_links(&mut chapters);
         let total_blocks = chapters.iter().map(|c| c.blocks.len()).sum();
 
         Ok(ParseResult {
             chapters,
             total_blocks,
             quality: ParseQuality::Native,
+            stylesheet: None,
+            warnings: Vec::new(),
         })
     }
 
@@ -280,38 +373,22 @@ impl MarkdownParser {
         let lines: Vec<&str> = content.lines().collect();
 
         // 提取所有标题信息用于目录
-        let mut heading_infos = Vec::new();
-
-        for (line_index, line) in lines.iter().enumerate() {
-            // 检查是否是任意级别的标题（H1-H6）
-            let heading_level = if line.starts_with("# ") && !line.starts_with("## ") {
-                Some(1)
-            } else if line.starts_with("## ") && !line.starts_with("### ") {
-                Some(2)
-            } else if line.starts_with("### ") && !line.starts_with("#### ") {
-                Some(3)
-            } else if line.starts_with("#### ") && !line.starts_with("##### ") {
-                Some(4)
-            } else if line.starts_with("##### ") && !line.starts_with("###### ") {
-                Some(5)
-            } else if line.starts_with("###### ") {
-                Some(6)
-            } else {
-                None
-            };
-
-            if let Some(level) = heading_level {
-                let title = line.trim_start_matches('#').trim().to_string();
-                heading_infos.push((title, level, line_index));
-            }
-        }
+        let heading_infos: Vec<(String, u32, usize)> = markdown_headings(content)
+            .into_iter()
+            .map(|heading| {
+                let title = lines[heading.line_index].trim_start_matches('#').trim().to_string();
+                (title, heading.level, heading.line_index)
+            })
+            .collect();
 
         // 如果有标题，为每个标题创建一个"虚拟章节"用于目录
         if !heading_infos.is_empty() {
             let full_content = content.to_string();
 
-            // 为每个标题创建一个章节条目（用于目录）
-            for (title, level, _) in heading_infos {
+            // 为每个标题创建一个章节条目（用于目录），源位置为标题行到下一个标题前的行范围
+            let line_starts: Vec<usize> = heading_infos.iter().map(|(_, _, line_index)| *line_index).collect();
+            for (i, (title, level, line_index)) in heading_infos.into_iter().enumerate() {
+                let end_line = line_starts.get(i + 1).copied().unwrap_or(lines.len());
                 chapters.push(ChapterData {
                     title,
                     blocks: Vec::new(),
@@ -320,6 +397,8 @@ impl MarkdownParser {
                     render_mode: "markdown".to_string(),
                     heading_level: Some(level),
                     anchor_id: None, // 锚点 ID 将在前端生成
+                    content_type: None,
+                    source_anchor: Some(format!("L{}-{}", line_index + 1, end_line)),
                 });
             }
         } else {
@@ -332,6 +411,8 @@ impl MarkdownParser {
                 render_mode: "markdown".to_string(),
                 heading_level: Some(1),
                 anchor_id: None,
+                content_type: None,
+                source_anchor: Some(format!("L1-{}", lines.len())),
             });
         }
 
@@ -356,6 +437,26 @@ mod tests {
         assert_eq!(parser.supported_extensions(), vec!["md", "markdown"]);
     }
 
+    #[test]
+    fn test_same_page_anchor_link_resolves_to_heading() {
+        let parser = MarkdownParser::new();
+        let content = "# 第一章\n\n参见[安装步骤](#安装-步骤)和[官网](https://example.com)。\n\n## 第二章\n\n### 安装 步骤\n\n正文。\n";
+
+        let mut chapters = parser.parse_markdown(content).unwrap();
+        link_resolver::resolve_links(&mut chapters);
+        let marks = &chapters[0].blocks[0].runs[0].marks;
+        assert_eq!(marks.len(), 2);
+        let text = &chapters[0].blocks[0].runs[0].text;
+        assert_eq!(&text[marks[0].start..marks[0].end], "安装步骤");
+
+        let internal = marks[0].attributes.as_ref().unwrap();
+        assert_eq!(internal["chapter_index"], "1");
+        assert_eq!(internal["anchor_id"], "安装-步骤");
+        let external = marks[1].attributes.as_ref().unwrap();
+        assert_eq!(external.get("chapter_index"), None);
+        assert_eq!(external["href"], "https://example.com");
+    }
+
     #[test]
     fn test_parse_simple_markdown() {
         let parser = MarkdownParser::new();
@@ -374,6 +475,48 @@ mod tests {
         assert_eq!(chapters[1].title, "第二章");
     }
 
+    #[test]
+    fn test_heading_levels_h1_to_h6() {
+        let parser = MarkdownParser::new();
+        let content = "# 一级\n\n## 二级\n\n### 三级\n\n#### 四级\n\n##### 五级\n\n###### 六级\n";
+
+        let chapters = parser.parse_markdown(content).unwrap();
+        // H1、H2 作为章节，H3-H6 作为带级别的标题块
+        assert_eq!(chapters[0].heading_level, Some(1));
+        assert_eq!(chapters[1].heading_level, Some(2));
+        let levels: Vec<(&str, Option<u8>)> = chapters[1]
+            .blocks
+            .iter()
+            .map(|block| (block.runs[0].text.as_str(), block.heading_level))
+            .collect();
+        assert_eq!(
+            levels,
+            vec![("三级", Some(3)), ("四级", Some(4)), ("五级", Some(5)), ("六级", Some(6))]
+        );
+    }
+
+    #[test]
+    fn test_code_block_language() {
+        let parser = MarkdownParser::new();
+        let content = "# 代码\n\n```python\nprint('hi')\n```\n\n```rust,ignore\nfn main() {}\n```\n\n    indented();\n";
+
+        let chapters = parser.parse_markdown(content).unwrap();
+        let code: Vec<(&str, Option<&str>)> = chapters[0]
+            .blocks
+            .iter()
+            .filter(|block| block.block_type == "code")
+            .map(|block| (block.runs[0].text.as_str(), block.lang.as_deref()))
+            .collect();
+        assert_eq!(
+            code,
+            vec![
+                ("print('hi')\n", Some("python")),
+                ("fn main() {}\n", Some("rust")),
+                ("indented();\n", None),
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_headings() {
         let parser = MarkdownParser::new();
@@ -465,6 +608,20 @@ fn main() {
         assert!(has_image);
     }
 
+    #[test]
+    fn test_image_alt_text() {
+        let parser = MarkdownParser::new();
+        let content = "# 标题\n\n![流程 diagram](x.png \"图 1\")\n";
+
+        let chapters = parser.parse_markdown(content).unwrap();
+        let image = chapters[0].blocks.iter().find(|b| b.block_type == "image").unwrap();
+        assert_eq!(image.runs[0].text, "x.png");
+        assert_eq!(image.runs[0].attribute("alt"), Some("流程 diagram"));
+        assert_eq!(image.runs[0].attribute("caption"), Some("图 1"));
+        // alt 文本不混入段落
+        assert!(chapters[0].blocks.iter().all(|b| b.block_type == "image"));
+    }
+
     #[test]
     fn test_no_chapters() {
         let parser = MarkdownParser::new();
@@ -476,6 +633,18 @@ fn main() {
         assert_eq!(chapters[0].confidence, "linear");
     }
 
+    #[test]
+    fn test_fenced_heading_is_not_a_chapter() {
+        let parser = MarkdownParser::new();
+        let content = "# 第一章\n```sh\n# 注释\n```\n~~~\n## 也不是\n```\n~~~\n## 1.1 小节\n内容\n";
+
+        let chapters = parser.split_markdown_by_headings(content).unwrap();
+        let titles: Vec<&str> = chapters.iter().map(|chapter| chapter.title.as_str()).collect();
+        assert_eq!(titles, vec!["第一章", "1.1 小节"]);
+        assert_eq!(chapters[0].source_anchor.as_deref(), Some("L1-8"));
+        assert_eq!(markdown_headings(content)[1].offset, content.find("## 1.1").unwrap());
+    }
+
     #[test]
     fn test_multiple_h1_chapters() {
         let parser = MarkdownParser::new();
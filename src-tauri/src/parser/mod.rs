@@ -2,13 +2,23 @@ use rusqlite::Connection;
 use std::path::Path;
 use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::hash::{Hash, Hasher};
 
 // 子模块声明
 pub mod epub_parser;
+pub mod epub_landmarks;
+pub mod epub_cover;
+pub mod epub_styles;
 pub mod txt_parser;
 pub mod md_parser;
 pub mod pdf_parser;
 pub mod chapter_detector;
+pub mod language;
+pub mod link_resolver;
+pub mod html_sanitizer;
+#[cfg(test)]
+pub mod test_fixtures;
 
 /// 解析质量等级
 ///
@@ -26,7 +36,7 @@ pub enum ParseQuality {
 /// 章节数据
 ///
 /// 表示解析后的一个章节，包含标题、内容块和置信度
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ChapterData {
     /// 章节标题
     pub title: String,
@@ -44,17 +54,55 @@ pub struct ChapterData {
     /// 锚点 ID（用于 Markdown 格式的目录跳转）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub anchor_id: Option<String>,
+    /// 内容类型："frontmatter"、"body" 或 "backmatter"（EPUB 从 landmarks/epub:type 识别，其他格式为 None）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// 章节在源文件中的位置：EPUB 为 spine 文档路径，Markdown 为行范围（如 "L3-10"），PDF 为页码范围（如 "p2-5"）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_anchor: Option<String>,
 }
 
 /// 内容块数据
 ///
 /// 表示文档的基本单元（段落、标题、图片等）
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct BlockData {
     /// 块类型：paragraph（段落）、heading（标题）、image（图片）、code（代码）
     pub block_type: String,
     /// 文本运行列表（包含文本和样式标记）
     pub runs: Vec<crate::irp::TextRun>,
+    /// 标题级别（1-6，对应源文档的 h1-h6），非标题块为 None
+    #[serde(default)]
+    pub heading_level: Option<u8>,
+    /// 代码块的语言（如 "python"），非代码块或未标注语言时为 None
+    #[serde(default)]
+    pub lang: Option<String>,
+}
+
+impl ChapterData {
+    /// 计算章节内容的规范指纹（SHA256 十六进制）
+    ///
+    /// 覆盖标题、内容块（含样式标记）、原始 HTML 等全部字段，结构相同的章节得到相同指纹，
+    /// 与进程无关，可以持久化后作为缓存键
+    pub fn content_hash(&self) -> String {
+        let mut hasher = Sha256Hasher(Sha256::new());
+        self.hash(&mut hasher);
+        format!("{:x}", hasher.0.finalize())
+    }
+}
+
+/// 把 `Hash` 写入的字节喂给 SHA256，得到比 `DefaultHasher` 更稳定的指纹
+struct Sha256Hasher(Sha256);
+
+impl Hasher for Sha256Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().expect("SHA256 摘要至少 8 字节"))
+    }
 }
 
 /// 解析结果
@@ -68,6 +116,93 @@ pub struct ParseResult {
     pub total_blocks: usize,
     /// 解析质量等级
     pub quality: ParseQuality,
+    /// 整本书的样式表（EPUB 中收集并限定作用域的 CSS），其他格式为 None
+    pub stylesheet: Option<String>,
+    /// 解析中发现但不影响导入的问题（解码错误、缺失的资源等），保存后展示给用户
+    pub warnings: Vec<String>,
+}
+
+impl ParseResult {
+    /// 提取指定章节的纯文本（用于 AI 输入）
+    ///
+    /// html/markdown 章节从原始内容中提取，IRP 章节拼接内容块文本（跳过图片）；
+    /// 序号越界时返回空字符串
+    pub fn plain_text(&self, chapter_index: usize) -> String {
+        let Some(chapter) = self.chapters.get(chapter_index) else {
+            return String::new();
+        };
+
+        if let Some(text) = crate::export::raw_content_to_text(
+            &chapter.render_mode,
+            chapter.raw_html.as_deref(),
+            chapter_index,
+        ) {
+            return text;
+        }
+
+        chapter
+            .blocks
+            .iter()
+            .filter(|block| block.block_type != "image")
+            .map(|block| crate::irp::extract_plain_text_from_runs(&block.runs).trim().to_string())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// 章节结构预览
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ChapterPreview {
+    pub title: String,
+    pub confidence: String,
+    /// 内容块数（html/markdown 章节没有内容块，按段落计）
+    pub block_count: usize,
+    /// 章节内容指纹，可与再次解析的预览对比找出变化的章节
+    pub content_hash: String,
+}
+
+/// 解析预览：导入前查看识别出的章节结构
+#[derive(Debug, Clone, Serialize)]
+pub struct ParsePreview {
+    pub chapters: Vec<ChapterPreview>,
+    pub quality: ParseQuality,
+    pub warnings: Vec<String>,
+}
+
+impl From<ParseResult> for ParsePreview {
+    fn from(result: ParseResult) -> Self {
+        let chapters = result
+            .chapters
+            .iter()
+            .enumerate()
+            .map(|(index, chapter)| ChapterPreview {
+                title: chapter.title.clone(),
+                confidence: chapter.confidence.clone(),
+                block_count: if chapter.blocks.is_empty() {
+                    result.plain_text(index).split("\n\n").filter(|p| !p.trim().is_empty()).count()
+                } else {
+                    chapter.blocks.len()
+                },
+                content_hash: chapter.content_hash(),
+            })
+            .collect();
+
+        Self {
+            chapters,
+            quality: result.quality,
+            warnings: result.warnings,
+        }
+    }
+}
+
+/// 解析文件但不写入数据库，返回章节结构预览
+///
+/// 解析器使用不带 AppHandle 的实例（不提取资源），数据库连接为临时的内存数据库
+pub fn preview_parse(file_path: &Path) -> Result<ParsePreview, String> {
+    let conn = Connection::open_in_memory().map_err(|e| e.to_string())?;
+    let result = ParserRouter::new().route(file_path)?.parse(file_path, 0, &conn)?;
+    Ok(result.into())
 }
 
 /// Parser trait
@@ -103,12 +238,21 @@ pub struct ParserRouter {
 impl ParserRouter {
     /// 创建新的路由器实例
     ///
-    /// 注册所有可用的解析器
+    /// 注册所有可用的解析器（不提取资源）
     pub fn new() -> Self {
+        Self::with_epub_parser(epub_parser::EpubParser::new())
+    }
+
+    /// 创建提取资源的路由器实例，EPUB 中的图片和样式资源保存到 `asset_sink`
+    pub fn with_asset_sink(asset_sink: std::sync::Arc<dyn crate::asset_manager::AssetSink>) -> Self {
+        Self::with_epub_parser(epub_parser::EpubParser::with_asset_sink(asset_sink))
+    }
+
+    fn with_epub_parser(epub: epub_parser::EpubParser) -> Self {
         let mut parsers: HashMap<String, Box<dyn Parser>> = HashMap::new();
 
         // 注册 EPUB 解析器
-        let epub = Box::new(epub_parser::EpubParser::new());
+        let epub = Box::new(epub);
         for ext in epub.supported_extensions() {
             parsers.insert(ext.to_string(), epub.clone());
         }
@@ -134,6 +278,18 @@ impl ParserRouter {
         Self { parsers }
     }
 
+    /// 使用指定编码解析 TXT 文件（`None` 时自动检测）
+    pub fn with_encoding_hint(mut self, encoding: Option<&'static encoding_rs::Encoding>) -> Self {
+        let txt = Box::new(match encoding {
+            Some(encoding) => txt_parser::TxtParser::with_encoding_hint(encoding),
+            None => txt_parser::TxtParser::new(),
+        });
+        for ext in txt.supported_extensions() {
+            self.parsers.insert(ext.to_string(), txt.clone());
+        }
+        self
+    }
+
     /// 根据文件路径路由到对应的解析器
     ///
     /// # 参数
@@ -188,6 +344,8 @@ mod tests {
                 chapters: vec![],
                 total_blocks: 0,
                 quality: self.quality.clone(),
+                stylesheet: None,
+                warnings: Vec::new(),
             })
         }
 
@@ -200,6 +358,55 @@ mod tests {
         }
     }
 
+    fn text_block(block_type: &str, text: &str) -> BlockData {
+        BlockData {
+            block_type: block_type.to_string(),
+            runs: vec![crate::irp::TextRun { text: text.to_string(), marks: vec![], attributes: None }],
+            heading_level: None,
+            lang: None,
+        }
+    }
+
+    fn chapter(render_mode: &str, raw_html: Option<&str>, blocks: Vec<BlockData>) -> ChapterData {
+        ChapterData {
+            title: "章节".to_string(),
+            blocks,
+            confidence: "explicit".to_string(),
+            raw_html: raw_html.map(|s| s.to_string()),
+            render_mode: render_mode.to_string(),
+            heading_level: None,
+            anchor_id: None,
+            content_type: None,
+            source_anchor: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_result_plain_text() {
+        let result = ParseResult {
+            chapters: vec![
+                chapter(
+                    "irp",
+                    None,
+                    vec![
+                        text_block("heading", "第一章"),
+                        text_block("image", "images/a.png"),
+                        text_block("paragraph", " 正文。 "),
+                    ],
+                ),
+                chapter("html", Some("<body><p>HTML <i>正文</i></p><p>第二段</p></body>"), vec![]),
+            ],
+            total_blocks: 3,
+            quality: ParseQuality::Light,
+            stylesheet: None,
+            warnings: Vec::new(),
+        };
+
+        assert_eq!(result.plain_text(0), "第一章\n\n正文。");
+        assert_eq!(result.plain_text(1), "HTML 正文\n\n第二段");
+        assert_eq!(result.plain_text(5), "");
+    }
+
     #[test]
     fn test_parse_quality_equality() {
         assert_eq!(ParseQuality::Native, ParseQuality::Native);
@@ -290,6 +497,8 @@ mod tests {
             render_mode: "irp".to_string(),
             heading_level: None,
             anchor_id: None,
+            content_type: None,
+            source_anchor: None,
         };
 
         assert_eq!(chapter.title, "第一章");
@@ -303,22 +512,93 @@ mod tests {
         let block = BlockData {
             block_type: "paragraph".to_string(),
             runs: vec![],
+            heading_level: None,
+            lang: None,
         };
 
         assert_eq!(block.block_type, "paragraph");
         assert_eq!(block.runs.len(), 0);
     }
 
+    #[test]
+    fn test_identical_chapters_hash_equal() {
+        use crate::irp::{MarkType, TextMark, TextRun};
+        use std::collections::HashSet;
+
+        // 属性 HashMap 的插入顺序不影响指纹
+        let chapter = |attributes: Vec<(&str, &str)>| ChapterData {
+            title: "第一章".to_string(),
+            blocks: vec![BlockData {
+                block_type: "paragraph".to_string(),
+                runs: vec![TextRun {
+                    text: "见链接".to_string(),
+                    marks: vec![TextMark {
+                        mark_type: MarkType::Link,
+                        start: 1,
+                        end: 3,
+                        attributes: Some(
+                            attributes.into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+                        ),
+                    }],
+                    attributes: None,
+                }],
+                heading_level: None,
+                lang: None,
+            }],
+            confidence: "explicit".to_string(),
+            raw_html: None,
+            render_mode: "irp".to_string(),
+            heading_level: None,
+            anchor_id: None,
+            content_type: None,
+            source_anchor: None,
+        };
+
+        let a = chapter(vec![("href", "#n1"), ("title", "注释")]);
+        let b = chapter(vec![("title", "注释"), ("href", "#n1")]);
+        assert_eq!(a, b);
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert_eq!(HashSet::from([a.clone(), b]).len(), 1);
+
+        let mut changed = a.clone();
+        changed.blocks[0].runs[0].marks[0].mark_type = MarkType::Bold;
+        assert_ne!(a.content_hash(), changed.content_hash());
+    }
+
     #[test]
     fn test_parse_result_creation() {
         let result = ParseResult {
             chapters: vec![],
             total_blocks: 0,
             quality: ParseQuality::Native,
+            stylesheet: None,
+            warnings: Vec::new(),
         };
 
         assert_eq!(result.chapters.len(), 0);
         assert_eq!(result.total_blocks, 0);
         assert_eq!(result.quality, ParseQuality::Native);
     }
+
+    #[test]
+    fn test_preview_txt_chapters() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("预览.txt");
+        std::fs::write(
+            &file_path,
+            "第一章 开始\n\n第一段。\n\n第二段。\n\n第二章 继续\n\n第三段。\n",
+        )
+        .unwrap();
+
+        let preview = preview_parse(&file_path).unwrap();
+        let titles: Vec<&str> = preview.chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["第一章 开始", "第二章 继续"]);
+        assert_eq!(preview.chapters[0].confidence, "explicit");
+        assert!(preview.chapters[0].block_count > preview.chapters[1].block_count);
+        assert_eq!(preview.quality, ParseQuality::Light);
+        assert!(preview.warnings.is_empty());
+        assert_ne!(preview.chapters[0].content_hash, preview.chapters[1].content_hash);
+
+        assert!(preview_parse(&temp_dir.path().join("预览.docx")).is_err());
+    }
 }
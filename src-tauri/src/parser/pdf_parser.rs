@@ -2,16 +2,81 @@ use super::*;
 use std::fs;
 use crate::irp::TextRun;
 
+/// 默认允许的最大文件大小（字节）
+const DEFAULT_MAX_FILE_SIZE: u64 = 200 * 1024 * 1024;
+/// 默认允许的最大提取文本长度（字节）
+const DEFAULT_MAX_TEXT_LENGTH: usize = 50 * 1024 * 1024;
+/// 每 MB 文件提取的字符数低于该值时提示可能有扫描页
+const LOW_TEXT_CHARS_PER_MB: f64 = 2000.0;
+
 /// PDF 解析器（基础版）
 ///
 /// 支持纯文本 PDF 的解析，不支持扫描版 PDF
 #[derive(Clone)]
-pub struct PdfParser;
+pub struct PdfParser {
+    /// 允许的最大文件大小（字节），超过时拒绝解析
+    pub max_file_size: u64,
+    /// 允许的最大提取文本长度（字节），超过时拒绝导入
+    pub max_text_length: usize,
+}
 
 impl PdfParser {
-    /// 创建新的 PDF 解析器实例
+    /// 创建新的 PDF 解析器实例（使用默认的资源上限）
     pub fn new() -> Self {
-        Self
+        Self {
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_text_length: DEFAULT_MAX_TEXT_LENGTH,
+        }
+    }
+
+    /// 检查文件大小是否超过上限
+    fn check_file_size(&self, size: u64) -> Result<(), String> {
+        if size > self.max_file_size {
+            return Err(format!(
+                "PDF 文件过大（{:.1} MB），超过上限 {:.1} MB",
+                size as f64 / 1024.0 / 1024.0,
+                self.max_file_size as f64 / 1024.0 / 1024.0
+            ));
+        }
+        Ok(())
+    }
+
+    /// 检查提取的文本长度是否超过上限
+    fn check_text_length(&self, text: &str) -> Result<(), String> {
+        if text.len() > self.max_text_length {
+            return Err(format!(
+                "PDF 提取的文本过长（{} 字节），超过上限 {} 字节",
+                text.len(),
+                self.max_text_length
+            ));
+        }
+        Ok(())
+    }
+
+    /// 检查提取的文本是否明显少于文件大小应有的量（部分页面可能是扫描图片）
+    ///
+    /// # 返回
+    /// 文本过少时返回警告
+    fn low_text_warning(file_size: u64, text: &str) -> Option<String> {
+        let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+        let size_mb = file_size as f64 / 1024.0 / 1024.0;
+        if size_mb < 1.0 || chars as f64 / size_mb >= LOW_TEXT_CHARS_PER_MB {
+            return None;
+        }
+        Some(format!(
+            "PDF 提取的文本较少（{:.1} MB 文件仅 {} 个字符），部分页面可能是扫描图片，内容可能不完整",
+            size_mb, chars
+        ))
+    }
+
+    /// 按页提取 PDF 文本
+    ///
+    /// pdf_extract 遇到格式错误的文件可能 panic，这里捕获后转换为错误
+    fn extract_pages(bytes: &[u8]) -> Result<Vec<String>, String> {
+        match std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes)) {
+            Ok(result) => result.map_err(|e| format!("PDF 解析失败: {}。可能是扫描版 PDF，暂不支持", e)),
+            Err(_) => Err("PDF 解析失败：文件格式损坏或不受支持".to_string()),
+        }
     }
 
     /// 分割文本为段落
@@ -26,14 +91,7 @@ impl PdfParser {
     fn split_into_blocks(&self, text: &str) -> Vec<BlockData> {
         let mut blocks = Vec::new();
 
-        // 按双换行符分割段落
-        let paragraphs: Vec<&str> = text
-            .split("\n\n")
-            .map(|p| p.trim())
-            .filter(|p| !p.is_empty())
-            .collect();
-
-        for paragraph in paragraphs {
+        for paragraph in Self::paragraphs(text) {
             // 将单个换行符替换为空格
             let text = paragraph.replace('\n', " ");
 
@@ -43,42 +101,105 @@ impl PdfParser {
                     runs: vec![TextRun {
                         text,
                         marks: vec![],
+                        attributes: None,
                     }],
+                    heading_level: None,
+                    lang: None,
                 });
             }
         }
 
         blocks
     }
+
+    /// 按双换行符分割段落（与 `split_into_blocks` 生成的块一一对应）
+    fn paragraphs(text: &str) -> Vec<&str> {
+        text.split("\n\n")
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .collect()
+    }
+
+    /// 每个段落所在的页码（从 1 开始）
+    ///
+    /// # 参数
+    /// - `text`: 各页文本依次拼接的全文
+    /// - `page_starts`: 每页在全文中的起始字节偏移
+    fn paragraph_pages(text: &str, page_starts: &[usize]) -> Vec<usize> {
+        Self::paragraphs(text)
+            .iter()
+            .map(|paragraph| {
+                let offset = paragraph.as_ptr() as usize - text.as_ptr() as usize;
+                page_starts.partition_point(|&start| start <= offset).max(1)
+            })
+            .collect()
+    }
+
+    /// 把章节覆盖的页码范围（如 "p2-5"）记为源位置
+    ///
+    /// 章节检测器按顺序切分块列表，依次在块列表中定位每个章节的第一个块
+    fn set_page_anchors(chapters: &mut [ChapterData], blocks: &[BlockData], block_pages: &[usize]) {
+        let mut cursor = 0;
+        for chapter in chapters {
+            let Some(first) = chapter.blocks.first() else { continue };
+            let Some(start) = (cursor..blocks.len()).find(|&i| &blocks[i] == first) else { continue };
+            let end = (start + chapter.blocks.len()).min(blocks.len()) - 1;
+            chapter.source_anchor = Some(format!("p{}-{}", block_pages[start], block_pages[end]));
+            cursor = end + 1;
+        }
+    }
 }
 
 impl Parser for PdfParser {
     fn parse(&self, file_path: &Path, _book_id: i32, _conn: &Connection) -> Result<ParseResult, String> {
+        // 读取前检查文件大小
+        let size = fs::metadata(file_path)
+            .map_err(|e| format!("读取文件失败: {}", e))?
+            .len();
+        self.check_file_size(size)?;
+
         // 读取文件字节
         let bytes = fs::read(file_path)
             .map_err(|e| format!("读取文件失败: {}", e))?;
 
-        // 提取 PDF 文本
-        let text = pdf_extract::extract_text_from_mem(&bytes)
-            .map_err(|e| format!("PDF 解析失败: {}。可能是扫描版 PDF，暂不支持", e))?;
+        // 提取 PDF 文本，记录每页的起始位置
+        let pages = Self::extract_pages(&bytes)?;
+        drop(bytes);
+        let page_starts: Vec<usize> = pages
+            .iter()
+            .scan(0, |offset, page| {
+                let start = *offset;
+                *offset += page.len();
+                Some(start)
+            })
+            .collect();
+        let text = pages.concat();
+        self.check_text_length(&text)?;
 
         // 检查是否为扫描版 PDF（无文本内容）
         if text.trim().is_empty() {
             return Err("此 PDF 文件无法提取文本内容。\n\n可能原因：\n1. 这是扫描版 PDF（图片格式），需要 OCR 识别\n2. PDF 文件已加密或受保护\n\n建议：\n- 使用文字版 PDF\n- 或使用 OCR 工具转换后再导入".to_string());
         }
 
+        let warnings: Vec<String> = Self::low_text_warning(size, &text).into_iter().collect();
+
         // 分割为段落块
         let blocks = self.split_into_blocks(&text);
         let total_blocks = blocks.len();
 
         // 使用章节检测器进行三层回退式章节识别
-        let detector = super::chapter_detector::ChapterDetector::new();
-        let chapters = detector.detect(&blocks);
+        // 按采样检测到的语言选择章节标题模式
+        let language = super::language::detect_language(&blocks);
+        let detector = super::chapter_detector::ChapterDetector::for_language(language);
+        let mut chapters = detector.detect(&blocks);
+        Self::set_page_anchors(&mut chapters, &blocks, &Self::paragraph_pages(&text, &page_starts));
 
         Ok(ParseResult {
             chapters,
             total_blocks,
             quality: ParseQuality::Light, // PDF 质量标记为 Light
+            stylesheet: None,
+            warnings,
         })
     }
 
@@ -180,4 +301,37 @@ mod tests {
         assert_eq!(blocks[0].runs[0].text, "第一段");
         assert_eq!(blocks[1].runs[0].text, "第二段");
     }
+
+    #[test]
+    fn test_file_over_size_limit_is_rejected() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let conn = crate::db::init_db(temp_dir.path().join("test.db")).unwrap();
+        let pdf_path = temp_dir.path().join("huge.pdf");
+        fs::write(&pdf_path, vec![b'0'; 4096]).unwrap();
+
+        let parser = PdfParser { max_file_size: 1024, ..PdfParser::new() };
+        let error = parser.parse(&pdf_path, 1, &conn).unwrap_err();
+        assert!(error.contains("PDF 文件过大"), "{}", error);
+    }
+
+    #[test]
+    fn test_low_text_warning() {
+        let mb = 1024 * 1024;
+        assert!(PdfParser::low_text_warning(10 * mb, "只有几个字").is_some());
+        assert!(PdfParser::low_text_warning(mb / 2, "小文件不检查").is_none());
+        assert!(PdfParser::low_text_warning(2 * mb, &"字".repeat(5000)).is_none());
+    }
+
+    #[test]
+    fn test_text_over_length_limit_is_rejected() {
+        let parser = PdfParser { max_text_length: 16, ..PdfParser::new() };
+        assert!(parser.check_text_length("短文本").is_ok());
+        assert!(parser.check_text_length("这段提取出的文本超过了上限").is_err());
+    }
+
+    #[test]
+    fn test_malformed_pdf_returns_error() {
+        let result = PdfParser::extract_pages(b"%PDF-1.4\n1 0 obj << /Type /Catalog /Pages 9 0 R >> endobj\ntrailer << /Root 1 0 R >>\n%%EOF");
+        assert!(result.is_err());
+    }
 }
@@ -0,0 +1,191 @@
+// 测试用电子书生成工具：按需生成最小可解析的 EPUB、PDF 文件，避免在仓库中存放二进制样本
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// 生成 EPUB 文件
+///
+/// # 参数
+/// - `path`: 输出路径
+/// - `metadata`: OPF `<metadata>` 内的 XML 片段
+/// - `chapters`: 章节列表（标题, `<body>` 内的 HTML），每章生成一个 XHTML 文件和一个目录项
+pub fn write_epub(path: &Path, metadata: &str, chapters: &[(&str, &str)]) {
+    write_epub_with_guide(path, metadata, chapters, "");
+}
+
+/// 生成带 OPF `<guide>` 的 EPUB 文件
+///
+/// `guide` 为 `<guide>` 内的 `<reference>` 列表，章节文件名依次为 ch1.xhtml、ch2.xhtml……
+pub fn write_epub_with_guide(path: &Path, metadata: &str, chapters: &[(&str, &str)], guide: &str) {
+    write_epub_full(path, metadata, chapters, guide, &[], &[]);
+}
+
+/// 生成带额外资源（样式表、字体、图片）的 EPUB 文件
+///
+/// `resources` 为 (相对 OEBPS 的路径, media-type, 内容) 列表；
+/// 其中的样式表（`text/css`）会在每个章节的 `<head>` 中以 `<link rel="stylesheet">` 引用
+pub fn write_epub_with_resources(path: &Path, metadata: &str, chapters: &[(&str, &str)], resources: &[(&str, &str, &[u8])]) {
+    write_epub_full(path, metadata, chapters, "", resources, &[]);
+}
+
+/// 生成部分 spine 条目为 `linear="no"` 的 EPUB 文件
+///
+/// `non_linear` 为章节序号（从 0 开始），这些章节仍会出现在目录中
+pub fn write_epub_with_non_linear(path: &Path, metadata: &str, chapters: &[(&str, &str)], non_linear: &[usize]) {
+    write_epub_full(path, metadata, chapters, "", &[], non_linear);
+}
+
+fn write_epub_full(
+    path: &Path,
+    metadata: &str,
+    chapters: &[(&str, &str)],
+    guide: &str,
+    resources: &[(&str, &str, &[u8])],
+    non_linear: &[usize],
+) {
+    let file = File::create(path).unwrap();
+    let mut zip = ZipWriter::new(file);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // mimetype 必须是第一个且不压缩
+    zip.start_file("mimetype", stored).unwrap();
+    zip.write_all(b"application/epub+zip").unwrap();
+
+    zip.start_file("META-INF/container.xml", deflated).unwrap();
+    zip.write_all(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#,
+    )
+    .unwrap();
+
+    let mut manifest = String::new();
+    let mut links = String::new();
+    for (i, (href, media_type, content)) in resources.iter().enumerate() {
+        manifest.push_str(&format!(
+            r#"<item id="res{}" href="{}" media-type="{}"/>"#,
+            i + 1,
+            href,
+            media_type
+        ));
+        if *media_type == "text/css" {
+            links.push_str(&format!(r#"<link rel="stylesheet" type="text/css" href="{}"/>"#, href));
+        }
+        zip.start_file(format!("OEBPS/{}", href), deflated).unwrap();
+        zip.write_all(content).unwrap();
+    }
+
+    let mut spine = String::new();
+    let mut nav_points = String::new();
+    for (i, (title, body)) in chapters.iter().enumerate() {
+        let id = format!("ch{}", i + 1);
+        let href = format!("{}.xhtml", id);
+        manifest.push_str(&format!(
+            r#"<item id="{}" href="{}" media-type="application/xhtml+xml"/>"#,
+            id, href
+        ));
+        let linear = if non_linear.contains(&i) { r#" linear="no""# } else { "" };
+        spine.push_str(&format!(r#"<itemref idref="{}"{}/>"#, id, linear));
+        nav_points.push_str(&format!(
+            r#"<navPoint id="nav{n}" playOrder="{n}"><navLabel><text>{title}</text></navLabel><content src="{href}"/></navPoint>"#,
+            n = i + 1,
+            title = title,
+            href = href
+        ));
+
+        zip.start_file(format!("OEBPS/{}", href), deflated).unwrap();
+        zip.write_all(
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml"><head><title>{}</title>{}</head><body>{}</body></html>"#,
+                title, links, body
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+    }
+
+    zip.start_file("OEBPS/content.opf", deflated).unwrap();
+    zip.write_all(
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0" unique-identifier="bookid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+    {}
+  </metadata>
+  <manifest>
+    <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    {}
+  </manifest>
+  <spine toc="ncx">{}</spine>
+  <guide>{}</guide>
+</package>"#,
+            metadata, manifest, spine, guide
+        )
+        .as_bytes(),
+    )
+    .unwrap();
+
+    zip.start_file("OEBPS/toc.ncx", deflated).unwrap();
+    zip.write_all(
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <head/>
+  <docTitle><text>测试书籍</text></docTitle>
+  <navMap>{}</navMap>
+</ncx>"#,
+            nav_points
+        )
+        .as_bytes(),
+    )
+    .unwrap();
+
+    zip.finish().unwrap();
+}
+
+/// 生成单页 PDF 文件（Helvetica 字体，每行一个文本对象）
+///
+/// `lines` 只能包含 ASCII 字符，且不能包含括号和反斜杠
+pub fn write_pdf(path: &Path, lines: &[&str]) {
+    let mut content = String::from("BT /F1 12 Tf 72 720 Td 14 TL");
+    for line in lines {
+        content.push_str(&format!(" ({}) Tj T*", line));
+    }
+    content.push_str(" ET");
+
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R /Resources << /Font << /F1 5 0 R >> >> >>"
+            .to_string(),
+        format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref_offset = pdf.len();
+    pdf.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref_offset
+    ));
+
+    std::fs::write(path, pdf).unwrap();
+}
@@ -1,18 +1,112 @@
 use super::*;
 use encoding_rs::*;
 use std::fs;
+use std::io::Read;
 use crate::irp::TextRun;
 
+/// 超过该大小（字节）的文件按块流式读取，避免整个文件和解码后的字符串同时驻留内存
+const STREAMING_THRESHOLD: u64 = 50 * 1024 * 1024;
+/// 流式读取时每块的大小（字节）
+const CHUNK_SIZE: usize = 1024 * 1024;
+/// 解码出错时的解析警告
+const DECODE_WARNING: &str = "文件解码时出现错误，可能存在乱码";
+/// 编码检测报告读取的文件开头字节数
+pub const ENCODING_SAMPLE_BYTES: usize = 64 * 1024;
+/// 编码检测报告中预览的字符数
+const PREVIEW_CHARS: usize = 500;
+/// 计算置信度时比较的候选编码
+static CANDIDATE_ENCODINGS: [&Encoding; 5] = [&UTF_8_INIT, &GBK_INIT, &BIG5_INIT, &SHIFT_JIS_INIT, &EUC_KR_INIT];
+
+/// 编码检测报告
+#[derive(Serialize, Debug, Clone)]
+pub struct EncodingReport {
+    /// 检测到的编码名称（如 "UTF-8"、"GBK"）
+    pub encoding: String,
+    /// 置信度（0-1）
+    pub confidence: f64,
+    /// 按检测到的编码解码的开头文本
+    pub sample: String,
+}
+
+/// 流式读取的统计信息
+#[derive(Debug, Default)]
+struct ChunkStats {
+    /// 读取的块数
+    chunks: usize,
+    /// 缓存的未切分文本的最大字节数
+    max_pending: usize,
+    /// 解码时是否出现错误
+    had_errors: bool,
+}
+
+/// 增量段落切分器：逐行输入，空行结束当前段落
+#[derive(Default)]
+struct ParagraphSplitter {
+    paragraphs: Vec<String>,
+    current: String,
+}
+
+impl ParagraphSplitter {
+    fn push_line(&mut self, line: &str) {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            // 空行，结束当前段落
+            self.end_paragraph();
+        } else {
+            // 非空行，添加到当前段落
+            if !self.current.is_empty() {
+                self.current.push(' ');
+            }
+            self.current.push_str(trimmed);
+        }
+    }
+
+    fn end_paragraph(&mut self) {
+        if !self.current.trim().is_empty() {
+            self.paragraphs.push(self.current.trim().to_string());
+        }
+        self.current.clear();
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        // 添加最后一个段落
+        self.end_paragraph();
+        self.paragraphs
+    }
+}
+
+/// 按指定编码解码（去除 BOM），`is_complete` 为 false 时末尾截断的多字节字符不计为错误
+fn decode_sample(encoding: &'static Encoding, bytes: &[u8], is_complete: bool) -> String {
+    let mut decoder = encoding.new_decoder_with_bom_removal();
+    let mut text = String::with_capacity(decoder.max_utf8_buffer_length(bytes.len()).unwrap_or(bytes.len() * 3));
+    let _ = decoder.decode_to_string(bytes, &mut text, is_complete);
+    text
+}
+
 /// TXT 解析器
 ///
-/// 支持纯文本文件的解析，自动检测编码（UTF-8, GBK 等）
+/// 支持纯文本文件的解析，自动检测编码（UTF-8, GBK 等），也可由用户指定编码
 #[derive(Clone)]
-pub struct TxtParser;
+pub struct TxtParser {
+    /// 用户指定的编码，为 None 时自动检测
+    encoding_hint: Option<&'static Encoding>,
+}
 
 impl TxtParser {
     /// 创建新的 TXT 解析器实例
     pub fn new() -> Self {
-        Self
+        Self { encoding_hint: None }
+    }
+
+    /// 创建使用指定编码（不再自动检测）的 TXT 解析器实例
+    pub fn with_encoding_hint(encoding: &'static Encoding) -> Self {
+        Self { encoding_hint: Some(encoding) }
+    }
+
+    /// 按名称查找编码（如 "gbk"、"big5"、"utf-8"，不区分大小写）
+    pub fn encoding_for_label(label: &str) -> Result<&'static Encoding, String> {
+        Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| format!("不支持的编码: {}", label))
     }
 
     /// 检测文件编码
@@ -44,6 +138,44 @@ impl TxtParser {
         UTF_8
     }
 
+    /// 检测编码并给出置信度和解码预览
+    ///
+    /// 置信度为检测到的编码解码后非 ASCII 字符中非替换字符的比例；BOM、纯 ASCII 和
+    /// 严格通过 UTF-8 校验的内容视为可靠。其他候选编码解码得同样干净时无法区分
+    /// （如 GBK 与 Big5），置信度减半
+    ///
+    /// # 参数
+    /// - `bytes`: 文件开头的字节
+    /// - `is_complete`: `bytes` 是否为完整文件（否则末尾可能截断多字节字符）
+    pub fn detect_encoding_report(&self, bytes: &[u8], is_complete: bool) -> EncodingReport {
+        let encoding = self.detect_chunk_encoding(bytes, is_complete);
+        let sample = decode_sample(encoding, bytes, is_complete);
+
+        let confidence = if Encoding::for_bom(bytes).is_some() || bytes.is_ascii() || encoding == UTF_8 {
+            1.0
+        } else {
+            let error_ratio = |encoding: &'static Encoding| {
+                let text = decode_sample(encoding, bytes, is_complete);
+                let non_ascii = text.chars().filter(|c| !c.is_ascii()).count();
+                let replaced = text.chars().filter(|c| *c == char::REPLACEMENT_CHARACTER).count();
+                replaced as f64 / non_ascii.max(1) as f64
+            };
+            let detected_ratio = error_ratio(encoding);
+            let ambiguous = CANDIDATE_ENCODINGS
+                .iter()
+                .filter(|candidate| **candidate != encoding && **candidate != UTF_8)
+                .any(|candidate| error_ratio(candidate) <= detected_ratio);
+            let clean = 1.0 - detected_ratio;
+            if ambiguous { clean / 2.0 } else { clean }
+        };
+
+        EncodingReport {
+            encoding: encoding.name().to_string(),
+            confidence,
+            sample: sample.chars().take(PREVIEW_CHARS).collect(),
+        }
+    }
+
     /// 检测字节序列是否像 GBK 编码
     ///
     /// GBK 编码特征：
@@ -89,33 +221,77 @@ impl TxtParser {
     /// # 返回
     /// 段落列表
     fn split_into_paragraphs(&self, content: &str) -> Vec<String> {
-        let mut paragraphs = Vec::new();
-        let mut current_paragraph = String::new();
-
+        let mut splitter = ParagraphSplitter::default();
         for line in content.lines() {
-            let trimmed = line.trim();
+            splitter.push_line(line);
+        }
+        splitter.finish()
+    }
 
-            if trimmed.is_empty() {
-                // 空行，结束当前段落
-                if !current_paragraph.trim().is_empty() {
-                    paragraphs.push(current_paragraph.trim().to_string());
-                    current_paragraph.clear();
-                }
-            } else {
-                // 非空行，添加到当前段落
-                if !current_paragraph.is_empty() {
-                    current_paragraph.push(' ');
+    /// 检测流式读取的第一块的编码
+    ///
+    /// 块末尾可能截断多字节字符，截断处之前都是合法 UTF-8 时仍判定为 UTF-8
+    fn detect_chunk_encoding(&self, chunk: &[u8], is_last: bool) -> &'static Encoding {
+        match std::str::from_utf8(chunk) {
+            Err(e) if !is_last && e.error_len().is_none() && Encoding::for_bom(chunk).is_none() => UTF_8,
+            _ => self.detect_encoding(chunk),
+        }
+    }
+
+    /// 按块读取并切分段落
+    ///
+    /// 用第一块检测编码，逐块增量解码（跨块的多字节字符由解码器衔接），
+    /// 只缓存当前块中尚未结束的最后一行
+    ///
+    /// 切分出的段落仍全部收集到一个列表中，之后才生成内容块，整本书的文本会驻留内存；
+    /// 这里只是避免同时持有原始字节和完整的解码字符串
+    ///
+    /// # 参数
+    /// - `reader`: 数据来源
+    /// - `chunk_size`: 每块的字节数
+    ///
+    /// # 返回
+    /// 段落列表和读取统计
+    fn read_paragraphs_chunked<R: Read>(&self, mut reader: R, chunk_size: usize) -> Result<(Vec<String>, ChunkStats), String> {
+        let mut buffer = vec![0u8; chunk_size.max(1)];
+        let mut decoder: Option<Decoder> = None;
+        let mut pending = String::new();
+        let mut splitter = ParagraphSplitter::default();
+        let mut stats = ChunkStats::default();
+
+        loop {
+            let n = reader.read(&mut buffer).map_err(|e| format!("读取文件失败: {}", e))?;
+            let is_last = n == 0;
+            if !is_last {
+                stats.chunks += 1;
+            }
+
+            let chunk = &buffer[..n];
+            let decoder = decoder.get_or_insert_with(|| {
+                self.encoding_hint
+                    .unwrap_or_else(|| self.detect_chunk_encoding(chunk, is_last))
+                    .new_decoder_with_bom_removal()
+            });
+            pending.reserve(decoder.max_utf8_buffer_length(n).unwrap_or(n * 3 + 4));
+            let (_, _, errors) = decoder.decode_to_string(chunk, &mut pending, is_last);
+            stats.had_errors |= errors;
+            stats.max_pending = stats.max_pending.max(pending.len());
+
+            // 切分完整的行，保留最后一个不完整的行
+            if let Some(end) = pending.rfind('\n') {
+                for line in pending[..end].split('\n') {
+                    splitter.push_line(line);
                 }
-                current_paragraph.push_str(trimmed);
+                pending.drain(..=end);
             }
-        }
 
-        // 添加最后一个段落
-        if !current_paragraph.trim().is_empty() {
-            paragraphs.push(current_paragraph.trim().to_string());
+            if is_last {
+                break;
+            }
         }
+        splitter.push_line(&pending);
 
-        paragraphs
+        Ok((splitter.finish(), stats))
     }
 
     /// 创建段落块
@@ -127,28 +303,47 @@ impl TxtParser {
             runs: vec![TextRun {
                 text,
                 marks: vec![],
+                attributes: None,
             }],
+            heading_level: None,
+            lang: None,
         }
     }
 }
 
 impl Parser for TxtParser {
     fn parse(&self, file_path: &Path, _book_id: i32, _conn: &Connection) -> Result<ParseResult, String> {
-        // 1. 读取文件字节
-        let bytes = fs::read(file_path)
-            .map_err(|e| format!("读取文件失败: {}", e))?;
-
-        // 2. 检测编码
-        let encoding = self.detect_encoding(&bytes);
-
-        // 3. 解码为字符串
-        let (content, _encoding_used, had_errors) = encoding.decode(&bytes);
-        if had_errors {
-            eprintln!("警告：文件解码时出现错误，可能存在乱码");
-        }
+        let file_size = fs::metadata(file_path)
+            .map_err(|e| format!("读取文件失败: {}", e))?
+            .len();
+        let mut warnings = Vec::new();
+
+        let paragraphs = if file_size > STREAMING_THRESHOLD {
+            // 大文件按块读取、解码和切分段落
+            let file = fs::File::open(file_path)
+                .map_err(|e| format!("读取文件失败: {}", e))?;
+            let (paragraphs, stats) = self.read_paragraphs_chunked(file, CHUNK_SIZE)?;
+            if stats.had_errors {
+                warnings.push(DECODE_WARNING.to_string());
+            }
+            paragraphs
+        } else {
+            // 1. 读取文件字节
+            let bytes = fs::read(file_path)
+                .map_err(|e| format!("读取文件失败: {}", e))?;
+
+            // 2. 检测编码（用户指定时直接使用）
+            let encoding = self.encoding_hint.unwrap_or_else(|| self.detect_encoding(&bytes));
+
+            // 3. 解码为字符串
+            let (content, _encoding_used, had_errors) = encoding.decode(&bytes);
+            if had_errors {
+                warnings.push(DECODE_WARNING.to_string());
+            }
 
-        // 4. 分割为段落
-        let paragraphs = self.split_into_paragraphs(&content);
+            // 4. 分割为段落
+            self.split_into_paragraphs(&content)
+        };
 
         // 5. 创建 Blocks
         let blocks: Vec<BlockData> = paragraphs
@@ -159,13 +354,17 @@ impl Parser for TxtParser {
         let total_blocks = blocks.len();
 
         // 6. 使用章节检测器进行三层回退式章节识别
-        let detector = super::chapter_detector::ChapterDetector::new();
+        // 按采样检测到的语言选择章节标题模式
+        let language = super::language::detect_language(&blocks);
+        let detector = super::chapter_detector::ChapterDetector::for_language(language);
         let chapters = detector.detect(&blocks);
 
         Ok(ParseResult {
             chapters,
             total_blocks,
             quality: ParseQuality::Light,
+            stylesheet: None,
+            warnings,
         })
     }
 
@@ -211,6 +410,33 @@ mod tests {
         assert_eq!(encoding, UTF_8); // ASCII 兼容 UTF-8
     }
 
+    #[test]
+    fn test_encoding_report_for_clean_utf8() {
+        let parser = TxtParser::new();
+        let content = "第一章 开端\n\n这是一段干净的 UTF-8 文本。".repeat(40);
+
+        let report = parser.detect_encoding_report(content.as_bytes(), true);
+        assert_eq!(report.encoding, "UTF-8");
+        assert!(report.confidence > 0.9);
+        assert_eq!(report.sample.chars().count(), PREVIEW_CHARS);
+        assert!(content.starts_with(&report.sample));
+
+        // 文件开头被截断在多字节字符中间时仍判定为 UTF-8
+        let truncated = &content.as_bytes()[..content.len() - 1];
+        assert_eq!(parser.detect_encoding_report(truncated, false).encoding, "UTF-8");
+    }
+
+    #[test]
+    fn test_encoding_report_for_gbk() {
+        let parser = TxtParser::new();
+        let (bytes, _, _) = GBK.encode("第一章 开端。这是一段用国标码保存的中文文本，用来检测编码。");
+
+        let report = parser.detect_encoding_report(&bytes, true);
+        assert_eq!(report.encoding, "GBK");
+        assert!(report.sample.starts_with("第一章 开端"));
+        assert!(report.confidence > 0.0 && report.confidence <= 1.0);
+    }
+
     #[test]
     fn test_split_into_paragraphs() {
         let parser = TxtParser::new();
@@ -289,4 +515,59 @@ mod tests {
 
         assert_eq!(paragraphs.len(), 0);
     }
+
+    #[test]
+    fn test_chunked_reading_matches_whole_file() {
+        let parser = TxtParser::new();
+        let mut content = String::new();
+        for i in 0..20_000 {
+            content.push_str(&format!("第{}段的第一行，包含一些中文内容。\r\n第二行。\n\n", i));
+        }
+        let bytes = content.as_bytes();
+        let chunk_size = 64 * 1024;
+
+        let (paragraphs, stats) = parser.read_paragraphs_chunked(bytes, chunk_size).unwrap();
+        assert_eq!(paragraphs, parser.split_into_paragraphs(&content));
+        assert_eq!(paragraphs.len(), 20_000);
+
+        // 按块读取，任一时刻只缓存约一块的文本
+        assert_eq!(stats.chunks, bytes.len().div_ceil(chunk_size));
+        assert!(stats.chunks > 10);
+        assert!(stats.max_pending < chunk_size * 2);
+    }
+
+    #[test]
+    fn test_chunked_reading_decodes_split_characters() {
+        let parser = TxtParser::new();
+        let content = "第一章 开始\n\n这是第一段。\n\n这是第二段，字符会被块边界截断。\n";
+
+        // 7 字节的块会截断 UTF-8 的三字节字符
+        let (paragraphs, _) = parser.read_paragraphs_chunked(content.as_bytes(), 7).unwrap();
+        assert_eq!(paragraphs, parser.split_into_paragraphs(content));
+
+        // GBK 文件的双字节字符同样会跨块
+        let (gbk_bytes, _, _) = GBK.encode(content);
+        let (paragraphs, _) = parser.read_paragraphs_chunked(&gbk_bytes[..], 7).unwrap();
+        assert_eq!(paragraphs, parser.split_into_paragraphs(content));
+    }
+
+    #[test]
+    fn test_decode_errors_yield_warning() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let conn = crate::db::init_db(temp_dir.path().join("test.db")).unwrap();
+        let parser = TxtParser::new();
+
+        let broken = temp_dir.path().join("broken.txt");
+        fs::write(&broken, b"Chapter 1\n\nHello \xff\xff world.\n").unwrap();
+        let result = parser.parse(&broken, 1, &conn).unwrap();
+        assert_eq!(result.warnings, vec![DECODE_WARNING.to_string()]);
+
+        let clean = temp_dir.path().join("clean.txt");
+        fs::write(&clean, "Chapter 1\n\nHello world.\n").unwrap();
+        assert!(parser.parse(&clean, 1, &conn).unwrap().warnings.is_empty());
+
+        // 流式读取同样记录警告
+        let (_, stats) = parser.read_paragraphs_chunked(&b"Hello \xff world"[..], 4).unwrap();
+        assert!(stats.had_errors);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::reading_unit::presets::ThresholdPreset;
 use crate::reading_unit::types::*;
 
 /// Decision Engine
@@ -17,6 +18,18 @@ impl DecisionEngine {
         }
     }
 
+    /// 使用阈值预设创建决策引擎
+    pub fn from_preset(preset: ThresholdPreset) -> Self {
+        let profile = preset.profile();
+        let mut engine = Self::new();
+        engine.set_thresholds(
+            profile.merge_threshold,
+            profile.new_threshold,
+            profile.gray_zone_length,
+        );
+        engine
+    }
+
     /// 做出合并决策
     ///
     /// # 参数
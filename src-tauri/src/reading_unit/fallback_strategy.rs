@@ -1,3 +1,4 @@
+use crate::reading_unit::presets::ThresholdPreset;
 use crate::reading_unit::types::*;
 use regex::Regex;
 
@@ -10,14 +11,26 @@ pub struct FallbackStrategy {
 
 impl FallbackStrategy {
     pub fn new() -> Self {
-        // 强章标题正则
-        let pattern = r"^(第\s*[一二三四五六七八九十0-9]+\s*章|Chapter\s+\d+|Part\s+[IVX0-9]+)";
-        let strong_heading_regex = Regex::new(pattern).unwrap();
+        // 强章标题正则（静态模式，编译不会失败）
+        Self::try_new(r"^(第\s*[一二三四五六七八九十0-9]+\s*章|Chapter\s+\d+|Part\s+[IVX0-9]+)").unwrap()
+    }
 
-        Self {
-            strong_heading_regex,
+    /// 使用自定义的强章标题正则创建降级策略
+    ///
+    /// # 返回
+    /// 模式不是合法正则时返回错误
+    pub fn try_new(strong_heading_pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            strong_heading_regex: Regex::new(strong_heading_pattern)?,
             gray_zone_length: 800,
-        }
+        })
+    }
+
+    /// 使用阈值预设的灰区长度创建降级策略
+    pub fn from_preset(preset: ThresholdPreset) -> Self {
+        let mut strategy = Self::new();
+        strategy.set_gray_zone_length(preset.profile().gray_zone_length);
+        strategy
     }
 
     /// 应用降级策略
@@ -166,4 +179,13 @@ mod tests {
         let (decision3, _) = strategy.apply(&segment3);
         assert_eq!(decision3, MergeDecision::CreateNew);
     }
+
+    #[test]
+    fn test_invalid_custom_pattern() {
+        assert!(FallbackStrategy::try_new("^(第").is_err());
+
+        let strategy = FallbackStrategy::try_new(r"^卷\s*\d+").unwrap();
+        let (decision, _) = strategy.apply(&create_test_segment(Some("卷 2"), 1000));
+        assert_eq!(decision, MergeDecision::CreateNew);
+    }
 }
@@ -19,14 +19,24 @@ pub struct FeatureExtractor {
 impl FeatureExtractor {
     pub fn new() -> Self {
         // 强章标题正则：第X章、Chapter X、Part X
-        let strong_pattern = r"^(第\s*[一二三四五六七八九十0-9]+\s*章|Chapter\s+\d+|Part\s+[IVX0-9]+)";
-        let strong_heading_regex = Regex::new(strong_pattern).unwrap();
-
         // 弱标题正则：1.1、1.2.3、§1
-        let weak_pattern = r"^(\d+\.\d+|\d+\.\d+\.\d+|§\s*\d+)";
-        let weak_heading_regex = Regex::new(weak_pattern).unwrap();
+        // 内置模式都是静态正则，编译不会失败
+        Self::try_new(
+            r"^(第\s*[一二三四五六七八九十0-9]+\s*章|Chapter\s+\d+|Part\s+[IVX0-9]+)",
+            r"^(\d+\.\d+|\d+\.\d+\.\d+|§\s*\d+)",
+        )
+        .unwrap()
+    }
+
+    /// 使用自定义的强/弱标题正则创建特征提取器
+    ///
+    /// # 返回
+    /// 模式不是合法正则时返回错误
+    pub fn try_new(strong_pattern: &str, weak_pattern: &str) -> Result<Self, regex::Error> {
+        let strong_heading_regex = Regex::new(strong_pattern)?;
+        let weak_heading_regex = Regex::new(weak_pattern)?;
 
-        Self {
+        Ok(Self {
             strong_heading_regex,
             weak_heading_regex,
             copyright_keywords: vec![
@@ -38,7 +48,7 @@ impl FeatureExtractor {
                 "序", "序言", "前言", "致谢", "鸣谢", "导读", "引言",
                 "Preface", "Foreword", "Introduction", "Acknowledgments", "Summary",
             ],
-        }
+        })
     }
 
     /// 提取 Segment 的所有特征
@@ -80,10 +90,15 @@ impl FeatureExtractor {
         }
     }
 
+    /// 文本是否为强章标题（第X章、Chapter X、Part X 或自定义模式）
+    pub fn is_strong_heading(&self, text: &str) -> bool {
+        self.strong_heading_regex.is_match(text)
+    }
+
     /// 提取标题强度
     fn extract_heading_strength(&self, segment: &Segment) -> HeadingStrength {
         if let Some(ref heading) = segment.heading {
-            if self.strong_heading_regex.is_match(&heading.text) {
+            if self.is_strong_heading(&heading.text) {
                 return HeadingStrength::Strong;
             }
             if self.weak_heading_regex.is_match(&heading.text) {
@@ -349,4 +364,11 @@ mod tests {
         // 不连续：层级不同
         assert!(!extractor.is_continuous_numbering(&[1, 1], &[1, 1, 1]));
     }
+
+    #[test]
+    fn test_invalid_custom_pattern() {
+        assert!(FeatureExtractor::try_new("[", r"^\d+\.\d+").is_err());
+        assert!(FeatureExtractor::try_new(r"^第\d+章", "(?P<").is_err());
+        assert!(FeatureExtractor::try_new(r"^第\d+章", r"^\d+\.\d+").is_ok());
+    }
 }
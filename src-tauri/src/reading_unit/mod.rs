@@ -8,6 +8,9 @@ pub mod scoring_engine;
 pub mod decision_engine;
 pub mod reading_unit_builder;
 pub mod fallback_strategy;
+pub mod presets;
+pub mod unit_editor;
+pub mod summarizer;
 
 #[cfg(test)]
 mod integration_tests;
@@ -19,4 +22,5 @@ pub use feature_extractor::FeatureExtractor;
 pub use scoring_engine::ScoringEngine;
 pub use decision_engine::DecisionEngine;
 pub use reading_unit_builder::ReadingUnitBuilder;
+pub use reading_unit_builder::build_book_units;
 pub use fallback_strategy::FallbackStrategy;
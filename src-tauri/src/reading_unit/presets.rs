@@ -0,0 +1,200 @@
+use crate::error::AppError;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// 阈值参数
+///
+/// 对应 `DecisionEngine::set_thresholds` 的三个参数
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct ThresholdProfile {
+    /// 总分 >= 该值时合并
+    pub merge_threshold: f64,
+    /// 总分 <= 该值时创建新章节
+    pub new_threshold: f64,
+    /// 灰区内长度小于该值时合并
+    pub gray_zone_length: usize,
+}
+
+/// 阈值预设
+///
+/// 不同类型书籍的章节节奏差异较大：小说的短章节通常独立成章，
+/// 技术书籍的短小节更适合合并到所属章节中阅读
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThresholdPreset {
+    /// 默认参数（±3.0，灰区 800 字）
+    Default,
+    /// 小说：灰区较小，短章节也独立成章
+    Novel,
+    /// 技术书籍：灰区较大，短小节合并
+    Technical,
+    /// 教材：介于两者之间
+    Textbook,
+}
+
+impl ThresholdPreset {
+    /// 所有预设
+    pub const ALL: [ThresholdPreset; 4] = [
+        ThresholdPreset::Default,
+        ThresholdPreset::Novel,
+        ThresholdPreset::Technical,
+        ThresholdPreset::Textbook,
+    ];
+
+    /// 从字符串解析预设名称
+    pub fn parse(value: &str) -> Result<Self, AppError> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.as_str() == value)
+            .ok_or_else(|| {
+                AppError::Validation(format!("未知的阈值预设: {}（可选值: default, novel, technical, textbook）", value))
+            })
+    }
+
+    /// 预设名称（与数据库存储值一致）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThresholdPreset::Default => "default",
+            ThresholdPreset::Novel => "novel",
+            ThresholdPreset::Technical => "technical",
+            ThresholdPreset::Textbook => "textbook",
+        }
+    }
+
+    /// 预设对应的阈值参数
+    pub fn profile(&self) -> ThresholdProfile {
+        let (merge_threshold, new_threshold, gray_zone_length) = match self {
+            ThresholdPreset::Default => (3.0, -3.0, 800),
+            ThresholdPreset::Novel => (4.0, -2.0, 400),
+            ThresholdPreset::Technical => (2.0, -4.0, 2000),
+            ThresholdPreset::Textbook => (2.5, -3.5, 1200),
+        };
+        ThresholdProfile {
+            merge_threshold,
+            new_threshold,
+            gray_zone_length,
+        }
+    }
+}
+
+/// 读取书籍的阈值预设，未设置时为默认预设
+pub fn get_book_preset(conn: &Connection, book_id: i32) -> Result<ThresholdPreset, AppError> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT threshold_preset FROM books WHERE id = ?1",
+            [book_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("找不到书籍".to_string()))?;
+
+    match value {
+        Some(value) => ThresholdPreset::parse(&value),
+        None => Ok(ThresholdPreset::Default),
+    }
+}
+
+/// 设置书籍的阈值预设
+pub fn set_book_preset(conn: &Connection, book_id: i32, preset: ThresholdPreset) -> Result<(), AppError> {
+    let updated = conn
+        .execute(
+            "UPDATE books SET threshold_preset = ?1 WHERE id = ?2",
+            rusqlite::params![preset.as_str(), book_id],
+        )
+        .map_err(|e| AppError::Db(format!("保存阈值预设失败: {}", e)))?;
+    if updated == 0 {
+        return Err(AppError::NotFound("找不到书籍".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::reading_unit::types::*;
+    use crate::reading_unit::{DecisionEngine, FallbackStrategy};
+    use tempfile::TempDir;
+
+    fn subsection(length: usize) -> Segment {
+        Segment {
+            id: "seg-1".to_string(),
+            chapter_id: 1,
+            heading: Some(Heading {
+                text: "1.2 小节".to_string(),
+                level: None,
+            }),
+            length,
+            position_ratio: 0.5,
+            toc_level: None,
+            source_format: SourceFormat::Epub,
+            start_block_id: 1,
+            end_block_id: 1,
+        }
+    }
+
+    fn gray_zone_decision(preset: ThresholdPreset, segment: &Segment) -> MergeDecision {
+        let features = SegmentFeatures {
+            toc_feature: None,
+            heading_feature: HeadingStrength::Weak,
+            length_feature: LengthFeature::Medium,
+            content_feature: ContentFeature::Body,
+            position_in_book: 0.5,
+            is_after_strong_heading: false,
+            is_consecutive_strong_heading: false,
+            numbering_continuity: None,
+        };
+        let mut score = SegmentScore::new();
+        score.total_score = 0.0;
+        score.content_score = Some(0.0);
+
+        DecisionEngine::from_preset(preset)
+            .make_decision(&score, &features, segment)
+            .0
+    }
+
+    #[test]
+    fn test_technical_merges_short_subsection_novel_splits() {
+        let segment = subsection(1000);
+
+        assert_eq!(
+            gray_zone_decision(ThresholdPreset::Technical, &segment),
+            MergeDecision::Merge
+        );
+        assert_eq!(
+            gray_zone_decision(ThresholdPreset::Novel, &segment),
+            MergeDecision::CreateNew
+        );
+
+        // 降级策略使用同一灰区长度
+        let technical = FallbackStrategy::from_preset(ThresholdPreset::Technical);
+        let novel = FallbackStrategy::from_preset(ThresholdPreset::Novel);
+        assert_eq!(technical.apply(&segment).0, MergeDecision::Merge);
+        assert_eq!(novel.apply(&segment).0, MergeDecision::CreateNew);
+    }
+
+    #[test]
+    fn test_default_preset_matches_engine_defaults() {
+        let segment = subsection(700);
+        assert_eq!(
+            gray_zone_decision(ThresholdPreset::Default, &segment),
+            MergeDecision::Merge
+        );
+        assert_eq!(ThresholdPreset::Default.profile().gray_zone_length, 800);
+    }
+
+    #[test]
+    fn test_book_preset_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('书', '/a')", [])
+            .unwrap();
+
+        assert_eq!(get_book_preset(&conn, 1).unwrap(), ThresholdPreset::Default);
+        set_book_preset(&conn, 1, ThresholdPreset::Technical).unwrap();
+        assert_eq!(get_book_preset(&conn, 1).unwrap(), ThresholdPreset::Technical);
+
+        assert!(set_book_preset(&conn, 99, ThresholdPreset::Novel).is_err());
+        assert!(ThresholdPreset::parse("poetry").is_err());
+    }
+}
@@ -1,21 +1,94 @@
+use crate::parser::ChapterData;
+use crate::reading_unit::presets::get_book_preset;
 use crate::reading_unit::types::*;
+use crate::reading_unit::{DecisionEngine, FeatureExtractor, ScoringEngine, SegmentBuilder};
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Reading Unit Builder
 /// 根据决策构建最终的 Reading Unit 结构
 pub struct ReadingUnitBuilder {
     book_id: i32,
-    next_unit_id: std::cell::Cell<u32>, // 用于生成唯一ID
+    /// 章节 ID -> 解析器标记的内容类型（EPUB landmarks）
+    chapter_content_types: HashMap<i32, ContentType>,
 }
 
 impl ReadingUnitBuilder {
     pub fn new(book_id: i32) -> Self {
         Self {
             book_id,
-            next_unit_id: std::cell::Cell::new(1),
+            chapter_content_types: HashMap::new(),
         }
     }
 
+    /// 从数据库加载章节的内容类型
+    ///
+    /// 有标记的章节直接使用该类型，不再按关键词和位置推断
+    pub fn load_chapter_content_types(mut self, conn: &Connection) -> Result<Self, String> {
+        let mut stmt = conn
+            .prepare("SELECT id, content_type FROM chapters WHERE book_id = ?1 AND content_type IS NOT NULL")
+            .map_err(|e| format!("准备查询失败: {}", e))?;
+        let rows = stmt
+            .query_map([self.book_id], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| format!("查询章节内容类型失败: {}", e))?;
+
+        for row in rows {
+            let (chapter_id, content_type) = row.map_err(|e| format!("查询章节内容类型失败: {}", e))?;
+            let content_type = match content_type.as_str() {
+                "frontmatter" => ContentType::Frontmatter,
+                "body" => ContentType::Body,
+                "backmatter" => ContentType::Backmatter,
+                _ => continue,
+            };
+            self.chapter_content_types.insert(chapter_id, content_type);
+        }
+
+        Ok(self)
+    }
+
+    /// 保存 Reading Unit 列表（替换该书已有的 Reading Unit）
+    pub fn save(&self, conn: &Connection, units: &[ReadingUnit]) -> Result<(), String> {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        conn.execute("DELETE FROM reading_units WHERE book_id = ?1", [self.book_id])
+            .map_err(|e| format!("清除 Reading Unit 失败: {}", e))?;
+
+        for unit in units {
+            let segment_ids = serde_json::to_string(&unit.segment_ids).map_err(|e| e.to_string())?;
+            let content_type = unit.content_type.as_ref().map(|content_type| match content_type {
+                ContentType::Frontmatter => "frontmatter",
+                ContentType::Body => "body",
+                ContentType::Backmatter => "backmatter",
+            });
+
+            conn.execute(
+                "INSERT INTO reading_units (id, book_id, title, level, parent_id, segment_ids,
+                    start_block_id, end_block_id, source, content_type, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                rusqlite::params![
+                    unit.id,
+                    unit.book_id,
+                    unit.title,
+                    unit.level,
+                    unit.parent_id,
+                    segment_ids,
+                    unit.start_block_id,
+                    unit.end_block_id,
+                    unit.source,
+                    content_type,
+                    created_at
+                ],
+            )
+            .map_err(|e| format!("保存 Reading Unit 失败: {}", e))?;
+        }
+
+        Ok(())
+    }
+
     /// 构建 Reading Unit 列表
     ///
     /// # 参数
@@ -36,6 +109,7 @@ impl ReadingUnitBuilder {
         let mut reading_units = Vec::new();
         let mut current_unit: Option<ReadingUnit> = None;
         let mut current_level_1_parent: Option<String> = None;
+        let mut used_ids = HashSet::new();
 
         for (i, (segment, (decision, _reason, level))) in
             segments.iter().zip(decisions.iter()).enumerate()
@@ -54,6 +128,7 @@ impl ReadingUnitBuilder {
                             1,
                             None,
                             content_type,
+                            &mut used_ids,
                         ));
                     }
                 }
@@ -78,6 +153,7 @@ impl ReadingUnitBuilder {
                         unit_level,
                         parent_id,
                         content_type,
+                        &mut used_ids,
                     );
 
                     // 如果是 level=1，更新当前父节点
@@ -99,12 +175,15 @@ impl ReadingUnitBuilder {
     }
 
     /// 创建 Reading Unit
+    ///
+    /// `used_ids` 记录本次构建已使用的 ID，用于处理起始块相同时的冲突
     fn create_reading_unit(
         &self,
         segment: &Segment,
         level: u32,
         parent_id: Option<String>,
         content_type: Option<ContentType>,
+        used_ids: &mut HashSet<String>,
     ) -> ReadingUnit {
         let title = segment
             .heading
@@ -118,12 +197,18 @@ impl ReadingUnitBuilder {
             "heuristic".to_string()
         };
 
-        // 生成唯一ID
-        let unit_id = self.next_unit_id.get();
-        self.next_unit_id.set(unit_id + 1);
+        // 由书籍 ID 和起始块生成稳定的 ID，重新运行流水线时保持不变；
+        // 起始块相同时按出现顺序追加序号，保证书内唯一
+        let base_id = format!("ru-{}-{}", self.book_id, segment.start_block_id);
+        let mut id = base_id.clone();
+        let mut suffix = 2;
+        while !used_ids.insert(id.clone()) {
+            id = format!("{}-{}", base_id, suffix);
+            suffix += 1;
+        }
 
         ReadingUnit {
-            id: format!("ru-{}-{}", self.book_id, unit_id),
+            id,
             book_id: self.book_id,
             title,
             level,
@@ -144,6 +229,11 @@ impl ReadingUnitBuilder {
         index: usize,
         total: usize,
     ) -> Option<ContentType> {
+        // 解析器已标记的章节（EPUB landmarks）直接使用
+        if let Some(content_type) = self.chapter_content_types.get(&segment.chapter_id) {
+            return Some(content_type.clone());
+        }
+
         // 检查标题中的关键词
         if let Some(ref heading) = segment.heading {
             let text = heading.text.to_lowercase();
@@ -202,6 +292,51 @@ impl ReadingUnitBuilder {
     }
 }
 
+/// 为书籍运行完整的章节划分流程并保存结果（替换该书已有的 Reading Unit）
+///
+/// 在章节和内容块保存之后调用：Segment → 特征 → 评分 → 决策 → Reading Unit，
+/// 章节的标题层级作为 TOC 层级，决策阈值使用书籍的阈值预设
+///
+/// # 参数
+/// - `chapters`: 解析得到的章节列表（与已保存章节的 chapter_index 一一对应）
+pub fn build_book_units(
+    conn: &Connection,
+    book_id: i32,
+    chapters: &[ChapterData],
+) -> Result<Vec<ReadingUnit>, String> {
+    let file_path: String = conn
+        .query_row("SELECT file_path FROM books WHERE id = ?1", [book_id], |row| row.get(0))
+        .map_err(|e| format!("查询书籍失败: {}", e))?;
+    let source_format = SourceFormat::from_path(std::path::Path::new(&file_path));
+
+    let mut segments = SegmentBuilder::new(book_id, source_format).build_segments(chapters, conn)?;
+    let toc_levels: HashMap<i32, u32> = segments
+        .iter()
+        .zip(chapters)
+        .filter_map(|(segment, chapter)| chapter.heading_level.map(|level| (segment.chapter_id, level)))
+        .collect();
+    SegmentBuilder::set_toc_levels(&mut segments, &toc_levels);
+
+    let extractor = FeatureExtractor::new();
+    let scorer = ScoringEngine::new();
+    let decider = DecisionEngine::from_preset(get_book_preset(conn, book_id)?);
+    let decisions: Vec<_> = segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| {
+            let prev = i.checked_sub(1).map(|prev| &segments[prev]);
+            let features = extractor.extract_features(segment, prev);
+            let score = scorer.calculate_score(&features);
+            decider.make_decision(&score, &features, segment)
+        })
+        .collect();
+
+    let builder = ReadingUnitBuilder::new(book_id).load_chapter_content_types(conn)?;
+    let units = builder.build(&segments, &decisions)?;
+    builder.save(conn, &units)?;
+    Ok(units)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -297,6 +432,115 @@ mod tests {
         assert_eq!(units[2].parent_id, Some(units[0].id.clone()));
     }
 
+    #[test]
+    fn test_unit_ids_are_deterministic() {
+        let segments = vec![
+            create_test_segment("seg-1", 3, "第一章", 1000),
+            create_test_segment("seg-2", 7, "1.1 小节", 500),
+            create_test_segment("seg-3", 7, "1.2 小节", 600),
+        ];
+        let decisions = vec![
+            (MergeDecision::CreateNew, "新章节".to_string(), Some(1)),
+            (MergeDecision::CreateNew, "新小节".to_string(), Some(2)),
+            (MergeDecision::CreateNew, "新小节".to_string(), Some(2)),
+        ];
+
+        let ids = |builder: &ReadingUnitBuilder| -> Vec<String> {
+            builder
+                .build(&segments, &decisions)
+                .unwrap()
+                .into_iter()
+                .map(|unit| unit.id)
+                .collect()
+        };
+
+        // 同一个 builder 重复运行、或新建 builder 运行，ID 都相同
+        let builder = ReadingUnitBuilder::new(9);
+        let first = ids(&builder);
+        assert_eq!(ids(&builder), first);
+        assert_eq!(ids(&ReadingUnitBuilder::new(9)), first);
+
+        // 起始块相同时仍保证唯一
+        assert_eq!(first, vec!["ru-9-3", "ru-9-7", "ru-9-7-2"]);
+    }
+
+    #[test]
+    fn test_epub_landmarks_classify_and_persist() {
+        use crate::async_import::save_parse_result;
+        use crate::db;
+        use crate::parser::epub_parser::EpubParser;
+        use crate::parser::test_fixtures::write_epub_with_guide;
+        use crate::parser::Parser;
+        use crate::reading_unit::SegmentBuilder;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let epub_path = temp_dir.path().join("landmarks.epub");
+        // 标题不含关键词，只能依靠 landmarks 识别
+        write_epub_with_guide(
+            &epub_path,
+            r#"<dc:title>测试</dc:title><dc:identifier id="bookid">test</dc:identifier>"#,
+            &[
+                ("封面", "<p>封面图片</p>"),
+                ("本书信息", "<p>© 2024 某出版社</p>"),
+                ("第一章", "<p>正文</p>"),
+                ("第二章", "<p>正文</p>"),
+            ],
+            r#"<reference type="cover" title="封面" href="ch1.xhtml"/>
+               <reference type="copyright-page" title="版权页" href="ch2.xhtml"/>
+               <reference type="text" title="正文" href="ch3.xhtml"/>"#,
+        );
+
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('测试', '/a')", [])
+            .unwrap();
+        let book_id = conn.last_insert_rowid() as i32;
+
+        let result = EpubParser::new().parse(&epub_path, book_id, &conn).unwrap();
+        let content_types: Vec<Option<&str>> =
+            result.chapters.iter().map(|c| c.content_type.as_deref()).collect();
+        assert_eq!(
+            content_types,
+            vec![Some("frontmatter"), Some("frontmatter"), Some("body"), None]
+        );
+        save_parse_result(&conn, book_id, &result, None).unwrap();
+
+        let segments = SegmentBuilder::new(book_id, SourceFormat::Epub)
+            .build_segments(&result.chapters, &conn)
+            .unwrap();
+        let decisions: Vec<_> = segments
+            .iter()
+            .map(|_| (MergeDecision::CreateNew, "新章节".to_string(), Some(1)))
+            .collect();
+        let builder = ReadingUnitBuilder::new(book_id)
+            .load_chapter_content_types(&conn)
+            .unwrap();
+        let units = builder.build(&segments, &decisions).unwrap();
+        builder.save(&conn, &units).unwrap();
+
+        let stored: Vec<(String, Option<String>)> = conn
+            .prepare("SELECT title, content_type FROM reading_units WHERE book_id = ?1 ORDER BY start_block_id")
+            .unwrap()
+            .query_map([book_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let expected = [
+            ("封面", "frontmatter"),
+            ("本书信息", "frontmatter"),
+            ("第一章", "body"),
+            // 未标记的章节仍按关键词和位置推断
+            ("第二章", "backmatter"),
+        ];
+        assert_eq!(
+            stored,
+            expected
+                .iter()
+                .map(|(title, content_type)| (title.to_string(), Some(content_type.to_string())))
+                .collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn test_determine_content_type_copyright() {
         let builder = ReadingUnitBuilder::new(1);
@@ -40,7 +40,7 @@ impl SegmentBuilder {
             let (start_block_id, end_block_id) = self.get_block_range(conn, chapter_id)?;
 
             // 计算正文长度（排除标题）
-            let length = self.calculate_content_length(chapter);
+            let length = self.calculate_content_length(chapter, index);
 
             // 计算位置比例
             let position_ratio = if total_chapters > 1 {
@@ -107,7 +107,17 @@ impl SegmentBuilder {
     }
 
     /// 计算正文长度（排除标题）
-    fn calculate_content_length(&self, chapter: &ChapterData) -> usize {
+    ///
+    /// html/markdown 章节没有内容块，按原始内容提取的纯文本计算（不含空白）
+    fn calculate_content_length(&self, chapter: &ChapterData, chapter_index: usize) -> usize {
+        if let Some(text) = crate::export::raw_content_to_text(
+            &chapter.render_mode,
+            chapter.raw_html.as_deref(),
+            chapter_index,
+        ) {
+            return text.chars().filter(|c| !c.is_whitespace()).count();
+        }
+
         chapter
             .blocks
             .iter()
@@ -184,14 +194,20 @@ mod tests {
                     runs: vec![TextRun {
                         text: "第一章 标题".to_string(),
                         marks: vec![],
+                        attributes: None,
                     }],
+                    heading_level: None,
+                    lang: None,
                 },
                 BlockData {
                     block_type: "paragraph".to_string(),
                     runs: vec![TextRun {
                         text: "这是正文内容。".to_string(),
                         marks: vec![],
+                        attributes: None,
                     }],
+                    heading_level: None,
+                    lang: None,
                 },
             ],
             confidence: "explicit".to_string(),
@@ -199,9 +215,11 @@ mod tests {
             render_mode: "irp".to_string(),
             heading_level: None,
             anchor_id: None,
+            content_type: None,
+            source_anchor: None,
         };
 
-        let length = builder.calculate_content_length(&chapter);
+        let length = builder.calculate_content_length(&chapter, 0);
         assert_eq!(length, 7); // "这是正文内容。" = 7 个字符（不含标点）
     }
 
@@ -217,6 +235,8 @@ mod tests {
             render_mode: "irp".to_string(),
             heading_level: None,
             anchor_id: None,
+            content_type: None,
+            source_anchor: None,
         };
 
         let heading = builder.extract_heading(&chapter);
@@ -235,13 +255,18 @@ mod tests {
                 runs: vec![TextRun {
                     text: "第一章 开始".to_string(),
                     marks: vec![],
+                    attributes: None,
                 }],
+                heading_level: None,
+                lang: None,
             }],
             confidence: "inferred".to_string(),
             raw_html: None,
             render_mode: "irp".to_string(),
             heading_level: None,
             anchor_id: None,
+            content_type: None,
+            source_anchor: None,
         };
 
         let heading = builder.extract_heading(&chapter);
@@ -0,0 +1,212 @@
+// Reading Unit 批量摘要：逐个单元生成摘要，每完成一个立即写入数据库，
+// 中途退出后再次运行只处理尚未生成摘要的单元
+
+use crate::db::Database;
+use crate::encryption;
+use crate::error::AppError;
+use crate::export;
+use crate::reading_unit::types::ReadingUnit;
+use crate::reading_unit::unit_editor::{is_book_encrypted, load_units, segment_chapter_id};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::future::Future;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 单个单元送给 AI 的最大字符数
+pub const MAX_UNIT_CHARS: usize = 8000;
+
+/// 批量摘要进度（`summarize-progress` 事件的负载）
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SummarizeProgress {
+    pub done: usize,
+    pub total: usize,
+    pub current_title: String,
+}
+
+/// 拼接单元包含的所有章节的纯文本，超过 MAX_UNIT_CHARS 时截断
+pub fn unit_text(conn: &Connection, unit: &ReadingUnit, key: Option<&[u8]>) -> Result<String, String> {
+    let mut texts = Vec::new();
+    for chapter_id in unit.segment_ids.iter().filter_map(|id| segment_chapter_id(id)) {
+        texts.push(export::chapter_plain_text(conn, chapter_id, key)?);
+    }
+    let text = texts.join("\n\n");
+
+    Ok(match text.char_indices().nth(MAX_UNIT_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    })
+}
+
+/// 保存单元摘要
+///
+/// 摘要概括了书籍内容，加密书籍的摘要用 `key` 加密存储
+pub fn save_unit_summary(
+    conn: &Connection,
+    id: &str,
+    text: &str,
+    model: &str,
+    key: Option<&[u8]>,
+) -> Result<(), String> {
+    let book_id: i32 = conn
+        .query_row("SELECT book_id FROM reading_units WHERE id = ?1", [id], |row| row.get(0))
+        .map_err(|e| format!("保存摘要失败: {}", e))?;
+    let text = if is_book_encrypted(conn, book_id)? {
+        let key = key.ok_or_else(|| "书籍内容已加密，缺少密钥".to_string())?;
+        encryption::encrypt_content(text, key).map_err(|e| e.to_string())?
+    } else {
+        text.to_string()
+    };
+
+    let generated_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    conn.execute(
+        "UPDATE reading_units SET summary_text = ?1, summary_generated_at = ?2, summary_model = ?3 WHERE id = ?4",
+        rusqlite::params![text, generated_at, model, id],
+    )
+    .map_err(|e| format!("保存摘要失败: {}", e))?;
+    Ok(())
+}
+
+/// 为书籍中尚未生成摘要的阅读单元逐个生成摘要
+///
+/// 每个摘要完成后立即保存，失败时已保存的摘要保留，再次调用从剩余单元继续。
+/// 只在读写数据库时短暂持有锁，`summarize` 执行期间不持有
+///
+/// # 参数
+/// - `db`: 数据库
+/// - `book_id`: 书籍 ID
+/// - `model`: 记录在摘要上的模型名
+/// - `key`: 加密书籍的解密密钥
+/// - `summarize`: 根据 (单元标题, 单元文本) 生成摘要
+/// - `on_progress`: 每完成一个单元后调用（`done` 包含之前已完成的单元）
+///
+/// # 返回
+/// 本次生成的摘要数
+pub async fn summarize_units<F, Fut>(
+    db: &Database,
+    book_id: i32,
+    model: &str,
+    key: Option<&[u8]>,
+    mut summarize: F,
+    mut on_progress: impl FnMut(SummarizeProgress),
+) -> Result<usize, AppError>
+where
+    F: FnMut(String, String) -> Fut,
+    Fut: Future<Output = Result<String, String>>,
+{
+    let units = db.with_conn(|conn| load_units(conn, book_id, key)).map_err(AppError::Db)?;
+    let total = units.len();
+    let pending: Vec<&ReadingUnit> = units.iter().filter(|unit| unit.summary.is_none()).collect();
+    let already_done = total - pending.len();
+
+    for (i, unit) in pending.iter().enumerate() {
+        let text = db.with_conn(|conn| unit_text(conn, unit, key)).map_err(AppError::Db)?;
+        let summary = summarize(unit.title.clone(), text).await.map_err(AppError::Ai)?;
+        db.with_conn(|conn| save_unit_summary(conn, &unit.id, &summary, model, key)).map_err(AppError::Db)?;
+
+        on_progress(SummarizeProgress {
+            done: already_done + i + 1,
+            total,
+            current_title: unit.title.clone(),
+        });
+    }
+
+    Ok(pending.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::irp::{self, TextRun};
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, Database, i32) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::open(temp_dir.path().join("test.db")).unwrap();
+        let book_id = db
+            .with_conn(|conn| {
+                conn.execute("INSERT INTO books (title, file_path) VALUES ('书', '/a')", [])?;
+                let book_id = conn.last_insert_rowid() as i32;
+                for (index, title) in ["第一章", "第二章", "第三章"].iter().enumerate() {
+                    let chapter_id = irp::create_chapter(conn, book_id, title, index as i32, "explicit")? as i32;
+                    let runs = vec![TextRun { text: format!("{}的正文。", title), marks: vec![], attributes: None }];
+                    let block_id = irp::create_block(conn, chapter_id, 0, "paragraph", &runs, None)?;
+                    conn.execute(
+                        "INSERT INTO reading_units (id, book_id, title, level, segment_ids,
+                            start_block_id, end_block_id, source, created_at)
+                         VALUES (?1, ?2, ?3, 1, ?4, ?5, ?5, 'heuristic', 0)",
+                        rusqlite::params![
+                            format!("ru-{}-{}", book_id, index),
+                            book_id,
+                            title,
+                            format!(r#"["seg-{}-{}"]"#, book_id, chapter_id),
+                            block_id
+                        ],
+                    )?;
+                }
+                Ok::<_, rusqlite::Error>(book_id)
+            })
+            .unwrap();
+        (temp_dir, db, book_id)
+    }
+
+    #[test]
+    fn test_resume_only_processes_remaining_units() {
+        let (_temp_dir, db, book_id) = setup();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let calls = Mutex::new(Vec::new());
+
+        // 第一次运行在第二个单元处中断
+        let result = rt.block_on(summarize_units(
+            &db,
+            book_id,
+            "test-model",
+            None,
+            |title, text| {
+                calls.lock().unwrap().push(title.clone());
+                async move {
+                    if title == "第二章" {
+                        Err("请求超时".to_string())
+                    } else {
+                        Ok(format!("摘要：{}", text))
+                    }
+                }
+            },
+            |_| {},
+        ));
+        assert!(result.is_err());
+        let units = db.with_conn(|conn| load_units(conn, book_id, None)).unwrap();
+        let summary = units[0].summary.as_ref().unwrap();
+        assert_eq!(summary.text, "摘要：第一章的正文。");
+        assert_eq!(summary.model, "test-model");
+        assert!(units[1].summary.is_none());
+
+        // 第二次运行跳过已完成的单元
+        calls.lock().unwrap().clear();
+        let progress = Mutex::new(Vec::new());
+        let generated = rt
+            .block_on(summarize_units(
+                &db,
+                book_id,
+                "test-model",
+                None,
+                |title, _text| {
+                    calls.lock().unwrap().push(title.clone());
+                    async move { Ok(format!("{}摘要", title)) }
+                },
+                |p| progress.lock().unwrap().push((p.done, p.total, p.current_title)),
+            ))
+            .unwrap();
+        assert_eq!(generated, 2);
+        assert_eq!(*calls.lock().unwrap(), vec!["第二章", "第三章"]);
+        assert_eq!(
+            *progress.lock().unwrap(),
+            vec![(2, 3, "第二章".to_string()), (3, 3, "第三章".to_string())]
+        );
+        let units = db.with_conn(|conn| load_units(conn, book_id, None)).unwrap();
+        assert!(units.iter().all(|unit| unit.summary.is_some()));
+    }
+}
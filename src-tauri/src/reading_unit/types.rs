@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 
 /// 源格式类型
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -12,6 +13,23 @@ pub enum SourceFormat {
     Html,
 }
 
+impl SourceFormat {
+    /// 按文件扩展名判断源格式，无法识别时视为纯文本
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("epub") => SourceFormat::Epub,
+            Some("pdf") => SourceFormat::Pdf,
+            Some("md") | Some("markdown") => SourceFormat::Md,
+            Some("html") | Some("htm") | Some("xhtml") => SourceFormat::Html,
+            _ => SourceFormat::Txt,
+        }
+    }
+}
+
 /// 内容类型
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
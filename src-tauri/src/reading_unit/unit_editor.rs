@@ -0,0 +1,441 @@
+// Reading Unit 手动编辑：读取已保存的 Reading Unit，并支持合并与拆分，用于修正自动划分的错误
+
+use crate::encryption;
+use crate::error::AppError;
+use crate::reading_unit::types::*;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const UNIT_COLUMNS: &str = "id, book_id, title, level, parent_id, segment_ids,
+    start_block_id, end_block_id, source, content_type, summary_text, summary_generated_at, summary_model";
+
+fn unit_from_row(row: &rusqlite::Row) -> rusqlite::Result<ReadingUnit> {
+    let segment_ids_json: String = row.get(5)?;
+    let content_type_str: Option<String> = row.get(9)?;
+    let summary_text: Option<String> = row.get(10)?;
+
+    let segment_ids: Vec<String> = serde_json::from_str(&segment_ids_json)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+    let content_type = content_type_str.and_then(|s| match s.as_str() {
+        "frontmatter" => Some(ContentType::Frontmatter),
+        "body" => Some(ContentType::Body),
+        "backmatter" => Some(ContentType::Backmatter),
+        _ => None,
+    });
+
+    Ok(ReadingUnit {
+        id: row.get(0)?,
+        book_id: row.get(1)?,
+        title: row.get(2)?,
+        level: row.get(3)?,
+        parent_id: row.get(4)?,
+        segment_ids,
+        start_block_id: row.get(6)?,
+        end_block_id: row.get(7)?,
+        source: row.get(8)?,
+        content_type,
+        summary: match summary_text {
+            Some(text) => Some(Summary {
+                text,
+                generated_at: row.get::<_, Option<i64>>(11)?.unwrap_or_default(),
+                model: row.get::<_, Option<String>>(12)?.unwrap_or_default(),
+            }),
+            None => None,
+        },
+    })
+}
+
+/// 书籍是否加密存储内容（摘要随之加密）
+pub fn is_book_encrypted(conn: &Connection, book_id: i32) -> Result<bool, String> {
+    conn.query_row("SELECT COALESCE(is_encrypted, 0) FROM books WHERE id = ?1", [book_id], |row| row.get(0))
+        .optional()
+        .map(|encrypted| encrypted.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+/// 按起始块顺序读取书籍的所有 Reading Unit
+///
+/// 加密书籍的摘要用 `key` 解密（解密失败时视为加密前保存的旧数据保留原值）；
+/// 只需要单元结构时（合并、拆分、目录）传 None，加密书籍的摘要为 None
+pub fn load_units(conn: &Connection, book_id: i32, key: Option<&[u8]>) -> Result<Vec<ReadingUnit>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM reading_units WHERE book_id = ?1 ORDER BY start_block_id",
+            UNIT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let mut units = stmt
+        .query_map([book_id], unit_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    if is_book_encrypted(conn, book_id)? {
+        for unit in &mut units {
+            unit.summary = match (unit.summary.take(), key) {
+                (Some(mut summary), Some(key)) => {
+                    if let Ok(text) = encryption::decrypt_content(&summary.text, key) {
+                        summary.text = text;
+                    }
+                    Some(summary)
+                }
+                _ => None,
+            };
+        }
+    }
+    Ok(units)
+}
+
+/// 读取单个 Reading Unit
+fn load_unit(conn: &Connection, id: &str) -> Result<ReadingUnit, AppError> {
+    conn.query_row(
+        &format!("SELECT {} FROM reading_units WHERE id = ?1", UNIT_COLUMNS),
+        [id],
+        unit_from_row,
+    )
+    .optional()
+    .map_err(|e| AppError::Db(e.to_string()))?
+    .ok_or_else(|| AppError::NotFound(format!("找不到阅读单元: {}", id)))
+}
+
+/// 合并多个相邻的 Reading Unit
+///
+/// 合并后保留最靠前单元的 ID 和标题，片段与块范围取并集，其余单元被删除
+///
+/// # 参数
+/// - `ids`: 要合并的单元 ID（至少两个，必须属于同一本书且在顺序上连续）
+///
+/// # 返回
+/// 合并后的 Reading Unit
+pub fn merge_units(conn: &mut Connection, ids: &[String]) -> Result<ReadingUnit, AppError> {
+    if ids.len() < 2 {
+        return Err(AppError::Validation("至少需要两个阅读单元才能合并".to_string()));
+    }
+
+    let first = load_unit(conn, &ids[0])?;
+    let units = load_units(conn, first.book_id, None).map_err(AppError::Db)?;
+
+    let requested: HashSet<&str> = ids.iter().map(String::as_str).collect();
+    let positions: Vec<usize> = units
+        .iter()
+        .enumerate()
+        .filter(|(_, unit)| requested.contains(unit.id.as_str()))
+        .map(|(i, _)| i)
+        .collect();
+    if positions.len() != requested.len() {
+        return Err(AppError::NotFound("阅读单元不存在或不属于同一本书".to_string()));
+    }
+    if positions.windows(2).any(|pair| pair[1] != pair[0] + 1) {
+        return Err(AppError::Validation("只能合并相邻的阅读单元".to_string()));
+    }
+
+    let merged_units = &units[positions[0]..=positions[positions.len() - 1]];
+    let mut merged = merged_units[0].clone();
+    for unit in &merged_units[1..] {
+        merged.segment_ids.extend(unit.segment_ids.iter().cloned());
+        merged.end_block_id = merged.end_block_id.max(unit.end_block_id);
+    }
+    // 合并了章级单元时提升为章
+    if merged_units.iter().any(|unit| unit.level == 1) {
+        merged.level = 1;
+    }
+
+    let tx = conn.transaction().map_err(|e| AppError::Db(e.to_string()))?;
+    for unit in &merged_units[1..] {
+        // 先把子节改挂到合并后的单元，避免级联删除
+        tx.execute(
+            "UPDATE reading_units SET parent_id = ?1 WHERE parent_id = ?2",
+            [&merged.id, &unit.id],
+        )
+        .map_err(|e| AppError::Db(format!("更新阅读单元层级失败: {}", e)))?;
+        tx.execute("DELETE FROM reading_units WHERE id = ?1", [&unit.id])
+            .map_err(|e| AppError::Db(format!("删除阅读单元失败: {}", e)))?;
+    }
+    let segment_ids = serde_json::to_string(&merged.segment_ids).map_err(|e| AppError::Db(e.to_string()))?;
+    // 内容已变化，清除旧摘要
+    tx.execute(
+        "UPDATE reading_units SET level = ?1, segment_ids = ?2, end_block_id = ?3,
+            summary_text = NULL, summary_generated_at = NULL, summary_model = NULL
+         WHERE id = ?4",
+        rusqlite::params![merged.level, segment_ids, merged.end_block_id, merged.id],
+    )
+    .map_err(|e| AppError::Db(format!("更新阅读单元失败: {}", e)))?;
+    normalize_hierarchy(&tx, merged.book_id).map_err(AppError::Db)?;
+    tx.commit().map_err(|e| AppError::Db(e.to_string()))?;
+
+    load_unit(conn, &merged.id)
+}
+
+/// 在指定块处拆分 Reading Unit
+///
+/// 原单元保留 `at_block_id` 之前的内容，从 `at_block_id` 开始的内容成为新的同级单元
+///
+/// # 参数
+/// - `id`: 要拆分的单元 ID
+/// - `at_block_id`: 新单元的起始块，必须位于原单元内且不是其第一个块
+///
+/// # 返回
+/// (拆分后的原单元, 新单元)
+pub fn split_unit(
+    conn: &mut Connection,
+    id: &str,
+    at_block_id: i32,
+) -> Result<(ReadingUnit, ReadingUnit), AppError> {
+    let unit = load_unit(conn, id)?;
+    if at_block_id <= unit.start_block_id || at_block_id > unit.end_block_id {
+        return Err(AppError::Validation(format!(
+            "拆分位置 {} 不在阅读单元范围 ({}, {}] 内",
+            at_block_id, unit.start_block_id, unit.end_block_id
+        )));
+    }
+
+    // 按片段的块范围分配；跨越拆分点的片段两边都保留
+    let mut head_segments = Vec::new();
+    let mut tail_segments = Vec::new();
+    for segment_id in &unit.segment_ids {
+        match segment_block_range(conn, segment_id) {
+            Some((start, end)) => {
+                if start < at_block_id {
+                    head_segments.push(segment_id.clone());
+                }
+                if end >= at_block_id {
+                    tail_segments.push(segment_id.clone());
+                }
+            }
+            None => head_segments.push(segment_id.clone()),
+        }
+    }
+
+    let existing: HashSet<String> = load_units(conn, unit.book_id, None).map_err(AppError::Db)?
+        .into_iter()
+        .map(|unit| unit.id)
+        .collect();
+    let base_id = format!("ru-{}-{}", unit.book_id, at_block_id);
+    let mut new_id = base_id.clone();
+    let mut suffix = 2;
+    while existing.contains(&new_id) {
+        new_id = format!("{}-{}", base_id, suffix);
+        suffix += 1;
+    }
+
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    let tx = conn.transaction().map_err(|e| AppError::Db(e.to_string()))?;
+    tx.execute(
+        "UPDATE reading_units SET segment_ids = ?1, end_block_id = ?2,
+            summary_text = NULL, summary_generated_at = NULL, summary_model = NULL
+         WHERE id = ?3",
+        rusqlite::params![
+            serde_json::to_string(&head_segments).map_err(|e| AppError::Db(e.to_string()))?,
+            at_block_id - 1,
+            unit.id
+        ],
+    )
+    .map_err(|e| AppError::Db(format!("更新阅读单元失败: {}", e)))?;
+    tx.execute(
+        "INSERT INTO reading_units (id, book_id, title, level, parent_id, segment_ids,
+            start_block_id, end_block_id, source, content_type, created_at)
+         SELECT ?1, book_id, ?2, level, parent_id, ?3, ?4, ?5, source, content_type, ?6
+         FROM reading_units WHERE id = ?7",
+        rusqlite::params![
+            new_id,
+            format!("{}（续）", unit.title),
+            serde_json::to_string(&tail_segments).map_err(|e| AppError::Db(e.to_string()))?,
+            at_block_id,
+            unit.end_block_id,
+            created_at,
+            unit.id
+        ],
+    )
+    .map_err(|e| AppError::Db(format!("创建阅读单元失败: {}", e)))?;
+    normalize_hierarchy(&tx, unit.book_id).map_err(AppError::Db)?;
+    tx.commit().map_err(|e| AppError::Db(e.to_string()))?;
+
+    Ok((load_unit(conn, &unit.id)?, load_unit(conn, &new_id)?))
+}
+
+/// 重命名 Reading Unit
+///
+/// # 参数
+/// - `title`: 新标题（去除首尾空白后不能为空）
+pub fn rename_unit(conn: &Connection, id: &str, title: &str) -> Result<ReadingUnit, AppError> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err(AppError::Validation("阅读单元标题不能为空".to_string()));
+    }
+
+    let updated = conn
+        .execute(
+            "UPDATE reading_units SET title = ?1 WHERE id = ?2",
+            rusqlite::params![title, id],
+        )
+        .map_err(|e| AppError::Db(format!("重命名阅读单元失败: {}", e)))?;
+    if updated == 0 {
+        return Err(AppError::NotFound(format!("找不到阅读单元: {}", id)));
+    }
+
+    load_unit(conn, id)
+}
+
+/// 从片段 ID（格式为 `seg-{book_id}-{chapter_id}`）中取出章节 ID
+pub fn segment_chapter_id(segment_id: &str) -> Option<i32> {
+    segment_id.rsplit('-').next()?.parse().ok()
+}
+
+/// 查询片段对应章节的块范围（片段 ID 格式为 `seg-{book_id}-{chapter_id}`）
+///
+/// 章节没有块时以章节 ID 作为占位范围，与 SegmentBuilder 一致
+fn segment_block_range(conn: &Connection, segment_id: &str) -> Option<(i32, i32)> {
+    let chapter_id = segment_chapter_id(segment_id)?;
+    let (start, end): (Option<i32>, Option<i32>) = conn
+        .query_row(
+            "SELECT MIN(id), MAX(id) FROM blocks WHERE chapter_id = ?1",
+            [chapter_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .ok()?;
+    Some((start.unwrap_or(chapter_id), end.unwrap_or(chapter_id)))
+}
+
+/// 按顺序重新计算层级关系：节归属于前面最近的章，前面没有章的节提升为章
+fn normalize_hierarchy(conn: &Connection, book_id: i32) -> Result<(), String> {
+    let mut current_chapter: Option<String> = None;
+    for unit in load_units(conn, book_id, None)? {
+        let (level, parent_id) = match (unit.level, &current_chapter) {
+            (2, Some(chapter)) => (2, Some(chapter.clone())),
+            _ => {
+                current_chapter = Some(unit.id.clone());
+                (1, None)
+            }
+        };
+        if level != unit.level || parent_id != unit.parent_id {
+            conn.execute(
+                "UPDATE reading_units SET level = ?1, parent_id = ?2 WHERE id = ?3",
+                rusqlite::params![level, parent_id, unit.id],
+            )
+            .map_err(|e| format!("更新阅读单元层级失败: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use tempfile::TempDir;
+
+    /// 插入一个 Reading Unit（块范围 [start, end]，片段 ID 为 seg-1-{start}）
+    fn insert_unit(conn: &Connection, id: &str, level: u32, parent_id: Option<&str>, start: i32, end: i32) {
+        conn.execute(
+            "INSERT INTO reading_units (id, book_id, title, level, parent_id, segment_ids,
+                start_block_id, end_block_id, source, content_type, summary_text, created_at)
+             VALUES (?1, 1, ?2, ?3, ?4, ?5, ?6, ?7, 'heuristic', 'body', '旧摘要', 0)",
+            rusqlite::params![
+                id,
+                format!("标题 {}", id),
+                level,
+                parent_id,
+                format!(r#"["seg-1-{}"]"#, start),
+                start,
+                end
+            ],
+        )
+        .unwrap();
+    }
+
+    fn setup() -> (TempDir, Connection) {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute("INSERT INTO books (title, file_path) VALUES ('书', '/a')", [])
+            .unwrap();
+        (temp_dir, conn)
+    }
+
+    #[test]
+    fn test_merge_adjacent_units() {
+        let (_temp_dir, mut conn) = setup();
+        insert_unit(&conn, "ru-1-1", 1, None, 1, 1);
+        insert_unit(&conn, "ru-1-2", 1, None, 2, 2);
+        insert_unit(&conn, "ru-1-3", 2, Some("ru-1-2"), 3, 3);
+        insert_unit(&conn, "ru-1-4", 1, None, 4, 4);
+
+        let merged = merge_units(&mut conn, &["ru-1-1".to_string(), "ru-1-2".to_string()]).unwrap();
+        assert_eq!(merged.id, "ru-1-1");
+        assert_eq!(merged.title, "标题 ru-1-1");
+        assert_eq!(merged.segment_ids, vec!["seg-1-1", "seg-1-2"]);
+        assert_eq!((merged.start_block_id, merged.end_block_id), (1, 2));
+
+        let units = load_units(&conn, 1, None).unwrap();
+        let ids: Vec<&str> = units.iter().map(|u| u.id.as_str()).collect();
+        assert_eq!(ids, vec!["ru-1-1", "ru-1-3", "ru-1-4"]);
+        // 被删除单元的子节改挂到合并后的单元
+        assert_eq!(units[1].parent_id.as_deref(), Some("ru-1-1"));
+
+        let summary: Option<String> = conn
+            .query_row("SELECT summary_text FROM reading_units WHERE id = 'ru-1-1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(summary, None);
+
+        // 不相邻的单元不能合并
+        assert!(merge_units(&mut conn, &["ru-1-1".to_string(), "ru-1-4".to_string()]).is_err());
+        assert!(merge_units(&mut conn, &["ru-1-1".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_rename_unit() {
+        let (_temp_dir, conn) = setup();
+        insert_unit(&conn, "ru-1-1", 1, None, 1, 1);
+
+        let renamed = rename_unit(&conn, "ru-1-1", "  序章  ").unwrap();
+        assert_eq!(renamed.title, "序章");
+        assert_eq!(load_units(&conn, 1, None).unwrap()[0].title, "序章");
+
+        assert!(rename_unit(&conn, "ru-1-1", "   ").is_err());
+        assert!(rename_unit(&conn, "ru-1-9", "第九章").is_err());
+        assert_eq!(load_units(&conn, 1, None).unwrap()[0].title, "序章");
+    }
+
+    #[test]
+    fn test_split_at_block_boundary() {
+        let (_temp_dir, mut conn) = setup();
+        // 两章合在一个单元里：块 1-2 属于章节 1，块 3-4 属于章节 3
+        conn.execute_batch(
+            "INSERT INTO chapters (id, book_id, title, chapter_index) VALUES (1, 1, '一', 0), (3, 1, '二', 1);
+             INSERT INTO blocks (id, chapter_id, block_index, block_type, runs_json) VALUES
+                (1, 1, 0, 'paragraph', '[]'), (2, 1, 1, 'paragraph', '[]'),
+                (3, 3, 0, 'paragraph', '[]'), (4, 3, 1, 'paragraph', '[]');",
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO reading_units (id, book_id, title, level, segment_ids, start_block_id, end_block_id, source, created_at)
+             VALUES ('ru-1-1', 1, '第一章', 1, '[\"seg-1-1\",\"seg-1-3\"]', 1, 4, 'heuristic', 0)",
+            [],
+        )
+        .unwrap();
+        insert_unit(&conn, "ru-1-5", 2, Some("ru-1-1"), 5, 5);
+
+        let (head, tail) = split_unit(&mut conn, "ru-1-1", 3).unwrap();
+        assert_eq!((head.start_block_id, head.end_block_id), (1, 2));
+        assert_eq!(head.segment_ids, vec!["seg-1-1"]);
+        assert_eq!(tail.id, "ru-1-3");
+        assert_eq!(tail.title, "第一章（续）");
+        assert_eq!((tail.start_block_id, tail.end_block_id), (3, 4));
+        assert_eq!(tail.segment_ids, vec!["seg-1-3"]);
+        assert_eq!((tail.level, tail.parent_id.as_deref()), (1, None));
+
+        // 拆分点之后的节归属于新单元
+        let units = load_units(&conn, 1, None).unwrap();
+        assert_eq!(units[2].parent_id.as_deref(), Some("ru-1-3"));
+
+        // 拆分点必须在单元内部
+        assert!(split_unit(&mut conn, "ru-1-1", 1).is_err());
+        assert!(split_unit(&mut conn, "ru-1-1", 5).is_err());
+    }
+}
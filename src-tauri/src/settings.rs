@@ -0,0 +1,141 @@
+use crate::book_stats::DEFAULT_WORDS_PER_MINUTE;
+use crate::error::AppError;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::str::FromStr;
+
+// 应用设置：settings 表中的键值对，已知设置项提供带默认值的类型化读取
+
+/// 导入队列的最大并发任务数
+pub const IMPORT_CONCURRENCY: &str = "import_concurrency";
+/// 阅读速度（字/分钟）
+pub const WORDS_PER_MINUTE: &str = "words_per_minute";
+/// 界面主题（light/dark）
+pub const THEME: &str = "theme";
+/// 是否记录 AI 请求调试日志（true/false）
+pub const AI_DEBUG_LOG: &str = "ai_debug_log";
+
+/// 默认导入并发数
+pub const DEFAULT_IMPORT_CONCURRENCY: usize = 3;
+/// 默认主题
+pub const DEFAULT_THEME: &str = "light";
+
+/// 已知设置项的默认值
+///
+/// # 返回
+/// 未知设置项返回 None
+pub fn default_value(key: &str) -> Option<String> {
+    match key {
+        IMPORT_CONCURRENCY => Some(DEFAULT_IMPORT_CONCURRENCY.to_string()),
+        WORDS_PER_MINUTE => Some(DEFAULT_WORDS_PER_MINUTE.to_string()),
+        THEME => Some(DEFAULT_THEME.to_string()),
+        AI_DEBUG_LOG => Some("false".to_string()),
+        _ => None,
+    }
+}
+
+/// 检查已知设置项的取值是否合法（未知设置项不做检查）
+fn validate(key: &str, value: &str) -> Result<(), AppError> {
+    let valid = match key {
+        IMPORT_CONCURRENCY => value.parse::<usize>().is_ok_and(|n| n > 0),
+        WORDS_PER_MINUTE => value.parse::<u32>().is_ok_and(|n| n > 0),
+        THEME => matches!(value, "light" | "dark"),
+        AI_DEBUG_LOG => value.parse::<bool>().is_ok(),
+        _ => true,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::Validation(format!("设置项 {} 的值无效: {}", key, value)))
+    }
+}
+
+/// 读取设置项的原始值
+///
+/// # 返回
+/// 未设置时返回 None
+pub fn get_setting(conn: &Connection, key: &str) -> Result<Option<String>, AppError> {
+    Ok(conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0))
+        .optional()?)
+}
+
+/// 保存设置项（已存在时覆盖）
+pub fn set_setting(conn: &Connection, key: &str, value: &str) -> Result<(), AppError> {
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(AppError::Validation("设置项名称不能为空".to_string()));
+    }
+    validate(key, value)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+/// 读取设置项并解析为指定类型，未设置、读取失败或无法解析时返回默认值
+fn get_parsed<T: FromStr>(conn: &Connection, key: &str, default: T) -> T {
+    get_setting(conn, key)
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// 导入队列的最大并发任务数
+pub fn import_concurrency(conn: &Connection) -> usize {
+    get_parsed(conn, IMPORT_CONCURRENCY, DEFAULT_IMPORT_CONCURRENCY).max(1)
+}
+
+/// 阅读速度（字/分钟）
+pub fn words_per_minute(conn: &Connection) -> u32 {
+    get_parsed(conn, WORDS_PER_MINUTE, DEFAULT_WORDS_PER_MINUTE).max(1)
+}
+
+/// 是否记录 AI 请求调试日志
+pub fn ai_debug_log(conn: &Connection) -> bool {
+    get_parsed(conn, AI_DEBUG_LOG, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_set_and_get_setting() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = crate::db::init_db(temp_dir.path().join("test.db")).unwrap();
+
+        assert_eq!(get_setting(&conn, "reader.font").unwrap(), None);
+        set_setting(&conn, "reader.font", "serif").unwrap();
+        assert_eq!(get_setting(&conn, "reader.font").unwrap().as_deref(), Some("serif"));
+        set_setting(&conn, "reader.font", "sans").unwrap();
+        assert_eq!(get_setting(&conn, "reader.font").unwrap().as_deref(), Some("sans"));
+
+        set_setting(&conn, WORDS_PER_MINUTE, "450").unwrap();
+        assert_eq!(words_per_minute(&conn), 450);
+        set_setting(&conn, THEME, "dark").unwrap();
+        assert_eq!(get_setting(&conn, THEME).unwrap().as_deref(), Some("dark"));
+    }
+
+    #[test]
+    fn test_known_settings_fall_back_to_defaults() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = crate::db::init_db(temp_dir.path().join("test.db")).unwrap();
+
+        assert_eq!(import_concurrency(&conn), DEFAULT_IMPORT_CONCURRENCY);
+        assert_eq!(words_per_minute(&conn), DEFAULT_WORDS_PER_MINUTE);
+        assert_eq!(default_value(THEME).as_deref(), Some(DEFAULT_THEME));
+        assert_eq!(default_value("unknown"), None);
+        assert!(!ai_debug_log(&conn));
+
+        // 非法取值被拒绝；库中残留的无法解析的值同样回退到默认值
+        assert!(matches!(set_setting(&conn, IMPORT_CONCURRENCY, "0"), Err(AppError::Validation(_))));
+        assert!(set_setting(&conn, THEME, "purple").is_err());
+        assert!(set_setting(&conn, AI_DEBUG_LOG, "yes").is_err());
+        conn.execute("INSERT INTO settings (key, value) VALUES (?1, 'fast')", params![WORDS_PER_MINUTE])
+            .unwrap();
+        assert_eq!(words_per_minute(&conn), DEFAULT_WORDS_PER_MINUTE);
+    }
+}
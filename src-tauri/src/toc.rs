@@ -0,0 +1,148 @@
+use crate::reading_unit::unit_editor;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashMap;
+
+// 目录模块：由已保存的 Reading Unit 组装嵌套目录，没有 Reading Unit 时退回章节列表
+
+/// 目录节点
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TocNode {
+    pub title: String,
+    /// 对应的 Reading Unit（按章节生成的目录为 None）
+    pub unit_id: Option<String>,
+    /// 跳转用的章节序号
+    pub chapter_index: Option<i32>,
+    pub children: Vec<TocNode>,
+}
+
+/// 获取书籍的嵌套目录
+///
+/// 有 Reading Unit 时按 `parent_id` 组装两级目录，否则返回扁平的章节列表
+pub fn get_toc(conn: &Connection, book_id: i32) -> Result<Vec<TocNode>, String> {
+    let units = unit_editor::load_units(conn, book_id, None)?;
+    // 只读取标题和序号，加密书籍无需密钥
+    let mut stmt = conn
+        .prepare("SELECT id, title, chapter_index FROM chapters WHERE book_id = ?1 ORDER BY chapter_index")
+        .map_err(|e| e.to_string())?;
+    let chapters = stmt
+        .query_map([book_id], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, i32>(2)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    if units.is_empty() {
+        return Ok(chapters
+            .into_iter()
+            .map(|(_, title, chapter_index)| TocNode {
+                title,
+                unit_id: None,
+                chapter_index: Some(chapter_index),
+                children: Vec::new(),
+            })
+            .collect());
+    }
+
+    // 片段 ID 为 seg-{book_id}-{chapter_id}，取单元的第一个片段定位章节
+    let chapter_indices: HashMap<i32, i32> = chapters
+        .iter()
+        .map(|(id, _, chapter_index)| (*id, *chapter_index))
+        .collect();
+    let chapter_index_of = |segment_ids: &[String]| {
+        segment_ids
+            .first()
+            .and_then(|id| id.rsplit('-').next()?.parse::<i32>().ok())
+            .and_then(|chapter_id| chapter_indices.get(&chapter_id).copied())
+    };
+
+    let mut roots: Vec<TocNode> = Vec::new();
+    let mut root_positions: HashMap<String, usize> = HashMap::new();
+    for unit in units {
+        let node = TocNode {
+            chapter_index: chapter_index_of(&unit.segment_ids),
+            title: unit.title,
+            unit_id: Some(unit.id.clone()),
+            children: Vec::new(),
+        };
+
+        // 父节点缺失的节当作顶层节点
+        match unit.parent_id.as_ref().and_then(|parent| root_positions.get(parent)) {
+            Some(&position) => roots[position].children.push(node),
+            None => {
+                root_positions.insert(unit.id, roots.len());
+                roots.push(node);
+            }
+        }
+    }
+
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, Connection) {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = db::init_db(temp_dir.path().join("test.db")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO books (title, file_path) VALUES ('书', '/a');
+             INSERT INTO chapters (id, book_id, title, chapter_index) VALUES
+                (1, 1, '第一章', 0), (2, 1, '1.1', 1), (3, 1, '1.2', 2), (4, 1, '第二章', 3);",
+        )
+        .unwrap();
+        (temp_dir, conn)
+    }
+
+    fn node(title: &str, unit_id: Option<&str>, chapter_index: i32, children: Vec<TocNode>) -> TocNode {
+        TocNode {
+            title: title.to_string(),
+            unit_id: unit_id.map(str::to_string),
+            chapter_index: Some(chapter_index),
+            children,
+        }
+    }
+
+    #[test]
+    fn test_two_level_tree_from_units() {
+        let (_temp_dir, conn) = setup();
+        conn.execute_batch(
+            r#"INSERT INTO reading_units (id, book_id, title, level, parent_id, segment_ids, start_block_id, end_block_id, source, created_at) VALUES
+                ('ru-1-1', 1, '第一章', 1, NULL, '["seg-1-1"]', 1, 1, 'toc', 0),
+                ('ru-1-2', 1, '1.1 起源', 2, 'ru-1-1', '["seg-1-2"]', 2, 2, 'toc', 0),
+                ('ru-1-3', 1, '1.2 发展', 2, 'ru-1-1', '["seg-1-3"]', 3, 3, 'toc', 0),
+                ('ru-1-4', 1, '第二章', 1, NULL, '["seg-1-4"]', 4, 4, 'toc', 0);"#,
+        )
+        .unwrap();
+
+        let toc = get_toc(&conn, 1).unwrap();
+        assert_eq!(
+            toc,
+            vec![
+                node(
+                    "第一章",
+                    Some("ru-1-1"),
+                    0,
+                    vec![
+                        node("1.1 起源", Some("ru-1-2"), 1, vec![]),
+                        node("1.2 发展", Some("ru-1-3"), 2, vec![]),
+                    ]
+                ),
+                node("第二章", Some("ru-1-4"), 3, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_falls_back_to_flat_chapters() {
+        let (_temp_dir, conn) = setup();
+
+        let toc = get_toc(&conn, 1).unwrap();
+        let titles: Vec<&str> = toc.iter().map(|node| node.title.as_str()).collect();
+        assert_eq!(titles, vec!["第一章", "1.1", "1.2", "第二章"]);
+        assert!(toc.iter().all(|node| node.unit_id.is_none() && node.children.is_empty()));
+        assert_eq!(toc[3].chapter_index, Some(3));
+    }
+}
@@ -0,0 +1,216 @@
+use crate::parser::ParserRouter;
+use std::path::{Path, PathBuf};
+
+// 从链接导入：下载公开的电子书文件到本地，再交给异步导入流程
+
+/// 允许下载的最大文件大小（字节）
+pub const MAX_DOWNLOAD_SIZE: u64 = 100 * 1024 * 1024;
+/// 下载超时时间（秒）
+pub const DOWNLOAD_TIMEOUT_SECS: u64 = 120;
+
+/// 根据 Content-Type 推断文件扩展名
+fn extension_for_content_type(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    match mime.as_str() {
+        "application/epub+zip" => Some("epub"),
+        "application/pdf" => Some("pdf"),
+        "text/plain" => Some("txt"),
+        "text/markdown" | "text/x-markdown" => Some("md"),
+        _ => None,
+    }
+}
+
+/// 取链接路径的最后一段作为文件名（去掉查询参数和锚点）
+fn file_name_from_url(url: &reqwest::Url) -> Option<String> {
+    url.path_segments()?
+        .rfind(|segment| !segment.is_empty())
+        .map(|segment| segment.replace(['/', '\\', ':'], "_"))
+}
+
+/// 确定保存文件名：优先使用链接中受支持的扩展名，否则按 Content-Type 补上扩展名
+fn resolve_file_name(url: &reqwest::Url, content_type: Option<&str>) -> Result<String, String> {
+    let router = ParserRouter::new();
+    let name = file_name_from_url(url).unwrap_or_else(|| "download".to_string());
+
+    let url_extension = Path::new(&name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .filter(|ext| router.supports(ext));
+    if url_extension.is_some() {
+        return Ok(name);
+    }
+
+    let extension = content_type
+        .and_then(extension_for_content_type)
+        .ok_or_else(|| format!("不支持的文件类型: {}", content_type.unwrap_or("未知")))?;
+    let stem = Path::new(&name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("download");
+    Ok(format!("{}.{}", stem, extension))
+}
+
+/// 在目录中选择不重名的文件路径
+fn unique_path(dir: &Path, file_name: &str) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+
+    let path = Path::new(file_name);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("download");
+    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or_default();
+    (1..)
+        .map(|n| dir.join(format!("{}-{}.{}", stem, n, extension)))
+        .find(|path| !path.exists())
+        .expect("无限序列总能找到未使用的文件名")
+}
+
+/// 下载电子书文件
+///
+/// # 参数
+/// - `url`: http/https 链接
+/// - `dest_dir`: 保存目录（不存在时创建）
+/// - `max_size`: 允许的最大字节数，超过时中止下载，不写入文件
+/// - `timeout_secs`: 整个下载的超时时间
+///
+/// # 返回
+/// 保存的文件路径
+pub async fn download_book(url: &str, dest_dir: &Path, max_size: u64, timeout_secs: u64) -> Result<PathBuf, String> {
+    let url = reqwest::Url::parse(url.trim()).map_err(|e| format!("链接无效: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("只支持 http 和 https 链接".to_string());
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(timeout_secs))
+        .build()
+        .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
+    let mut response = client.get(url.clone()).send().await.map_err(|e| {
+        if e.is_timeout() {
+            format!("下载超时（超过 {} 秒）", timeout_secs)
+        } else {
+            format!("无法连接: {}", e)
+        }
+    })?;
+
+    if !response.status().is_success() {
+        return Err(format!("服务器返回错误状态 {}", response.status()));
+    }
+    if response.content_length().is_some_and(|length| length > max_size) {
+        return Err(format!("文件超过 {} MB 的大小上限", max_size / 1024 / 1024));
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let file_name = resolve_file_name(response.url(), content_type.as_deref())?;
+
+    std::fs::create_dir_all(dest_dir).map_err(|e| format!("创建下载目录失败: {}", e))?;
+    let path = unique_path(dest_dir, &file_name);
+
+    // 按块接收并检查大小，Content-Length 缺失或不准确时同样生效
+    let mut bytes = Vec::new();
+    loop {
+        let chunk = response.chunk().await.map_err(|e| {
+            if e.is_timeout() {
+                format!("下载超时（超过 {} 秒）", timeout_secs)
+            } else {
+                format!("下载中断: {}", e)
+            }
+        })?;
+        let Some(chunk) = chunk else { break };
+        if bytes.len() as u64 + chunk.len() as u64 > max_size {
+            return Err(format!("文件超过 {} MB 的大小上限", max_size / 1024 / 1024));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    std::fs::write(&path, &bytes).map_err(|e| format!("保存文件失败: {}", e))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use tempfile::TempDir;
+
+    // 模拟服务器：读完请求头后返回一次固定响应
+    fn serve_once(content_type: &'static str, body: Vec<u8>) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                let n = stream.read(&mut buffer).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..n]);
+            }
+            let header = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                content_type,
+                body.len()
+            );
+            stream.write_all(header.as_bytes()).unwrap();
+            stream.write_all(&body).unwrap();
+        });
+        format!("http://{}", address)
+    }
+
+    fn sample_epub(dir: &Path) -> Vec<u8> {
+        let path = dir.join("source.epub");
+        crate::parser::test_fixtures::write_epub(
+            &path,
+            r#"<dc:title>公版书</dc:title><dc:identifier id="bookid">pd</dc:identifier>"#,
+            &[("第一章", "<p>正文</p>")],
+        );
+        std::fs::read(path).unwrap()
+    }
+
+    #[test]
+    fn test_download_epub_from_mock_server() {
+        let temp_dir = TempDir::new().unwrap();
+        let epub = sample_epub(temp_dir.path());
+        let base_url = serve_once("application/epub+zip", epub.clone());
+        let downloads = temp_dir.path().join("downloads");
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        // 链接没有扩展名时按 Content-Type 补全
+        let path = rt
+            .block_on(download_book(&format!("{}/books/sample?download=1", base_url), &downloads, MAX_DOWNLOAD_SIZE, 5))
+            .unwrap();
+        assert_eq!(path, downloads.join("sample.epub"));
+        assert_eq!(std::fs::read(&path).unwrap(), epub);
+
+        let conn = crate::db::init_db(temp_dir.path().join("test.db")).unwrap();
+        let result = ParserRouter::new().route(&path).unwrap().parse(&path, 1, &conn).unwrap();
+        assert_eq!(result.chapters[0].title, "第一章");
+    }
+
+    #[test]
+    fn test_download_rejects_oversized_and_unsupported_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        let base_url = serve_once("application/epub+zip", vec![0u8; 4096]);
+        let error = rt
+            .block_on(download_book(&format!("{}/big.epub", base_url), temp_dir.path(), 1024, 5))
+            .unwrap_err();
+        assert!(error.contains("大小上限"), "{}", error);
+
+        let base_url = serve_once("text/html", b"<html></html>".to_vec());
+        let error = rt
+            .block_on(download_book(&format!("{}/page", base_url), temp_dir.path(), MAX_DOWNLOAD_SIZE, 5))
+            .unwrap_err();
+        assert!(error.contains("不支持的文件类型"), "{}", error);
+
+        assert!(rt.block_on(download_book("ftp://example.com/a.epub", temp_dir.path(), 1024, 5)).is_err());
+    }
+}
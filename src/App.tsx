@@ -10,12 +10,14 @@ import ImmersiveReader from "./components/immersive-reader/ImmersiveReader";
 import { ThemeMode } from "./components/immersive-reader/types";
 // 导入 Debug 面板
 import ReadingUnitDebugger from "./components/debug/ReadingUnitDebugger";
+import { handleError } from "./utils/errorHandler";
 
 interface Book {
   id: number;
   title: string;
   author: string;
-  cover_image: string | null;
+  has_cover: boolean;
+  cover_image?: string | null; // 通过 get_book_cover 按需加载
 }
 
 interface Chapter {
@@ -108,7 +110,7 @@ function App() {
       const msg = await invoke("upload_epub_file");
       alert(msg);
     } catch (error) {
-      alert(`Upload Failed: ${error}`);
+      alert(`Upload Failed: ${handleError(error)}`);
     } finally {
       setLoading(false);
     }
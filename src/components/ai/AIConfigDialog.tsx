@@ -2,6 +2,7 @@ import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { X, Eye, EyeOff, Save } from "lucide-react";
 import { useTranslation } from "react-i18next";
+import { handleError } from "../../utils/errorHandler";
 
 interface AIConfig {
   id: number;
@@ -12,6 +13,10 @@ interface AIConfig {
   temperature: number;
   max_tokens: number;
   is_active: boolean;
+  timeout_secs: number;
+  proxy_url: string | null;
+  deployment: string | null;
+  api_version: string | null;
 }
 
 interface AIConfigDialogProps {
@@ -32,6 +37,7 @@ const DEFAULT_MODELS: Record<string, string> = {
   "openai-cn": "gpt-3.5-turbo",
   anthropic: "claude-3-sonnet-20240229",
   google: "gemini-pro",
+  "azure-openai": "gpt-4o",
 };
 
 const DEFAULT_BASE_URLS: Record<string, string> = {
@@ -51,6 +57,8 @@ export default function AIConfigDialog({
   const [editingConfig, setEditingConfig] = useState<AIConfig | null>(null);
   const [showApiKey, setShowApiKey] = useState<Record<number, boolean>>({});
   const [loading, setLoading] = useState(false);
+  const [testing, setTesting] = useState(false);
+  const [availableModels, setAvailableModels] = useState<string[]>([]);
 
   const getPlatformName = (platform: string): string => {
     const names: Record<string, string> = {
@@ -58,6 +66,7 @@ export default function AIConfigDialog({
       "openai-cn": t('ai.openaiCn'),
       anthropic: "Anthropic (Claude)",
       google: "Google (Gemini)",
+      "azure-openai": "Azure OpenAI",
     };
     return names[platform] || platform;
   };
@@ -80,6 +89,22 @@ export default function AIConfigDialog({
 
   const handleEdit = (config: AIConfig) => {
     setEditingConfig({ ...config });
+    loadModels(config);
+  };
+
+  const loadModels = async (config: AIConfig) => {
+    try {
+      const models = await invoke<string[]>("list_models", {
+        platform: config.platform,
+        apiKey: config.api_key,
+        baseUrl: config.base_url,
+        proxyUrl: config.proxy_url,
+      });
+      setAvailableModels(models);
+    } catch (error) {
+      console.error(error);
+      setAvailableModels([]);
+    }
   };
 
   const handleSave = async () => {
@@ -98,12 +123,26 @@ export default function AIConfigDialog({
       onSuccess();
     } catch (error) {
       console.error(t('ai.saveConfigFailed'), error);
-      alert(`${t('ai.saveConfigFailed')}: ${error}`);
+      alert(`${t('ai.saveConfigFailed')}: ${handleError(error)}`);
     } finally {
       setLoading(false);
     }
   };
 
+  const handleTest = async () => {
+    if (!editingConfig) return;
+
+    setTesting(true);
+    try {
+      await invoke("test_ai_config", { configId: editingConfig.id, draft: editingConfig });
+      alert(t('ai.testSuccess'));
+    } catch (error) {
+      alert(`${t('ai.testFailed')}: ${handleError(error)}`);
+    } finally {
+      setTesting(false);
+    }
+  };
+
   const handleCancel = () => {
     setEditingConfig(null);
   };
@@ -206,12 +245,70 @@ export default function AIConfigDialog({
                       />
                     </div>
 
+                    {config.platform === "azure-openai" && (
+                      <div className="grid grid-cols-2 gap-3">
+                        <div>
+                          <label className="block text-sm font-medium text-gray-700 mb-1">
+                            {t('ai.deployment')}
+                          </label>
+                          <input
+                            type="text"
+                            value={editingConfig.deployment || ""}
+                            onChange={(e) =>
+                              setEditingConfig({
+                                ...editingConfig,
+                                deployment: e.target.value || null,
+                              })
+                            }
+                            placeholder={editingConfig.model}
+                            className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-indigo-500"
+                          />
+                        </div>
+                        <div>
+                          <label className="block text-sm font-medium text-gray-700 mb-1">
+                            API Version
+                          </label>
+                          <input
+                            type="text"
+                            value={editingConfig.api_version || ""}
+                            onChange={(e) =>
+                              setEditingConfig({
+                                ...editingConfig,
+                                api_version: e.target.value || null,
+                              })
+                            }
+                            placeholder="2024-06-01"
+                            className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-indigo-500"
+                          />
+                        </div>
+                      </div>
+                    )}
+
+                    <div>
+                      <label className="block text-sm font-medium text-gray-700 mb-1">
+                        {t('ai.proxyUrl')}
+                      </label>
+                      <input
+                        type="text"
+                        value={editingConfig.proxy_url || ""}
+                        onChange={(e) =>
+                          setEditingConfig({
+                            ...editingConfig,
+                            proxy_url: e.target.value || null,
+                          })
+                        }
+                        placeholder="http://127.0.0.1:7890"
+                        className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-indigo-500"
+                      />
+                    </div>
+
                     <div>
                       <label className="block text-sm font-medium text-gray-700 mb-1">
                         {t('ai.model')}
                       </label>
                       <input
                         type="text"
+                        list={`models-${config.id}`}
                         value={editingConfig.model}
                         onChange={(e) =>
                           setEditingConfig({
@@ -222,6 +319,11 @@ export default function AIConfigDialog({
                         placeholder={DEFAULT_MODELS[config.platform] || "Model"}
                         className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-indigo-500"
                       />
+                      <datalist id={`models-${config.id}`}>
+                        {availableModels.map((model) => (
+                          <option key={model} value={model} />
+                        ))}
+                      </datalist>
                     </div>
 
                     <div className="grid grid-cols-2 gap-3">
@@ -262,6 +364,24 @@ export default function AIConfigDialog({
                           className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-indigo-500"
                         />
                       </div>
+                      <div>
+                        <label className="block text-sm font-medium text-gray-700 mb-1">
+                          {t('ai.timeoutSecs')}
+                        </label>
+                        <input
+                          type="number"
+                          min="5"
+                          max="600"
+                          value={editingConfig.timeout_secs}
+                          onChange={(e) =>
+                            setEditingConfig({
+                              ...editingConfig,
+                              timeout_secs: parseInt(e.target.value) || 60,
+                            })
+                          }
+                          className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-indigo-500"
+                        />
+                      </div>
                     </div>
 
                     <div className="flex items-center">
@@ -294,6 +414,13 @@ export default function AIConfigDialog({
                         <Save className="w-4 h-4" />
                         {loading ? t('ai.saving') : t('ai.save')}
                       </button>
+                      <button
+                        onClick={handleTest}
+                        disabled={testing}
+                        className="flex-1 px-4 py-2 text-indigo-700 bg-indigo-50 hover:bg-indigo-100 rounded-lg transition-colors disabled:opacity-50"
+                      >
+                        {testing ? t('ai.testing') : t('ai.testConnection')}
+                      </button>
                       <button
                         onClick={handleCancel}
                         className="flex-1 px-4 py-2 text-gray-700 bg-gray-100 hover:bg-gray-200 rounded-lg transition-colors"
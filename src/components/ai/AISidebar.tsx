@@ -3,6 +3,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { MessageSquare, X, Send, Loader2, Sparkles } from 'lucide-react';
 import { useTranslation } from 'react-i18next';
 import { ThemeMode } from '../immersive-reader/types';
+import { handleError } from '../../utils/errorHandler';
 
 interface AISidebarProps {
   isOpen: boolean;
@@ -74,7 +75,7 @@ const AISidebar = ({
       setExplainResult(result);
     } catch (error) {
       console.error(t('ai.explainFailed'), error);
-      setExplainResult(`${t('ai.explainError')}: ${error instanceof Error ? error.message : String(error)}`);
+      setExplainResult(`${t('ai.explainError')}: ${handleError(error)}`);
     } finally {
       setIsLoading(false);
     }
@@ -116,7 +117,7 @@ const AISidebar = ({
       console.error(t('ai.chatFailed'), error);
       const errorMessage: ChatMessage = {
         role: 'assistant',
-        content: `${t('ai.chatError')}: ${error instanceof Error ? error.message : String(error)}`,
+        content: `${t('ai.chatError')}: ${handleError(error)}`,
       };
       setChatMessages(prev => [...prev, errorMessage]);
     } finally {
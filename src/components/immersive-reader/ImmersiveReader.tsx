@@ -3,8 +3,9 @@ import { useTranslation } from 'react-i18next';
 import { BookOpen, ArrowLeft, Plus, BarChart3, Settings } from 'lucide-react';
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import { getCurrentWebview } from "@tauri-apps/api/webview";
 import { Book, ViewMode, ThemeMode, Chapter } from './types';
-import { Note, Category, Tag } from '../../types/notes';
+import { Note, Category, Tag, Page } from '../../types/notes';
 import BookCard from './BookCard';
 import ChapterList from './ChapterList';
 import ReaderContent, { ReaderContentHandle } from './ReaderContent';
@@ -15,13 +16,18 @@ import AnalyticsView from '../notes/AnalyticsView';
 import { ToastContainer, useToastManager } from '../common/Toast';
 import GlobalSettingsDialog from '../common/GlobalSettingsDialog';
 import FirstTimeHint from '../common/FirstTimeHint';
+import { handleError } from '../../utils/errorHandler';
 
 // 后端返回的书籍类型
 interface BackendBook {
   id: number;
   title: string;
   author: string;
-  cover_image: string | null;
+  has_cover: boolean; // 封面通过 get_book_cover 按需加载
+  parse_status: string | null; // pending / parsing / completed / failed: ...
+  parse_quality: string | null;
+  total_blocks: number;
+  parse_warnings: string[]; // 解析中发现的问题（解码错误、缺失的图片等）
 }
 
 // 后端返回的章节信息类型
@@ -29,6 +35,7 @@ interface BackendChapterInfo {
   title: string;
   id: string;
   heading_level?: number | null;
+  char_count?: number | null; // 章节字符数（不含空白）
 }
 
 interface ImmersiveReaderProps {
@@ -72,7 +79,7 @@ const ImmersiveReader = ({ theme }: ImmersiveReaderProps) => {
 
   // 将后端书籍数据转换为前端格式
   const convertBackendBookToBook = useCallback((backendBook: BackendBook): Book => {
-    // 根据 cover_image 生成 coverColor，始终提供一个后备背景色
+    // 生成 coverColor，始终提供一个后备背景色
     const defaultColors = [
       'bg-gradient-to-br from-slate-700 to-slate-900',
       'bg-gradient-to-br from-blue-700 to-blue-900',
@@ -89,7 +96,7 @@ const ImmersiveReader = ({ theme }: ImmersiveReaderProps) => {
       title: backendBook.title,
       author: backendBook.author,
       coverColor,
-      coverImage: backendBook.cover_image,
+      coverImage: null, // 封面在 loadBooks 中按需加载
       progress: 0, // 默认进度为 0，后续可以从本地存储读取
       chapters: [], // 章节数据懒加载
     };
@@ -98,7 +105,7 @@ const ImmersiveReader = ({ theme }: ImmersiveReaderProps) => {
   // 加载书籍列表
   const loadBooks = useCallback(async () => {
     try {
-      const backendBooks = await invoke<BackendBook[]>("get_books");
+      const { items: backendBooks } = await invoke<Page<BackendBook>>("get_books");
       console.log('📚 加载书籍列表:', backendBooks.length, '本书');
 
       // 为每本书加载阅读进度并计算百分比
@@ -106,6 +113,15 @@ const ImmersiveReader = ({ theme }: ImmersiveReaderProps) => {
         backendBooks.map(async (backendBook) => {
           const book = convertBackendBookToBook(backendBook);
 
+          // 按需加载封面缩略图
+          if (backendBook.has_cover) {
+            try {
+              book.coverImage = await invoke<string | null>("get_book_cover", { id: backendBook.id });
+            } catch (error) {
+              console.error(`❌ 加载书籍 "${backendBook.title}" 封面失败:`, error);
+            }
+          }
+
           try {
             // 获取章节列表以计算总章节数
             const chapters = await invoke<BackendChapterInfo[]>("get_book_details", {
@@ -184,6 +200,53 @@ const ImmersiveReader = ({ theme }: ImmersiveReaderProps) => {
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, []);
 
+  // 拖放文件导入（支持一次拖入多个文件）
+  useEffect(() => {
+    const unlistenDrop = getCurrentWebview().onDragDropEvent(async (event) => {
+      if (event.payload.type !== 'drop' || event.payload.paths.length === 0) return;
+
+      try {
+        const result = await invoke<{ book_ids: number[]; errors: { path: string; error: string }[] }>(
+          "import_files",
+          { paths: event.payload.paths }
+        );
+        if (result.book_ids.length > 0) {
+          showSuccess(t('nav.processing'));
+        }
+        result.errors.forEach(({ path, error }) => {
+          showError(`${t('errors.uploadFailed')}: ${path.split(/[\\/]/).pop()} - ${error}`);
+        });
+      } catch (error) {
+        showError(`${t('errors.uploadFailed')}: ${handleError(error)}`);
+      }
+    });
+
+    return () => {
+      unlistenDrop.then((unlisten) => unlisten());
+    };
+  }, [showSuccess, showError, t]);
+
+  // 注入当前书籍的样式表（选择器已由后端限定在 .reader-content 内）
+  const activeBookId = activeBook?.id;
+  useEffect(() => {
+    if (activeBookId === undefined) return;
+
+    const styleElement = document.createElement('style');
+    styleElement.id = 'book-styles';
+    document.head.appendChild(styleElement);
+    invoke<string | null>('get_book_styles', { bookId: activeBookId })
+      .then((css) => {
+        styleElement.textContent = css ?? '';
+      })
+      .catch((error) => {
+        console.error('加载书籍样式失败:', error);
+      });
+
+    return () => {
+      styleElement.remove();
+    };
+  }, [activeBookId]);
+
   // F11 键监听 - 切换阅读模式
   useEffect(() => {
     const handleKeyDown = (e: KeyboardEvent) => {
@@ -217,7 +280,7 @@ const ImmersiveReader = ({ theme }: ImmersiveReaderProps) => {
       showSuccess(t('nav.processing'));
     } catch (error) {
       console.error("Upload Failed:", error);
-      showError(`${t('errors.uploadFailed')}: ${error}`);
+      showError(`${t('errors.uploadFailed')}: ${handleError(error)}`);
     } finally {
       setLoading(false);
     }
@@ -260,6 +323,11 @@ const ImmersiveReader = ({ theme }: ImmersiveReaderProps) => {
 
   // 打开书籍
   const handleBookClick = useCallback(async (book: Book) => {
+    // 记录打开时间（用于"继续阅读"书架）
+    invoke('mark_book_opened', { bookId: book.id }).catch(error => {
+      console.error('记录打开时间失败:', error);
+    });
+
     // 获取阅读进度
     let savedProgress: { chapter_index: number; scroll_offset: number } | null = null;
     try {
@@ -577,7 +645,7 @@ const ImmersiveReader = ({ theme }: ImmersiveReaderProps) => {
   const loadChapterNotes = useCallback(async () => {
     if (!activeBook) return;
     try {
-      const allNotes = await invoke<Note[]>("get_notes", { categoryId: null, tagId: null });
+      const { items: allNotes } = await invoke<Page<Note>>("get_notes", { categoryId: null, tagId: null });
       const filtered = allNotes.filter(n =>
         n.book_id === activeBook.id && n.chapter_index === activeChapterIndex
       );
@@ -669,8 +737,8 @@ const ImmersiveReader = ({ theme }: ImmersiveReaderProps) => {
     setNotesRefreshKey(prev => prev + 1);
     if (selectedNote) {
       // 重新加载选中的笔记
-      invoke<Note[]>("get_notes", { categoryId: null, tagId: null })
-      .then(notes => {
+      invoke<Page<Note>>("get_notes", { categoryId: null, tagId: null })
+      .then(({ items: notes }) => {
         const updatedNote = notes.find(n => n.id === selectedNote.id);
         if (updatedNote) {
           setSelectedNote(updatedNote);
@@ -63,7 +63,7 @@ export default function NoteDetailPanel({
         id: note.id,
         title: editedTitle,
         content: editedContent,
-        category_id: selectedCategoryId || undefined,
+        category_id: selectedCategoryId,
         tag_ids: selectedTagIds,
       };
 
@@ -121,7 +121,7 @@ export default function NoteDetailPanel({
         id: note.id,
         title: editedTitle,
         content: editedContent,
-        category_id: selectedCategoryId || undefined,
+        category_id: selectedCategoryId,
         tag_ids: selectedTagIds,
       };
 
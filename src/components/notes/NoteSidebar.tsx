@@ -1,6 +1,6 @@
 import { useState, useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { Note, Category, Tag, SearchNotesRequest } from "../../types/notes";
+import { Note, Category, Tag, SearchNotesRequest, Page } from "../../types/notes";
 import { Search, Plus, Filter, X, Trash2, ChevronDown, ChevronUp, ArrowUpDown } from "lucide-react";
 import { useTranslation } from 'react-i18next';
 import { translateCategoryName } from '../../utils/categoryTranslation';
@@ -56,11 +56,11 @@ export default function NoteSidebar({
         const results = await invoke<Note[]>("search_notes", { request });
         setNotes(results);
       } else {
-        const notesData = await invoke<Note[]>("get_notes", {
+        const notesPage = await invoke<Page<Note>>("get_notes", {
           categoryId: selectedCategory,
           tagId: selectedTagIds.length === 1 ? selectedTagIds[0] : null,
         });
-        setNotes(notesData);
+        setNotes(notesPage.items);
       }
     } catch (error) {
       console.error(t('errors.loadFailed'), error);
@@ -3,6 +3,7 @@ import { useTranslation } from "react-i18next";
 import { invoke } from "@tauri-apps/api/core";
 import { Note } from "../../types/notes";
 import { Trash2, RotateCcw, X, Calendar } from "lucide-react";
+import { handleError } from "../../utils/errorHandler";
 
 interface TrashViewProps {
   onRestore?: (id: number) => void;
@@ -41,7 +42,7 @@ export default function TrashView({
       onRestore?.(id);
     } catch (error) {
       console.error(t('notes.restoreNoteFailed'), error);
-      alert(`${t('notes.restoreFailed')}: ${error}`);
+      alert(`${t('notes.restoreFailed')}: ${handleError(error)}`);
     }
   }, [loadTrashNotes, onRestore]);
 
@@ -55,7 +56,7 @@ export default function TrashView({
       onPermanentlyDelete?.(id);
     } catch (error) {
       console.error(t('notes.permanentDeleteFailed'), error);
-      alert(`${t('errors.deleteFailed')}: ${error}`);
+      alert(`${t('errors.deleteFailed')}: ${handleError(error)}`);
     }
   }, [loadTrashNotes, onPermanentlyDelete, t]);
 
@@ -72,7 +73,7 @@ export default function TrashView({
       await loadTrashNotes();
     } catch (error) {
       console.error(t('notes.batchRestoreFailed'), error);
-      alert(`${t('notes.batchRestoreFailed')}: ${error}`);
+      alert(`${t('notes.batchRestoreFailed')}: ${handleError(error)}`);
     }
   }, [selectedIds, loadTrashNotes, t]);
 
@@ -89,7 +90,7 @@ export default function TrashView({
       await loadTrashNotes();
     } catch (error) {
       console.error(t('notes.batchDeleteFailed'), error);
-      alert(`${t('notes.batchDeleteFailed')}: ${error}`);
+      alert(`${t('notes.batchDeleteFailed')}: ${handleError(error)}`);
     }
   }, [selectedIds, loadTrashNotes, t]);
 
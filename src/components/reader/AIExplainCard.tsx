@@ -2,6 +2,7 @@ import { useState, useEffect } from 'react';
 import { X, Loader2, Sparkles } from 'lucide-react';
 import { invoke } from "@tauri-apps/api/core";
 import { useTranslation } from 'react-i18next';
+import { handleError } from "../../utils/errorHandler";
 
 interface AIExplainCardProps {
   selectedText: string;
@@ -40,7 +41,7 @@ export default function AIExplainCard({
         setResult(explanation);
       } catch (err) {
         console.error(t('ai.explainFailed'), err);
-        setError(err instanceof Error ? err.message : String(err));
+        setError(handleError(err));
       } finally {
         setLoading(false);
       }
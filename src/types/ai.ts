@@ -7,11 +7,17 @@ export interface AIConfig {
     temperature: number;
     max_tokens: number;
     is_active: boolean;
+    timeout_secs: number;
+    proxy_url: string | null;
+    deployment: string | null; // Azure OpenAI 部署名
+    api_version: string | null; // Azure OpenAI api-version
   }
   
   export interface AIRequest {
     note_content: string;
     note_title: string;
     highlighted_text?: string | null;
-    action: "summarize" | "questions" | "suggestions" | "expand";
+    action: "summarize" | "questions" | "suggestions" | "expand" | "translate" | "define";
+    target_language?: string | null;
+    force_refresh?: boolean;
   }
\ No newline at end of file
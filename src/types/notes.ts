@@ -1,3 +1,11 @@
+export type AnnotationType = 'highlight' | 'underline' | 'note' | 'bookmark';
+
+// 分页结果
+export interface Page<T> {
+  items: T[];
+  total: number;
+}
+
 export interface Note {
   id: number;
   title: string;
@@ -7,11 +15,12 @@ export interface Note {
   book_id: number | null;
   chapter_index: number | null;
   highlighted_text: string | null;
-  annotation_type: 'highlight' | 'underline' | null;
+  annotation_type: AnnotationType | null;
   tags: Tag[];
   created_at: string;
   updated_at: string;
   deleted_at: string | null;
+  needs_review: boolean; // 重新解析后未能迁移到新章节
 }
 
 export interface Category {
@@ -33,7 +42,7 @@ export interface CreateNoteRequest {
   book_id?: number;
   chapter_index?: number;
   highlighted_text?: string;
-  annotation_type?: 'highlight' | 'underline';
+  annotation_type?: AnnotationType;
   position_start?: number;
   position_end?: number;
   tag_ids?: number[];
@@ -42,9 +51,11 @@ export interface CreateNoteRequest {
 export interface UpdateNoteRequest {
   id: number;
   title?: string;
-  content?: string;
-  category_id?: number;
+  content?: string | null; // 省略表示不修改，null 表示清空
+  category_id?: number | null; // 省略表示不修改，null 表示取消分类
+  annotation_type?: AnnotationType;
   tag_ids?: number[];
+  expected_updated_at?: string; // 读取时的 updated_at，不一致时后端拒绝更新
 }
 
 export interface SearchNotesRequest {
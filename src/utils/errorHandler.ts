@@ -10,11 +10,30 @@ export class AppError extends Error {
   }
 }
 
+// 后端命令返回的结构化错误（对应 Rust 侧的 AppError）
+export interface CommandError {
+  kind: 'notFound' | 'db' | 'parse' | 'validation' | 'conflict' | 'ai' | 'io';
+  message: string;
+}
+
+export function isCommandError(error: unknown): error is CommandError {
+  return (
+    typeof error === 'object' &&
+    error !== null &&
+    typeof (error as CommandError).kind === 'string' &&
+    typeof (error as CommandError).message === 'string'
+  );
+}
+
 // 全局错误处理
 export function handleError(error: unknown): string {
   if (error instanceof AppError) {
     return error.message;
   }
+
+  if (isCommandError(error)) {
+    return error.message;
+  }
   
   if (error instanceof Error) {
     console.error('Error:', error);